@@ -0,0 +1,298 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Папки, которые пользователь сам добавил к разрешённым через системный диалог:
+// обычные (allowed_dirs.json) и сетевые, разрешённые после предупреждения
+// (network_dirs.json). Оба списка подписаны HMAC-SHA256 одним ключом, созданным при
+// первом добавлении папки: дописанная в файл вручную или другой программой папка не
+// пройдёт проверку подписи, и тогда весь список игнорируется, пока пользователь не
+// добавит папки заново. Неподписанный список сетевых папок прежних версий поэтому
+// тоже не действует - сетевые папки нужно разрешить повторно.
+//
+// Ключ хранится в системном хранилище секретов (связка ключей macOS, диспетчер
+// учётных данных Windows, Secret Service в Linux), а не рядом со списком: иначе
+// программа, способная переписать список, переписала бы и подпись. Если хранилища
+// нет (Linux без Secret Service), ключ лежит в файле allowed_dirs.key в папке
+// настроек - тогда подпись защищает только от случайной правки файла. Ключ из
+// файла прежних версий переносится в хранилище при первом чтении.
+
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::paths;
+
+// Списки в папке настроек; ключ подписи - в хранилище секретов или в запасном файле
+const ALLOW_LIST_FILE: &str = "allowed_dirs.json";
+const NETWORK_LIST_FILE: &str = "network_dirs.json";
+const KEY_FILE: &str = "allowed_dirs.key";
+
+/// Файлы списков и ключа в папке настроек. Подпись действует только с ключом этого
+/// компьютера, поэтому полная резервная копия переносит папки не этими файлами
+pub const SIGNED_FILES: [&str; 3] = [ALLOW_LIST_FILE, NETWORK_LIST_FILE, KEY_FILE];
+const KEYRING_SERVICE: &str = "time-to-table";
+const KEYRING_USER: &str = "allowed_dirs";
+
+const KEY_SIZE: usize = 32;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Прочитанный ключ: хранилище секретов не опрашивается при каждой проверке пути
+static KEY: LazyLock<Mutex<Option<Vec<u8>>>> = LazyLock::new(|| Mutex::new(None));
+
+#[derive(Serialize, Deserialize)]
+struct SignedList {
+    dirs: Vec<PathBuf>,
+    signature: String,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok()).collect()
+}
+
+/// HMAC списка: от его компактной JSON-записи
+fn list_mac(key: &[u8], dirs: &[PathBuf]) -> HmacSha256 {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(key).expect("HMAC принимает ключ любой длины");
+    mac.update(&serde_json::to_vec(dirs).unwrap_or_default());
+    mac
+}
+
+/// Подпись списка в hex
+fn sign(key: &[u8], dirs: &[PathBuf]) -> String {
+    hex(&list_mac(key, dirs).finalize().into_bytes())
+}
+
+/// Проверка подписи за постоянное время: по времени сравнения нельзя подобрать подпись
+fn verify(key: &[u8], dirs: &[PathBuf], signature: &str) -> bool {
+    unhex(signature).is_some_and(|bytes| list_mac(key, dirs).verify_slice(&bytes).is_ok())
+}
+
+fn keyring_entry() -> Option<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER).ok()
+}
+
+fn read_key_file(config: &Path) -> Option<Vec<u8>> {
+    let key = std::fs::read(config.join(KEY_FILE)).ok()?;
+    (key.len() == KEY_SIZE).then_some(key)
+}
+
+fn remember(key: Vec<u8>) -> Vec<u8> {
+    *KEY.lock().unwrap_or_else(|e| e.into_inner()) = Some(key.clone());
+    key
+}
+
+fn read_key(config: &Path) -> Option<Vec<u8>> {
+    if let Some(key) = KEY.lock().unwrap_or_else(|e| e.into_inner()).clone() {
+        return Some(key);
+    }
+    let entry = keyring_entry();
+    let key = match entry.as_ref().map(|entry| entry.get_secret()) {
+        // Ключ неверной длины в хранилище - как неверная подпись: список не принимается
+        Some(Ok(key)) => (key.len() == KEY_SIZE).then_some(key)?,
+        Some(Err(keyring::Error::NoEntry)) => {
+            let key = read_key_file(config)?;
+            if entry.is_some_and(|entry| entry.set_secret(&key).is_ok()) {
+                let _ = std::fs::remove_file(config.join(KEY_FILE));
+            }
+            key
+        }
+        // Хранилище недоступно
+        _ => read_key_file(config)?,
+    };
+    Some(remember(key))
+}
+
+/// Ключ подписи; создаётся при первом добавлении папки
+fn key_or_create(config: &Path) -> Result<Vec<u8>, String> {
+    if let Some(key) = read_key(config) {
+        return Ok(key);
+    }
+    let mut key = vec![0u8; KEY_SIZE];
+    getrandom::getrandom(&mut key).map_err(|e| format!("Не удалось создать ключ подписи: {}", e))?;
+    if keyring_entry().is_none_or(|entry| entry.set_secret(&key).is_err()) {
+        std::fs::write(config.join(KEY_FILE), &key)
+            .map_err(|e| paths::io_error_message("Ошибка сохранения ключа подписи", &e))?;
+    }
+    Ok(remember(key))
+}
+
+/// Подписанный список из файла file; при неверной подписи - пустой список
+fn read_list(file: &str) -> Vec<PathBuf> {
+    let Some(config) = paths::app_config_dir() else {
+        return Vec::new();
+    };
+    // Список читается первым: пока папок не добавляли, хранилище секретов не нужно
+    let Some(list) = std::fs::read_to_string(config.join(file))
+        .ok()
+        .and_then(|raw| serde_json::from_str::<SignedList>(&raw).ok())
+    else {
+        return Vec::new();
+    };
+    let Some(key) = read_key(&config) else {
+        return Vec::new();
+    };
+    if verify(&key, &list.dirs, &list.signature) {
+        list.dirs
+    } else {
+        Vec::new()
+    }
+}
+
+fn save_list(file: &str, dirs: Vec<PathBuf>) -> Result<(), String> {
+    let config = paths::app_config_dir().ok_or("Не удалось определить папку настроек")?;
+    std::fs::create_dir_all(&config)
+        .map_err(|e| paths::io_error_message("Ошибка создания папки настроек", &e))?;
+    let key = key_or_create(&config)?;
+    let signature = sign(&key, &dirs);
+    let content = serde_json::to_string_pretty(&SignedList { dirs, signature })
+        .map_err(|e| format!("Ошибка сохранения списка папок: {}", e))?;
+    std::fs::write(config.join(file), content)
+        .map_err(|e| paths::io_error_message("Ошибка сохранения списка папок", &e))
+}
+
+/// Добавленные пользователем папки
+pub fn dirs() -> Vec<PathBuf> {
+    read_list(ALLOW_LIST_FILE)
+}
+
+/// Разрешённые пользователем сетевые папки
+pub fn network_dirs() -> Vec<PathBuf> {
+    read_list(NETWORK_LIST_FILE)
+}
+
+fn add_to(file: &str, dir: PathBuf) -> Result<PathBuf, String> {
+    let mut list = read_list(file);
+    if !list.iter().any(|p| paths::same_path(p, &dir)) {
+        list.push(dir.clone());
+        save_list(file, list)?;
+    }
+    Ok(dir)
+}
+
+fn remove_from(file: &str, dir: &Path) -> Result<(), String> {
+    let mut list = read_list(file);
+    let before = list.len();
+    list.retain(|p| !paths::same_path(p, dir));
+    if list.len() == before {
+        return Err("Папка не найдена в списке разрешённых".into());
+    }
+    save_list(file, list)
+}
+
+/// Добавляет папку. Корень диска и папки, пересекающиеся с настройками приложения
+/// (там лежит сам список), не разрешаются
+pub fn add(dir: &Path) -> Result<PathBuf, String> {
+    let canonical = dir
+        .canonicalize()
+        .map(|p| paths::nfc(&paths::strip_verbatim(&p)))
+        .map_err(|e| paths::io_error_message("Папка недоступна", &e))?;
+    if !canonical.is_dir() {
+        return Err("Выберите папку, а не файл".into());
+    }
+    if canonical.parent().is_none() {
+        return Err("Нельзя разрешить доступ ко всему диску, выберите папку на нём".into());
+    }
+    if let Some(config) = paths::app_config_dir().and_then(|c| c.canonicalize().ok()) {
+        let config = paths::strip_verbatim(&config);
+        if paths::is_within(&canonical, &config) || paths::is_within(&config, &canonical) {
+            return Err("Эта папка содержит настройки приложения, выберите другую".into());
+        }
+    }
+    add_to(ALLOW_LIST_FILE, canonical)
+}
+
+/// Убирает папку из списка
+pub fn remove(dir: &Path) -> Result<(), String> {
+    remove_from(ALLOW_LIST_FILE, dir)
+}
+
+/// Добавляет сетевую папку; что путь сетевой, проверяет вызывающий
+pub fn add_network(dir: &Path) -> Result<PathBuf, String> {
+    let canonical = dir
+        .canonicalize()
+        .map(|p| paths::nfc(&paths::strip_verbatim(&p)))
+        .map_err(|e| paths::io_error_message("Сетевая папка недоступна", &e))?;
+    add_to(NETWORK_LIST_FILE, canonical)
+}
+
+/// Убирает сетевую папку из списка
+pub fn remove_network(dir: &Path) -> Result<(), String> {
+    remove_from(NETWORK_LIST_FILE, dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Тестовые векторы HMAC-SHA-256 из RFC 4231, случаи 1-7
+    #[test]
+    fn hmac_rfc4231() {
+        let long_key = [0xaa; 131];
+        let cases: [(&[u8], &[u8], &str); 7] = [
+            (&[0x0b; 20], b"Hi There", "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"),
+            (
+                b"Jefe",
+                b"what do ya want for nothing?",
+                "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            ),
+            (&[0xaa; 20], &[0xdd; 50], "773ea91e36800e46854db8ebd09181a72959098b3ef8c122d9635514ced565fe"),
+            (
+                &[
+                    0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f, 0x10,
+                    0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19,
+                ],
+                &[0xcd; 50],
+                "82558a389a443c0ea4cc819899f2083a85f0faa3e578f8077a2e3ff46729665b",
+            ),
+            // В RFC результат усечён до 128 бит
+            (&[0x0c; 20], b"Test With Truncation", "a3b6167473100ee06e0c796c2955552b"),
+            (
+                &long_key,
+                b"Test Using Larger Than Block-Size Key - Hash Key First",
+                "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+            ),
+            (
+                &long_key,
+                b"This is a test using a larger than block-size key and a larger than block-size data. \
+                  The key needs to be hashed before being used by the HMAC algorithm.",
+                "9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2",
+            ),
+        ];
+        for (i, (key, message, expected)) in cases.iter().enumerate() {
+            let mac = <HmacSha256 as Mac>::new_from_slice(key).unwrap().chain_update(message);
+            let expected = unhex(expected).unwrap();
+            if expected.len() < 32 {
+                mac.verify_truncated_left(&expected)
+            } else {
+                mac.verify_slice(&expected)
+            }
+            .unwrap_or_else(|_| panic!("случай {}", i + 1));
+        }
+    }
+
+    #[test]
+    fn signature_covers_list() {
+        let key = [7u8; KEY_SIZE];
+        let dirs = vec![PathBuf::from("/home/user/Расписания")];
+        let signature = sign(&key, &dirs);
+        assert_eq!(signature, sign(&key, &dirs));
+        assert_ne!(signature, sign(&key, &[dirs[0].clone(), PathBuf::from("/")]));
+        assert_ne!(signature, sign(&[8u8; KEY_SIZE], &dirs));
+        assert!(verify(&key, &dirs, &signature));
+        assert!(!verify(&key, &dirs, &signature[..62]));
+        let flipped = format!("{}{}", if signature.starts_with('0') { '1' } else { '0' }, &signature[1..]);
+        assert!(!verify(&key, &dirs, &flipped));
+        assert!(!verify(&key, &dirs, "не подпись"));
+        assert!(!verify(&[8u8; KEY_SIZE], &dirs, &signature));
+    }
+}
@@ -0,0 +1,147 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Рабочие данные фронтенда в папках приложения, а не в Документах, где их легко
+// удалить по ошибке. Три области: настройки (папка настроек), автосохранения
+// (папка данных) и кэш (папка кэша, её может очищать система). Каждая область -
+// отдельная подпапка, чтобы фронтенд не мог перезаписать служебные файлы бэкенда
+// (список разрешённых папок, лимиты) в корне папки настроек.
+
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::files::{self, FileEntry};
+use crate::paths;
+
+/// Наибольший размер одного файла данных
+pub const MAX_DATA_SIZE: usize = 10 * 1024 * 1024;
+
+// Допустимые расширения файлов данных
+const EXTENSIONS: [&str; 3] = ["json", "xml", "txt"];
+
+/// Область хранения
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Area {
+    Settings,
+    Autosave,
+    Cache,
+}
+
+impl Area {
+    fn dir(self) -> Option<PathBuf> {
+        match self {
+            Area::Settings => paths::app_config_dir().map(|d| d.join("user")),
+            Area::Autosave => paths::app_data_dir().map(|d| d.join("autosave")),
+            Area::Cache => paths::app_cache_dir().map(|d| d.join("cache")),
+        }
+    }
+}
+
+/// Путь к файлу области. Имя - без папок, с расширением .json, .xml или .txt
+fn file_path(area: Area, name: &str) -> Result<PathBuf, String> {
+    let name = name.trim();
+    if name.is_empty() || name.len() > 128 || name.starts_with('.') {
+        return Err("Недопустимое имя файла данных".into());
+    }
+    let path = Path::new(name);
+    paths::check_file_name(path)?;
+    let valid_ext = path
+        .extension()
+        .is_some_and(|ext| EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str()));
+    if !valid_ext {
+        return Err("Файлы данных могут быть только .json, .xml или .txt".into());
+    }
+    let dir = area.dir().ok_or("Не удалось определить папку данных приложения")?;
+    Ok(dir.join(name))
+}
+
+/// Записывает файл данных. Запись идёт во временный файл с заменой, поэтому
+/// сбой посреди записи не оставляет настройки наполовину записанными
+pub fn write(area: Area, name: &str, content: &str) -> Result<(), String> {
+    if content.len() > MAX_DATA_SIZE {
+        return Err(format!("Размер данных превышает максимальный ({} МБ)", MAX_DATA_SIZE / 1024 / 1024));
+    }
+    let path = file_path(area, name)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| paths::io_error_message("Ошибка создания папки данных", &e))?;
+    }
+    let temp = path.with_file_name(format!(".{}.tmp", name.trim()));
+    std::fs::write(&temp, content)
+        .and_then(|_| std::fs::rename(&temp, &path))
+        .map_err(|e| {
+            let _ = std::fs::remove_file(&temp);
+            paths::io_error_message("Ошибка записи данных", &e)
+        })
+}
+
+// Путь к существующему файлу данных допустимого размера; None - файла нет
+fn existing(area: Area, name: &str) -> Result<Option<PathBuf>, String> {
+    let path = file_path(area, name)?;
+    match std::fs::metadata(&path) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(paths::io_error_message("Ошибка чтения данных", &e)),
+        Ok(m) if m.len() > MAX_DATA_SIZE as u64 => {
+            Err(format!("Размер данных превышает максимальный ({} МБ)", MAX_DATA_SIZE / 1024 / 1024))
+        }
+        Ok(_) => Ok(Some(path)),
+    }
+}
+
+/// Читает файл данных; None - файла нет
+pub fn read(area: Area, name: &str) -> Result<Option<String>, String> {
+    let Some(path) = existing(area, name)? else {
+        return Ok(None);
+    };
+    std::fs::read_to_string(&path)
+        .map(Some)
+        .map_err(|e| paths::io_error_message("Ошибка чтения данных", &e))
+}
+
+/// Читает файл данных как есть, без проверки UTF-8; None - файла нет
+pub fn read_bytes(area: Area, name: &str) -> Result<Option<Vec<u8>>, String> {
+    let Some(path) = existing(area, name)? else {
+        return Ok(None);
+    };
+    std::fs::read(&path).map(Some).map_err(|e| paths::io_error_message("Ошибка чтения данных", &e))
+}
+
+/// Переименовывает файл данных; файл с именем to заменяется
+pub fn rename(area: Area, from: &str, to: &str) -> Result<(), String> {
+    let (from, to) = (file_path(area, from)?, file_path(area, to)?);
+    std::fs::rename(&from, &to).map_err(|e| paths::io_error_message("Ошибка переименования данных", &e))
+}
+
+/// Файлы области, новые первыми
+pub fn list(area: Area) -> Vec<FileEntry> {
+    let Some(dir) = area.dir() else { return Vec::new() };
+    let Ok(entries) = std::fs::read_dir(&dir) else { return Vec::new() };
+    let mut list: Vec<FileEntry> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') || file_path(area, &name).is_err() {
+                return None;
+            }
+            let metadata = entry.metadata().ok().filter(|m| m.is_file())?;
+            Some(FileEntry {
+                path: dir.join(&name).to_string_lossy().to_string(),
+                name,
+                size: metadata.len(),
+                modified: files::modified_ms(&metadata),
+            })
+        })
+        .collect();
+    list.sort_by_key(|f| std::cmp::Reverse(f.modified));
+    list
+}
+
+/// Удаляет файл данных; отсутствующий файл - не ошибка
+pub fn delete(area: Area, name: &str) -> Result<(), String> {
+    let path = file_path(area, name)?;
+    match std::fs::remove_file(&path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(paths::io_error_message("Ошибка удаления данных", &e)),
+        _ => Ok(()),
+    }
+}
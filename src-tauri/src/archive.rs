@@ -0,0 +1,553 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Архив расписаний в SQLite (archive.sqlite3 в папке данных приложения). Основной
+// формат остаётся файловым (.ttable, .json); архив нужен для многолетней истории:
+// каждое сохранение проекта - новая ревизия, а записи и операции лежат в таблицах,
+// по которым можно строить запросы (например, загрузку исполнителя за год).
+//
+// Таблицы соответствуют модели расписания: исполнители - workers, записи истории
+// (расчёты техкарт) - entries, строки расчёта - operations, занятое внешними
+// событиями время - blocked. Помещений в модели нет, поэтому нет и таблицы для них.
+//
+// Схема меняется только миграциями (MIGRATIONS), номер применённой - PRAGMA user_version.
+// Перед обновлением существующего архива рядом сохраняется его копия, а сами миграции
+// выполняются в одной транзакции: при ошибке архив остаётся в прежней версии.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::Local;
+use rusqlite::{params, Connection, OptionalExtension, Transaction, TransactionBehavior};
+use serde::Serialize;
+
+use crate::model::{BlockedSlot, OperationRow, Schedule, ScheduleEntry};
+use crate::paths;
+
+/// Имя файла архива в папке данных приложения
+pub const DB_FILE: &str = "archive.sqlite3";
+
+// Миграции схемы по порядку: MIGRATIONS[i] переводит архив с версии i на i + 1.
+// Выпущенные миграции не меняются - новая схема добавляется миграцией в конец списка
+const MIGRATIONS: &[&str] = &[SCHEMA_V1];
+
+// Версия схемы в PRAGMA user_version
+const SCHEMA_VERSION: i64 = MIGRATIONS.len() as i64;
+
+const MAX_PROJECT_NAME_CHARS: usize = 200;
+
+const TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+// Сколько ждать, пока другой экземпляр приложения закончит запись в архив
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SCHEMA_V1: &str = "
+CREATE TABLE IF NOT EXISTS projects (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    created_at TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS revisions (
+    id INTEGER PRIMARY KEY,
+    project_id INTEGER NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    created_at TEXT NOT NULL,
+    label TEXT NOT NULL DEFAULT ''
+);
+CREATE INDEX IF NOT EXISTS revisions_project ON revisions(project_id);
+CREATE TABLE IF NOT EXISTS workers (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+CREATE TABLE IF NOT EXISTS entries (
+    id INTEGER PRIMARY KEY,
+    revision_id INTEGER NOT NULL REFERENCES revisions(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    chain INTEGER NOT NULL,
+    time_mode TEXT NOT NULL,
+    z7 TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS entries_revision ON entries(revision_id);
+CREATE TABLE IF NOT EXISTS operations (
+    id INTEGER PRIMARY KEY,
+    entry_id INTEGER NOT NULL REFERENCES entries(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    original_op_index TEXT NOT NULL,
+    op_idx TEXT NOT NULL,
+    op_numeric REAL,
+    name TEXT NOT NULL,
+    worker_id INTEGER REFERENCES workers(id),
+    worker_index INTEGER,
+    dur_val REAL NOT NULL,
+    dur_text TEXT NOT NULL,
+    unit TEXT NOT NULL,
+    start_date TEXT NOT NULL,
+    start_time TEXT NOT NULL,
+    end_date TEXT NOT NULL,
+    end_time TEXT NOT NULL,
+    crossed_lunch INTEGER NOT NULL,
+    pause_text TEXT NOT NULL,
+    posting_date TEXT NOT NULL,
+    pdtv_auto_mode INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS operations_entry ON operations(entry_id);
+CREATE INDEX IF NOT EXISTS operations_worker ON operations(worker_id);
+CREATE TABLE IF NOT EXISTS blocked (
+    id INTEGER PRIMARY KEY,
+    revision_id INTEGER NOT NULL REFERENCES revisions(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    worker_id INTEGER REFERENCES workers(id),
+    start_date TEXT NOT NULL,
+    start_time TEXT NOT NULL,
+    end_date TEXT NOT NULL,
+    end_time TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS blocked_revision ON blocked(revision_id);
+";
+
+/// Проект в архиве
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectInfo {
+    pub name: String,
+    pub revisions: usize,
+    /// Время последней ревизии: ГГГГ-ММ-ДДTЧЧ:ММ:СС
+    pub updated_at: Option<String>,
+}
+
+/// Ревизия проекта
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RevisionInfo {
+    pub id: i64,
+    pub created_at: String,
+    pub label: String,
+    pub entries: usize,
+    pub operations: usize,
+}
+
+fn db_error(e: rusqlite::Error) -> String {
+    format!("Ошибка архива расписаний: {}", e)
+}
+
+fn db_path() -> Result<PathBuf, String> {
+    paths::app_data_dir()
+        .map(|dir| dir.join(DB_FILE))
+        .ok_or_else(|| "Не удалось определить папку данных приложения".into())
+}
+
+/// Открывает архив и доводит его схему до текущей версии
+fn open() -> Result<Connection, String> {
+    let path = db_path()?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| paths::io_error_message("Не удалось создать папку данных", &e))?;
+    }
+    let mut conn = Connection::open(&path).map_err(db_error)?;
+    conn.busy_timeout(BUSY_TIMEOUT).map_err(db_error)?;
+    // Миграция, пересоздающая таблицу, не должна каскадно удалять строки, поэтому
+    // внешние ключи включаются после миграций (внутри транзакции PRAGMA не действует)
+    migrate(&mut conn, &path)?;
+    conn.pragma_update(None, "foreign_keys", true).map_err(db_error)?;
+    Ok(conn)
+}
+
+fn schema_version(conn: &Connection) -> Result<i64, String> {
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0)).map_err(db_error)?;
+    if version > SCHEMA_VERSION {
+        return Err("Архив создан более новой версией приложения, обновите программу".into());
+    }
+    Ok(version)
+}
+
+/// Применяет недостающие миграции в одной транзакции. Непустой архив перед этим
+/// копируется в archive.sqlite3.v<версия>.bak
+fn migrate(conn: &mut Connection, path: &Path) -> Result<(), String> {
+    let version = schema_version(conn)?;
+    if version == SCHEMA_VERSION {
+        return Ok(());
+    }
+    if version > 0 {
+        backup(conn, path, version)?;
+    }
+
+    // IMMEDIATE сразу берёт блокировку записи: два экземпляра не обновят архив одновременно
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(db_error)?;
+    // Пока ждали блокировку, архив мог обновить другой экземпляр
+    let version = schema_version(&tx)?;
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        tx.execute_batch(migration)
+            .map_err(|e| format!("Ошибка обновления архива до версии {}: {}", i + 1, e))?;
+    }
+    let broken: Option<String> = tx
+        .query_row("PRAGMA foreign_key_check", [], |row| row.get(0))
+        .optional()
+        .map_err(db_error)?;
+    if let Some(table) = broken {
+        return Err(format!("Обновление архива нарушило связи таблицы {}, архив не изменён", table));
+    }
+    tx.pragma_update(None, "user_version", SCHEMA_VERSION).map_err(db_error)?;
+    tx.commit().map_err(db_error)
+}
+
+/// Копия архива перед миграцией. Копия с тем же номером версии остаётся от прошлой
+/// неудачной попытки, архив с тех пор не менялся, поэтому она заменяется
+fn backup(conn: &Connection, path: &Path, version: i64) -> Result<(), String> {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{}.bak", version));
+    let backup = path.with_file_name(name);
+    match std::fs::remove_file(&backup) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            return Err(paths::io_error_message("Не удалось заменить копию архива", &e));
+        }
+        _ => {}
+    }
+    // VACUUM INTO записывает согласованный снимок, даже если архив открыт другим экземпляром
+    conn.execute("VACUUM INTO ?1", params![backup.to_string_lossy()])
+        .map_err(|e| format!("Не удалось сохранить копию архива перед обновлением, архив не изменён: {}", e))?;
+    Ok(())
+}
+
+/// Согласованная копия архива для полной резервной копии приложения; None - архива ещё нет
+pub fn snapshot() -> Result<Option<Vec<u8>>, String> {
+    let path = db_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let copy = path.with_file_name(format!("{}.snapshot", DB_FILE));
+    match std::fs::remove_file(&copy) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            return Err(paths::io_error_message("Не удалось заменить копию архива", &e));
+        }
+        _ => {}
+    }
+    {
+        let conn = Connection::open(&path).map_err(db_error)?;
+        conn.busy_timeout(BUSY_TIMEOUT).map_err(db_error)?;
+        conn.execute("VACUUM INTO ?1", params![copy.to_string_lossy()])
+            .map_err(|e| format!("Не удалось сохранить копию архива: {}", e))?;
+    }
+    let bytes = std::fs::read(&copy).map_err(|e| paths::io_error_message("Ошибка чтения копии архива", &e));
+    let _ = std::fs::remove_file(&copy);
+    bytes.map(Some)
+}
+
+/// Итог сжатия архива
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Compaction {
+    /// Освобождено байт
+    pub reclaimed: u64,
+    /// Удалено исполнителей, на которых не ссылается ни одна ревизия
+    pub workers: usize,
+}
+
+/// Удаляет исполнителей, оставшихся от удалённых проектов, и сжимает архив (VACUUM)
+pub fn compact() -> Result<Compaction, String> {
+    let path = db_path()?;
+    let size = |path: &Path| std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    if !path.exists() {
+        return Ok(Compaction::default());
+    }
+    let before = size(&path);
+    let workers = {
+        let conn = open()?;
+        let workers = conn
+            .execute(
+                "DELETE FROM workers WHERE id NOT IN (SELECT worker_id FROM operations WHERE worker_id IS NOT NULL)
+                 AND id NOT IN (SELECT worker_id FROM blocked WHERE worker_id IS NOT NULL)",
+                [],
+            )
+            .map_err(db_error)?;
+        conn.execute_batch("VACUUM").map_err(db_error)?;
+        workers
+    };
+    Ok(Compaction { reclaimed: before.saturating_sub(size(&path)), workers })
+}
+
+fn check_name(project: &str) -> Result<&str, String> {
+    let name = project.trim();
+    if name.is_empty() {
+        return Err("Не указано название проекта".into());
+    }
+    if name.chars().count() > MAX_PROJECT_NAME_CHARS {
+        return Err(format!("Название проекта длиннее {} символов", MAX_PROJECT_NAME_CHARS));
+    }
+    Ok(name)
+}
+
+fn project_id(conn: &Connection, name: &str) -> Result<Option<i64>, String> {
+    conn.query_row("SELECT id FROM projects WHERE name = ?1", params![name], |row| row.get(0))
+        .optional()
+        .map_err(db_error)
+}
+
+/// Идентификатор исполнителя (создаётся при первом упоминании); пустое имя - None
+fn worker_id(tx: &Transaction, worker: &str, cache: &mut HashMap<String, i64>) -> Result<Option<i64>, String> {
+    let worker = worker.trim();
+    if worker.is_empty() {
+        return Ok(None);
+    }
+    if let Some(id) = cache.get(worker) {
+        return Ok(Some(*id));
+    }
+    tx.execute("INSERT OR IGNORE INTO workers (name) VALUES (?1)", params![worker])
+        .map_err(db_error)?;
+    let id: i64 = tx
+        .query_row("SELECT id FROM workers WHERE name = ?1", params![worker], |row| row.get(0))
+        .map_err(db_error)?;
+    cache.insert(worker.to_string(), id);
+    Ok(Some(id))
+}
+
+fn insert_entry(
+    tx: &Transaction,
+    revision: i64,
+    position: usize,
+    entry: &ScheduleEntry,
+    workers: &mut HashMap<String, i64>,
+) -> Result<(), String> {
+    let z7 = serde_json::to_string(&entry.z7).map_err(|e| format!("Ошибка сохранения в архив: {}", e))?;
+    tx.execute(
+        "INSERT INTO entries (revision_id, position, title, chain, time_mode, z7) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![revision, position as i64, entry.title, entry.chain, entry.time_mode, z7],
+    )
+    .map_err(db_error)?;
+    let entry_id = tx.last_insert_rowid();
+
+    for (position, row) in entry.rows.iter().enumerate() {
+        let worker = worker_id(tx, &row.worker, workers)?;
+        tx.execute(
+            "INSERT INTO operations (entry_id, position, original_op_index, op_idx, op_numeric, name, worker_id,
+                worker_index, dur_val, dur_text, unit, start_date, start_time, end_date, end_time,
+                crossed_lunch, pause_text, posting_date, pdtv_auto_mode)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
+            params![
+                entry_id,
+                position as i64,
+                row.original_op_index,
+                row.op_idx,
+                row.op_numeric,
+                row.name,
+                worker,
+                row.worker_index,
+                row.dur_val,
+                row.dur_text,
+                row.unit,
+                row.start_date,
+                row.start_time,
+                row.end_date,
+                row.end_time,
+                row.crossed_lunch,
+                row.pause_text,
+                row.posting_date,
+                row.pdtv_auto_mode,
+            ],
+        )
+        .map_err(db_error)?;
+    }
+    Ok(())
+}
+
+/// Сохраняет расписание новой ревизией проекта (проект создаётся при первом сохранении).
+/// Возвращает идентификатор ревизии
+pub fn save(project: &str, schedule: &Schedule, label: &str) -> Result<i64, String> {
+    let name = check_name(project)?;
+    let mut conn = open()?;
+    let tx = conn.transaction().map_err(db_error)?;
+    let now = Local::now().format(TIME_FORMAT).to_string();
+
+    let project = match project_id(&tx, name)? {
+        Some(id) => id,
+        None => {
+            tx.execute("INSERT INTO projects (name, created_at) VALUES (?1, ?2)", params![name, now])
+                .map_err(db_error)?;
+            tx.last_insert_rowid()
+        }
+    };
+    tx.execute(
+        "INSERT INTO revisions (project_id, created_at, label) VALUES (?1, ?2, ?3)",
+        params![project, now, label.trim()],
+    )
+    .map_err(db_error)?;
+    let revision = tx.last_insert_rowid();
+
+    let mut workers = HashMap::new();
+    for (position, entry) in schedule.entries.iter().enumerate() {
+        insert_entry(&tx, revision, position, entry, &mut workers)?;
+    }
+    for (position, slot) in schedule.blocked.iter().enumerate() {
+        let worker = worker_id(&tx, &slot.worker, &mut workers)?;
+        tx.execute(
+            "INSERT INTO blocked (revision_id, position, title, worker_id, start_date, start_time, end_date, end_time)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![revision, position as i64, slot.title, worker, slot.start_date, slot.start_time, slot.end_date, slot.end_time],
+        )
+        .map_err(db_error)?;
+    }
+
+    tx.commit().map_err(db_error)?;
+    Ok(revision)
+}
+
+fn read_operations(conn: &Connection, entry_id: i64) -> Result<Vec<OperationRow>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT o.original_op_index, o.op_idx, o.op_numeric, o.name, COALESCE(w.name, ''), o.worker_index,
+                o.dur_val, o.dur_text, o.unit, o.start_date, o.start_time, o.end_date, o.end_time,
+                o.crossed_lunch, o.pause_text, o.posting_date, o.pdtv_auto_mode
+             FROM operations o LEFT JOIN workers w ON w.id = o.worker_id
+             WHERE o.entry_id = ?1 ORDER BY o.position",
+        )
+        .map_err(db_error)?;
+    let rows = stmt
+        .query_map(params![entry_id], |row| {
+            Ok(OperationRow {
+                original_op_index: row.get(0)?,
+                op_idx: row.get(1)?,
+                op_numeric: row.get(2)?,
+                name: row.get(3)?,
+                worker: row.get(4)?,
+                worker_index: row.get(5)?,
+                dur_val: row.get(6)?,
+                dur_text: row.get(7)?,
+                unit: row.get(8)?,
+                start_date: row.get(9)?,
+                start_time: row.get(10)?,
+                end_date: row.get(11)?,
+                end_time: row.get(12)?,
+                crossed_lunch: row.get(13)?,
+                pause_text: row.get(14)?,
+                posting_date: row.get(15)?,
+                pdtv_auto_mode: row.get(16)?,
+            })
+        })
+        .map_err(db_error)?;
+    rows.collect::<Result<_, _>>().map_err(db_error)
+}
+
+/// Читает ревизию проекта; revision не указана - последняя
+pub fn load(project: &str, revision: Option<i64>) -> Result<Schedule, String> {
+    let name = check_name(project)?;
+    let conn = open()?;
+    let project = project_id(&conn, name)?.ok_or("Проект не найден в архиве")?;
+    let revision: i64 = match revision {
+        Some(id) => conn
+            .query_row("SELECT id FROM revisions WHERE id = ?1 AND project_id = ?2", params![id, project], |row| row.get(0))
+            .optional()
+            .map_err(db_error)?
+            .ok_or("Ревизия не найдена")?,
+        None => conn
+            .query_row("SELECT MAX(id) FROM revisions WHERE project_id = ?1", params![project], |row| row.get::<_, Option<i64>>(0))
+            .map_err(db_error)?
+            .ok_or("У проекта нет сохранённых ревизий")?,
+    };
+
+    let mut stmt = conn
+        .prepare("SELECT id, title, chain, time_mode, z7 FROM entries WHERE revision_id = ?1 ORDER BY position")
+        .map_err(db_error)?;
+    let heads = stmt
+        .query_map(params![revision], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, bool>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+            ))
+        })
+        .map_err(db_error)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(db_error)?;
+    let mut entries = Vec::with_capacity(heads.len());
+    for (id, title, chain, time_mode, z7) in heads {
+        entries.push(ScheduleEntry {
+            title,
+            rows: read_operations(&conn, id)?,
+            z7: serde_json::from_str(&z7).unwrap_or_default(),
+            chain,
+            time_mode,
+        });
+    }
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT b.title, COALESCE(w.name, ''), b.start_date, b.start_time, b.end_date, b.end_time
+             FROM blocked b LEFT JOIN workers w ON w.id = b.worker_id
+             WHERE b.revision_id = ?1 ORDER BY b.position",
+        )
+        .map_err(db_error)?;
+    let blocked = stmt
+        .query_map(params![revision], |row| {
+            Ok(BlockedSlot {
+                title: row.get(0)?,
+                worker: row.get(1)?,
+                start_date: row.get(2)?,
+                start_time: row.get(3)?,
+                end_date: row.get(4)?,
+                end_time: row.get(5)?,
+            })
+        })
+        .map_err(db_error)?
+        .collect::<Result<_, _>>()
+        .map_err(db_error)?;
+
+    Ok(Schedule { entries, blocked })
+}
+
+/// Проекты архива, недавно изменённые первыми
+pub fn projects() -> Result<Vec<ProjectInfo>, String> {
+    let conn = open()?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT p.name, COUNT(r.id), MAX(r.created_at)
+             FROM projects p LEFT JOIN revisions r ON r.project_id = p.id
+             GROUP BY p.id ORDER BY MAX(r.created_at) DESC, p.name",
+        )
+        .map_err(db_error)?;
+    let projects = stmt
+        .query_map([], |row| {
+            Ok(ProjectInfo { name: row.get(0)?, revisions: row.get::<_, i64>(1)? as usize, updated_at: row.get(2)? })
+        })
+        .map_err(db_error)?;
+    projects.collect::<Result<_, _>>().map_err(db_error)
+}
+
+/// Ревизии проекта, новые первыми
+pub fn revisions(project: &str) -> Result<Vec<RevisionInfo>, String> {
+    let name = check_name(project)?;
+    let conn = open()?;
+    let project = project_id(&conn, name)?.ok_or("Проект не найден в архиве")?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT r.id, r.created_at, r.label,
+                (SELECT COUNT(*) FROM entries e WHERE e.revision_id = r.id),
+                (SELECT COUNT(*) FROM operations o JOIN entries e ON e.id = o.entry_id WHERE e.revision_id = r.id)
+             FROM revisions r WHERE r.project_id = ?1 ORDER BY r.id DESC",
+        )
+        .map_err(db_error)?;
+    let revisions = stmt
+        .query_map(params![project], |row| {
+            Ok(RevisionInfo {
+                id: row.get(0)?,
+                created_at: row.get(1)?,
+                label: row.get(2)?,
+                entries: row.get::<_, i64>(3)? as usize,
+                operations: row.get::<_, i64>(4)? as usize,
+            })
+        })
+        .map_err(db_error)?;
+    revisions.collect::<Result<_, _>>().map_err(db_error)
+}
+
+/// Удаляет проект со всеми ревизиями
+pub fn delete(project: &str) -> Result<(), String> {
+    let name = check_name(project)?;
+    let conn = open()?;
+    let deleted = conn.execute("DELETE FROM projects WHERE name = ?1", params![name]).map_err(db_error)?;
+    if deleted == 0 {
+        return Err("Проект не найден в архиве".into());
+    }
+    Ok(())
+}
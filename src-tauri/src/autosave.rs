@@ -0,0 +1,209 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Автосохранение открытых документов. Фоновый поток раз в секунду смотрит, какие
+// документы из истории правок (edits.rs) изменились, и не чаще заданного интервала
+// записывает их в область автосохранений (appdata): запись атомарная, через
+// временный файл. Пока пользователь правит, запись откладывается до паузы в QUIET.
+// Интервал хранится в настройках приложения (settings.rs).
+//
+// Файл автосохранения удаляется, когда документ закрывают; оставшийся файл значит,
+// что приложение завершилось, не закрыв документ.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::Manager;
+
+use crate::appdata::{self, Area};
+use crate::edits::{Edits, Unsaved};
+use crate::locks;
+use crate::model::Schedule;
+use crate::settings::{self, Settings};
+use crate::workspace::Workspace;
+
+// Пауза в правках, после которой документ можно записывать
+const QUIET: Duration = Duration::from_secs(2);
+
+const TICK: Duration = Duration::from_secs(1);
+
+/// Содержимое файла автосохранения
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutosaveFile {
+    pub document: String,
+    /// Файл документа рабочей области
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    pub version: u64,
+    /// ГГГГ-ММ-ДДTЧЧ:ММ:СС
+    pub saved_at: String,
+    pub schedule: Schedule,
+}
+
+/// Автосохранение документа
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutosavedDocument {
+    pub document: String,
+    pub saved_at: String,
+}
+
+/// Состояние автосохранения для фронтенда
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutosaveState {
+    pub interval_secs: u64,
+    pub enabled: bool,
+    pub last_saved_at: Option<String>,
+    pub last_error: Option<String>,
+    /// Документы с изменениями, которые ещё не попали в автосохранение
+    pub pending: Vec<String>,
+    pub documents: Vec<AutosavedDocument>,
+}
+
+struct Status {
+    interval_secs: u64,
+    last_run: Option<Instant>,
+    last_saved_at: Option<String>,
+    last_error: Option<String>,
+    saved: HashMap<String, String>,
+}
+
+/// Автосохранение, хранится в состоянии приложения
+pub struct Autosave {
+    status: Mutex<Status>,
+}
+
+impl Default for Autosave {
+    fn default() -> Self {
+        Autosave {
+            status: Mutex::new(Status {
+                interval_secs: settings::load().autosave_interval_secs,
+                last_run: None,
+                last_saved_at: None,
+                last_error: None,
+                saved: HashMap::new(),
+            }),
+        }
+    }
+}
+
+/// Имя файла автосохранения документа: идентификатор документа может быть путём
+pub fn file_name(document: &str) -> String {
+    let hash = Sha256::digest(document.as_bytes());
+    let hex: String = hash.iter().take(8).map(|b| format!("{:02x}", b)).collect();
+    format!("session-{}.json", hex)
+}
+
+/// Удаляет автосохранение закрытого документа
+pub fn discard(autosave: &Autosave, document: &str) -> Result<(), String> {
+    if let Ok(mut status) = autosave.status.lock() {
+        status.saved.remove(document);
+    }
+    appdata::delete(Area::Autosave, &file_name(document))
+}
+
+fn write(doc: &Unsaved, path: Option<PathBuf>) -> Result<String, String> {
+    let saved_at = Local::now().format("%Y-%m-%dT%H:%M:%S").to_string();
+    let file = AutosaveFile {
+        document: doc.document.clone(),
+        path,
+        version: doc.version,
+        saved_at: saved_at.clone(),
+        schedule: doc.schedule.clone(),
+    };
+    let content = serde_json::to_string(&file).map_err(|e| format!("Ошибка автосохранения: {}", e))?;
+    appdata::write(Area::Autosave, &file_name(&doc.document), &content)?;
+    Ok(saved_at)
+}
+
+impl Autosave {
+    /// Задаёт интервал и сохраняет его в настройках; 0 выключает автосохранение
+    pub fn set_interval(&self, secs: u64) -> Result<(), String> {
+        let settings = settings::set("autosaveIntervalSecs", secs.into())?;
+        self.apply(&settings);
+        Ok(())
+    }
+
+    /// Применяет изменённые настройки
+    pub fn apply(&self, settings: &Settings) {
+        if let Ok(mut status) = self.status.lock() {
+            status.interval_secs = settings.autosave_interval_secs;
+        }
+    }
+
+    pub fn state(&self, edits: &Edits) -> Result<AutosaveState, String> {
+        let status = self.status.lock().map_err(|_| "Ошибка доступа к автосохранению".to_string())?;
+        let mut documents: Vec<AutosavedDocument> = status
+            .saved
+            .iter()
+            .map(|(document, saved_at)| AutosavedDocument { document: document.clone(), saved_at: saved_at.clone() })
+            .collect();
+        documents.sort_by(|a, b| a.document.cmp(&b.document));
+        Ok(AutosaveState {
+            interval_secs: status.interval_secs,
+            enabled: status.interval_secs > 0,
+            last_saved_at: status.last_saved_at.clone(),
+            last_error: status.last_error.clone(),
+            pending: edits.unsaved(Duration::ZERO).into_iter().map(|d| d.document).collect(),
+            documents,
+        })
+    }
+
+    // Пора ли записывать: автосохранение включено и интервал с прошлой записи прошёл
+    fn due(&self) -> bool {
+        let Ok(status) = self.status.lock() else {
+            return false;
+        };
+        status.interval_secs > 0
+            && status
+                .last_run
+                .is_none_or(|at| at.elapsed() >= Duration::from_secs(status.interval_secs))
+    }
+
+    fn run(&self, edits: &Edits, workspace: &Workspace, unsaved: Vec<Unsaved>) {
+        let results: Vec<(Unsaved, Result<String, String>)> = unsaved
+            .into_iter()
+            .map(|doc| {
+                let result = write(&doc, workspace.path(&doc.document));
+                (doc, result)
+            })
+            .collect();
+        let Ok(mut status) = self.status.lock() else {
+            return;
+        };
+        status.last_run = Some(Instant::now());
+        for (doc, result) in results {
+            match result {
+                Ok(saved_at) => {
+                    edits.mark_autosaved(&doc.document, doc.version);
+                    status.last_saved_at = Some(saved_at.clone());
+                    status.last_error = None;
+                    status.saved.insert(doc.document, saved_at);
+                }
+                Err(e) => status.last_error = Some(e),
+            }
+        }
+    }
+}
+
+/// Запускает фоновый поток автосохранения
+pub fn start(app: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(TICK);
+        // Заодно держим живыми блокировки открытых файлов
+        locks::refresh();
+        let autosave = app.state::<Autosave>();
+        let edits = app.state::<Edits>();
+        let unsaved = edits.unsaved(QUIET);
+        if !unsaved.is_empty() && autosave.due() {
+            autosave.run(&edits, &app.state::<Workspace>(), unsaved);
+        }
+    });
+}
@@ -0,0 +1,276 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Резервные копии перед перезаписью. Прежняя версия файла копируется в папку .backups
+// рядом с ним под именем «имя.ГГГГММДД-ЧЧММСС-мс.расширение»; хранятся последние
+// N копий каждого файла (N задаётся в настройках, 0 - копии не создаются).
+//
+// Вместе с копией сохраняется контрольная сумма исходного файла (integrity.rs),
+// записанная при его сохранении: проверка копии (verify_backup) находит и копию,
+// испорченную на диске, и версию, которая была повреждена ещё до копирования. Если
+// включена проверка после создания, свежая копия перечитывается и сверяется с
+// исходным файлом.
+
+use std::path::{Path, PathBuf};
+
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::{integrity, paths};
+
+/// Имя папки с резервными копиями
+pub const BACKUP_DIR: &str = ".backups";
+
+// Файл с настройкой числа копий в папке настроек
+const SETTINGS_FILE: &str = "backups.json";
+
+/// Число копий по умолчанию
+pub const DEFAULT_KEEP: usize = 10;
+
+/// Наибольшее число хранимых копий одного файла
+pub const MAX_KEEP: usize = 100;
+
+const STAMP_FORMAT: &str = "%Y%m%d-%H%M%S-%3f";
+
+#[derive(Serialize, Deserialize)]
+struct Settings {
+    keep: usize,
+    /// Сверять каждую новую копию с исходным файлом
+    #[serde(default)]
+    verify: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings { keep: DEFAULT_KEEP, verify: false }
+    }
+}
+
+/// Резервная копия файла
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    pub path: String,
+    /// Время создания копии: «ДД.ММ.ГГГГ ЧЧ:ММ:СС»
+    pub created: String,
+    pub size: u64,
+}
+
+/// Результат проверки резервной копии
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupCheck {
+    pub path: String,
+    /// Восстановление из копии пройдёт успешно
+    pub restorable: bool,
+    /// Содержимое сверено с контрольной суммой; false - суммы у копии нет
+    pub checksum_verified: bool,
+    /// Копия зашифрована, а пароль не передан: содержимое не проверено
+    pub needs_password: bool,
+    /// Версия формата, из которой содержимое будет обновлено при открытии
+    pub migrated_from: Option<u32>,
+    /// Почему восстановление не пройдёт
+    pub error: Option<String>,
+}
+
+fn settings() -> Settings {
+    paths::app_config_dir()
+        .and_then(|dir| std::fs::read_to_string(dir.join(SETTINGS_FILE)).ok())
+        .and_then(|raw| serde_json::from_str::<Settings>(&raw).ok())
+        .unwrap_or_default()
+}
+
+fn save_settings(settings: &Settings) -> Result<(), String> {
+    let dir = paths::app_config_dir().ok_or("Не удалось определить папку настроек")?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| paths::io_error_message("Ошибка создания папки настроек", &e))?;
+    let content = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Ошибка сохранения настроек: {}", e))?;
+    std::fs::write(dir.join(SETTINGS_FILE), content)
+        .map_err(|e| paths::io_error_message("Ошибка сохранения настроек", &e))
+}
+
+/// Сколько копий хранить
+pub fn keep() -> usize {
+    settings().keep.min(MAX_KEEP)
+}
+
+/// Задаёт число хранимых копий
+pub fn set_keep(keep: usize) -> Result<(), String> {
+    if keep > MAX_KEEP {
+        return Err(format!("Можно хранить не больше {} копий", MAX_KEEP));
+    }
+    save_settings(&Settings { keep, ..settings() })
+}
+
+/// Проверяется ли каждая новая копия
+pub fn verify_enabled() -> bool {
+    settings().verify
+}
+
+/// Включает или выключает проверку копии после создания
+pub fn set_verify(verify: bool) -> Result<(), String> {
+    save_settings(&Settings { verify, ..settings() })
+}
+
+/// Имя файла без расширения и расширение
+fn split_name(path: &Path) -> Option<(String, String)> {
+    let path = paths::nfc(path);
+    let stem = path.file_stem()?.to_string_lossy().to_string();
+    let ext = path.extension().map(|e| e.to_string_lossy().to_string()).unwrap_or_default();
+    Some((stem, ext))
+}
+
+fn backup_dir(path: &Path) -> Option<PathBuf> {
+    path.parent().map(|dir| dir.join(BACKUP_DIR))
+}
+
+/// Время создания, если имя - копия этого файла
+fn backup_stamp(name: &str, stem: &str, ext: &str) -> Option<NaiveDateTime> {
+    let rest = name.strip_prefix(stem)?.strip_prefix('.')?;
+    let stamp = if ext.is_empty() { rest } else { rest.strip_suffix(ext)?.strip_suffix('.')? };
+    NaiveDateTime::parse_from_str(stamp, STAMP_FORMAT).ok()
+}
+
+/// Копии файла, новые первыми
+fn backups_of(path: &Path) -> Vec<(PathBuf, NaiveDateTime)> {
+    let (Some(dir), Some((stem, ext))) = (backup_dir(path), split_name(path)) else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(paths::to_fs_path(&dir)) else {
+        return Vec::new();
+    };
+    let mut list: Vec<(PathBuf, NaiveDateTime)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let stamp = backup_stamp(&paths::nfc(Path::new(&name)).to_string_lossy(), &stem, &ext)?;
+            Some((dir.join(name), stamp))
+        })
+        .collect();
+    list.sort_by_key(|b| std::cmp::Reverse(b.1));
+    list
+}
+
+/// Копирует существующий файл в .backups перед перезаписью и удаляет лишние копии
+pub fn rotate(path: &Path) -> Result<(), String> {
+    let keep = keep();
+    let source = paths::to_fs_path(path);
+    if keep == 0 || !source.is_file() {
+        return Ok(());
+    }
+    let (Some(dir), Some((stem, ext))) = (backup_dir(path), split_name(path)) else {
+        return Ok(());
+    };
+
+    std::fs::create_dir_all(paths::to_fs_path(&dir))
+        .map_err(|e| paths::io_error_message("Не удалось создать папку резервных копий", &e))?;
+    let stamp = Local::now().format(STAMP_FORMAT);
+    let name = if ext.is_empty() { format!("{}.{}", stem, stamp) } else { format!("{}.{}.{}", stem, stamp, ext) };
+    let backup = dir.join(name);
+    std::fs::copy(&source, paths::to_fs_path(&backup))
+        .map_err(|e| paths::io_error_message("Не удалось сохранить резервную копию прежней версии", &e))?;
+    integrity::copy(path, &backup);
+    if verify_enabled() {
+        check_copy(&source, &backup)?;
+    }
+
+    for (old, _) in backups_of(path).into_iter().skip(keep) {
+        let _ = std::fs::remove_file(paths::to_fs_path(&old));
+        integrity::forget(&old);
+    }
+    Ok(())
+}
+
+/// Перечитывает свежую копию и сверяет с исходным файлом. Испорченная копия
+/// удаляется, а перезапись отменяется: иначе прежняя версия осталась бы без копии
+fn check_copy(source: &Path, backup: &Path) -> Result<(), String> {
+    let read = |path: &Path| std::fs::read(path).map(|bytes| integrity::digest(&bytes));
+    let same = match (read(source), read(&paths::to_fs_path(backup))) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    };
+    if same {
+        return Ok(());
+    }
+    let _ = std::fs::remove_file(paths::to_fs_path(backup));
+    integrity::forget(backup);
+    Err("Резервная копия прежней версии записалась с ошибкой, файл не перезаписан. Проверьте диск".into())
+}
+
+/// Путь копии, если это копия файла path
+pub fn find(path: &Path, backup: &Path) -> Result<PathBuf, String> {
+    backups_of(path)
+        .into_iter()
+        .map(|(b, _)| b)
+        .find(|b| paths::same_path(b, backup))
+        .ok_or_else(|| "Резервная копия не найдена или относится к другому файлу".into())
+}
+
+/// Резервные копии файла, новые первыми
+pub fn list(path: &Path) -> Vec<BackupInfo> {
+    backups_of(path)
+        .into_iter()
+        .map(|(backup, stamp)| BackupInfo {
+            size: std::fs::metadata(paths::to_fs_path(&backup)).map(|m| m.len()).unwrap_or(0),
+            path: backup.to_string_lossy().to_string(),
+            created: stamp.format("%d.%m.%Y %H:%M:%S").to_string(),
+        })
+        .collect()
+}
+
+/// Имя исходного файла по имени копии «имя.ГГГГММДД-ЧЧММСС-мс.расширение»
+fn source_name(name: &str) -> Option<String> {
+    let parts: Vec<&str> = name.split('.').collect();
+    let is_stamp = |part: &str| NaiveDateTime::parse_from_str(part, STAMP_FORMAT).is_ok();
+    let n = parts.len();
+    if n >= 3 && is_stamp(parts[n - 2]) {
+        Some(format!("{}.{}", parts[..n - 2].join("."), parts[n - 1]))
+    } else if n >= 2 && is_stamp(parts[n - 1]) {
+        Some(parts[..n - 1].join("."))
+    } else {
+        None
+    }
+}
+
+/// Удаляет из .backups папки folder копии файлов, которых в папке больше нет
+/// (файл удалили или переименовали). Возвращает число удалённых копий и их размер
+pub fn prune_orphans(folder: &Path) -> (usize, u64) {
+    let dir = folder.join(BACKUP_DIR);
+    let Ok(entries) = std::fs::read_dir(paths::to_fs_path(&dir)) else {
+        return (0, 0);
+    };
+    let (mut files, mut bytes) = (0, 0);
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        // Контрольные суммы («.имя.sha256») удаляются вместе со своей копией
+        let Some(source) = source_name(&name).filter(|_| !name.starts_with('.')) else {
+            continue;
+        };
+        if paths::to_fs_path(&folder.join(&source)).exists() {
+            continue;
+        }
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        let backup = dir.join(&name);
+        if std::fs::remove_file(paths::to_fs_path(&backup)).is_ok() {
+            integrity::forget(&backup);
+            files += 1;
+            bytes += size;
+        }
+    }
+    // Пустая папка копий не нужна; непустую remove_dir не тронет
+    let _ = std::fs::remove_dir(paths::to_fs_path(&dir));
+    (files, bytes)
+}
+
+/// Восстанавливает файл из копии. Текущая версия перед этим сама попадает в копии,
+/// поэтому восстановление можно отменить
+pub fn restore(path: &Path, backup: &Path) -> Result<(), String> {
+    let backup = find(path, backup)?;
+    // Копия читается до ротации: при полном наборе ротация могла бы удалить её саму
+    let content = std::fs::read(paths::to_fs_path(&backup))
+        .map_err(|e| paths::io_error_message("Ошибка чтения резервной копии", &e))?;
+    rotate(path)?;
+    std::fs::write(paths::to_fs_path(path), content)
+        .map_err(|e| paths::io_error_message("Ошибка восстановления из резервной копии", &e))
+}
@@ -0,0 +1,302 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// График звонков: рабочие периоды смены и перерывы между ними. Вариант дня задаёт
+// смену, дни недели, начало первого периода, длительность периодов и перерывов;
+// сокращённые дни (например, суббота) - отдельный вариант с другими длительностями.
+// Вариант может действовать только по неделям-числителям или знаменателям.
+// По графику считаются конкретные начало и окончание каждого периода - для дня
+// недели и для каждой даты диапазона - и выгружается таблица звонков.
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::export::report::{Cell, Row, Table};
+
+const DATE_FORMAT: &str = "%d.%m.%Y";
+
+const MAX_PERIODS: usize = 16;
+const MAX_RANGE_DAYS: i64 = 366;
+
+const WEEKDAYS: [&str; 7] = ["пн", "вт", "ср", "чт", "пт", "сб", "вс"];
+
+fn default_period() -> u32 {
+    45
+}
+
+fn default_break() -> u32 {
+    10
+}
+
+/// Недели, по которым действует вариант дня
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Week {
+    #[default]
+    Every,
+    /// Числитель
+    Numerator,
+    /// Знаменатель
+    Denominator,
+}
+
+impl Week {
+    fn label(self) -> &'static str {
+        match self {
+            Week::Every => "",
+            Week::Numerator => ", числитель",
+            Week::Denominator => ", знаменатель",
+        }
+    }
+
+    // Варианты с такими неделями могут прийтись на один день
+    fn overlaps(self, other: Week) -> bool {
+        self == Week::Every || other == Week::Every || self == other
+    }
+}
+
+/// Вариант дня
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DayVariant {
+    /// Название: «Будни», «Суббота»
+    pub name: String,
+    /// Смена; в разных сменах дни недели могут совпадать
+    #[serde(default)]
+    pub shift: String,
+    /// Дни недели: 1 - понедельник
+    pub weekdays: Vec<u8>,
+    /// Недели: каждая, только числитель или только знаменатель
+    #[serde(default)]
+    pub week: Week,
+    /// Начало первого периода ЧЧ:ММ
+    pub start: String,
+    pub periods: usize,
+    /// Длительность периода по умолчанию, минуты
+    #[serde(default = "default_period")]
+    pub period_minutes: u32,
+    /// Длительности отдельных периодов по порядку; недостающие - по умолчанию
+    #[serde(default)]
+    pub lengths: Vec<u32>,
+    /// Перерыв по умолчанию, минуты
+    #[serde(default = "default_break")]
+    pub break_minutes: u32,
+    /// Перерывы после каждого периода по порядку; недостающие - по умолчанию
+    #[serde(default)]
+    pub breaks: Vec<u32>,
+}
+
+/// График звонков
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BellSchedule {
+    #[serde(default)]
+    pub name: String,
+    pub variants: Vec<DayVariant>,
+    /// Любой день недели-числителя дд.мм.гггг; по умолчанию числитель - нечётные недели года
+    #[serde(default)]
+    pub numerator_week: Option<String>,
+}
+
+/// Период с конкретным временем
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Period {
+    /// Номер с 1
+    pub number: usize,
+    /// ЧЧ:ММ
+    pub start: String,
+    pub end: String,
+    pub minutes: u32,
+    /// Перерыв после периода; у последнего - 0
+    pub break_after: u32,
+}
+
+/// Звонки варианта дня
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VariantTimes {
+    pub name: String,
+    pub shift: String,
+    pub weekdays: Vec<u8>,
+    pub week: Week,
+    pub periods: Vec<Period>,
+}
+
+/// Звонки конкретного дня и смены
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DayTimes {
+    /// дд.мм.гггг
+    pub date: String,
+    pub variant: String,
+    pub shift: String,
+    pub periods: Vec<Period>,
+}
+
+impl DayVariant {
+    /// Начало и окончание периодов; день не должен переходить через полночь
+    fn times(&self) -> Result<Vec<Period>, String> {
+        let prefix = format!("Вариант «{}»", self.name);
+        if self.periods == 0 || self.periods > MAX_PERIODS {
+            return Err(format!("{}: число периодов - от 1 до {}", prefix, MAX_PERIODS));
+        }
+        if self.weekdays.is_empty() || self.weekdays.iter().any(|d| !(1..=7).contains(d)) {
+            return Err(format!("{}: дни недели - числа от 1 (понедельник) до 7", prefix));
+        }
+        let start = NaiveTime::parse_from_str(self.start.trim(), "%H:%M")
+            .map_err(|_| format!("{}: начало «{}» не в формате ЧЧ:ММ", prefix, self.start))?;
+
+        let day = NaiveDate::default();
+        let mut at = day.and_time(start);
+        let mut periods = Vec::with_capacity(self.periods);
+        for i in 0..self.periods {
+            let minutes = self.lengths.get(i).copied().unwrap_or(self.period_minutes);
+            if minutes == 0 {
+                return Err(format!("{}: период {} нулевой длительности", prefix, i + 1));
+            }
+            let last = i + 1 == self.periods;
+            let break_after = if last { 0 } else { self.breaks.get(i).copied().unwrap_or(self.break_minutes) };
+            let end = at + Duration::minutes(minutes as i64);
+            if end.date() != day {
+                return Err(format!("{}: период {} заканчивается после полуночи", prefix, i + 1));
+            }
+            periods.push(Period {
+                number: i + 1,
+                start: at.format("%H:%M").to_string(),
+                end: end.format("%H:%M").to_string(),
+                minutes,
+                break_after,
+            });
+            at = end + Duration::minutes(break_after as i64);
+        }
+        Ok(periods)
+    }
+}
+
+impl VariantTimes {
+    /// Действует ли вариант в дату date недели week (числитель или знаменатель)
+    pub fn applies_on(&self, date: NaiveDate, week: Week) -> bool {
+        self.weekdays.contains(&(date.weekday().number_from_monday() as u8)) && self.week.overlaps(week)
+    }
+}
+
+impl BellSchedule {
+    fn check(&self) -> Result<(), String> {
+        if self.variants.is_empty() {
+            return Err("В графике звонков нет ни одного варианта дня".into());
+        }
+        for (i, a) in self.variants.iter().enumerate() {
+            for b in &self.variants[i + 1..] {
+                if a.shift.trim() == b.shift.trim()
+                    && a.week.overlaps(b.week)
+                    && a.weekdays.iter().any(|d| b.weekdays.contains(d))
+                {
+                    return Err(format!(
+                        "Варианты «{}» и «{}» одной смены приходятся на один день недели",
+                        a.name, b.name
+                    ));
+                }
+            }
+        }
+        self.numerator_monday()?;
+        Ok(())
+    }
+
+    fn numerator_monday(&self) -> Result<Option<NaiveDate>, String> {
+        let Some(value) = self.numerator_week.as_deref().map(str::trim).filter(|v| !v.is_empty()) else {
+            return Ok(None);
+        };
+        let day = NaiveDate::parse_from_str(value, DATE_FORMAT)
+            .map_err(|_| format!("Дата «{}» не в формате дд.мм.гггг", value))?;
+        Ok(Some(day.week(Weekday::Mon).first_day()))
+    }
+
+    /// Числитель или знаменатель - неделя даты date
+    pub fn week_of(&self, date: NaiveDate) -> Result<Week, String> {
+        let numerator = match self.numerator_monday()? {
+            Some(monday) => (date.week(Weekday::Mon).first_day() - monday).num_weeks() % 2 == 0,
+            None => date.iso_week().week() % 2 == 1,
+        };
+        Ok(if numerator { Week::Numerator } else { Week::Denominator })
+    }
+
+    /// Звонки каждого варианта дня
+    pub fn times(&self) -> Result<Vec<VariantTimes>, String> {
+        self.check()?;
+        self.variants
+            .iter()
+            .map(|v| {
+                Ok(VariantTimes {
+                    name: v.name.clone(),
+                    shift: v.shift.clone(),
+                    weekdays: v.weekdays.clone(),
+                    week: v.week,
+                    periods: v.times()?,
+                })
+            })
+            .collect()
+    }
+
+    /// Звонки каждого дня диапазона дд.мм.гггг включительно, по сменам
+    pub fn days(&self, from: &str, to: &str) -> Result<Vec<DayTimes>, String> {
+        let parse = |value: &str| {
+            NaiveDate::parse_from_str(value.trim(), DATE_FORMAT)
+                .map_err(|_| format!("Дата «{}» не в формате дд.мм.гггг", value))
+        };
+        let (from, to) = (parse(from)?, parse(to)?);
+        if to < from || (to - from).num_days() >= MAX_RANGE_DAYS {
+            return Err(format!("Диапазон дат - от одного дня до {} дней", MAX_RANGE_DAYS));
+        }
+        let variants = self.times()?;
+        let mut days = Vec::new();
+        for date in from.iter_days().take_while(|d| *d <= to) {
+            let week = self.week_of(date)?;
+            for variant in variants.iter().filter(|v| v.applies_on(date, week)) {
+                days.push(DayTimes {
+                    date: date.format(DATE_FORMAT).to_string(),
+                    variant: variant.name.clone(),
+                    shift: variant.shift.clone(),
+                    periods: variant.periods.clone(),
+                });
+            }
+        }
+        Ok(days)
+    }
+
+    /// Таблица звонков для выгрузки: лист на каждый вариант дня
+    pub fn tables(&self) -> Result<Vec<Table>, String> {
+        let title = if self.name.trim().is_empty() { "График звонков" } else { self.name.trim() };
+        Ok(self
+            .times()?
+            .into_iter()
+            .map(|v| {
+                let days: Vec<&str> = v.weekdays.iter().map(|&d| WEEKDAYS[d as usize - 1]).collect();
+                let mut shift = if v.shift.trim().is_empty() { String::new() } else { format!(", {}", v.shift.trim()) };
+                shift.push_str(v.week.label());
+                Table {
+                    name: format!("{}{}", v.name, shift),
+                    title: format!("{}: {}{} ({})", title, v.name, shift, days.join(", ")),
+                    headers: ["№", "Начало", "Окончание", "Длительность, мин", "Перерыв, мин"]
+                        .map(String::from)
+                        .to_vec(),
+                    widths: vec![6.0, 12.0, 12.0, 18.0, 14.0],
+                    rows: v
+                        .periods
+                        .iter()
+                        .map(|p| Row {
+                            cells: vec![
+                                Cell::Number(p.number as f64),
+                                Cell::Text(p.start.clone()),
+                                Cell::Text(p.end.clone()),
+                                Cell::Number(p.minutes as f64),
+                                if p.break_after > 0 { Cell::Number(p.break_after as f64) } else { Cell::Empty },
+                            ],
+                            marked: false,
+                        })
+                        .collect(),
+                }
+            })
+            .collect())
+    }
+}
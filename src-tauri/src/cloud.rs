@@ -0,0 +1,84 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Файлы-заглушки облачных папок (OneDrive, Яндекс.Диск, iCloud): содержимое хранится
+// только в облаке и скачивается при первом чтении.
+
+use std::fs::Metadata;
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
+
+// Сколько ждём загрузки файла из облака при чтении
+const HYDRATION_TIMEOUT: Duration = Duration::from_secs(60);
+
+// Атрибуты Windows Cloud Files API
+#[cfg(windows)]
+const FILE_ATTRIBUTE_OFFLINE: u32 = 0x1000;
+#[cfg(windows)]
+const FILE_ATTRIBUTE_RECALL_ON_OPEN: u32 = 0x40000;
+#[cfg(windows)]
+const FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS: u32 = 0x400000;
+
+// Флаг dataless-файла APFS (содержимое выгружено в облако)
+#[cfg(target_os = "macos")]
+const SF_DATALESS: u32 = 0x40000000;
+
+/// Файл является облачной заглушкой без локального содержимого
+#[cfg(windows)]
+pub fn is_placeholder(metadata: &Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    metadata.file_attributes()
+        & (FILE_ATTRIBUTE_OFFLINE | FILE_ATTRIBUTE_RECALL_ON_OPEN | FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS)
+        != 0
+}
+
+#[cfg(target_os = "macos")]
+pub fn is_placeholder(metadata: &Metadata) -> bool {
+    use std::os::macos::fs::MetadataExt;
+    metadata.st_flags() & SF_DATALESS != 0
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+pub fn is_placeholder(_metadata: &Metadata) -> bool {
+    false
+}
+
+/// Проверяет, не заменён ли отсутствующий файл заглушкой iCloud (.имя.icloud)
+pub fn is_icloud_stub(path: &Path) -> bool {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => parent
+            .join(format!(".{}.icloud", name.to_string_lossy()))
+            .exists(),
+        _ => false,
+    }
+}
+
+/// Сообщение об ошибке для файла, который есть только в облаке
+pub fn online_only_message(path: &Path) -> String {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    format!(
+        "Файл «{}» хранится только в облаке и не загружен на компьютер. \
+         Выберите для него «Всегда сохранять на этом устройстве» или дождитесь синхронизации и повторите",
+        name
+    )
+}
+
+/// Читает заглушку: чтение запускает загрузку из облака, ждём её не дольше HYDRATION_TIMEOUT.
+/// Если не успели, поток дочитывает файл в фоне и следующая попытка будет быстрой
+pub fn read_placeholder(path: &Path) -> Result<Vec<u8>, String> {
+    let (tx, rx) = mpsc::channel();
+    let owned = path.to_path_buf();
+    std::thread::spawn(move || {
+        let _ = tx.send(std::fs::read(owned));
+    });
+
+    match rx.recv_timeout(HYDRATION_TIMEOUT) {
+        Ok(Ok(bytes)) => Ok(bytes),
+        // Клиент синхронизации не запущен или нет сети
+        Ok(Err(_)) | Err(_) => Err(online_only_message(path)),
+    }
+}
@@ -0,0 +1,70 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Сжатие рабочих файлов в gzip (.json.gz, .xml.gz). Расписание за год с историей
+// в виде JSON с отступами не помещается в 10 МБ, а сжимается в десятки раз.
+// При чтении gzip распознаётся по сигнатуре, а не по расширению.
+
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+/// Наибольший размер распакованного содержимого: защита от gzip-бомб
+pub const MAX_UNPACKED_SIZE: usize = 100 * 1024 * 1024;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Содержимое сжато gzip
+pub fn is_gzip(bytes: &[u8]) -> bool {
+    bytes.starts_with(&GZIP_MAGIC)
+}
+
+/// Путь с расширением .gz
+pub fn is_gz_path(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("gz"))
+}
+
+/// Путь без .gz: по нему определяется формат содержимого (plan.json.gz -> plan.json)
+pub fn inner_path(path: &Path) -> PathBuf {
+    if is_gz_path(path) {
+        path.with_extension("")
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// Добавляет .gz к пути, если его нет
+pub fn gz_path(path: &Path) -> PathBuf {
+    if is_gz_path(path) {
+        return path.to_path_buf();
+    }
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".gz");
+    PathBuf::from(name)
+}
+
+pub fn compress(content: &[u8]) -> Result<Vec<u8>, String> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(content)
+        .and_then(|_| encoder.finish())
+        .map_err(|e| format!("Ошибка сжатия файла: {}", e))
+}
+
+pub fn decompress(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    GzDecoder::new(bytes)
+        .take(MAX_UNPACKED_SIZE as u64 + 1)
+        .read_to_end(&mut out)
+        .map_err(|_| "Сжатый файл повреждён".to_string())?;
+    if out.len() > MAX_UNPACKED_SIZE {
+        return Err(format!(
+            "Распакованный файл больше {} МБ",
+            MAX_UNPACKED_SIZE / 1024 / 1024
+        ));
+    }
+    Ok(out)
+}
@@ -0,0 +1,378 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Шифрование файлов расписания паролем: в них бывают персональные данные
+// исполнителей. Содержимое шифруется AES-256-GCM случайным ключом данных, а ключ
+// данных хранится в заголовке дважды: зашифрованным ключом из пароля (Argon2id) и
+// ключом восстановления. Ключ восстановления - 160 случайных бит, показывается один
+// раз при шифровании для печати; по нему открывается файл с забытым паролем.
+// Формат файла (.json.enc, .xml.enc):
+//
+//   TTENC | версия 2 | m_cost, t_cost, p_cost (u32 LE) | слот пароля | слот ключа
+//   восстановления | nonce (12) | шифртекст с тегом
+//
+// Слот - соль (16) | nonce (12) | ключ данных с тегом (48). Ключ слота пароля
+// выводится Argon2id из пароля и соли, ключ слота восстановления - SHA-256 от соли
+// и ключа восстановления: в нём достаточно случайности, медленный вывод не нужен.
+// Заголовок целиком передаётся в GCM как связанные данные, поэтому подмена
+// параметров Argon2, соли или слотов тоже обнаруживается при расшифровке.
+//
+// Поддерживается только версия 2: файлы версии 1 (ключ из пароля напрямую, без ключа
+// восстановления) не открываются.
+
+use std::path::{Path, PathBuf};
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// Код ошибки «неверный пароль» в начале сообщения
+pub const WRONG_PASSWORD: &str = "WRONG_PASSWORD";
+
+/// Наименьшая длина пароля
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+const MAGIC: &[u8; 5] = b"TTENC";
+const FORMAT_VERSION: u8 = 2;
+const SALT_SIZE: usize = 16;
+const NONCE_SIZE: usize = 12;
+const KEY_SIZE: usize = 32;
+const TAG_SIZE: usize = 16;
+const PARAMS_AT: usize = MAGIC.len() + 1;
+const SLOT_SIZE: usize = SALT_SIZE + NONCE_SIZE + KEY_SIZE + TAG_SIZE;
+const PASSWORD_SLOT_AT: usize = PARAMS_AT + 12;
+const RECOVERY_SLOT_AT: usize = PASSWORD_SLOT_AT + SLOT_SIZE;
+const HEADER_SIZE: usize = RECOVERY_SLOT_AT + SLOT_SIZE + NONCE_SIZE;
+
+// Ключ восстановления: 20 байт в base32, группами по 4 символа
+const RECOVERY_KEY_SIZE: usize = 20;
+const RECOVERY_GROUP: usize = 4;
+const BASE32: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const RECOVERY_CONTEXT: &[u8] = b"time-to-table recovery key";
+
+// Параметры Argon2id при шифровании: 64 МБ памяти, 3 прохода
+const M_COST_KIB: u32 = 64 * 1024;
+const T_COST: u32 = 3;
+const P_COST: u32 = 1;
+
+// Пределы параметров из чужого файла: иначе подобранный заголовок
+// заставил бы приложение выделить гигабайты памяти
+const MAX_M_COST_KIB: u32 = 512 * 1024;
+const MAX_T_COST: u32 = 16;
+const MAX_P_COST: u32 = 8;
+
+/// Путь с расширением .enc
+pub fn is_enc_path(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("enc"))
+}
+
+/// Путь без .enc: по нему определяется формат содержимого (plan.json.enc -> plan.json)
+pub fn inner_path(path: &Path) -> PathBuf {
+    if is_enc_path(path) {
+        path.with_extension("")
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// Сохранённый зашифрованный файл
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptedFile {
+    pub path: String,
+    /// Новый ключ восстановления для печати; null - прежний ключ файла действует
+    pub recovery_key: Option<String>,
+}
+
+/// Результат шифрования
+pub struct Sealed {
+    pub bytes: Vec<u8>,
+    pub recovery_key: Option<String>,
+}
+
+// Заголовок без nonce содержимого и ключ данных: при повторном сохранении тем же
+// паролем слоты переносятся без изменений
+struct Keys {
+    prefix: Vec<u8>,
+    data_key: Vec<u8>,
+}
+
+fn random<const N: usize>() -> Result<[u8; N], String> {
+    let mut bytes = [0u8; N];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("Ошибка шифрования: {}", e))?;
+    Ok(bytes)
+}
+
+fn derive_key(password: &str, salt: &[u8], m_cost: u32, t_cost: u32, p_cost: u32) -> Result<Vec<u8>, String> {
+    let params = Params::new(m_cost, t_cost, p_cost, Some(KEY_SIZE))
+        .map_err(|e| format!("Некорректные параметры шифрования: {}", e))?;
+    let mut key = vec![0u8; KEY_SIZE];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Ошибка вычисления ключа: {}", e))?;
+    Ok(key)
+}
+
+fn recovery_slot_key(secret: &[u8], salt: &[u8]) -> Vec<u8> {
+    Sha256::new().chain_update(RECOVERY_CONTEXT).chain_update(salt).chain_update(secret).finalize().to_vec()
+}
+
+fn cipher(key: &[u8]) -> Result<Aes256Gcm, String> {
+    Aes256Gcm::new_from_slice(key).map_err(|e| format!("Ошибка шифрования: {}", e))
+}
+
+/// Слот: соль, nonce и ключ данных, зашифрованный ключом слота
+fn seal_slot(slot_key: &[u8], salt: &[u8], data_key: &[u8]) -> Result<Vec<u8>, String> {
+    let nonce: [u8; NONCE_SIZE] = random()?;
+    let wrapped = cipher(slot_key)?
+        .encrypt(Nonce::from_slice(&nonce), data_key)
+        .map_err(|_| "Ошибка шифрования".to_string())?;
+    Ok([salt, &nonce, &wrapped].concat())
+}
+
+/// Ключ данных из слота; None - ключ слота не подходит
+fn open_slot(slot_key: &[u8], slot: &[u8]) -> Option<Vec<u8>> {
+    let nonce = &slot[SALT_SIZE..SALT_SIZE + NONCE_SIZE];
+    cipher(slot_key).ok()?.decrypt(Nonce::from_slice(nonce), &slot[SALT_SIZE + NONCE_SIZE..]).ok()
+}
+
+fn check_password(password: &str) -> Result<(), String> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(format!("Пароль должен быть не короче {} символов", MIN_PASSWORD_LENGTH));
+    }
+    Ok(())
+}
+
+fn params_header() -> Vec<u8> {
+    let mut header = Vec::with_capacity(HEADER_SIZE);
+    header.extend_from_slice(MAGIC);
+    header.push(FORMAT_VERSION);
+    for value in [M_COST_KIB, T_COST, P_COST] {
+        header.extend_from_slice(&value.to_le_bytes());
+    }
+    header
+}
+
+/// Слот пароля с новой солью
+fn password_slot(password: &str, data_key: &[u8]) -> Result<Vec<u8>, String> {
+    let salt: [u8; SALT_SIZE] = random()?;
+    let key = derive_key(password, &salt, M_COST_KIB, T_COST, P_COST)?;
+    seal_slot(&key, &salt, data_key)
+}
+
+/// Новый ключ данных, слоты пароля и восстановления; второе значение - ключ восстановления
+fn new_keys(password: &str) -> Result<(Keys, String), String> {
+    let data_key: [u8; KEY_SIZE] = random()?;
+    let secret: [u8; RECOVERY_KEY_SIZE] = random()?;
+    let salt: [u8; SALT_SIZE] = random()?;
+
+    let mut prefix = params_header();
+    prefix.extend_from_slice(&password_slot(password, &data_key)?);
+    prefix.extend_from_slice(&seal_slot(&recovery_slot_key(&secret, &salt), &salt, &data_key)?);
+    Ok((Keys { prefix, data_key: data_key.to_vec() }, format_recovery_key(&secret)))
+}
+
+/// Шифрует содержимое ключом данных с новым nonce
+fn seal(content: &[u8], keys: &Keys) -> Result<Vec<u8>, String> {
+    let nonce: [u8; NONCE_SIZE] = random()?;
+    let mut header = keys.prefix.clone();
+    header.extend_from_slice(&nonce);
+    let sealed = cipher(&keys.data_key)?
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: content, aad: &header })
+        .map_err(|_| "Ошибка шифрования".to_string())?;
+    header.extend_from_slice(&sealed);
+    Ok(header)
+}
+
+/// Шифрует содержимое паролем. previous - прежнее содержимое файла: если оно
+/// открывается тем же паролем, ключ данных и слот восстановления переносятся, и
+/// напечатанный ключ восстановления остаётся действительным. Иначе создаётся новый
+/// ключ восстановления, он возвращается в recovery_key
+pub fn encrypt(content: &[u8], password: &str, previous: Option<&[u8]>) -> Result<Sealed, String> {
+    check_password(password)?;
+    let (keys, recovery_key) = match previous.and_then(|bytes| unlock_password(bytes, password).ok()) {
+        Some(keys) => (keys, None),
+        None => {
+            let (keys, recovery_key) = new_keys(password)?;
+            (keys, Some(recovery_key))
+        }
+    };
+    Ok(Sealed { bytes: seal(content, &keys)?, recovery_key })
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+/// Параметры Argon2 из заголовка; версия формата должна быть FORMAT_VERSION
+fn header(bytes: &[u8]) -> Result<(u32, u32, u32), String> {
+    if bytes.len() <= MAGIC.len() || !bytes.starts_with(MAGIC) {
+        return Err("Файл не является зашифрованным расписанием".into());
+    }
+    match bytes[MAGIC.len()] {
+        FORMAT_VERSION => {}
+        version if version < FORMAT_VERSION => {
+            return Err("Файл зашифрован прежней версией формата, которая больше не поддерживается".into())
+        }
+        _ => return Err("Файл зашифрован более новой версией приложения, обновите программу".into()),
+    }
+    if bytes.len() < HEADER_SIZE + TAG_SIZE {
+        return Err("Файл не является зашифрованным расписанием".into());
+    }
+    let (m_cost, t_cost, p_cost) =
+        (read_u32(bytes, PARAMS_AT), read_u32(bytes, PARAMS_AT + 4), read_u32(bytes, PARAMS_AT + 8));
+    if m_cost > MAX_M_COST_KIB || t_cost > MAX_T_COST || p_cost > MAX_P_COST {
+        return Err("Недопустимые параметры шифрования в файле".into());
+    }
+    Ok((m_cost, t_cost, p_cost))
+}
+
+/// Ключ данных файла по паролю
+fn unlock_password(bytes: &[u8], password: &str) -> Result<Keys, String> {
+    let (m_cost, t_cost, p_cost) = header(bytes)?;
+    let slot = &bytes[PASSWORD_SLOT_AT..RECOVERY_SLOT_AT];
+    let key = derive_key(password, &slot[..SALT_SIZE], m_cost, t_cost, p_cost)?;
+    let data_key = open_slot(&key, slot).ok_or_else(|| format!("{}: неверный пароль", WRONG_PASSWORD))?;
+    Ok(Keys { prefix: bytes[..HEADER_SIZE - NONCE_SIZE].to_vec(), data_key })
+}
+
+/// Ключ данных файла по ключу восстановления
+fn unlock_recovery(bytes: &[u8], recovery_key: &str) -> Result<Keys, String> {
+    header(bytes)?;
+    let secret = parse_recovery_key(recovery_key)?;
+    let slot = &bytes[RECOVERY_SLOT_AT..HEADER_SIZE - NONCE_SIZE];
+    let data_key = open_slot(&recovery_slot_key(&secret, &slot[..SALT_SIZE]), slot)
+        .ok_or_else(|| format!("{}: неверный ключ восстановления", WRONG_PASSWORD))?;
+    Ok(Keys { prefix: bytes[..HEADER_SIZE - NONCE_SIZE].to_vec(), data_key })
+}
+
+/// Расшифровывает содержимое файла подходящим ключом данных
+fn open(bytes: &[u8], keys: &Keys) -> Result<Vec<u8>, String> {
+    let (header, sealed) = bytes.split_at(HEADER_SIZE);
+    cipher(&keys.data_key)?
+        .decrypt(Nonce::from_slice(&header[HEADER_SIZE - NONCE_SIZE..]), Payload { msg: sealed, aad: header })
+        .map_err(|_| "Файл повреждён: содержимое не прошло проверку подлинности".to_string())
+}
+
+/// Расшифровывает содержимое. Неверный пароль - ошибка с кодом WRONG_PASSWORD
+pub fn decrypt(bytes: &[u8], password: &str) -> Result<Vec<u8>, String> {
+    open(bytes, &unlock_password(bytes, password)?)
+}
+
+/// Открывает файл ключом восстановления и задаёт новый пароль. Возвращает
+/// расшифрованное содержимое и файл, зашифрованный заново: ключ данных и слот
+/// восстановления прежние, поэтому напечатанный ключ остаётся действительным.
+/// Неверный ключ - ошибка с кодом WRONG_PASSWORD
+pub fn recover(bytes: &[u8], recovery_key: &str, new_password: &str) -> Result<(Vec<u8>, Vec<u8>), String> {
+    check_password(new_password)?;
+    let keys = unlock_recovery(bytes, recovery_key)?;
+    let content = open(bytes, &keys)?;
+
+    let mut prefix = bytes[..PASSWORD_SLOT_AT].to_vec();
+    prefix.extend_from_slice(&password_slot(new_password, &keys.data_key)?);
+    prefix.extend_from_slice(&bytes[RECOVERY_SLOT_AT..HEADER_SIZE - NONCE_SIZE]);
+    let resealed = seal(&content, &Keys { prefix, data_key: keys.data_key })?;
+    Ok((content, resealed))
+}
+
+/// Ключ восстановления для печати: base32 группами по 4 символа через дефис
+fn format_recovery_key(secret: &[u8]) -> String {
+    let mut chars = Vec::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+    for &byte in secret {
+        buffer = (buffer << 8) | u32::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            chars.push(BASE32[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    chars.chunks(RECOVERY_GROUP).map(|g| g.iter().collect::<String>()).collect::<Vec<_>>().join("-")
+}
+
+/// Разбирает ключ восстановления: регистр, пробелы и дефисы не важны, цифры 0, 1 и 8
+/// читаются как похожие буквы O, I и B
+fn parse_recovery_key(text: &str) -> Result<Vec<u8>, String> {
+    let invalid = || "Ключ восстановления введён с ошибкой: проверьте символы".to_string();
+    let mut secret = Vec::with_capacity(RECOVERY_KEY_SIZE);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in text.chars().filter(|c| !c.is_whitespace() && *c != '-') {
+        let c = match c.to_ascii_uppercase() {
+            '0' => 'O',
+            '1' => 'I',
+            '8' => 'B',
+            c => c,
+        };
+        let value = BASE32.iter().position(|&b| b as char == c).ok_or_else(invalid)?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            secret.push((buffer >> bits) as u8);
+        }
+    }
+    if secret.len() != RECOVERY_KEY_SIZE || bits != 0 {
+        return Err(invalid());
+    }
+    Ok(secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT: &[u8] = r#"{"entries":[{"title":"Техкарта"}]}"#.as_bytes();
+    const PASSWORD: &str = "правильный пароль";
+
+    #[test]
+    fn round_trip() {
+        let sealed = encrypt(CONTENT, PASSWORD, None).unwrap();
+        assert!(sealed.recovery_key.is_some());
+        assert!(sealed.bytes.starts_with(MAGIC));
+        assert_eq!(decrypt(&sealed.bytes, PASSWORD).unwrap(), CONTENT);
+    }
+
+    #[test]
+    fn wrong_password() {
+        let sealed = encrypt(CONTENT, PASSWORD, None).unwrap();
+        let error = decrypt(&sealed.bytes, "неправильный пароль").unwrap_err();
+        assert!(error.starts_with(WRONG_PASSWORD), "{}", error);
+    }
+
+    // Изменённые байты - повреждение, а не неверный пароль, и в содержимом, и в заголовке
+    #[test]
+    fn tampered_file() {
+        let sealed = encrypt(CONTENT, PASSWORD, None).unwrap();
+        let mut body = sealed.bytes.clone();
+        *body.last_mut().unwrap() ^= 1;
+        let error = decrypt(&body, PASSWORD).unwrap_err();
+        assert!(!error.starts_with(WRONG_PASSWORD), "{}", error);
+
+        let mut header = sealed.bytes;
+        header[HEADER_SIZE - 1] ^= 1;
+        assert!(decrypt(&header, PASSWORD).is_err());
+    }
+
+    #[test]
+    fn short_password() {
+        assert!(encrypt(CONTENT, "1234567", None).is_err());
+    }
+
+    // Повторное сохранение тем же паролем сохраняет ключ восстановления, и им
+    // можно открыть файл и задать новый пароль
+    #[test]
+    fn recovery_key() {
+        let first = encrypt(CONTENT, PASSWORD, None).unwrap();
+        let recovery_key = first.recovery_key.unwrap();
+        let second = encrypt(b"{}", PASSWORD, Some(&first.bytes)).unwrap();
+        assert!(second.recovery_key.is_none());
+
+        assert!(recover(&second.bytes, "AAAA-AAAA-AAAA-AAAA-AAAA-AAAA-AAAA-AAAA", "новый пароль").is_err());
+        let (content, resealed) = recover(&second.bytes, &recovery_key.to_lowercase(), "новый пароль").unwrap();
+        assert_eq!(content, b"{}");
+        assert_eq!(decrypt(&resealed, "новый пароль").unwrap(), b"{}");
+        assert!(decrypt(&resealed, PASSWORD).unwrap_err().starts_with(WRONG_PASSWORD));
+    }
+}
@@ -0,0 +1,164 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Определение съёмных носителей (USB-флешки) и сетевых дисков.
+
+use serde::Serialize;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::path::Path;
+
+/// Подключённый съёмный носитель
+#[derive(Debug, Clone, Serialize)]
+pub struct RemovableDrive {
+    /// Корень носителя (E:\, /media/user/FLASH, /Volumes/FLASH)
+    pub path: String,
+    /// Метка тома, если есть
+    pub label: String,
+}
+
+/// Признак подключённого сетевого диска Windows (Z: -> \\сервер\папка)
+#[cfg(windows)]
+pub fn is_remote_drive(letter: u8) -> bool {
+    use windows_sys::Win32::System::WindowsProgramming::DRIVE_REMOTE;
+    drive_type(letter) == DRIVE_REMOTE
+}
+
+#[cfg(not(windows))]
+pub fn is_remote_drive(_letter: u8) -> bool {
+    false
+}
+
+#[cfg(windows)]
+fn drive_type(letter: u8) -> u32 {
+    use windows_sys::Win32::Storage::FileSystem::GetDriveTypeW;
+
+    let root = wide(&format!("{}:\\", letter as char));
+    // SAFETY: root - корректная строка UTF-16 с завершающим нулём
+    unsafe { GetDriveTypeW(root.as_ptr()) }
+}
+
+#[cfg(windows)]
+fn wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(Some(0)).collect()
+}
+
+/// Список подключённых съёмных носителей
+#[cfg(windows)]
+pub fn removable_drives() -> Vec<RemovableDrive> {
+    use windows_sys::Win32::Storage::FileSystem::{GetLogicalDrives, GetVolumeInformationW};
+    use windows_sys::Win32::System::WindowsProgramming::DRIVE_REMOVABLE;
+
+    // SAFETY: функция без аргументов, возвращает битовую маску дисков
+    let mask = unsafe { GetLogicalDrives() };
+    (0..26u8)
+        .filter(|i| mask & (1 << i) != 0)
+        .map(|i| b'A' + i)
+        .filter(|&letter| drive_type(letter) == DRIVE_REMOVABLE)
+        .map(|letter| {
+            let root = format!("{}:\\", letter as char);
+            let root_w = wide(&root);
+            let mut label = [0u16; 261];
+            // SAFETY: буфер метки передан вместе с его длиной, необязательные параметры - null
+            let ok = unsafe {
+                GetVolumeInformationW(
+                    root_w.as_ptr(),
+                    label.as_mut_ptr(),
+                    label.len() as u32,
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                    0,
+                )
+            };
+            let label = if ok != 0 {
+                let len = label.iter().position(|&c| c == 0).unwrap_or(label.len());
+                String::from_utf16_lossy(&label[..len])
+            } else {
+                String::new()
+            };
+            RemovableDrive { path: root, label }
+        })
+        .collect()
+}
+
+/// Список подключённых съёмных носителей: точки монтирования устройств с флагом removable в sysfs
+#[cfg(target_os = "linux")]
+pub fn removable_drives() -> Vec<RemovableDrive> {
+    let Ok(mounts) = std::fs::read_to_string("/proc/mounts") else {
+        return Vec::new();
+    };
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let device = fields.next()?.strip_prefix("/dev/")?;
+            let mount_point = unescape_mount(fields.next()?);
+            is_removable_block(device).then(|| {
+                let label = Path::new(&mount_point)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                RemovableDrive { path: mount_point, label }
+            })
+        })
+        .collect()
+}
+
+// Раздел sdb1 -> устройство sdb; флаг /sys/block/<устройство>/removable
+#[cfg(target_os = "linux")]
+fn is_removable_block(device: &str) -> bool {
+    let disk: String = if device.starts_with("mmcblk") || device.starts_with("nvme") {
+        device.split('p').next().unwrap_or(device).to_string()
+    } else {
+        device.trim_end_matches(|c: char| c.is_ascii_digit()).to_string()
+    };
+    std::fs::read_to_string(format!("/sys/block/{}/removable", disk))
+        .map(|v| v.trim() == "1")
+        .unwrap_or(false)
+}
+
+// В /proc/mounts пробелы и спецсимволы записаны восьмеричными escape-последовательностями (\040)
+#[cfg(target_os = "linux")]
+fn unescape_mount(raw: &str) -> String {
+    let bytes = raw.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 3 < bytes.len() && bytes[i + 1..i + 4].iter().all(|b| (b'0'..=b'7').contains(b)) {
+            let code = bytes[i + 1..i + 4].iter().fold(0u32, |acc, b| acc * 8 + u32::from(b - b'0'));
+            out.push(code as u8);
+            i += 4;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+/// Список подключённых съёмных носителей: тома в /Volumes, кроме системного
+#[cfg(target_os = "macos")]
+pub fn removable_drives() -> Vec<RemovableDrive> {
+    let Ok(entries) = std::fs::read_dir("/Volumes") else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| {
+            // Системный том смонтирован в /Volumes как ссылка на /
+            std::fs::canonicalize(entry.path())
+                .map(|p| p != Path::new("/"))
+                .unwrap_or(false)
+        })
+        .map(|entry| RemovableDrive {
+            path: entry.path().to_string_lossy().to_string(),
+            label: entry.file_name().to_string_lossy().to_string(),
+        })
+        .collect()
+}
+
+#[cfg(not(any(windows, target_os = "linux", target_os = "macos")))]
+pub fn removable_drives() -> Vec<RemovableDrive> {
+    Vec::new()
+}
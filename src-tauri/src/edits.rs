@@ -0,0 +1,298 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// История правок открытых документов. Фронтенд не меняет расписание сам, а
+// отправляет правку (добавить, перенести, удалить операцию); бэкенд применяет её,
+// запоминает обратную правку и возвращает новое расписание. Стеки отмены живут в
+// состоянии приложения, поэтому после перезагрузки страницы фронтенд получает
+// документ и его историю обратно через edit_state.
+//
+// Документ - произвольный идентификатор от фронтенда (обычно путь к файлу).
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::{OperationRow, Schedule, ScheduleEntry};
+
+// Сколько правок помнить на документ
+const MAX_UNDO: usize = 200;
+
+/// Правка расписания, type - add, move или delete. entry и row - индексы записи истории и строки в ней
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", rename_all_fields = "camelCase")]
+pub enum Edit {
+    /// Добавить операцию; без index - в конец записи
+    Add { entry: usize, index: Option<usize>, row: Box<OperationRow> },
+    /// Перенести операцию на другое время и, если указан, к другому исполнителю
+    Move {
+        entry: usize,
+        row: usize,
+        worker: Option<String>,
+        start_date: String,
+        start_time: String,
+        end_date: String,
+        end_time: String,
+    },
+    /// Удалить операцию
+    Delete { entry: usize, row: usize },
+}
+
+impl Edit {
+    /// Подпись для меню «Отменить …»
+    fn label(&self) -> &'static str {
+        match self {
+            Edit::Add { .. } => "добавление операции",
+            Edit::Move { .. } => "перенос операции",
+            Edit::Delete { .. } => "удаление операции",
+        }
+    }
+}
+
+// Правка и обратная к ней
+#[derive(Debug, Clone)]
+struct Step {
+    edit: Edit,
+    inverse: Edit,
+}
+
+#[derive(Debug, Clone)]
+struct Document {
+    schedule: Schedule,
+    undo: Vec<Step>,
+    redo: Vec<Step>,
+    /// Растёт с каждой правкой, отменой и повтором
+    version: u64,
+    /// Версии, сохранённые пользователем и автосохранением
+    saved: u64,
+    autosaved: u64,
+    changed_at: Instant,
+}
+
+/// Документ, изменившийся после последнего автосохранения
+pub struct Unsaved {
+    pub document: String,
+    pub version: u64,
+    pub schedule: Schedule,
+}
+
+/// Состояние документа для фронтенда
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditState {
+    pub schedule: Schedule,
+    /// Что отменит следующая отмена и что вернёт повтор
+    pub undo: Option<&'static str>,
+    pub redo: Option<&'static str>,
+    pub undo_depth: usize,
+    pub redo_depth: usize,
+}
+
+impl Document {
+    fn new(schedule: Schedule) -> Self {
+        Document {
+            schedule,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            version: 0,
+            saved: 0,
+            autosaved: 0,
+            changed_at: Instant::now(),
+        }
+    }
+
+    fn touch(&mut self) {
+        self.version += 1;
+        self.changed_at = Instant::now();
+    }
+
+    fn state(&self) -> EditState {
+        EditState {
+            schedule: self.schedule.clone(),
+            undo: self.undo.last().map(|s| s.edit.label()),
+            redo: self.redo.last().map(|s| s.edit.label()),
+            undo_depth: self.undo.len(),
+            redo_depth: self.redo.len(),
+        }
+    }
+}
+
+/// Истории правок открытых документов, хранится в состоянии приложения
+#[derive(Default)]
+pub struct Edits {
+    documents: Mutex<HashMap<String, Document>>,
+}
+
+impl Edits {
+    fn with<T>(&self, document: &str, f: impl FnOnce(&mut Document) -> Result<T, String>) -> Result<T, String> {
+        let mut documents = self.documents.lock().map_err(|_| "Ошибка доступа к истории правок".to_string())?;
+        let doc = documents.get_mut(document).ok_or("Документ не открыт для правки")?;
+        f(doc)
+    }
+
+    /// Начинает историю документа; прежняя история того же документа сбрасывается
+    pub fn open(&self, document: String, schedule: Schedule) -> Result<EditState, String> {
+        let mut documents = self.documents.lock().map_err(|_| "Ошибка доступа к истории правок".to_string())?;
+        let doc = Document::new(schedule);
+        let state = doc.state();
+        documents.insert(document, doc);
+        Ok(state)
+    }
+
+    /// Открывает документ, восстановленный после сбоя: он считается изменённым,
+    /// пока его не сохранят, и снова попадёт в автосохранение
+    pub fn restore(&self, document: String, schedule: Schedule) -> Result<EditState, String> {
+        let mut documents = self.documents.lock().map_err(|_| "Ошибка доступа к истории правок".to_string())?;
+        let mut doc = Document::new(schedule);
+        doc.touch();
+        let state = doc.state();
+        documents.insert(document, doc);
+        Ok(state)
+    }
+
+    pub fn close(&self, document: &str) -> Result<(), String> {
+        let mut documents = self.documents.lock().map_err(|_| "Ошибка доступа к истории правок".to_string())?;
+        documents.remove(document);
+        Ok(())
+    }
+
+    pub fn state(&self, document: &str) -> Result<EditState, String> {
+        self.with(document, |doc| Ok(doc.state()))
+    }
+
+    /// Применяет правку; новая правка очищает стек повтора
+    pub fn apply(&self, document: &str, edit: Edit) -> Result<EditState, String> {
+        self.with(document, |doc| {
+            let inverse = apply(&mut doc.schedule, &edit)?;
+            doc.undo.push(Step { edit, inverse });
+            if doc.undo.len() > MAX_UNDO {
+                doc.undo.remove(0);
+            }
+            doc.redo.clear();
+            doc.touch();
+            Ok(doc.state())
+        })
+    }
+
+    pub fn undo(&self, document: &str) -> Result<EditState, String> {
+        self.with(document, |doc| {
+            let step = doc.undo.pop().ok_or("Нечего отменять")?;
+            apply(&mut doc.schedule, &step.inverse)?;
+            doc.redo.push(step);
+            doc.touch();
+            Ok(doc.state())
+        })
+    }
+
+    pub fn redo(&self, document: &str) -> Result<EditState, String> {
+        self.with(document, |doc| {
+            let step = doc.redo.pop().ok_or("Нечего повторять")?;
+            apply(&mut doc.schedule, &step.edit)?;
+            doc.undo.push(step);
+            doc.touch();
+            Ok(doc.state())
+        })
+    }
+
+    /// Повторяет все неотменённые правки на другой версии документа (например, заново
+    /// прочитанной с диска) и делает её текущей. Если правка не применяется, документ
+    /// не меняется
+    pub fn replay(&self, document: &str, base: Schedule) -> Result<EditState, String> {
+        self.with(document, |doc| {
+            let mut schedule = base;
+            let mut steps = Vec::with_capacity(doc.undo.len());
+            for (n, step) in doc.undo.iter().enumerate() {
+                let inverse = apply(&mut schedule, &step.edit).map_err(|e| format!("Правка {}: {}", n + 1, e))?;
+                steps.push(Step { edit: step.edit.clone(), inverse });
+            }
+            doc.schedule = schedule;
+            doc.undo = steps;
+            doc.redo.clear();
+            doc.touch();
+            Ok(doc.state())
+        })
+    }
+
+    /// Документы, изменённые после автосохранения, в которых правок не было
+    /// дольше quiet: пока пользователь правит, запись откладывается
+    pub fn unsaved(&self, quiet: Duration) -> Vec<Unsaved> {
+        let Ok(documents) = self.documents.lock() else {
+            return Vec::new();
+        };
+        documents
+            .iter()
+            .filter(|(_, doc)| doc.version != doc.autosaved && doc.changed_at.elapsed() >= quiet)
+            .map(|(id, doc)| Unsaved { document: id.clone(), version: doc.version, schedule: doc.schedule.clone() })
+            .collect()
+    }
+
+    /// Документ сохранён пользователем: текущая версия не нуждается в автосохранении
+    pub fn mark_saved(&self, document: &str) -> Result<(), String> {
+        self.with(document, |doc| {
+            doc.saved = doc.version;
+            doc.autosaved = doc.version;
+            Ok(())
+        })
+    }
+
+    /// В документе есть изменения, не сохранённые пользователем
+    pub fn is_dirty(&self, document: &str) -> bool {
+        self.with(document, |doc| Ok(doc.version != doc.saved)).unwrap_or(false)
+    }
+
+    /// Отмечает, что версия документа записана автосохранением
+    pub fn mark_autosaved(&self, document: &str, version: u64) {
+        if let Some(doc) = self.documents.lock().ok().as_mut().and_then(|d| d.get_mut(document)) {
+            doc.autosaved = version;
+        }
+    }
+}
+
+/// Применяет правку и возвращает обратную
+pub fn apply(schedule: &mut Schedule, edit: &Edit) -> Result<Edit, String> {
+    match edit {
+        Edit::Add { entry, index, row } => {
+            let rows = &mut entry_mut(schedule, *entry)?.rows;
+            let at = index.unwrap_or(rows.len());
+            if at > rows.len() {
+                return Err(format!("Нет позиции {} в записи", at + 1));
+            }
+            rows.insert(at, (**row).clone());
+            Ok(Edit::Delete { entry: *entry, row: at })
+        }
+        Edit::Move { entry, row, worker, start_date, start_time, end_date, end_time } => {
+            let target = row_mut(schedule, *entry, *row)?;
+            let inverse = Edit::Move {
+                entry: *entry,
+                row: *row,
+                worker: worker.as_ref().map(|_| target.worker.clone()),
+                start_date: std::mem::replace(&mut target.start_date, start_date.clone()),
+                start_time: std::mem::replace(&mut target.start_time, start_time.clone()),
+                end_date: std::mem::replace(&mut target.end_date, end_date.clone()),
+                end_time: std::mem::replace(&mut target.end_time, end_time.clone()),
+            };
+            if let Some(worker) = worker {
+                target.worker = worker.clone();
+            }
+            Ok(inverse)
+        }
+        Edit::Delete { entry, row } => {
+            row_mut(schedule, *entry, *row)?;
+            let removed = entry_mut(schedule, *entry)?.rows.remove(*row);
+            Ok(Edit::Add { entry: *entry, index: Some(*row), row: Box::new(removed) })
+        }
+    }
+}
+
+fn entry_mut(schedule: &mut Schedule, entry: usize) -> Result<&mut ScheduleEntry, String> {
+    schedule.entries.get_mut(entry).ok_or_else(|| format!("Нет записи истории {}", entry + 1))
+}
+
+fn row_mut(schedule: &mut Schedule, entry: usize, row: usize) -> Result<&mut OperationRow, String> {
+    entry_mut(schedule, entry)?
+        .rows
+        .get_mut(row)
+        .ok_or_else(|| format!("Нет операции {} в записи {}", row + 1, entry + 1))
+}
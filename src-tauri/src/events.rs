@@ -0,0 +1,53 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// События, которые Rust отправляет фронтенду, - все каналы в одном месте. Имя канала
+// закреплено за типом данных (Event::NAME), поэтому событие нельзя отправить в чужой
+// канал или с чужими данными. Данные - JSON в camelCase:
+//
+// «file-opened» (opening.rs) - OpenedFile: файл открыт перетаскиванием или из системы
+//   { path, format: "json" | "xml" | "xlsx" | "encrypted", text?, base64? }
+// «file-open-failed» (opening.rs) - OpenFailed: файл не прошёл проверки
+//   { path, message }
+// «file-changed» (watcher.rs) - FileChange: открытый файл изменила другая программа
+//   { path, kind: "modified" | "removed" }
+// «export://progress» (progress.rs) - ProgressEvent: ход длительной операции
+//   { operation?, percent, current, done, total }
+//
+// Новый канал добавляется сюда: имя - в реализации Event для типа данных, описание -
+// в список выше.
+
+use serde::Serialize;
+use tauri::Emitter;
+
+use crate::opening::{OpenFailed, OpenedFile};
+use crate::progress::ProgressEvent;
+use crate::watcher::FileChange;
+
+/// Данные события и канал, в который оно отправляется
+pub trait Event: Serialize + Clone {
+    /// Имя канала для listen во фронтенде
+    const NAME: &'static str;
+}
+
+impl Event for OpenedFile {
+    const NAME: &'static str = "file-opened";
+}
+
+impl Event for OpenFailed {
+    const NAME: &'static str = "file-open-failed";
+}
+
+impl Event for FileChange {
+    const NAME: &'static str = "file-changed";
+}
+
+impl Event for ProgressEvent {
+    const NAME: &'static str = "export://progress";
+}
+
+/// Отправляет событие фронтенду. Ошибка (окно уже закрыто) не мешает операции,
+/// о которой сообщает событие, поэтому пропускается
+pub fn emit<E: Event>(app: &tauri::AppHandle, event: E) {
+    let _ = app.emit(E::NAME, event);
+}
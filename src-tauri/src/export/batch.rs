@@ -0,0 +1,361 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Пакетная выгрузка: расписание делится на части (по записям истории или по исполнителям),
+// каждая часть сохраняется отдельным файлом.
+//
+// Выгрузку с идентификатором операции можно продолжить после сбоя (диск заполнен,
+// пропала сетевая папка) или отмены. Ход записывается в манифест в папке данных
+// приложения: расписание и параметры - один раз, отметка о готовом файле - после
+// каждого файла. Файл пишется во временный «.имя.part» и переименовывается, когда
+// записан целиком, поэтому в папке нет недописанных файлов: при ошибке удаляется
+// только временный. Файлы, которые уже были в папке, не перезаписываются: имя
+// получает номер «(2)», и выбранные имена запоминаются в манифесте. resume_export
+// пропускает готовые файлы, содержимое которых не изменилось, и дописывает остальные;
+// манифест удаляется, когда записаны все файлы.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::provenance::Provenance;
+use super::templates::ExportTemplate;
+use super::Format;
+use crate::integrity;
+use crate::model::Schedule;
+use crate::paths;
+
+// Папка манифестов в папке данных приложения
+const MANIFEST_DIR: &str = "exports";
+
+/// Запись одного файла выгрузки по пути
+pub type WriteFile<'a> = &'a dyn Fn(&Path, &[u8]) -> Result<(), String>;
+
+// Ограничение длины имени файла без расширения
+const MAX_NAME_CHARS: usize = 80;
+
+/// Как делить расписание на файлы
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Split {
+    /// Файл на каждую запись истории
+    #[default]
+    Entry,
+    /// Файл на каждого исполнителя
+    Worker,
+}
+
+/// Части расписания с названиями, из которых получаются имена файлов
+fn parts(schedule: &Schedule, split: Split) -> Vec<(String, Schedule)> {
+    match split {
+        Split::Entry => schedule
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let name = format!("{:02} {}", i + 1, entry.card_name());
+                (name, Schedule { entries: vec![entry.clone()], ..Default::default() })
+            })
+            .collect(),
+        Split::Worker => schedule
+            .workers()
+            .into_iter()
+            .map(|worker| {
+                let part = schedule.for_worker(&worker);
+                (worker, part)
+            })
+            .collect(),
+    }
+}
+
+/// Части расписания с именами файлов (без расширения). Имена не совпадают между
+/// собой без учёта регистра и не заняты: taken - есть ли уже файл с таким именем
+pub fn plan(schedule: &Schedule, split: Split, taken: impl Fn(&str) -> bool) -> Vec<(String, Schedule)> {
+    let mut used: Vec<String> = Vec::new();
+    parts(schedule, split)
+        .into_iter()
+        .map(|(name, part)| {
+            let base = file_stem(&name);
+            let mut unique = base.clone();
+            let mut n = 2;
+            // to_lowercase, а не eq_ignore_ascii_case: «Иванов» и «ИВАНОВ» в Windows - один файл
+            while used.contains(&unique.to_lowercase()) || taken(&unique) {
+                unique = format!("{} ({})", base, n);
+                n += 1;
+            }
+            used.push(unique.to_lowercase());
+            (unique, part)
+        })
+        .collect()
+}
+
+/// Имя файла из названия: недопустимые в Windows символы заменяются на «_»
+fn file_stem(name: &str) -> String {
+    let mut stem: String = name
+        .chars()
+        .map(|c| if c.is_control() || r#"<>:"/\|?*"#.contains(c) { '_' } else { c })
+        .take(MAX_NAME_CHARS)
+        .collect();
+    stem = stem.trim_end_matches(['.', ' ']).trim_start().to_string();
+    if stem.is_empty() {
+        stem = "расписание".into();
+    }
+    // Зарезервированные имена устройств (CON, NUL...) не годятся даже с расширением
+    if paths::check_file_name(std::path::Path::new(&stem)).is_err() {
+        stem = format!("_{}", stem);
+    }
+    stem
+}
+
+/// Файл выгрузки в манифесте
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Item {
+    /// Имя файла с расширением
+    file: String,
+    /// SHA-256 записанного файла; None - ещё не записан
+    hash: Option<String>,
+}
+
+/// Ход выгрузки без расписания: перезаписывается после каждого файла
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Progress {
+    operation: String,
+    dir: PathBuf,
+    format: Format,
+    split: Split,
+    template: Option<String>,
+    items: Vec<Item>,
+}
+
+/// Незавершённая выгрузка
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnfinishedExport {
+    pub operation: String,
+    pub dir: String,
+    pub format: Format,
+    pub done: usize,
+    pub total: usize,
+}
+
+/// Пакетная выгрузка: части расписания и ход записи
+pub struct Batch {
+    progress: Progress,
+    parts: Vec<Schedule>,
+    // Манифест сохраняется только у выгрузки с идентификатором
+    resumable: bool,
+}
+
+// Манифест и расписание выгрузки; имя - хеш идентификатора, чтобы идентификатор
+// фронтенда не становился путём
+fn manifest_paths(operation: &str) -> Result<(PathBuf, PathBuf), String> {
+    let dir = paths::app_data_dir().ok_or("Не удалось определить папку данных приложения")?.join(MANIFEST_DIR);
+    let name = &integrity::digest(operation.as_bytes())[..32];
+    Ok((dir.join(format!("{}.json", name)), dir.join(format!("{}.schedule.json", name))))
+}
+
+fn write_json(path: &Path, value: &impl Serialize) -> Result<(), String> {
+    let content = serde_json::to_vec(value).map_err(|e| format!("Ошибка сохранения хода выгрузки: {}", e))?;
+    // Запись с заменой: оборванная запись не портит прежний манифест
+    let temp = path.with_extension("tmp");
+    std::fs::write(&temp, content)
+        .and_then(|_| std::fs::rename(&temp, path))
+        .map_err(|e| paths::io_error_message("Ошибка сохранения хода выгрузки", &e))
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> Option<T> {
+    std::fs::read(path).ok().and_then(|raw| serde_json::from_slice(&raw).ok())
+}
+
+impl Batch {
+    /// Новая выгрузка в папку dir. С идентификатором операции ход сохраняется,
+    /// и выгрузку можно продолжить
+    pub fn new(
+        dir: &Path,
+        schedule: &Schedule,
+        format: Format,
+        split: Split,
+        template: Option<String>,
+        operation: Option<String>,
+    ) -> Result<Self, String> {
+        let taken = |name: &str| paths::to_fs_path(&dir.join(format!("{}.{}", name, format.extension()))).exists();
+        let (items, parts): (Vec<Item>, Vec<Schedule>) = plan(schedule, split, taken)
+            .into_iter()
+            .map(|(name, part)| (Item { file: format!("{}.{}", name, format.extension()), hash: None }, part))
+            .unzip();
+        if items.is_empty() {
+            return Err("Нет данных для выгрузки".into());
+        }
+        let resumable = operation.is_some();
+        let progress = Progress {
+            operation: operation.unwrap_or_default(),
+            dir: dir.to_path_buf(),
+            format,
+            split,
+            template,
+            items,
+        };
+        if resumable {
+            let (manifest, schedule_file) = manifest_paths(&progress.operation)?;
+            if let Some(parent) = manifest.parent() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| paths::io_error_message("Ошибка создания папки данных", &e))?;
+            }
+            write_json(&schedule_file, schedule)?;
+            write_json(&manifest, &progress)?;
+        }
+        Ok(Batch { progress, parts, resumable })
+    }
+
+    /// Незавершённая выгрузка с идентификатором operation
+    pub fn load(operation: &str) -> Result<Self, String> {
+        let (manifest, schedule_file) = manifest_paths(operation)?;
+        let not_found = || "Незавершённая выгрузка не найдена: она завершена или отменена".to_string();
+        let progress: Progress = read_json(&manifest).ok_or_else(not_found)?;
+        let schedule: Schedule = read_json(&schedule_file).ok_or_else(not_found)?;
+        let parts: Vec<Schedule> = parts(&schedule, progress.split).into_iter().map(|(_, part)| part).collect();
+        if progress.operation != operation || parts.len() != progress.items.len() {
+            return Err("Файл хода выгрузки повреждён, начните выгрузку заново".into());
+        }
+        Ok(Batch { progress, parts, resumable: true })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.progress.dir
+    }
+
+    pub fn template(&self) -> Option<&str> {
+        self.progress.template.as_deref()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        if !self.resumable {
+            return Ok(());
+        }
+        write_json(&manifest_paths(&self.progress.operation)?.0, &self.progress)
+    }
+
+    // Файл уже записан и с тех пор не менялся
+    fn is_written(&self, item: &Item) -> bool {
+        let Some(hash) = &item.hash else {
+            return false;
+        };
+        std::fs::read(paths::to_fs_path(&self.progress.dir.join(&item.file)))
+            .is_ok_and(|content| integrity::digest(&content) == *hash)
+    }
+
+    /// Записывает незаписанные файлы; write - запись одного файла с проверкой размера.
+    /// Возвращает пути всех файлов выгрузки. При ошибке и отмене ход сохраняется,
+    /// после успеха манифест удаляется
+    pub fn run(
+        &mut self,
+        template: &ExportTemplate,
+        write: WriteFile,
+        progress: &mut dyn FnMut(usize, usize, &str) -> Result<(), String>,
+    ) -> Result<Vec<String>, String> {
+        let total = self.progress.items.len();
+        for i in 0..total {
+            if self.is_written(&self.progress.items[i]) {
+                continue;
+            }
+            self.progress.items[i].hash = None;
+            let done = self.progress.items.iter().filter(|item| item.hash.is_some()).count();
+            let file = self.progress.items[i].file.clone();
+            let result = progress(done, total, &file).and_then(|_| self.write_item(i, template, write));
+            if let Err(e) = result {
+                // Ошибка сохранения хода не должна скрыть причину остановки
+                let _ = self.save();
+                return Err(if self.resumable {
+                    format!("{}. Записано файлов: {} из {}, выгрузку можно продолжить", e, done, total)
+                } else {
+                    e
+                });
+            }
+            self.save()?;
+        }
+        self.finish();
+        let dir = &self.progress.dir;
+        Ok(self.progress.items.iter().map(|item| dir.join(&item.file).to_string_lossy().to_string()).collect())
+    }
+
+    fn write_item(
+        &mut self,
+        index: usize,
+        template: &ExportTemplate,
+        write: WriteFile,
+    ) -> Result<(), String> {
+        let file = self.progress.items[index].file.clone();
+        let target = self.progress.dir.join(&file);
+        let temp = self.progress.dir.join(format!(".{}.part", file));
+        let part = &self.parts[index];
+        let options = serde_json::json!({ "template": template.name, "split": self.progress.split, "part": file });
+        let provenance = Provenance::new(self.progress.format, part, options);
+        let content = self.progress.format.render(part, template, &provenance);
+        let content = content.map_err(|e| format!("{}: {}", file, e))?;
+        let result = write(&temp, &content).and_then(|_| {
+            std::fs::rename(paths::to_fs_path(&temp), paths::to_fs_path(&target))
+                .map_err(|e| paths::io_error_message("Ошибка записи", &e))
+        });
+        // Файл сведений не входит в манифест: без него выгрузка всё равно готова
+        if result.is_ok() {
+            if let Some((sidecar, content)) = provenance.sidecar(&target) {
+                let _ = write(&sidecar, &content);
+            }
+        }
+        if let Err(e) = result {
+            let _ = std::fs::remove_file(paths::to_fs_path(&temp));
+            return Err(format!("{}: {}", file, e));
+        }
+        self.progress.items[index].hash = Some(integrity::digest(&content));
+        Ok(())
+    }
+
+    // Удаляет манифест и сохранённое расписание
+    fn finish(&self) {
+        if !self.resumable {
+            return;
+        }
+        if let Ok((manifest, schedule_file)) = manifest_paths(&self.progress.operation) {
+            let _ = std::fs::remove_file(manifest);
+            let _ = std::fs::remove_file(schedule_file);
+        }
+    }
+
+    /// Отказ от продолжения: удаляются записанные файлы выгрузки и манифест.
+    /// Файлы, изменённые после выгрузки, остаются
+    pub fn discard(self) {
+        for item in &self.progress.items {
+            if self.is_written(item) {
+                let _ = std::fs::remove_file(paths::to_fs_path(&self.progress.dir.join(&item.file)));
+            }
+        }
+        self.finish();
+    }
+}
+
+/// Незавершённые выгрузки, которые можно продолжить
+pub fn unfinished() -> Vec<UnfinishedExport> {
+    let Some(dir) = paths::app_data_dir().map(|d| d.join(MANIFEST_DIR)) else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name.ends_with(".json") && !name.ends_with(".schedule.json")
+        })
+        .filter_map(|entry| read_json::<Progress>(&entry.path()))
+        .map(|p| UnfinishedExport {
+            dir: p.dir.to_string_lossy().to_string(),
+            format: p.format,
+            done: p.items.iter().filter(|item| item.hash.is_some()).count(),
+            total: p.items.len(),
+            operation: p.operation,
+        })
+        .collect()
+}
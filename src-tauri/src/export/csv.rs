@@ -0,0 +1,81 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Выгрузка расписания в CSV: одна плоская таблица, запись истории - в первой колонке.
+
+use serde::Deserialize;
+
+use super::{cells, headers, WORK_COLUMN};
+use crate::model::{Schedule, ScheduleEntry};
+
+const BOM: &str = "\u{feff}";
+
+/// Параметры CSV
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CsvOptions {
+    /// Разделитель: «;» (Excel с русской локалью), «,» или табуляция
+    pub delimiter: char,
+    /// Метка порядка байтов: без неё Excel в Windows открывает UTF-8 как ANSI
+    pub bom: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions { delimiter: ';', bom: true }
+    }
+}
+
+/// Формирует CSV со всеми строками расписания
+pub fn render(schedule: &Schedule, options: CsvOptions) -> Result<String, String> {
+    if ![';', ',', '\t'].contains(&options.delimiter) {
+        return Err("Разделитель CSV должен быть «;», «,» или табуляцией".into());
+    }
+
+    let mut out = String::new();
+    if options.bom {
+        out.push_str(BOM);
+    }
+
+    // Единицы длительности в разных записях могут отличаться, поэтому у них своя колонка
+    let mut header: Vec<String> = headers(&ScheduleEntry::default()).into();
+    header[WORK_COLUMN] = "Работа".into();
+    header.insert(WORK_COLUMN + 1, "Ед. изм.".into());
+    header.insert(0, "Запись".into());
+    push_line(&mut out, &header, options.delimiter);
+
+    for entry in &schedule.entries {
+        for row in entry.sorted_rows() {
+            let mut line: Vec<String> = cells(row).into();
+            line[WORK_COLUMN] = row.dur_val.to_string();
+            line.insert(WORK_COLUMN + 1, row.unit_label().into());
+            line.insert(0, entry.title.clone());
+            push_line(&mut out, &line, options.delimiter);
+        }
+    }
+
+    Ok(out)
+}
+
+fn push_line(out: &mut String, values: &[String], delimiter: char) {
+    let line: Vec<String> = values.iter().map(|v| quote(&sanitize(v), delimiter)).collect();
+    out.push_str(&line.join(&delimiter.to_string()));
+    out.push_str("\r\n");
+}
+
+/// Экранирование по RFC 4180: поле в кавычках, если содержит разделитель, кавычку или перевод строки
+fn quote(value: &str, delimiter: char) -> String {
+    if value.contains(delimiter) || value.contains(['"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Защита от выполнения формул при открытии в Excel, как excelSanitizeCell во фронтенде
+fn sanitize(value: &str) -> String {
+    match value.trim_start().chars().next() {
+        Some('=' | '+' | '-' | '@') => format!("'{}", value),
+        _ => value.to_string(),
+    }
+}
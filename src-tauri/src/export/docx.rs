@@ -0,0 +1,159 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Выгрузка расписания в Word (.docx) для официальных документов: шапка организации
+// в колонтитуле, гриф утверждения, таблицы записей и строки подписей.
+
+use std::io::Cursor;
+
+use docx_rs::{
+    AlignmentType, Docx, Header, PageOrientationType, Paragraph, Run, RunFonts, Shading, Table, TableCell, TableRow,
+};
+use serde::Deserialize;
+
+use super::templates::ExportTemplate;
+use super::{cells, entry_title, headers, COLUMN_COUNT};
+use crate::model::{Schedule, ScheduleEntry};
+
+// A4 альбомной ориентации в twips (1/20 пункта)
+const PAGE_WIDTH: u32 = 16838;
+const PAGE_HEIGHT: u32 = 11906;
+
+// Ширина одного символа колонки в twips
+const CHAR_WIDTH_TWIPS: f64 = 115.0;
+
+// Заголовок записи крупнее текста таблиц, в половинах пункта
+const TITLE_SIZE_STEP: usize = 6;
+
+/// Подпись под документом: «Должность ____________ Фамилия И.О.»
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Signature {
+    pub role: String,
+    pub name: String,
+}
+
+/// Оформление документа
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DocxOptions {
+    /// Строки шапки организации (повторяются в колонтитуле каждой страницы)
+    pub header: Vec<String>,
+    /// Гриф утверждения над таблицами («УТВЕРЖДАЮ», должность, дата)
+    pub approval: Vec<String>,
+    pub signatures: Vec<Signature>,
+}
+
+/// Формирует документ Word со всеми записями расписания
+pub fn render(schedule: &Schedule, options: &DocxOptions, template: &ExportTemplate) -> Result<Vec<u8>, String> {
+    let style = Style::new(template);
+    let mut docx = Docx::new()
+        .page_size(PAGE_WIDTH, PAGE_HEIGHT)
+        .page_orient(PageOrientationType::Landscape);
+
+    if !options.header.is_empty() {
+        let header = options.header.iter().fold(Header::new(), |header, line| {
+            header.add_paragraph(style.text(line, style.size, true).align(AlignmentType::Center))
+        });
+        docx = docx.header(header);
+    }
+
+    for line in &options.approval {
+        docx = docx.add_paragraph(style.text(line, style.size, false).align(AlignmentType::Right));
+    }
+
+    let header_text = template.header_text.trim();
+    if !header_text.is_empty() {
+        docx = docx.add_paragraph(style.text(header_text, style.title_size, true).align(AlignmentType::Center));
+    }
+
+    for entry in &schedule.entries {
+        docx = docx
+            .add_paragraph(style.text(&entry_title(entry), style.title_size, true).align(AlignmentType::Center))
+            .add_table(entry_table(entry, &style));
+    }
+
+    for signature in &options.signatures {
+        let line = format!("{}  ____________________  {}", signature.role, signature.name);
+        docx = docx.add_paragraph(Paragraph::new()).add_paragraph(style.text(line.trim(), style.size, false));
+    }
+
+    let mut buffer = Cursor::new(Vec::new());
+    docx.build()
+        .pack(&mut buffer)
+        .map_err(|e| format!("Ошибка формирования DOCX: {}", e))?;
+    Ok(buffer.into_inner())
+}
+
+/// Оформление из шаблона в единицах Word
+struct Style {
+    font: String,
+    // Размеры шрифта в половинах пункта
+    size: usize,
+    title_size: usize,
+    header_fill: String,
+    z7_fill: String,
+    grid: Vec<usize>,
+}
+
+impl Style {
+    fn new(template: &ExportTemplate) -> Self {
+        let size = (template.font_size * 2.0).round() as usize;
+        Style {
+            font: template.font_name.clone(),
+            size,
+            title_size: size + TITLE_SIZE_STEP,
+            header_fill: format!("{:06X}", template.header_rgb()),
+            z7_fill: format!("{:06X}", template.z7_rgb()),
+            grid: template
+                .column_widths()
+                .iter()
+                .map(|w| (w * CHAR_WIDTH_TWIPS) as usize)
+                .collect(),
+        }
+    }
+
+    fn cell(&self, value: &str, bold: bool) -> TableCell {
+        TableCell::new().add_paragraph(self.text(value, self.size, bold).align(AlignmentType::Center))
+    }
+
+    fn text(&self, value: &str, size: usize, bold: bool) -> Paragraph {
+        // Кириллица относится к диапазону hAnsi, латиница - к ascii
+        let fonts = RunFonts::new().ascii(&self.font).hi_ansi(&self.font).cs(&self.font);
+        let run = Run::new().add_text(value).size(size).fonts(fonts);
+        Paragraph::new().add_run(if bold { run.bold() } else { run })
+    }
+}
+
+fn entry_table(entry: &ScheduleEntry, style: &Style) -> Table {
+    let mut rows = vec![TableRow::new(
+        headers(entry)
+            .iter()
+            .map(|label| style.cell(label, true).shading(Shading::new().fill(&style.header_fill)))
+            .collect(),
+    )];
+
+    for op in entry.sorted_rows() {
+        rows.push(TableRow::new(
+            cells(op)
+                .iter()
+                .enumerate()
+                .map(|(col, value)| style.cell(value, col == 2))
+                .collect(),
+        ));
+    }
+
+    if !entry.z7.is_empty() {
+        rows.push(TableRow::new(vec![style
+            .cell("Z7", true)
+            .grid_span(COLUMN_COUNT)
+            .shading(Shading::new().fill(&style.z7_fill))]));
+        for line in &entry.z7 {
+            rows.push(TableRow::new(vec![TableCell::new()
+                .add_paragraph(style.text(line, style.size, false))
+                .grid_span(COLUMN_COUNT)]));
+        }
+    }
+
+    Table::new(rows).set_grid(style.grid.clone())
+}
@@ -0,0 +1,238 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Таблички на дверь рабочего места (аналог кабинета) для печати: страница A4 на место,
+// крупно название места и под ним сетка недели - дни по столбцам, часы рабочего дня
+// по строкам, в ячейке - техкарты, операции которых идут на месте в этот час.
+// Рабочие места, рабочие часы и дни недели задаются так же, как для room_utilization;
+// операции без исполнителя место тоже занимают. Вместо часов строками сетки могут
+// быть периоды графика звонков одной смены - тогда время строки своё в каждый день.
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::Deserialize;
+
+use super::pdf::{self, Canvas, PdfCanvas, PT_TO_MM};
+use super::templates::ExportTemplate;
+use crate::bells::BellSchedule;
+use crate::model::Schedule;
+use crate::schedule::utilization::{self, UtilizationOptions, Workplace};
+use crate::schedule::{self as analysis, Slot};
+
+const DATE_FORMAT: &str = "%d.%m.%Y";
+
+// A4 альбомной ориентации и поля, мм
+const PAGE: (f32, f32) = (297.0, 210.0);
+const MARGIN: f32 = 10.0;
+
+// Кегль названия места, подзаголовка, заголовков дней и ячеек
+const TITLE_SIZE: f32 = 40.0;
+const SUBTITLE_SIZE: f32 = 14.0;
+const HEADER_SIZE: f32 = 14.0;
+const CELL_SIZE: f32 = 12.0;
+// Межстрочный интервал относительно кегля
+const LINE_SPACING: f32 = 1.3;
+
+// Колонка с временем, строка заголовков дней и пределы высоты строки сетки, мм
+const LABEL_WIDTH: f32 = 30.0;
+const HEADER_HEIGHT: f32 = 10.0;
+const MAX_ROW_HEIGHT: f32 = 24.0;
+const MIN_ROW_HEIGHT: f32 = 6.0;
+
+const WEEKDAYS: [&str; 7] = ["Пн", "Вт", "Ср", "Чт", "Пт", "Сб", "Вс"];
+
+/// Параметры табличек
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DoorSignOptions {
+    /// Рабочие места, рабочие часы и дни недели - как у room_utilization
+    #[serde(flatten)]
+    pub places: UtilizationOptions,
+    /// Любой день нужной недели дд.мм.гггг; по умолчанию - неделя первой операции рабочих мест
+    pub week: Option<String>,
+}
+
+/// Строка сетки: подпись и время в каждый из выводимых дней (None - в этот день строки нет)
+struct GridRow {
+    label: String,
+    times: Vec<Option<(NaiveTime, NaiveTime)>>,
+}
+
+/// Формирует PDF: страница на каждое рабочее место, строки сетки - часы рабочего дня
+pub fn render(schedule: &Schedule, options: &DoorSignOptions, template: &ExportTemplate) -> Result<Vec<u8>, String> {
+    let (day_start, day_end) = options.places.working_hours()?;
+    let slots = analysis::timed_slots(schedule);
+    let days = week_days(options, &slots)?;
+    let rows: Vec<GridRow> = utilization::day_hours(day_start, day_end)
+        .into_iter()
+        .map(|(from, to)| GridRow {
+            label: format!("{}-{}", from.format("%H:%M"), to.format("%H:%M")),
+            times: vec![Some((from, to)); days.len()],
+        })
+        .collect();
+    draw(&slots, options, &days, &rows, template)
+}
+
+/// Формирует PDF: страница на каждое рабочее место, строки сетки - периоды графика звонков смены shift
+pub fn render_periods(
+    schedule: &Schedule,
+    options: &DoorSignOptions,
+    bells: &BellSchedule,
+    shift: &str,
+    template: &ExportTemplate,
+) -> Result<Vec<u8>, String> {
+    options.places.working_hours()?;
+    let slots = analysis::timed_slots(schedule);
+    let days = week_days(options, &slots)?;
+    let variants = bells.times()?;
+    // Периоды каждого дня по варианту смены: None - в этот день смена не работает
+    let mut periods = Vec::with_capacity(days.len());
+    for day in &days {
+        let week = bells.week_of(*day)?;
+        let variant = variants.iter().find(|v| v.shift.trim() == shift.trim() && v.applies_on(*day, week));
+        let times = match variant {
+            Some(variant) => variant
+                .periods
+                .iter()
+                .map(|p| Ok((parse_time(&p.start)?, parse_time(&p.end)?)))
+                .collect::<Result<Vec<_>, String>>()?,
+            None => Vec::new(),
+        };
+        periods.push(times);
+    }
+    let count = periods.iter().map(Vec::len).max().unwrap_or(0);
+    if count == 0 {
+        return Err(format!("В графике звонков нет вариантов смены «{}» на выбранные дни", shift.trim()));
+    }
+    let rows = (0..count)
+        .map(|i| {
+            let times: Vec<Option<(NaiveTime, NaiveTime)>> = periods.iter().map(|p| p.get(i).copied()).collect();
+            // Время в подписи, только если в каждый день оно одно и то же
+            let first = times[0];
+            let label = match first {
+                Some((from, to)) if times.iter().all(|t| *t == first) => {
+                    format!("{}. {}-{}", i + 1, from.format("%H:%M"), to.format("%H:%M"))
+                }
+                _ => format!("{}", i + 1),
+            };
+            GridRow { label, times }
+        })
+        .collect::<Vec<_>>();
+    draw(&slots, options, &days, &rows, template)
+}
+
+fn parse_time(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value, "%H:%M").map_err(|_| format!("Время «{}» не в формате ЧЧ:ММ", value))
+}
+
+/// Рабочие дни выбранной недели
+fn week_days(options: &DoorSignOptions, slots: &[Slot]) -> Result<Vec<NaiveDate>, String> {
+    let day = match options.week.as_deref().map(str::trim).filter(|w| !w.is_empty()) {
+        Some(week) => NaiveDate::parse_from_str(week, DATE_FORMAT)
+            .map_err(|_| format!("Дата «{}» не в формате дд.мм.гггг", week))?,
+        None => slots
+            .iter()
+            .filter(|s| options.places.workplaces.iter().any(|p| p.performs(&s.operation.name)))
+            .map(|s| s.start.date())
+            .min()
+            .ok_or("В расписании нет операций рабочих мест с корректным временем")?,
+    };
+    let monday = day - Duration::days(day.weekday().num_days_from_monday() as i64);
+    let days: Vec<NaiveDate> =
+        (0..7).map(|i| monday + Duration::days(i)).filter(|d| options.places.is_working_day(*d)).collect();
+    if days.is_empty() {
+        return Err("Не выбрано ни одного рабочего дня недели".into());
+    }
+    Ok(days)
+}
+
+fn draw(
+    slots: &[Slot],
+    options: &DoorSignOptions,
+    days: &[NaiveDate],
+    rows: &[GridRow],
+    template: &ExportTemplate,
+) -> Result<Vec<u8>, String> {
+    let top = PAGE.1 - MARGIN - TITLE_SIZE * PT_TO_MM - SUBTITLE_SIZE * PT_TO_MM * LINE_SPACING * 2.0;
+    let row_height = ((top - MARGIN - HEADER_HEIGHT) / rows.len().max(1) as f32).min(MAX_ROW_HEIGHT);
+    if row_height < MIN_ROW_HEIGHT {
+        return Err(format!("Строк сетки слишком много для одной страницы: {}", rows.len()));
+    }
+    let day_width = (PAGE.0 - MARGIN * 2.0 - LABEL_WIDTH) / days.len() as f32;
+    let (Some(first), Some(last)) = (days.first(), days.last()) else {
+        return Err("Не выбрано ни одного рабочего дня недели".into());
+    };
+    let mut subtitle = format!("Неделя {} - {}", first.format(DATE_FORMAT), last.format(DATE_FORMAT));
+    if !template.header_text.trim().is_empty() {
+        subtitle = format!("{} | {}", template.header_text.trim(), subtitle);
+    }
+
+    let mut canvas = PdfCanvas::new(PAGE, None)?;
+    for (i, place) in options.places.workplaces.iter().enumerate() {
+        if i > 0 {
+            canvas.add_page(PAGE.0, PAGE.1);
+        }
+        let width = PAGE.0 - MARGIN * 2.0;
+        let mut y = PAGE.1 - MARGIN - TITLE_SIZE * PT_TO_MM;
+        canvas.text(&pdf::fit(place.name.trim(), width, TITLE_SIZE), TITLE_SIZE, MARGIN, y);
+        y -= SUBTITLE_SIZE * PT_TO_MM * LINE_SPACING * 1.5;
+        canvas.text(&pdf::fit(&subtitle, width, SUBTITLE_SIZE), SUBTITLE_SIZE, MARGIN, y);
+
+        // Заголовки дней
+        let mut y = top - HEADER_HEIGHT;
+        canvas.rect(MARGIN, y, LABEL_WIDTH, HEADER_HEIGHT);
+        for (d, day) in days.iter().enumerate() {
+            let x = MARGIN + LABEL_WIDTH + day_width * d as f32;
+            let name = format!("{} {}", WEEKDAYS[day.weekday().num_days_from_monday() as usize], day.format("%d.%m"));
+            canvas.rect(x, y, day_width, HEADER_HEIGHT);
+            canvas.text(&pdf::fit(&name, day_width - 2.0, HEADER_SIZE), HEADER_SIZE, x + 1.5, y + 3.0);
+        }
+
+        for row in rows {
+            y -= row_height;
+            canvas.rect(MARGIN, y, LABEL_WIDTH, row_height);
+            cell_text(&mut canvas, &row.label, MARGIN, y, LABEL_WIDTH, row_height);
+            for (d, day) in days.iter().enumerate() {
+                let x = MARGIN + LABEL_WIDTH + day_width * d as f32;
+                canvas.rect(x, y, day_width, row_height);
+                if let Some((from, to)) = row.times[d] {
+                    let cards = occupants(slots, place, day.and_time(from), day.and_time(to));
+                    cell_text(&mut canvas, &cards.join(", "), x, y, day_width, row_height);
+                }
+            }
+        }
+    }
+    canvas.save()
+}
+
+/// Техкарты, операции которых идут на месте place с from по to, в порядке начала
+fn occupants(slots: &[Slot], place: &Workplace, from: NaiveDateTime, to: NaiveDateTime) -> Vec<String> {
+    let mut own: Vec<&Slot> =
+        slots.iter().filter(|s| s.start < to && from < s.end && place.performs(&s.operation.name)).collect();
+    own.sort_by_key(|s| s.start);
+    let mut cards: Vec<String> = Vec::new();
+    for slot in own {
+        let card = slot.card.trim();
+        if !card.is_empty() && !cards.iter().any(|c| c == card) {
+            cards.push(card.to_string());
+        }
+    }
+    cards
+}
+
+/// Текст ячейки с переносом по словам; не поместившиеся строки отбрасываются с многоточием
+fn cell_text(canvas: &mut PdfCanvas, text: &str, x: f32, y: f32, width: f32, height: f32) {
+    let line = CELL_SIZE * PT_TO_MM * LINE_SPACING;
+    let max_lines = ((height - 1.0) / line).max(1.0) as usize;
+    let mut lines = pdf::wrap(text, width - 2.0, CELL_SIZE);
+    if lines.len() > max_lines {
+        lines.truncate(max_lines);
+        if let Some(last) = lines.last_mut() {
+            *last = pdf::fit(&format!("{} …", last), width - 2.0, CELL_SIZE);
+        }
+    }
+    let mut baseline = y + height - line;
+    for part in lines {
+        canvas.text(&part, CELL_SIZE, x + 1.0, baseline + 1.0);
+        baseline -= line;
+    }
+}
@@ -0,0 +1,122 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Выгрузка расписания в один HTML-файл со встроенными стилями: для публикации
+// на сайте или отправки по почте без приложенных файлов.
+
+use std::fmt::Write as _;
+
+use base64::Engine as _;
+
+use super::logo;
+use super::provenance::Provenance;
+use super::templates::{css_color, ExportTemplate, LogoPlacement};
+use super::{cells, entry_title, escape_xml, headers, COLUMN_COUNT};
+use crate::model::{Schedule, ScheduleEntry};
+
+// Заливки заголовков светлой темы берутся из шаблона оформления
+const LIGHT_THEME: &str = "--bg:#ffffff;--fg:#1f2328;--border:#8c959f;--stripe:#f6f8fa;";
+const DARK_THEME: &str = "--bg:#0d1117;--fg:#e6edf3;--border:#484f58;--head:#1f3a5f;--z7:#4d3d00;--stripe:#161b22;";
+
+const STYLE: &str = "body{margin:16px;background:var(--bg);color:var(--fg);font:var(--size)/1.4 var(--font),'Segoe UI',Arial,sans-serif}\
+h1{font-size:1.4em}h2{font-size:1.15em;margin:24px 0 8px}\
+table{border-collapse:collapse;width:100%;margin-bottom:8px}\
+th,td{border:1px solid var(--border);padding:3px 6px;text-align:center}\
+th{background:var(--head)}tbody tr:nth-child(even){background:var(--stripe)}\
+header{display:flex;align-items:center;gap:16px}header.right{flex-direction:row-reverse;justify-content:space-between}\
+.logo{max-height:64px;max-width:240px}td.name{font-weight:600}.z7 th{background:var(--z7)}.z7 td{text-align:left}\
+@media print{body{margin:0}h2{break-before:page}h2:first-of-type{break-before:auto}}";
+
+/// Формирует HTML-страницу со всеми записями расписания; сведения о происхождении - в <meta>
+pub fn render(schedule: &Schedule, dark: bool, template: &ExportTemplate, provenance: &Provenance) -> String {
+    let mut theme = if dark {
+        DARK_THEME.to_string()
+    } else {
+        format!(
+            "{}--head:{};--z7:{};",
+            LIGHT_THEME,
+            css_color(template.header_rgb()),
+            css_color(template.z7_rgb())
+        )
+    };
+    // Кавычки и точки с запятой в названии шрифта сломали бы CSS
+    let font: String = template.font_name.chars().filter(|c| !matches!(c, '"' | '\'' | ';' | '<' | '>' | '{' | '}')).collect();
+    let _ = write!(theme, "--font:\"{}\";--size:{}pt;", font, template.font_size);
+
+    let heading = match template.header_text.trim() {
+        "" => "Расписание",
+        text => text,
+    };
+
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"ru\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <meta name=\"generator\" content=\"{generator}\">\n",
+        generator = escape_xml(&provenance.creator())
+    );
+    for (name, value) in provenance.properties() {
+        let _ = writeln!(html, "<meta name=\"{}\" content=\"{}\">", escape_xml(&name), escape_xml(&value));
+    }
+    let _ = write!(
+        html,
+        "<title>{heading}</title>\n<style>:root{{{theme}}}{style}</style>\n</head>\n<body>\n",
+        heading = escape_xml(heading),
+        theme = theme,
+        style = STYLE
+    );
+
+    // Логотип встраивается в страницу, чтобы файл оставался самодостаточным
+    match logo::for_template(template) {
+        Some((logo, placement)) => {
+            let side = if placement == LogoPlacement::Right { "right" } else { "left" };
+            let _ = writeln!(
+                html,
+                "<header class=\"{}\"><img class=\"logo\" alt=\"\" src=\"data:{};base64,{}\"><h1>{}</h1></header>",
+                side,
+                logo.kind.mime(),
+                base64::engine::general_purpose::STANDARD.encode(&logo.bytes),
+                escape_xml(heading)
+            );
+        }
+        None => {
+            let _ = writeln!(html, "<h1>{}</h1>", escape_xml(heading));
+        }
+    }
+
+    for entry in &schedule.entries {
+        write_entry(&mut html, entry);
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn write_entry(html: &mut String, entry: &ScheduleEntry) {
+    let _ = writeln!(html, "<h2>{}</h2>", escape_xml(&entry_title(entry)));
+
+    html.push_str("<table>\n<thead><tr>");
+    for label in headers(entry) {
+        let _ = write!(html, "<th>{}</th>", escape_xml(&label));
+    }
+    html.push_str("</tr></thead>\n<tbody>\n");
+
+    for op in entry.sorted_rows() {
+        html.push_str("<tr>");
+        for (col, value) in cells(op).iter().enumerate() {
+            let class = if col == 2 { " class=\"name\"" } else { "" };
+            let _ = write!(html, "<td{}>{}</td>", class, escape_xml(value));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</tbody>\n</table>\n");
+
+    if !entry.z7.is_empty() {
+        let _ = writeln!(html, "<table class=\"z7\">\n<tr><th colspan=\"{}\">Z7</th></tr>", COLUMN_COUNT);
+        for line in &entry.z7 {
+            let _ = writeln!(html, "<tr><td colspan=\"{}\">{}</td></tr>", COLUMN_COUNT, escape_xml(line));
+        }
+        html.push_str("</table>\n");
+    }
+}
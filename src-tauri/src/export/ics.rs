@@ -0,0 +1,114 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Выгрузка операций в iCalendar (.ics) для импорта в календарь телефона.
+// Каждая операция - отдельное событие с конкретными датой и временем: расчёт
+// привязан к календарю, поэтому правила повторения (RRULE) не используются.
+
+use sha2::{Digest, Sha256};
+
+use crate::model::{OperationRow, Schedule, ScheduleEntry};
+
+// Максимальная длина строки iCalendar в байтах (RFC 5545, 3.1)
+const MAX_LINE_OCTETS: usize = 75;
+
+const DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+/// Формирует календарь. worker - только операции этого исполнителя,
+/// entry - только операции одной записи истории (индекс в schedule.entries)
+pub fn render(schedule: &Schedule, worker: Option<&str>, entry: Option<usize>) -> Result<String, String> {
+    if let Some(index) = entry {
+        if index >= schedule.entries.len() {
+            return Err("Запись истории не найдена".into());
+        }
+    }
+
+    let stamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//time-to-table//Калькулятор для ленивых//RU".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+
+    let mut events = 0;
+    for (i, item) in schedule.entries.iter().enumerate() {
+        if entry.is_some_and(|index| index != i) {
+            continue;
+        }
+        for row in &item.rows {
+            if worker.is_some_and(|w| row.worker.trim() != w.trim()) {
+                continue;
+            }
+            if let Some(event) = event(item, row, &stamp) {
+                lines.extend(event);
+                events += 1;
+            }
+        }
+    }
+
+    if events == 0 {
+        return Err("Нет операций с датой и временем для выгрузки в календарь".into());
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+    let mut out = String::new();
+    for line in lines {
+        fold(&mut out, &line);
+    }
+    Ok(out)
+}
+
+fn event(entry: &ScheduleEntry, row: &OperationRow, stamp: &str) -> Option<Vec<String>> {
+    let start = row.start()?;
+    let end = row.end().filter(|end| *end > start).unwrap_or(start);
+
+    // Стабильный UID: повторный импорт обновляет события, а не дублирует их
+    let uid = Sha256::digest(format!("{}|{}|{}|{}", entry.title, row.original_op_index, row.worker, start));
+    let uid = format!("{:x}", uid);
+
+    let mut description = vec![entry.title.clone()];
+    if !row.op_idx.is_empty() {
+        description.push(format!("ПДТВ: {}", row.op_idx));
+    }
+    if !row.worker.is_empty() {
+        description.push(format!("Исполнитель: {}", row.worker));
+    }
+    description.push(format!("Работа: {} {}", row.dur_text, row.unit_label()));
+
+    Some(vec![
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}@time-to-table", &uid[..32]),
+        format!("DTSTAMP:{}", stamp),
+        // Время без часового пояса: событие показывается в местном времени устройства
+        format!("DTSTART:{}", start.format(DATE_TIME_FORMAT)),
+        format!("DTEND:{}", end.format(DATE_TIME_FORMAT)),
+        format!("SUMMARY:{}", escape_text(format!("{} {}", row.original_op_index, row.name).trim())),
+        format!("DESCRIPTION:{}", escape_text(&description.join("\n"))),
+        "END:VEVENT".to_string(),
+    ])
+}
+
+/// Экранирование текстовых значений (RFC 5545, 3.3.11)
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Дописывает строку, перенося её по MAX_LINE_OCTETS байт без разрыва символов UTF-8
+fn fold(out: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > MAX_LINE_OCTETS {
+            out.push_str("\r\n ");
+            // Пробел в начале строки продолжения тоже считается
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
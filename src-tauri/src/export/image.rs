@@ -0,0 +1,452 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Выгрузка сетки расписания в картинку: SVG строится напрямую, PNG и JPEG - растеризацией
+// того же SVG через resvg с нужным масштабом (для печати на A3 без потери качества).
+// Сетка почти одноцветная, поэтому PNG с палитрой до 256 цветов в разы меньше
+// полноцветного; для почты и мессенджеров можно задать предельный размер файла -
+// тогда снижаются качество JPEG (или включается палитра PNG), а затем масштаб.
+//
+// Одна картинка со всеми записями для большой организации непригодна, поэтому есть и
+// постраничный SVG: страницы размечаются тем же разбиением, что и PDF, и каждая
+// страница - отдельный SVG с размерами в миллиметрах.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+use base64::Engine as _;
+use resvg::{tiny_skia, usvg};
+use serde::Deserialize;
+
+use super::logo::{self, Logo};
+use super::pdf::{self, Canvas, PageSetup};
+use super::templates::{css_color, ExportTemplate};
+use super::{cells, entry_title, escape_xml, headers, COLUMN_COUNT};
+use crate::model::Schedule;
+
+// Ширина символа колонки и высота строки в пикселях SVG
+const CHAR_WIDTH_PX: f64 = 7.0;
+const ROW_HEIGHT: f64 = 22.0;
+const TITLE_HEIGHT: f64 = 30.0;
+const PADDING: f64 = 16.0;
+const FONT_SIZE: f64 = 12.0;
+// Примерная ширина символа при FONT_SIZE для обрезки текста по ширине ячейки
+const GLYPH_WIDTH_PX: f64 = 6.5;
+
+// Запасные шрифты после шрифта из шаблона
+const FALLBACK_FONTS: &str = "Segoe UI, Arial, DejaVu Sans, sans-serif";
+
+// Ограничение размера растра: больше не открывают многие просмотрщики
+const MAX_PIXELS: u32 = 16384;
+
+/// Масштаб растра по умолчанию
+pub const DEFAULT_SCALE: f32 = 2.0;
+
+const MIN_SCALE: f32 = 0.5;
+const MAX_SCALE: f32 = 8.0;
+
+// Качество JPEG по умолчанию и нижняя граница при подгонке под размер файла
+const DEFAULT_QUALITY: u8 = 85;
+const MIN_FIT_QUALITY: u8 = 40;
+const QUALITY_STEP: u8 = 10;
+// Шаг уменьшения масштаба при подгонке под размер файла
+const FIT_SCALE_STEP: f32 = 0.8;
+
+const MIN_MAX_BYTES: u64 = 1024;
+const PALETTE_SIZE: usize = 256;
+
+/// Формат растра
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Raster {
+    Png,
+    Jpeg,
+}
+
+/// Параметры растра
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ImageOptions {
+    pub scale: f32,
+    /// Качество JPEG от 1 до 100
+    pub quality: u8,
+    /// PNG с палитрой до 256 цветов
+    pub palette: bool,
+    /// Предельный размер файла в байтах
+    pub max_bytes: Option<u64>,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        ImageOptions { scale: DEFAULT_SCALE, quality: DEFAULT_QUALITY, palette: false, max_bytes: None }
+    }
+}
+
+impl ImageOptions {
+    fn validate(&self) -> Result<(), String> {
+        if !(MIN_SCALE..=MAX_SCALE).contains(&self.scale) {
+            return Err(format!("Масштаб изображения должен быть от {} до {}", MIN_SCALE, MAX_SCALE));
+        }
+        if !(1..=100).contains(&self.quality) {
+            return Err("Качество JPEG должно быть от 1 до 100".into());
+        }
+        if self.max_bytes.is_some_and(|max| max < MIN_MAX_BYTES) {
+            return Err(format!("Предельный размер файла - не меньше {} байт", MIN_MAX_BYTES));
+        }
+        Ok(())
+    }
+}
+
+/// Формирует SVG с сеткой всех записей расписания. Из шаблона берутся шрифт, цвета,
+/// ширины колонок и текст шапки; размеры строк фиксированы
+pub fn render_svg(schedule: &Schedule, template: &ExportTemplate) -> String {
+    let columns: Vec<f64> = template.column_widths().iter().map(|w| w * CHAR_WIDTH_PX).collect();
+    let table_width: f64 = columns.iter().sum();
+    let width = table_width + PADDING * 2.0;
+    let header_fill = css_color(template.header_rgb());
+    let z7_fill = css_color(template.z7_rgb());
+
+    let mut body = String::new();
+    let mut y = PADDING;
+    let header_text = template.header_text.trim();
+    if !header_text.is_empty() {
+        let _ = writeln!(
+            body,
+            r#"<text x="{}" y="{}" font-size="{}" font-weight="bold">{}</text>"#,
+            PADDING,
+            y + TITLE_HEIGHT * 0.65,
+            FONT_SIZE + 4.0,
+            escape_xml(&fit(header_text, table_width))
+        );
+        y += TITLE_HEIGHT + ROW_HEIGHT / 2.0;
+    }
+    for entry in &schedule.entries {
+        let _ = writeln!(
+            body,
+            r#"<text x="{}" y="{}" font-size="{}" font-weight="bold">{}</text>"#,
+            PADDING,
+            y + TITLE_HEIGHT * 0.65,
+            FONT_SIZE + 2.0,
+            escape_xml(&fit(&entry_title(entry), table_width))
+        );
+        y += TITLE_HEIGHT;
+
+        row(&mut body, &columns, y, &headers(entry), &header_fill, true);
+        y += ROW_HEIGHT;
+        for op in entry.sorted_rows() {
+            row(&mut body, &columns, y, &cells(op), "#ffffff", false);
+            y += ROW_HEIGHT;
+        }
+
+        if !entry.z7.is_empty() {
+            y += ROW_HEIGHT / 2.0;
+            merged_row(&mut body, table_width, y, "Z7", &z7_fill, true);
+            y += ROW_HEIGHT;
+            for line in &entry.z7 {
+                merged_row(&mut body, table_width, y, line, "#ffffff", false);
+                y += ROW_HEIGHT;
+            }
+        }
+        y += ROW_HEIGHT;
+    }
+    let height = y + PADDING;
+
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" \
+         font-family=\"{font}\" font-size=\"{size}\">\n<rect width=\"100%\" height=\"100%\" fill=\"#ffffff\"/>\n{body}</svg>\n",
+        w = width,
+        h = height,
+        font = escape_xml(&format!("{}, {}", template.font_name, FALLBACK_FONTS)),
+        size = FONT_SIZE,
+        body = body
+    )
+}
+
+/// Формирует постраничный SVG: страница на элемент списка, разбиение и шапка
+/// страниц - как у PDF с параметрами setup
+pub fn render_svg_pages(
+    schedule: &Schedule,
+    organization: Option<&str>,
+    setup: &PageSetup,
+    template: &ExportTemplate,
+) -> Result<Vec<String>, String> {
+    setup.validate(schedule)?;
+    let logo = logo::for_template(template);
+    let canvas = SvgCanvas::new(setup.page(0)?, template, logo.as_ref().map(|(logo, _)| logo));
+    Ok(pdf::paginate(canvas, schedule, organization, setup, template, logo.as_ref())?.finish())
+}
+
+// Толщина рамок ячеек постраничного SVG, мм
+const PAGE_STROKE_MM: f64 = 0.1;
+// 1 пункт в мм, как в PDF
+const PT_TO_MM: f64 = 0.3528;
+
+/// Страницы SVG в миллиметрах; ось y направлена вниз, поэтому координаты Canvas
+/// (от нижнего края) переворачиваются
+struct SvgCanvas {
+    font: String,
+    // data: URI логотипа
+    logo: Option<String>,
+    pages: Vec<String>,
+    body: String,
+    width: f32,
+    height: f32,
+}
+
+impl SvgCanvas {
+    fn new((width, height): (f32, f32), template: &ExportTemplate, logo: Option<&Logo>) -> Self {
+        SvgCanvas {
+            font: escape_xml(&format!("{}, {}", template.font_name, FALLBACK_FONTS)),
+            logo: logo.map(|logo| {
+                format!(
+                    "data:{};base64,{}",
+                    logo.kind.mime(),
+                    base64::engine::general_purpose::STANDARD.encode(&logo.bytes)
+                )
+            }),
+            pages: Vec::new(),
+            body: String::new(),
+            width,
+            height,
+        }
+    }
+
+    fn close_page(&mut self) {
+        let body = std::mem::take(&mut self.body);
+        self.pages.push(format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}mm\" height=\"{h}mm\" viewBox=\"0 0 {w} {h}\" \
+             font-family=\"{font}\">\n<rect width=\"100%\" height=\"100%\" fill=\"#ffffff\"/>\n{body}</svg>\n",
+            w = self.width,
+            h = self.height,
+            font = self.font,
+            body = body
+        ));
+    }
+
+    fn finish(mut self) -> Vec<String> {
+        self.close_page();
+        self.pages
+    }
+
+    fn top(&self, y: f32) -> f32 {
+        self.height - y
+    }
+}
+
+impl Canvas for SvgCanvas {
+    fn add_page(&mut self, width: f32, height: f32) {
+        self.close_page();
+        self.width = width;
+        self.height = height;
+    }
+
+    fn text(&mut self, text: &str, size: f32, x: f32, y: f32) {
+        let _ = writeln!(
+            self.body,
+            r#"<text x="{:.2}" y="{:.2}" font-size="{:.2}">{}</text>"#,
+            x,
+            self.top(y),
+            f64::from(size) * PT_TO_MM,
+            escape_xml(text)
+        );
+    }
+
+    fn rect(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        let _ = writeln!(
+            self.body,
+            r##"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="none" stroke="#000000" stroke-width="{}"/>"##,
+            x,
+            self.top(y + height),
+            width,
+            height,
+            PAGE_STROKE_MM
+        );
+    }
+
+    fn logo(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        let Some(href) = &self.logo else {
+            return;
+        };
+        let _ = writeln!(
+            self.body,
+            r#"<image x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" preserveAspectRatio="none" href="{}"/>"#,
+            x,
+            self.top(y + height),
+            width,
+            height,
+            href
+        );
+    }
+}
+
+/// Растеризует сетку в PNG
+pub fn render_png(schedule: &Schedule, options: &ImageOptions, template: &ExportTemplate) -> Result<Vec<u8>, String> {
+    render_raster(schedule, Raster::Png, options, template)
+}
+
+/// Растеризует сетку в JPEG
+pub fn render_jpeg(schedule: &Schedule, options: &ImageOptions, template: &ExportTemplate) -> Result<Vec<u8>, String> {
+    render_raster(schedule, Raster::Jpeg, options, template)
+}
+
+/// Растеризует сетку. С предельным размером файла сначала снижается качество JPEG до
+/// MIN_FIT_QUALITY (у PNG включается палитра), затем масштаб - до MIN_SCALE
+pub fn render_raster(
+    schedule: &Schedule,
+    format: Raster,
+    options: &ImageOptions,
+    template: &ExportTemplate,
+) -> Result<Vec<u8>, String> {
+    options.validate()?;
+
+    let svg = render_svg(schedule, template);
+    let mut usvg_options = usvg::Options::default();
+    usvg_options.fontdb_mut().load_system_fonts();
+    let tree =
+        usvg::Tree::from_str(&svg, &usvg_options).map_err(|e| format!("Ошибка формирования изображения: {}", e))?;
+
+    let mut scale = options.scale;
+    let mut quality = options.quality;
+    let mut palette = options.palette;
+    loop {
+        let pixmap = rasterize(&tree, scale)?;
+        loop {
+            let bytes = match format {
+                Raster::Png => encode_png(&pixmap, palette)?,
+                Raster::Jpeg => encode_jpeg(&pixmap, quality)?,
+            };
+            let Some(max) = options.max_bytes.filter(|max| bytes.len() as u64 > *max) else {
+                return Ok(bytes);
+            };
+            match format {
+                Raster::Jpeg if quality > MIN_FIT_QUALITY => {
+                    quality = quality.saturating_sub(QUALITY_STEP).max(MIN_FIT_QUALITY);
+                }
+                Raster::Png if !palette => palette = true,
+                _ if scale > MIN_SCALE => break,
+                _ => {
+                    return Err(format!(
+                        "Изображение не уложилось в {} КБ даже при масштабе {}: выгрузите записи по отдельности",
+                        max.div_ceil(1024),
+                        MIN_SCALE
+                    ));
+                }
+            }
+        }
+        scale = (scale * FIT_SCALE_STEP).max(MIN_SCALE);
+    }
+}
+
+fn rasterize(tree: &usvg::Tree, scale: f32) -> Result<tiny_skia::Pixmap, String> {
+    let size = tree.size();
+    let width = (size.width() * scale).ceil() as u32;
+    let height = (size.height() * scale).ceil() as u32;
+    if width > MAX_PIXELS || height > MAX_PIXELS {
+        return Err("Изображение слишком большое: уменьшите масштаб или выгрузите записи по отдельности".into());
+    }
+
+    let mut pixmap = tiny_skia::Pixmap::new(width, height).ok_or("Не удалось выделить память под изображение")?;
+    resvg::render(tree, tiny_skia::Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+    Ok(pixmap)
+}
+
+// Фон сетки непрозрачный, поэтому альфа-канал отбрасывается
+fn rgb(pixmap: &tiny_skia::Pixmap) -> Vec<u8> {
+    pixmap.data().chunks_exact(4).flat_map(|p| [p[0], p[1], p[2]]).collect()
+}
+
+fn encode_png(pixmap: &tiny_skia::Pixmap, palette: bool) -> Result<Vec<u8>, String> {
+    let failed = |e: png::EncodingError| format!("Ошибка формирования PNG: {}", e);
+    let pixels = rgb(pixmap);
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, pixmap.width(), pixmap.height());
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_compression(png::Compression::Best);
+    encoder.set_adaptive_filter(png::AdaptiveFilterType::Adaptive);
+    let data = if palette {
+        let (colors, indices) = quantize(&pixels);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_palette(colors);
+        indices
+    } else {
+        encoder.set_color(png::ColorType::Rgb);
+        pixels
+    };
+    let mut writer = encoder.write_header().map_err(failed)?;
+    writer.write_image_data(&data).map_err(failed)?;
+    writer.finish().map_err(failed)?;
+    Ok(out)
+}
+
+fn encode_jpeg(pixmap: &tiny_skia::Pixmap, quality: u8) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    // Размеры растра не больше MAX_PIXELS, поэтому помещаются в u16
+    jpeg_encoder::Encoder::new(&mut out, quality)
+        .encode(&rgb(pixmap), pixmap.width() as u16, pixmap.height() as u16, jpeg_encoder::ColorType::Rgb)
+        .map_err(|e| format!("Ошибка формирования JPEG: {}", e))?;
+    Ok(out)
+}
+
+/// Палитра из самых частых цветов и индексы пикселей; редкие цвета (сглаживание
+/// текста) заменяются ближайшими из палитры
+fn quantize(pixels: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let mut counts: HashMap<[u8; 3], u64> = HashMap::new();
+    for p in pixels.chunks_exact(3) {
+        *counts.entry([p[0], p[1], p[2]]).or_default() += 1;
+    }
+    let mut colors: Vec<([u8; 3], u64)> = counts.into_iter().collect();
+    colors.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    colors.truncate(PALETTE_SIZE);
+
+    let mut index: HashMap<[u8; 3], u8> = colors.iter().enumerate().map(|(i, (c, _))| (*c, i as u8)).collect();
+    let nearest = |color: [u8; 3]| {
+        let distance = |c: &[u8; 3]| (0..3).map(|i| (c[i] as i32 - color[i] as i32).pow(2)).sum::<i32>();
+        colors.iter().enumerate().min_by_key(|(_, (c, _))| distance(c)).map_or(0, |(i, _)| i as u8)
+    };
+    let indices = pixels
+        .chunks_exact(3)
+        .map(|p| {
+            let color = [p[0], p[1], p[2]];
+            *index.entry(color).or_insert_with(|| nearest(color))
+        })
+        .collect();
+    (colors.iter().flat_map(|(c, _)| *c).collect(), indices)
+}
+
+fn row(body: &mut String, columns: &[f64], y: f64, values: &[String; COLUMN_COUNT], fill: &str, bold: bool) {
+    let mut x = PADDING;
+    for (value, width) in values.iter().zip(columns) {
+        cell(body, x, y, *width, &fit(value, width - 6.0), fill, bold);
+        x += width;
+    }
+}
+
+fn merged_row(body: &mut String, width: f64, y: f64, value: &str, fill: &str, bold: bool) {
+    cell(body, PADDING, y, width, &fit(value, width - 6.0), fill, bold);
+}
+
+fn cell(body: &mut String, x: f64, y: f64, width: f64, value: &str, fill: &str, bold: bool) {
+    let _ = writeln!(
+        body,
+        r##"<rect x="{}" y="{}" width="{}" height="{}" fill="{}" stroke="#8c959f" stroke-width="1"/><text x="{}" y="{}" text-anchor="middle"{}>{}</text>"##,
+        x,
+        y,
+        width,
+        ROW_HEIGHT,
+        fill,
+        x + width / 2.0,
+        y + ROW_HEIGHT * 0.68,
+        if bold { r#" font-weight="bold""# } else { "" },
+        escape_xml(value)
+    );
+}
+
+/// Обрезает текст по ширине в пикселях, добавляя многоточие
+fn fit(text: &str, width: f64) -> String {
+    let max = (width / GLYPH_WIDTH_PX).max(1.0) as usize;
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut out: String = text.chars().take(max.saturating_sub(1)).collect();
+    out.push('…');
+    out
+}
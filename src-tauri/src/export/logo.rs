@@ -0,0 +1,201 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Логотип организации для шапки выгрузок. Файл PNG или JPEG проверяется при
+// подключении и копируется в папку настроек; где его разместить, задаёт шаблон оформления.
+
+use serde::Serialize;
+
+use super::templates::{ExportTemplate, LogoPlacement};
+use crate::paths;
+
+// Логотип хранится в папке настроек без расширения: формат определяется по содержимому
+const LOGO_FILE: &str = "export_logo";
+
+/// Наибольший размер файла логотипа
+pub const MAX_LOGO_SIZE: usize = 2 * 1024 * 1024;
+
+// Наибольшая сторона изображения в пикселях
+const MAX_LOGO_SIDE: u32 = 4096;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Формат изображения логотипа
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LogoKind {
+    Png,
+    Jpeg,
+}
+
+impl LogoKind {
+    pub fn mime(self) -> &'static str {
+        match self {
+            LogoKind::Png => "image/png",
+            LogoKind::Jpeg => "image/jpeg",
+        }
+    }
+}
+
+/// Проверенное изображение логотипа
+#[derive(Debug, Clone)]
+pub struct Logo {
+    pub kind: LogoKind,
+    pub bytes: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    /// Число цветовых компонент JPEG (1 - оттенки серого, 3 - RGB, 4 - CMYK)
+    pub components: u8,
+}
+
+/// Сведения о подключённом логотипе для фронтенда
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogoInfo {
+    pub kind: LogoKind,
+    pub width: u32,
+    pub height: u32,
+    pub size: usize,
+}
+
+impl Logo {
+    /// Проверяет содержимое файла: сигнатуру, размеры и целостность PNG
+    pub fn parse(bytes: Vec<u8>) -> Result<Logo, String> {
+        if bytes.len() > MAX_LOGO_SIZE {
+            return Err(format!("Размер логотипа превышает максимальный ({} МБ)", MAX_LOGO_SIZE / 1024 / 1024));
+        }
+        let (kind, width, height, components) = if bytes.starts_with(PNG_SIGNATURE) {
+            // Распаковка заодно проверяет, что файл не повреждён
+            let (width, height, _) = decode_png(&bytes)?;
+            (LogoKind::Png, width, height, 3)
+        } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            let (width, height, components) = jpeg_info(&bytes).ok_or("Файл JPEG повреждён или не поддерживается")?;
+            (LogoKind::Jpeg, width, height, components)
+        } else {
+            return Err("Логотип должен быть изображением PNG или JPEG".into());
+        };
+        if width == 0 || height == 0 || width > MAX_LOGO_SIDE || height > MAX_LOGO_SIDE {
+            return Err(format!("Размер логотипа должен быть от 1 до {} пикселей по каждой стороне", MAX_LOGO_SIDE));
+        }
+        Ok(Logo { kind, bytes, width, height, components })
+    }
+
+    pub fn info(&self) -> LogoInfo {
+        LogoInfo { kind: self.kind, width: self.width, height: self.height, size: self.bytes.len() }
+    }
+
+    /// Отношение ширины к высоте
+    pub fn aspect(&self) -> f64 {
+        f64::from(self.width) / f64::from(self.height)
+    }
+}
+
+/// Пиксели PNG в RGB 8 бит; прозрачность накладывается на белый фон
+pub fn decode_png(bytes: &[u8]) -> Result<(u32, u32, Vec<u8>), String> {
+    let broken = |e: png::DecodingError| format!("Файл PNG повреждён: {}", e);
+    let mut decoder = png::Decoder::new(std::io::Cursor::new(bytes));
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(broken)?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buffer).map_err(broken)?;
+    let data = &buffer[..frame.buffer_size()];
+
+    let over_white = |value: u8, alpha: u8| ((u16::from(value) * u16::from(alpha) + 255 * (255 - u16::from(alpha))) / 255) as u8;
+    let rgb = match frame.color_type {
+        png::ColorType::Rgb => data.to_vec(),
+        png::ColorType::Rgba => data
+            .chunks_exact(4)
+            .flat_map(|p| [over_white(p[0], p[3]), over_white(p[1], p[3]), over_white(p[2], p[3])])
+            .collect(),
+        png::ColorType::Grayscale => data.iter().flat_map(|v| [*v; 3]).collect(),
+        png::ColorType::GrayscaleAlpha => data.chunks_exact(2).flat_map(|p| [over_white(p[0], p[1]); 3]).collect(),
+        // Палитра раскрывается в RGB преобразованием EXPAND
+        png::ColorType::Indexed => return Err("Файл PNG с палитрой не удалось раскрыть".into()),
+    };
+    Ok((frame.width, frame.height, rgb))
+}
+
+/// Размеры и число компонент JPEG из маркера SOF
+fn jpeg_info(bytes: &[u8]) -> Option<(u32, u32, u8)> {
+    let mut pos = 2;
+    while pos + 4 <= bytes.len() {
+        if bytes[pos] != 0xFF {
+            return None;
+        }
+        let marker = bytes[pos + 1];
+        // Заполняющие байты 0xFF между маркерами
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        let length = usize::from(u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]));
+        // SOF0..SOF15, кроме DHT (C4), JPG (C8) и DAC (CC)
+        if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            let sof = bytes.get(pos + 4..pos + 10)?;
+            let height = u32::from(u16::from_be_bytes([sof[1], sof[2]]));
+            let width = u32::from(u16::from_be_bytes([sof[3], sof[4]]));
+            return Some((width, height, sof[5]));
+        }
+        // Начало сжатых данных без найденного SOF
+        if marker == 0xDA {
+            return None;
+        }
+        pos += 2 + length;
+    }
+    None
+}
+
+/// Подключает логотип: сохраняет проверенное изображение в папку настроек
+pub fn register(bytes: Vec<u8>) -> Result<LogoInfo, String> {
+    let logo = Logo::parse(bytes)?;
+    let dir = paths::app_config_dir().ok_or("Не удалось определить папку настроек")?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| paths::io_error_message("Ошибка создания папки настроек", &e))?;
+    std::fs::write(dir.join(LOGO_FILE), &logo.bytes)
+        .map_err(|e| paths::io_error_message("Ошибка сохранения логотипа", &e))?;
+    Ok(logo.info())
+}
+
+/// Удаляет подключённый логотип
+pub fn remove() -> Result<(), String> {
+    let Some(file) = paths::app_config_dir().map(|dir| dir.join(LOGO_FILE)) else {
+        return Ok(());
+    };
+    match std::fs::remove_file(file) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(paths::io_error_message("Ошибка удаления логотипа", &e)),
+        _ => Ok(()),
+    }
+}
+
+/// Удаляет подключённый логотип, если его не размещает ни один шаблон или файл
+/// испорчен. Возвращает освобождённый размер
+pub fn prune_unused() -> u64 {
+    let Some(file) = paths::app_config_dir().map(|dir| dir.join(LOGO_FILE)) else {
+        return 0;
+    };
+    let Ok(bytes) = std::fs::read(&file) else {
+        return 0;
+    };
+    let used = super::templates::list().iter().any(|t| t.logo != LogoPlacement::None);
+    if used && Logo::parse(bytes.clone()).is_ok() {
+        return 0;
+    }
+    match std::fs::remove_file(&file) {
+        Ok(()) => bytes.len() as u64,
+        Err(_) => 0,
+    }
+}
+
+/// Подключённый логотип; файл, испорченный после подключения, пропускается
+pub fn load() -> Option<Logo> {
+    let file = paths::app_config_dir()?.join(LOGO_FILE);
+    Logo::parse(std::fs::read(file).ok()?).ok()
+}
+
+/// Логотип для выгрузки по шаблону: None, если шаблон его не размещает или логотип не подключён
+pub fn for_template(template: &ExportTemplate) -> Option<(Logo, LogoPlacement)> {
+    match template.logo {
+        LogoPlacement::None => None,
+        placement => load().map(|logo| (logo, placement)),
+    }
+}
@@ -0,0 +1,97 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Выгрузка расписания в Markdown-таблицы для вставки в вики.
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use super::{cells, entry_title, headers, WORK_COLUMN};
+use crate::model::{OperationRow, Schedule, ScheduleEntry};
+
+/// Как разбивать расписание на таблицы
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Grouping {
+    /// Таблица на каждую запись истории
+    #[default]
+    Entry,
+    /// Таблица на каждый день начала операций
+    Day,
+}
+
+/// Формирует Markdown-документ
+pub fn render(schedule: &Schedule, grouping: Grouping) -> String {
+    let mut out = String::from("# Расписание\n");
+    match grouping {
+        Grouping::Entry => {
+            for entry in &schedule.entries {
+                out.push_str(&format!("\n## {}\n\n", escape(&entry_title(entry))));
+                table(&mut out, entry, entry.sorted_rows());
+                if !entry.z7.is_empty() {
+                    out.push_str("\n**Z7**\n\n");
+                    for line in &entry.z7 {
+                        out.push_str(&format!("- {}\n", escape(line)));
+                    }
+                }
+            }
+        }
+        Grouping::Day => {
+            // Ключ - дата начала, внутри дня операции идут по времени
+            let mut days: BTreeMap<_, Vec<(&ScheduleEntry, &OperationRow)>> = BTreeMap::new();
+            for entry in &schedule.entries {
+                for row in entry.sorted_rows() {
+                    days.entry(row.start().map(|s| s.date())).or_default().push((entry, row));
+                }
+            }
+            for (day, mut rows) in days {
+                rows.sort_by_key(|(_, row)| row.start());
+                let title = day.map_or("Без даты".to_string(), |d| d.format("%d.%m.%Y").to_string());
+                out.push_str(&format!("\n## {}\n\n", title));
+                day_table(&mut out, &rows);
+            }
+        }
+    }
+    out
+}
+
+fn table(out: &mut String, entry: &ScheduleEntry, rows: Vec<&OperationRow>) {
+    line(out, &headers(entry));
+    separator(out, headers(entry).len());
+    for row in rows {
+        line(out, &cells(row));
+    }
+}
+
+// В таблице дня строки из разных записей: добавляется колонка с заголовком записи,
+// а единица длительности пишется в каждой ячейке
+fn day_table(out: &mut String, rows: &[(&ScheduleEntry, &OperationRow)]) {
+    let mut header = vec!["Запись".to_string()];
+    header.extend(headers(&ScheduleEntry::default()));
+    header[WORK_COLUMN + 1] = "Работа".into();
+    line(out, &header);
+    separator(out, header.len());
+    for (entry, row) in rows {
+        let mut values = vec![entry.title.clone()];
+        values.extend(cells(row));
+        values[WORK_COLUMN + 1] = format!("{} {}", row.dur_text, row.unit_label());
+        line(out, &values);
+    }
+}
+
+fn line(out: &mut String, values: &[String]) {
+    let cells: Vec<String> = values.iter().map(|v| escape(v)).collect();
+    out.push_str(&format!("| {} |\n", cells.join(" | ")));
+}
+
+fn separator(out: &mut String, count: usize) {
+    out.push_str(&format!("|{}\n", " --- |".repeat(count)));
+}
+
+/// Экранирует символы разметки, ломающие таблицу
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace(['\r', '\n'], " ")
+}
@@ -0,0 +1,163 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Экспорт расписания на стороне Rust. Общая для всех форматов раскладка таблицы
+// повторяет историю расчётов во фронтенде.
+
+pub mod batch;
+pub mod csv;
+pub mod docx;
+pub mod doorsigns;
+pub mod html;
+pub mod ics;
+pub mod image;
+pub mod logo;
+pub mod markdown;
+pub mod ods;
+pub mod pdf;
+pub mod personal;
+pub mod provenance;
+pub mod report;
+pub mod templates;
+pub mod xlsx;
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::{OperationRow, Schedule, ScheduleEntry};
+use templates::ExportTemplate;
+
+/// Количество колонок таблицы записи
+pub const COLUMN_COUNT: usize = 12;
+
+/// Колонка «Работа» (длительность)
+pub const WORK_COLUMN: usize = 5;
+
+/// Ширины колонок таблиц в символах
+pub const COLUMN_WIDTHS: [f64; COLUMN_COUNT] = [5.0, 14.5, 50.0, 7.5, 12.5, 12.0, 14.0, 16.0, 12.0, 10.0, 12.0, 10.0];
+
+/// Формат выгрузки для команд, которые выбирают его параметром
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Format {
+    Xlsx,
+    Pdf,
+    Csv,
+    Ods,
+    Html,
+    Markdown,
+    Docx,
+    Svg,
+    Png,
+    Jpeg,
+    Ics,
+}
+
+impl Format {
+    /// Расширение файла без точки
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Xlsx => "xlsx",
+            Format::Pdf => "pdf",
+            Format::Csv => "csv",
+            Format::Ods => "ods",
+            Format::Html => "html",
+            Format::Markdown => "md",
+            Format::Docx => "docx",
+            Format::Svg => "svg",
+            Format::Png => "png",
+            Format::Jpeg => "jpg",
+            Format::Ics => "ics",
+        }
+    }
+
+    /// Формирует файл в оформлении шаблона с остальными параметрами формата по умолчанию.
+    /// Сведения о происхождении встраиваются в .xlsx, PDF и HTML
+    pub fn render(
+        self,
+        schedule: &Schedule,
+        template: &ExportTemplate,
+        provenance: &provenance::Provenance,
+    ) -> Result<Vec<u8>, String> {
+        match self {
+            Format::Xlsx => xlsx::render(schedule, false, template, provenance),
+            Format::Pdf => pdf::render(schedule, None, &pdf::PageSetup::for_template(template), template, provenance),
+            Format::Csv => csv::render(schedule, Default::default()).map(String::into_bytes),
+            Format::Ods => ods::render(schedule, template),
+            Format::Html => Ok(html::render(schedule, false, template, provenance).into_bytes()),
+            Format::Markdown => Ok(markdown::render(schedule, Default::default()).into_bytes()),
+            Format::Docx => docx::render(schedule, &Default::default(), template),
+            Format::Svg => Ok(image::render_svg(schedule, template).into_bytes()),
+            Format::Png => image::render_png(schedule, &Default::default(), template),
+            Format::Jpeg => image::render_jpeg(schedule, &Default::default(), template),
+            Format::Ics => ics::render(schedule, None, None).map(String::into_bytes),
+        }
+    }
+}
+
+/// Заголовок записи с режимами, как в выгрузке Excel из фронтенда
+pub fn entry_title(entry: &ScheduleEntry) -> String {
+    let pdtv_mode = match entry.rows.first() {
+        Some(row) if row.pdtv_auto_mode => "Авто",
+        _ => "НЕ Авто",
+    };
+    format!(
+        "{} | Режим Времени: {} | Режим ПДТВ: {}",
+        entry.title,
+        entry.time_mode_label(),
+        pdtv_mode
+    )
+}
+
+/// Заголовки колонок таблицы записи
+pub fn headers(entry: &ScheduleEntry) -> [String; COLUMN_COUNT] {
+    [
+        "№".to_string(),
+        "ПДТВ".to_string(),
+        "Операция".to_string(),
+        "Обед?".to_string(),
+        "Пауза".to_string(),
+        format!("Работа{}", entry.unit_suffix()),
+        "Дата проводки".to_string(),
+        "Исполнитель".to_string(),
+        "Дата Начала".to_string(),
+        "Время Начала".to_string(),
+        "Дата Конца".to_string(),
+        "Время Конца".to_string(),
+    ]
+}
+
+/// Значения ячеек строки в порядке headers
+pub fn cells(row: &OperationRow) -> [String; COLUMN_COUNT] {
+    [
+        row.original_op_index.clone(),
+        row.op_idx.clone(),
+        row.name.clone(),
+        if row.crossed_lunch { "Да".to_string() } else { String::new() },
+        row.pause_text.clone(),
+        row.dur_text.clone(),
+        row.posting_date.clone(),
+        row.worker.clone(),
+        row.start_date.clone(),
+        row.start_time.clone(),
+        row.end_date.clone(),
+        row.end_time.clone(),
+    ]
+}
+
+/// Экранирует текст для вставки в XML и HTML
+pub fn escape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            // Управляющие символы недопустимы в XML 1.0
+            c if c.is_control() && !matches!(c, '\t' | '\n' | '\r') => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,218 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Выгрузка расписания в OpenDocument (.ods) для LibreOffice Calc.
+// Файл - zip-архив: mimetype (первым и без сжатия), манифест, content.xml с таблицей
+// и styles.xml с параметрами страницы для печати из шаблона оформления.
+
+use std::fmt::Write as _;
+use std::io::{Cursor, Write};
+
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use super::templates::{css_color, ExportTemplate, Orientation};
+use super::{cells, entry_title, escape_xml, headers, COLUMN_COUNT, WORK_COLUMN};
+use crate::model::{Schedule, ScheduleEntry};
+
+const MIMETYPE: &str = "application/vnd.oasis.opendocument.spreadsheet";
+
+const SHEET_NAME: &str = "История";
+
+// Ширина одного символа колонки в сантиметрах
+const CHAR_WIDTH_CM: f64 = 0.2;
+
+const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0" manifest:version="1.2">
+ <manifest:file-entry manifest:full-path="/" manifest:version="1.2" manifest:media-type="application/vnd.oasis.opendocument.spreadsheet"/>
+ <manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/>
+ <manifest:file-entry manifest:full-path="styles.xml" manifest:media-type="text/xml"/>
+</manifest:manifest>
+"#;
+
+const CONTENT_HEAD: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0" xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0" office:version="1.2">
+"#;
+
+const STYLES_HEAD: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-styles xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0" xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0" office:version="1.2">
+"#;
+
+// Стили ячеек: те же заливки и рамки, что в выгрузке .xlsx. $HEADER, $Z7, $FONT и $FONT_TITLE
+// заменяются значениями из шаблона оформления
+const CELL_STYLES: &str = r##"<style:style style:name="title" style:family="table-cell"><style:table-cell-properties fo:background-color="$HEADER" fo:border="0.5pt solid #000000" style:vertical-align="middle"/><style:paragraph-properties fo:text-align="center"/><style:text-properties $FONT_TITLE fo:font-weight="bold"/></style:style>
+<style:style style:name="header" style:family="table-cell"><style:table-cell-properties fo:background-color="$HEADER" fo:border="0.5pt solid #000000" fo:wrap-option="wrap" style:vertical-align="middle"/><style:paragraph-properties fo:text-align="center"/><style:text-properties $FONT fo:font-weight="bold"/></style:style>
+<style:style style:name="cell" style:family="table-cell"><style:table-cell-properties fo:border="0.5pt solid #000000" style:vertical-align="middle"/><style:paragraph-properties fo:text-align="center"/><style:text-properties $FONT/></style:style>
+<style:style style:name="name" style:family="table-cell"><style:table-cell-properties fo:border="0.5pt solid #000000" fo:wrap-option="wrap" style:vertical-align="middle"/><style:paragraph-properties fo:text-align="center"/><style:text-properties $FONT fo:font-weight="bold"/></style:style>
+<style:style style:name="z7header" style:family="table-cell"><style:table-cell-properties fo:background-color="$Z7" fo:border="0.5pt solid #000000"/><style:paragraph-properties fo:text-align="center"/><style:text-properties $FONT fo:font-weight="bold"/></style:style>
+<style:style style:name="z7line" style:family="table-cell"><style:table-cell-properties fo:border="0.5pt solid #000000" fo:wrap-option="wrap"/><style:paragraph-properties fo:text-align="start"/><style:text-properties $FONT/></style:style>
+"##;
+
+/// Формирует документ .ods со всеми записями расписания
+pub fn render(schedule: &Schedule, template: &ExportTemplate) -> Result<Vec<u8>, String> {
+    let content = content_xml(schedule, template);
+    let styles = styles_xml(template)?;
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let files = [
+        ("mimetype", MIMETYPE, stored),
+        ("META-INF/manifest.xml", MANIFEST, deflated),
+        ("content.xml", content.as_str(), deflated),
+        ("styles.xml", styles.as_str(), deflated),
+    ];
+    for (name, data, options) in files {
+        zip.start_file(name, options)
+            .map_err(|e| format!("Ошибка формирования ODS: {}", e))?;
+        zip.write_all(data.as_bytes())
+            .map_err(|e| format!("Ошибка формирования ODS: {}", e))?;
+    }
+
+    zip.finish()
+        .map(Cursor::into_inner)
+        .map_err(|e| format!("Ошибка формирования ODS: {}", e))
+}
+
+/// Разметка страницы для печати: формат бумаги, ориентация и поля шаблона.
+/// Таблица ссылается на неё через стиль таблицы ta и страницу-образец Default
+fn styles_xml(template: &ExportTemplate) -> Result<String, String> {
+    let page = &template.page;
+    let (width, height) = page.page()?;
+    let orientation = match page.orientation {
+        Orientation::Portrait => "portrait",
+        Orientation::Landscape => "landscape",
+    };
+    let m = &page.margins;
+    let mut xml = String::from(STYLES_HEAD);
+    let _ = writeln!(
+        xml,
+        r#"<office:automatic-styles><style:page-layout style:name="pm"><style:page-layout-properties fo:page-width="{}mm" fo:page-height="{}mm" style:print-orientation="{}" fo:margin-top="{}mm" fo:margin-right="{}mm" fo:margin-bottom="{}mm" fo:margin-left="{}mm"/></style:page-layout></office:automatic-styles>"#,
+        width, height, orientation, m.top, m.right, m.bottom, m.left
+    );
+    xml.push_str(r#"<office:master-styles><style:master-page style:name="Default" style:page-layout-name="pm"/></office:master-styles>"#);
+    xml.push_str("\n</office:document-styles>\n");
+    Ok(xml)
+}
+
+fn content_xml(schedule: &Schedule, template: &ExportTemplate) -> String {
+    let mut xml = String::from(CONTENT_HEAD);
+
+    xml.push_str("<office:automatic-styles>\n");
+    xml.push_str(r#"<style:style style:name="ta" style:family="table" style:master-page-name="Default"><style:table-properties table:display="true"/></style:style>"#);
+    xml.push('\n');
+    for (i, width) in template.column_widths().iter().enumerate() {
+        let _ = writeln!(
+            xml,
+            r#"<style:style style:name="co{}" style:family="table-column"><style:table-column-properties style:column-width="{:.2}cm"/></style:style>"#,
+            i,
+            width * CHAR_WIDTH_CM
+        );
+    }
+    let font = format!(
+        r#"fo:font-family="{}" fo:font-size="{}pt""#,
+        escape_xml(&template.font_name),
+        template.font_size
+    );
+    let title_font = format!(
+        r#"fo:font-family="{}" fo:font-size="{}pt""#,
+        escape_xml(&template.font_name),
+        template.font_size + 2.0
+    );
+    xml.push_str(
+        &CELL_STYLES
+            .replace("$HEADER", &css_color(template.header_rgb()))
+            .replace("$Z7", &css_color(template.z7_rgb()))
+            .replace("$FONT_TITLE", &title_font)
+            .replace("$FONT", &font),
+    );
+    let row_height = if template.row_height > 0.0 {
+        format!(r#"style:row-height="{}pt""#, template.row_height)
+    } else {
+        r#"style:use-optimal-row-height="true""#.to_string()
+    };
+    let _ = writeln!(
+        xml,
+        r#"<style:style style:name="ro" style:family="table-row"><style:table-row-properties {}/></style:style>"#,
+        row_height
+    );
+    xml.push_str("</office:automatic-styles>\n");
+
+    let _ = writeln!(xml, r#"<office:body><office:spreadsheet><table:table table:name="{}" table:style-name="ta">"#, SHEET_NAME);
+    for i in 0..COLUMN_COUNT {
+        let _ = writeln!(xml, r#"<table:table-column table:style-name="co{}"/>"#, i);
+    }
+
+    let header_text = template.header_text.trim();
+    if !header_text.is_empty() {
+        merged_row(&mut xml, header_text, "title");
+        xml.push_str("<table:table-row><table:table-cell/></table:table-row>\n");
+    }
+
+    for entry in &schedule.entries {
+        write_entry(&mut xml, entry);
+        // Пустая строка между записями
+        xml.push_str("<table:table-row><table:table-cell/></table:table-row>\n");
+    }
+
+    xml.push_str("</table:table></office:spreadsheet></office:body></office:document-content>\n");
+    xml
+}
+
+fn write_entry(xml: &mut String, entry: &ScheduleEntry) {
+    merged_row(xml, &entry_title(entry), "title");
+
+    xml.push_str("<table:table-row>");
+    for label in headers(entry) {
+        string_cell(xml, &label, "header");
+    }
+    xml.push_str("</table:table-row>\n");
+
+    for op in entry.sorted_rows() {
+        xml.push_str(r#"<table:table-row table:style-name="ro">"#);
+        for (col, value) in cells(op).iter().enumerate() {
+            match col {
+                WORK_COLUMN => {
+                    let _ = write!(
+                        xml,
+                        r#"<table:table-cell table:style-name="cell" office:value-type="float" office:value="{}"><text:p>{}</text:p></table:table-cell>"#,
+                        op.dur_val,
+                        escape_xml(value)
+                    );
+                }
+                2 => string_cell(xml, value, "name"),
+                _ => string_cell(xml, value, "cell"),
+            }
+        }
+        xml.push_str("</table:table-row>\n");
+    }
+
+    if !entry.z7.is_empty() {
+        merged_row(xml, "Z7", "z7header");
+        for line in &entry.z7 {
+            merged_row(xml, line, "z7line");
+        }
+    }
+}
+
+fn string_cell(xml: &mut String, value: &str, style: &str) {
+    let _ = write!(
+        xml,
+        r#"<table:table-cell table:style-name="{}" office:value-type="string"><text:p>{}</text:p></table:table-cell>"#,
+        style,
+        escape_xml(value)
+    );
+}
+
+/// Строка с одной ячейкой, объединённой на всю ширину таблицы
+fn merged_row(xml: &mut String, value: &str, style: &str) {
+    let _ = writeln!(
+        xml,
+        r#"<table:table-row><table:table-cell table:style-name="{}" table:number-columns-spanned="{}" office:value-type="string"><text:p>{}</text:p></table:table-cell><table:covered-table-cell table:number-columns-repeated="{}"/></table:table-row>"#,
+        style,
+        COLUMN_COUNT,
+        escape_xml(value),
+        COLUMN_COUNT - 1
+    );
+}
@@ -0,0 +1,541 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Выгрузка расписания в PDF для печати: каждая запись истории начинается с новой
+// страницы. Параметры страницы (PageSetup) - формат бумаги, поля и ориентация
+// (PageLayout, по умолчанию - из шаблона оформления), ориентация отдельных записей
+// и вписывание таблицы в ширину страницы.
+// Встроенные шрифты PDF не содержат кириллицы, поэтому используется системный
+// TrueType-шрифт.
+//
+// Разбивка на страницы (Writer) не зависит от формата: она выводит текст, рамки и
+// логотип на поверхность Canvas. Кроме PDF, по ней строится постраничный SVG (image.rs).
+
+use std::fs::File;
+use std::path::PathBuf;
+
+use printpdf::{
+    ColorBits, ColorSpace, Image, ImageFilter, ImageTransform, ImageXObject, IndirectFontRef, Line, Mm, PdfDocument,
+    PdfDocumentReference, PdfLayerReference, Point, Px,
+};
+use serde::Deserialize;
+
+use super::logo::{self, Logo, LogoKind};
+use super::provenance::Provenance;
+use super::templates::{ExportTemplate, LogoPlacement, Margins, Orientation, PageLayout};
+use super::{cells, entry_title, headers, COLUMN_COUNT};
+use crate::model::{Schedule, ScheduleEntry};
+
+// Высота строки по умолчанию и запас над текстом, мм
+const ROW_HEIGHT: f32 = 6.0;
+const ROW_PADDING: f32 = 3.0;
+// Кегль шаблона уменьшается до печатного: 12 колонок на A4 не помещаются крупнее
+const MAX_FONT_SIZE: f32 = 10.0;
+const TITLE_FONT_STEP: f32 = 3.0;
+
+// 1 пункт = 0.3528 мм; средняя ширина символа - около половины кегля
+pub(super) const PT_TO_MM: f32 = 0.3528;
+const CHAR_WIDTH_RATIO: f32 = 0.5;
+
+// Логотип в шапке страницы: высота и наибольшая ширина, мм
+const LOGO_HEIGHT: f32 = 12.0;
+const LOGO_MAX_WIDTH: f32 = 50.0;
+const LOGO_GAP: f32 = 3.0;
+const IMAGE_DPI: f32 = 300.0;
+const MM_PER_INCH: f32 = 25.4;
+
+/// Ориентация страниц одной записи истории
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SectionSetup {
+    /// Индекс записи в schedule.entries
+    pub entry: usize,
+    pub orientation: Orientation,
+}
+
+/// Параметры страницы
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PageSetup {
+    /// Формат бумаги, поля и ориентация документа
+    #[serde(flatten)]
+    pub layout: PageLayout,
+    /// Растянуть или сжать колонки на ширину страницы; иначе колонки выводятся
+    /// шириной из шаблона, и таблица должна поместиться между полями
+    pub fit_to_page: bool,
+    /// Ориентация отдельных записей, остальные - по orientation
+    pub sections: Vec<SectionSetup>,
+    /// Для двусторонней печати: у каждой записи чётное число страниц (добавляется
+    /// пустая), и каждая начинается с лицевой стороны листа
+    pub duplex: bool,
+}
+
+impl Default for PageSetup {
+    fn default() -> Self {
+        PageSetup { layout: PageLayout::default(), fit_to_page: true, sections: Vec::new(), duplex: false }
+    }
+}
+
+impl PageSetup {
+    /// Параметры страницы из шаблона оформления: выгрузка без своих параметров
+    /// печатается так же, как в шаблоне
+    pub fn for_template(template: &ExportTemplate) -> PageSetup {
+        PageSetup { layout: template.page, ..PageSetup::default() }
+    }
+
+    /// Размер страницы записи entry с учётом ориентации, мм
+    pub(super) fn page(&self, entry: usize) -> Result<(f32, f32), String> {
+        let orientation = self
+            .sections
+            .iter()
+            .find(|s| s.entry == entry)
+            .map_or(self.layout.orientation, |s| s.orientation);
+        PageLayout { orientation, ..self.layout }.page()
+    }
+
+    pub(super) fn validate(&self, schedule: &Schedule) -> Result<(), String> {
+        self.layout.validate()?;
+        for (i, section) in self.sections.iter().enumerate() {
+            if section.entry >= schedule.entries.len() {
+                return Err(format!("Запись {} для ориентации страниц не найдена", section.entry + 1));
+            }
+            if self.sections[..i].iter().any(|s| s.entry == section.entry) {
+                return Err(format!("Ориентация записи {} указана дважды", section.entry + 1));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Системные шрифты с кириллицей в порядке предпочтения
+fn font_candidates() -> Vec<PathBuf> {
+    let mut list = Vec::new();
+    #[cfg(windows)]
+    {
+        let windir = std::env::var_os("WINDIR").map(PathBuf::from).unwrap_or_else(|| PathBuf::from(r"C:\Windows"));
+        list.push(windir.join("Fonts").join("arial.ttf"));
+        list.push(windir.join("Fonts").join("calibri.ttf"));
+    }
+    #[cfg(target_os = "macos")]
+    {
+        list.push(PathBuf::from("/System/Library/Fonts/Supplemental/Arial.ttf"));
+        list.push(PathBuf::from("/Library/Fonts/Arial Unicode.ttf"));
+    }
+    #[cfg(not(any(windows, target_os = "macos")))]
+    {
+        list.push(PathBuf::from("/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf"));
+        list.push(PathBuf::from("/usr/share/fonts/TTF/DejaVuSans.ttf"));
+        list.push(PathBuf::from("/usr/share/fonts/dejavu/DejaVuSans.ttf"));
+        list.push(PathBuf::from("/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf"));
+    }
+    list
+}
+
+/// Формирует PDF со всеми записями расписания. В шапке каждой страницы - название
+/// организации (если указано), текст шапки шаблона и дата формирования.
+/// При setup.fit_to_page ширины колонок шаблона масштабируются на ширину страницы.
+/// Сведения о происхождении записываются в сведения о документе
+pub fn render(
+    schedule: &Schedule,
+    organization: Option<&str>,
+    setup: &PageSetup,
+    template: &ExportTemplate,
+    provenance: &Provenance,
+) -> Result<Vec<u8>, String> {
+    setup.validate(schedule)?;
+    let logo = logo::for_template(template);
+    let canvas = PdfCanvas::new(setup.page(0)?, logo.as_ref().map(|(logo, _)| logo))?.with_provenance(provenance);
+    paginate(canvas, schedule, organization, setup, template, logo.as_ref())?.save()
+}
+
+/// Поверхность, на которую Writer выводит страницы. Координаты и размеры - в мм
+/// от левого нижнего угла страницы, кегль - в пунктах. Первая страница размером
+/// setup.page(0) создаётся вместе с поверхностью
+pub(super) trait Canvas {
+    fn add_page(&mut self, width: f32, height: f32);
+    /// Текст: y - базовая линия
+    fn text(&mut self, text: &str, size: f32, x: f32, y: f32);
+    fn rect(&mut self, x: f32, y: f32, width: f32, height: f32);
+    /// Логотип из for_template: x, y - левый нижний угол
+    fn logo(&mut self, x: f32, y: f32, width: f32, height: f32);
+}
+
+/// Раскладывает записи расписания по страницам поверхности canvas; logo - результат
+/// logo::for_template. Параметры страницы уже проверены (setup.validate)
+pub(super) fn paginate<C: Canvas>(
+    canvas: C,
+    schedule: &Schedule,
+    organization: Option<&str>,
+    setup: &PageSetup,
+    template: &ExportTemplate,
+    logo: Option<&(Logo, LogoPlacement)>,
+) -> Result<C, String> {
+    let date = chrono::Local::now().format("%d.%m.%Y").to_string();
+    let page_header: Vec<&str> = [organization.unwrap_or(""), template.header_text.as_str(), date.as_str()]
+        .into_iter()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    let mut writer = Writer::new(canvas, page_header.join(" | "), setup, template, logo)?;
+    for (i, entry) in schedule.entries.iter().enumerate() {
+        if i > 0 && setup.duplex {
+            writer.pad_to_even();
+        }
+        writer.set_page(setup.page(i)?)?;
+        if i > 0 {
+            writer.new_page();
+        }
+        writer.entry(entry);
+    }
+    if setup.duplex {
+        writer.pad_to_even();
+    }
+    Ok(writer.canvas)
+}
+
+/// PDF-документ с системным шрифтом
+pub(super) struct PdfCanvas {
+    doc: PdfDocumentReference,
+    font: IndirectFontRef,
+    layer: PdfLayerReference,
+    logo: Option<ImageXObject>,
+}
+
+impl PdfCanvas {
+    /// Документ с первой страницей размером width x height мм
+    pub(super) fn new((width, height): (f32, f32), logo: Option<&Logo>) -> Result<Self, String> {
+        let (doc, page, layer) = PdfDocument::new("Расписание", Mm(width), Mm(height), "Слой 1");
+        let font_path = font_candidates()
+            .into_iter()
+            .find(|p| p.exists())
+            .ok_or("Не найден системный шрифт с кириллицей для PDF")?;
+        let file = File::open(&font_path).map_err(|e| format!("Ошибка чтения шрифта: {}", e))?;
+        let font = doc
+            .add_external_font(file)
+            .map_err(|e| format!("Ошибка загрузки шрифта: {}", e))?;
+        let layer = doc.get_page(page).get_layer(layer);
+        let logo = logo.map(logo_image).transpose()?;
+        Ok(PdfCanvas { doc, font, layer, logo })
+    }
+
+    /// Записывает сведения о происхождении: программу - в Creator, контрольную сумму -
+    /// в идентификатор документа, остальные свойства - в ключевые слова «имя=значение»
+    pub(super) fn with_provenance(self, provenance: &Provenance) -> Self {
+        let keywords: Vec<String> = provenance
+            .properties()
+            .into_iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        let doc = self
+            .doc
+            .with_creator(provenance.creator())
+            .with_producer(provenance.creator())
+            .with_identifier(provenance.checksum.clone())
+            .with_keywords(keywords);
+        PdfCanvas { doc, ..self }
+    }
+
+    pub(super) fn save(self) -> Result<Vec<u8>, String> {
+        self.doc
+            .save_to_bytes()
+            .map_err(|e| format!("Ошибка формирования PDF: {}", e))
+    }
+}
+
+impl Canvas for PdfCanvas {
+    fn add_page(&mut self, width: f32, height: f32) {
+        let (page, layer) = self.doc.add_page(Mm(width), Mm(height), "Слой 1");
+        self.layer = self.doc.get_page(page).get_layer(layer);
+    }
+
+    fn text(&mut self, text: &str, size: f32, x: f32, y: f32) {
+        self.layer.use_text(text, size, Mm(x), Mm(y), &self.font);
+    }
+
+    fn rect(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        let points = vec![
+            (Point::new(Mm(x), Mm(y)), false),
+            (Point::new(Mm(x + width), Mm(y)), false),
+            (Point::new(Mm(x + width), Mm(y + height)), false),
+            (Point::new(Mm(x), Mm(y + height)), false),
+        ];
+        self.layer.set_outline_thickness(0.3);
+        self.layer.add_line(Line { points, is_closed: true });
+    }
+
+    fn logo(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        let Some(image) = &self.logo else {
+            return;
+        };
+        // Без масштаба изображение занимает пиксели / DPI дюймов
+        let natural = |px: usize| px as f32 / IMAGE_DPI * MM_PER_INCH;
+        let transform = ImageTransform {
+            translate_x: Some(Mm(x)),
+            translate_y: Some(Mm(y)),
+            scale_x: Some(width / natural(image.width.0)),
+            scale_y: Some(height / natural(image.height.0)),
+            dpi: Some(IMAGE_DPI),
+            ..Default::default()
+        };
+        Image::from(image.clone()).add_to_layer(self.layer.clone(), transform);
+    }
+}
+
+/// Изображение логотипа для встраивания в PDF
+fn logo_image(logo: &Logo) -> Result<ImageXObject, String> {
+    let (image_data, color_space, image_filter) = match logo.kind {
+        LogoKind::Png => (logo::decode_png(&logo.bytes)?.2, ColorSpace::Rgb, None),
+        // JPEG встраивается без перекодирования
+        LogoKind::Jpeg => {
+            let color_space = match logo.components {
+                1 => ColorSpace::Greyscale,
+                4 => ColorSpace::Cmyk,
+                _ => ColorSpace::Rgb,
+            };
+            (logo.bytes.clone(), color_space, Some(ImageFilter::DCT))
+        }
+    };
+    Ok(ImageXObject {
+        width: Px(logo.width as usize),
+        height: Px(logo.height as usize),
+        color_space,
+        bits_per_component: ColorBits::Bit8,
+        interpolate: true,
+        image_data,
+        image_filter,
+        smask: None,
+        clipping_bbox: None,
+    })
+}
+
+struct Writer<C: Canvas> {
+    canvas: C,
+    page_header: String,
+    logo: Option<PageLogo>,
+    // Размер текущей страницы и поля, мм
+    page_width: f32,
+    page_height: f32,
+    margins: Margins,
+    fit_to_page: bool,
+    // Ширины колонок шаблона в символах и на текущей странице в мм
+    chars: [f64; COLUMN_COUNT],
+    widths: [f32; COLUMN_COUNT],
+    font_size: f32,
+    title_font_size: f32,
+    row_height: f32,
+    // Текущая позиция по вертикали (от нижнего края страницы)
+    y: f32,
+    // Страниц на поверхности
+    pages: usize,
+}
+
+impl<C: Canvas> Writer<C> {
+    fn new(
+        canvas: C,
+        page_header: String,
+        setup: &PageSetup,
+        template: &ExportTemplate,
+        logo: Option<&(Logo, LogoPlacement)>,
+    ) -> Result<Self, String> {
+        let (page_width, page_height) = setup.page(0)?;
+        let font_size = (template.font_size as f32).min(MAX_FONT_SIZE);
+        let row_height = if template.row_height > 0.0 {
+            (template.row_height as f32 * PT_TO_MM).max(font_size * PT_TO_MM + ROW_PADDING)
+        } else {
+            ROW_HEIGHT.max(font_size * PT_TO_MM + ROW_PADDING)
+        };
+
+        let mut writer = Writer {
+            canvas,
+            page_header,
+            logo: logo.map(|(logo, placement)| PageLogo::new(logo, *placement)),
+            page_width,
+            page_height,
+            margins: setup.layout.margins,
+            fit_to_page: setup.fit_to_page,
+            chars: template.column_widths(),
+            widths: [0.0; COLUMN_COUNT],
+            font_size,
+            title_font_size: font_size + TITLE_FONT_STEP,
+            row_height,
+            y: 0.0,
+            pages: 1,
+        };
+        writer.set_page((page_width, page_height))?;
+        writer.start_page();
+        Ok(writer)
+    }
+
+    /// Размер следующих страниц и ширины колонок на них
+    fn set_page(&mut self, (width, height): (f32, f32)) -> Result<(), String> {
+        let printable = width - self.margins.left - self.margins.right;
+        let total: f64 = self.chars.iter().sum();
+        if self.fit_to_page {
+            self.widths = self.chars.map(|w| (w / total * f64::from(printable)) as f32);
+        } else {
+            let char_width = self.font_size * PT_TO_MM * CHAR_WIDTH_RATIO;
+            self.widths = self.chars.map(|w| w as f32 * char_width);
+            let table = self.widths.iter().sum::<f32>();
+            if table > printable {
+                return Err(format!(
+                    "Таблица шириной {:.0} мм не помещается между полями ({:.0} мм): \
+                     включите вписывание в страницу, уменьшите шрифт или поля",
+                    table, printable
+                ));
+            }
+        }
+        self.page_width = width;
+        self.page_height = height;
+        Ok(())
+    }
+
+    fn new_page(&mut self) {
+        self.canvas.add_page(self.page_width, self.page_height);
+        self.pages += 1;
+        self.start_page();
+    }
+
+    /// Добавляет пустую страницу без шапки, если страниц нечётное число: следующая
+    /// печатается на лицевой стороне
+    fn pad_to_even(&mut self) {
+        if self.pages % 2 == 1 {
+            self.canvas.add_page(self.page_width, self.page_height);
+            self.pages += 1;
+        }
+    }
+
+    fn start_page(&mut self) {
+        self.y = self.page_height - self.margins.top;
+        let mut header_x = self.margins.left;
+        let mut header_height = self.row_height;
+        if let Some(logo) = &self.logo {
+            let x = match logo.placement {
+                LogoPlacement::Right => self.page_width - self.margins.right - logo.width,
+                _ => {
+                    header_x += logo.width + LOGO_GAP;
+                    self.margins.left
+                }
+            };
+            // По высоте логотип выравнивается по центру шапки
+            let y = self.y - LOGO_HEIGHT + (LOGO_HEIGHT - logo.height) / 2.0;
+            self.canvas.logo(x, y, logo.width, logo.height);
+            header_height = header_height.max(LOGO_HEIGHT);
+        }
+        let y = self.y - self.font_size * PT_TO_MM;
+        self.canvas.text(&self.page_header, self.font_size, header_x, y);
+        self.y -= header_height + 2.0;
+    }
+
+    /// Переносит вывод на новую страницу, если блок высотой height не помещается
+    fn ensure_space(&mut self, height: f32) -> bool {
+        if self.y - height < self.margins.bottom {
+            self.new_page();
+            return true;
+        }
+        false
+    }
+
+    fn entry(&mut self, entry: &ScheduleEntry) {
+        let width = self.widths.iter().sum::<f32>();
+        let title = fit(&entry_title(entry), width, self.title_font_size);
+        let header = headers(entry);
+
+        self.text(&title, self.title_font_size, self.margins.left, self.y - self.title_font_size * PT_TO_MM);
+        self.y -= self.row_height + 2.0;
+        self.row(&header);
+
+        for op in entry.sorted_rows() {
+            // Заголовок таблицы повторяется на каждой странице
+            if self.ensure_space(self.row_height) {
+                self.row(&header);
+            }
+            self.row(&cells(op));
+        }
+
+        if !entry.z7.is_empty() {
+            self.y -= self.row_height / 2.0;
+            self.ensure_space(self.row_height * 2.0);
+            self.text("Z7", self.title_font_size, self.margins.left, self.y - self.title_font_size * PT_TO_MM);
+            self.y -= self.row_height;
+            for line in &entry.z7 {
+                for part in wrap(line, width, self.font_size) {
+                    self.ensure_space(self.row_height);
+                    self.text(&part, self.font_size, self.margins.left, self.y - self.row_height + 2.0);
+                    self.y -= self.row_height;
+                }
+            }
+        }
+    }
+
+    /// Строка таблицы: ячейки с рамками, текст обрезается по ширине колонки
+    fn row(&mut self, values: &[String; COLUMN_COUNT]) {
+        let bottom = self.y - self.row_height;
+        let mut x = self.margins.left;
+        for (value, width) in values.iter().zip(self.widths) {
+            self.rect(x, bottom, width, self.row_height);
+            self.text(&fit(value, width - 2.0, self.font_size), self.font_size, x + 1.0, bottom + 2.0);
+            x += width;
+        }
+        self.y = bottom;
+    }
+
+    fn text(&mut self, text: &str, size: f32, x: f32, y: f32) {
+        self.canvas.text(text, size, x, y);
+    }
+
+    fn rect(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        self.canvas.rect(x, y, width, height);
+    }
+}
+
+/// Место логотипа в шапке каждой страницы
+struct PageLogo {
+    placement: LogoPlacement,
+    // Размер на странице, мм
+    width: f32,
+    height: f32,
+}
+
+impl PageLogo {
+    fn new(logo: &Logo, placement: LogoPlacement) -> Self {
+        let width = (LOGO_HEIGHT * logo.aspect() as f32).min(LOGO_MAX_WIDTH);
+        PageLogo { placement, width, height: width / logo.aspect() as f32 }
+    }
+}
+
+/// Сколько символов помещается в ширину width (мм) при кегле size
+fn chars_in(width: f32, size: f32) -> usize {
+    (width / (size * PT_TO_MM * CHAR_WIDTH_RATIO)).max(1.0) as usize
+}
+
+/// Обрезает текст по ширине, добавляя многоточие
+pub(super) fn fit(text: &str, width: f32, size: f32) -> String {
+    let max = chars_in(width, size);
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut out: String = text.chars().take(max.saturating_sub(1)).collect();
+    out.push('…');
+    out
+}
+
+/// Разбивает текст на строки по ширине, перенося по пробелам
+pub(super) fn wrap(text: &str, width: f32, size: f32) -> Vec<String> {
+    let max = chars_in(width, size);
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        if !current.is_empty() && current.chars().count() + 1 + word.chars().count() > max {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
@@ -0,0 +1,102 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Личное расписание исполнителя: его операции из всех записей истории в порядке
+// времени, с простоями между ними. Результат - обычное расписание из одной записи,
+// поэтому его можно выгрузить любым форматом. Расписания всех исполнителей собираются
+// в одно расписание по записи на исполнителя - для печати одним заданием.
+
+use chrono::NaiveDateTime;
+
+use crate::model::{OperationRow, Schedule, ScheduleEntry};
+
+// Перерывы короче минуты простоем не считаются
+const MIN_GAP_MINUTES: i64 = 1;
+
+const GAP_NAME: &str = "Простой";
+
+/// Собирает личное расписание исполнителя
+pub fn worker_schedule(schedule: &Schedule, worker: &str) -> Result<Schedule, String> {
+    let worker = worker.trim();
+    let mut rows: Vec<OperationRow> = schedule
+        .entries
+        .iter()
+        .flat_map(|entry| {
+            entry
+                .rows
+                .iter()
+                .filter(|r| r.worker.trim() == worker)
+                .map(move |r| labelled(entry, r))
+        })
+        .collect();
+    if rows.is_empty() {
+        return Err(format!("У исполнителя «{}» нет операций", worker));
+    }
+
+    // Операции без времени - в конце списка
+    rows.sort_by_key(|r| (r.start().is_none(), r.start()));
+
+    let mut timeline = Vec::with_capacity(rows.len() * 2);
+    let mut previous_end: Option<NaiveDateTime> = None;
+    for row in rows {
+        if let (Some(end), Some(start)) = (previous_end, row.start()) {
+            if (start - end).num_minutes() >= MIN_GAP_MINUTES {
+                timeline.push(gap(worker, end, start));
+            }
+        }
+        previous_end = row.end().max(previous_end);
+        timeline.push(row);
+    }
+    for (i, row) in timeline.iter_mut().enumerate() {
+        row.op_numeric = Some(i as f64);
+    }
+
+    Ok(Schedule {
+        entries: vec![ScheduleEntry {
+            title: format!("Исполнитель: {}", worker),
+            rows: timeline,
+            ..Default::default()
+        }],
+        ..Default::default()
+    })
+}
+
+/// Личные расписания всех исполнителей по алфавиту: запись на исполнителя
+pub fn all_workers(schedule: &Schedule) -> Result<Schedule, String> {
+    let mut workers = schedule.workers();
+    if workers.is_empty() {
+        return Err("В расписании нет исполнителей".into());
+    }
+    workers.sort_by_key(|worker| worker.to_lowercase());
+    let mut entries = Vec::with_capacity(workers.len());
+    for worker in &workers {
+        entries.extend(worker_schedule(schedule, worker)?.entries);
+    }
+    Ok(Schedule { entries, ..Default::default() })
+}
+
+// Название операции дополняется техкартой: в личном расписании смешаны разные записи
+fn labelled(entry: &ScheduleEntry, row: &OperationRow) -> OperationRow {
+    let mut row = row.clone();
+    let card = entry.card_name();
+    if !card.is_empty() {
+        row.name = format!("{}: {}", card, row.name);
+    }
+    row
+}
+
+fn gap(worker: &str, from: NaiveDateTime, to: NaiveDateTime) -> OperationRow {
+    let minutes = (to - from).num_minutes();
+    OperationRow {
+        name: GAP_NAME.into(),
+        worker: worker.into(),
+        dur_val: minutes as f64,
+        dur_text: minutes.to_string(),
+        unit: "min".into(),
+        start_date: from.format("%d.%m.%Y").to_string(),
+        start_time: from.format("%H:%M:%S").to_string(),
+        end_date: to.format("%d.%m.%Y").to_string(),
+        end_time: to.format("%H:%M:%S").to_string(),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,87 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Сведения о происхождении выгрузки: версия программы, контрольная сумма исходных
+// данных (расписания или графика звонков), время формирования и параметры выгрузки.
+// По ним опубликованный файл можно сверить с проектом, из которого он сформирован.
+// Сведения встраиваются в файл там, где формат это позволяет: пользовательские свойства
+// документа .xlsx, сведения о документе PDF, <meta> в HTML. Если в настройках включено
+// provenanceSidecar, рядом с файлом любого формата пишется «имя.provenance.json».
+
+use std::path::{Path, PathBuf};
+
+use chrono::{Local, SecondsFormat};
+use serde::Serialize;
+use serde_json::Value;
+
+use super::Format;
+use crate::{integrity, settings};
+
+/// Название программы в сведениях о происхождении
+pub const GENERATOR: &str = "time-to-table";
+
+// Префикс имён встроенных свойств
+const PROPERTY_PREFIX: &str = "timetotable:";
+
+const SIDECAR_SUFFIX: &str = ".provenance.json";
+
+/// Сведения о происхождении выгрузки
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Provenance {
+    pub generator: String,
+    pub app_version: String,
+    /// SHA-256 исходных данных в JSON
+    pub checksum: String,
+    /// Время формирования в RFC 3339 с часовым поясом
+    pub generated_at: String,
+    pub format: Format,
+    /// Параметры выгрузки: шаблон, фильтры, разбивка
+    pub options: Value,
+}
+
+impl Provenance {
+    /// Сведения для выгрузки source в формате format
+    pub fn new<T: Serialize>(format: Format, source: &T, options: Value) -> Provenance {
+        let checksum = serde_json::to_vec(source).map(|json| integrity::digest(&json)).unwrap_or_default();
+        Provenance {
+            generator: GENERATOR.into(),
+            app_version: env!("CARGO_PKG_VERSION").into(),
+            checksum,
+            generated_at: Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+            format,
+            options,
+        }
+    }
+
+    /// Программа и версия, например «time-to-table 1.4.0»
+    pub fn creator(&self) -> String {
+        format!("{} {}", self.generator, self.app_version)
+    }
+
+    /// Пары «имя - значение» для встраивания в файл; параметры - строкой JSON
+    pub fn properties(&self) -> Vec<(String, String)> {
+        [
+            ("appVersion", self.app_version.clone()),
+            ("checksum", self.checksum.clone()),
+            ("generatedAt", self.generated_at.clone()),
+            ("format", self.format.extension().to_string()),
+            ("options", self.options.to_string()),
+        ]
+        .into_iter()
+        .map(|(name, value)| (format!("{}{}", PROPERTY_PREFIX, name), value))
+        .collect()
+    }
+
+    /// Файл сведений рядом с выгрузкой path и его содержимое; None - файл
+    /// сведений выключен в настройках
+    pub fn sidecar(&self, path: &Path) -> Option<(PathBuf, Vec<u8>)> {
+        if !settings::load().provenance_sidecar {
+            return None;
+        }
+        let mut name = path.file_name()?.to_os_string();
+        name.push(SIDECAR_SUFFIX);
+        let content = serde_json::to_vec_pretty(self).ok()?;
+        Some((path.with_file_name(name), content))
+    }
+}
@@ -0,0 +1,97 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Отчёты-таблицы в .xlsx, которые не являются расписанием (загрузка исполнителей и т. п.):
+// на каждом листе заголовок и таблица со строкой заголовков. Шрифт и цвет заголовков
+// берутся из шаблона выгрузки, как у расписаний.
+
+use rust_xlsxwriter::{Color, Format, FormatAlign, FormatBorder, Workbook, XlsxError};
+
+use super::templates::ExportTemplate;
+use super::xlsx::sheet_name;
+
+// Заливка отмеченных строк (превышения, нарушения)
+const MARKED_RGB: u32 = 0xF8D7DA;
+
+/// Ячейка отчёта
+#[derive(Debug, Clone)]
+pub enum Cell {
+    Text(String),
+    Number(f64),
+    Empty,
+}
+
+/// Строка отчёта; marked - выделить цветом
+#[derive(Debug, Clone)]
+pub struct Row {
+    pub cells: Vec<Cell>,
+    pub marked: bool,
+}
+
+/// Лист отчёта
+#[derive(Debug, Clone)]
+pub struct Table {
+    pub name: String,
+    pub title: String,
+    pub headers: Vec<String>,
+    /// Ширины колонок в символах
+    pub widths: Vec<f64>,
+    pub rows: Vec<Row>,
+}
+
+/// Формирует книгу Excel с листами отчёта
+pub fn render(tables: &[Table], template: &ExportTemplate) -> Result<Vec<u8>, String> {
+    build(tables, template).map_err(|e| format!("Ошибка формирования Excel: {}", e))
+}
+
+fn build(tables: &[Table], template: &ExportTemplate) -> Result<Vec<u8>, XlsxError> {
+    let cell = Format::new()
+        .set_font_name(template.font_name.as_str())
+        .set_font_size(template.font_size)
+        .set_border(FormatBorder::Thin)
+        .set_align(FormatAlign::VerticalCenter);
+    let header = cell
+        .clone()
+        .set_bold()
+        .set_align(FormatAlign::Center)
+        .set_background_color(Color::RGB(template.header_rgb()))
+        .set_text_wrap();
+    let title = header.clone().set_font_size(template.font_size + 2.0);
+    let number = cell.clone().set_align(FormatAlign::Center).set_num_format("0.##");
+    let marked = cell.clone().set_background_color(Color::RGB(MARKED_RGB));
+    let marked_number = number.clone().set_background_color(Color::RGB(MARKED_RGB));
+
+    let mut workbook = Workbook::new();
+    let mut used: Vec<String> = Vec::new();
+    for table in tables {
+        let sheet = workbook.add_worksheet();
+        let name = sheet_name(&table.name, &used);
+        sheet.set_name(&name)?;
+        used.push(name);
+        for (col, width) in table.widths.iter().enumerate() {
+            sheet.set_column_width(col as u16, *width)?;
+        }
+        let last = table.headers.len().saturating_sub(1) as u16;
+        if last > 0 {
+            sheet.merge_range(0, 0, 0, last, &table.title, &title)?;
+        } else {
+            sheet.write_string_with_format(0, 0, &table.title, &title)?;
+        }
+        sheet.set_row_height(0, 24)?;
+        for (col, label) in table.headers.iter().enumerate() {
+            sheet.write_string_with_format(1, col as u16, label, &header)?;
+        }
+        for (r, row) in (2..).zip(&table.rows) {
+            let (text_format, number_format) = if row.marked { (&marked, &marked_number) } else { (&cell, &number) };
+            for (col, value) in row.cells.iter().enumerate() {
+                let col = col as u16;
+                match value {
+                    Cell::Text(text) => sheet.write_string_with_format(r, col, text, text_format)?,
+                    Cell::Number(n) => sheet.write_number_with_format(r, col, *n, number_format)?,
+                    Cell::Empty => sheet.write_blank(r, col, text_format)?,
+                };
+            }
+        }
+    }
+    workbook.save_to_buffer()
+}
@@ -0,0 +1,304 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Шаблоны оформления выгрузок: шрифт, цвета, размеры ячеек, текст шапки, положение
+// логотипа и параметры страницы для печати (PDF, ODS). Пользовательские шаблоны хранятся в папке настроек, встроенный
+// «Стандартный» доступен всегда и повторяет оформление выгрузки по умолчанию.
+
+use serde::{Deserialize, Serialize};
+
+use super::{COLUMN_COUNT, COLUMN_WIDTHS};
+use crate::paths;
+
+// Файл шаблонов в папке настроек
+const TEMPLATES_FILE: &str = "export_templates.json";
+
+/// Имя встроенного шаблона
+pub const DEFAULT_TEMPLATE: &str = "Стандартный";
+
+// Пределы своего размера страницы и полей, мм
+const MIN_PAGE_SIDE: f32 = 50.0;
+const MAX_PAGE_SIDE: f32 = 2000.0;
+const MAX_MARGIN: f32 = 100.0;
+// Меньше этого на таблицу между полями не остаётся места, мм
+const MIN_PRINTABLE: f32 = 40.0;
+
+const MAX_NAME_CHARS: usize = 64;
+
+/// Положение логотипа в шапке выгрузки
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LogoPlacement {
+    #[default]
+    None,
+    Left,
+    Right,
+}
+
+/// Формат бумаги; размеры - для книжной ориентации
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PaperSize {
+    A3,
+    #[default]
+    A4,
+    A5,
+    Letter,
+    Legal,
+    /// Размер задаётся в width и height
+    Custom,
+}
+
+impl PaperSize {
+    /// Ширина и высота в книжной ориентации, мм
+    fn portrait(self) -> Option<(f32, f32)> {
+        match self {
+            PaperSize::A3 => Some((297.0, 420.0)),
+            PaperSize::A4 => Some((210.0, 297.0)),
+            PaperSize::A5 => Some((148.0, 210.0)),
+            PaperSize::Letter => Some((215.9, 279.4)),
+            PaperSize::Legal => Some((215.9, 355.6)),
+            PaperSize::Custom => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Orientation {
+    Portrait,
+    #[default]
+    Landscape,
+}
+
+/// Поля страницы, мм
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Margins {
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub left: f32,
+}
+
+impl Default for Margins {
+    fn default() -> Self {
+        Margins { top: 10.0, right: 10.0, bottom: 10.0, left: 10.0 }
+    }
+}
+
+/// Страница для печати: по умолчанию A4 альбомной ориентации с полями 10 мм
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PageLayout {
+    pub size: PaperSize,
+    /// Свой размер для size = custom, мм; стороны указываются для книжной ориентации
+    pub width: Option<f32>,
+    pub height: Option<f32>,
+    pub orientation: Orientation,
+    pub margins: Margins,
+}
+
+impl PageLayout {
+    /// Ширина и высота страницы в книжной ориентации, мм
+    pub fn portrait(&self) -> Result<(f32, f32), String> {
+        if let Some(size) = self.size.portrait() {
+            return Ok(size);
+        }
+        let (Some(width), Some(height)) = (self.width, self.height) else {
+            return Err("Для своего формата бумаги укажите ширину и высоту в мм".into());
+        };
+        let side = MIN_PAGE_SIDE..=MAX_PAGE_SIDE;
+        if !side.contains(&width) || !side.contains(&height) {
+            return Err(format!("Стороны страницы - от {} до {} мм", MIN_PAGE_SIDE, MAX_PAGE_SIDE));
+        }
+        Ok((width, height))
+    }
+
+    /// Ширина и высота страницы с учётом ориентации, мм
+    pub fn page(&self) -> Result<(f32, f32), String> {
+        let (width, height) = self.portrait()?;
+        let (short, long) = (width.min(height), width.max(height));
+        Ok(match self.orientation {
+            Orientation::Portrait => (short, long),
+            Orientation::Landscape => (long, short),
+        })
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let (width, height) = self.portrait()?;
+        let m = &self.margins;
+        if [m.top, m.right, m.bottom, m.left].iter().any(|v| !(0.0..=MAX_MARGIN).contains(v)) {
+            return Err(format!("Поля страницы - от 0 до {} мм", MAX_MARGIN));
+        }
+        // Ориентация меняет стороны местами, поэтому поля проверяются по меньшей стороне
+        let short = width.min(height);
+        if short - m.left - m.right < MIN_PRINTABLE || short - m.top - m.bottom < MIN_PRINTABLE {
+            return Err(format!("Между полями должно оставаться не меньше {} мм", MIN_PRINTABLE));
+        }
+        Ok(())
+    }
+}
+
+/// Шаблон оформления
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExportTemplate {
+    pub name: String,
+    pub font_name: String,
+    /// Кегль текста таблиц в пунктах
+    pub font_size: f64,
+    /// Цвета в формате #RRGGBB
+    pub header_fill: String,
+    pub z7_fill: String,
+    /// Ширины колонок в символах; пустой список - ширины по умолчанию
+    pub column_widths: Vec<f64>,
+    /// Высота строки в пунктах; 0 - автоматически
+    pub row_height: f64,
+    /// Текст шапки над таблицами (название отчёта, организация)
+    pub header_text: String,
+    pub logo: LogoPlacement,
+    /// Страница при печати
+    pub page: PageLayout,
+}
+
+impl Default for ExportTemplate {
+    fn default() -> Self {
+        ExportTemplate {
+            name: DEFAULT_TEMPLATE.into(),
+            font_name: "Calibri".into(),
+            font_size: 10.0,
+            header_fill: "#D9E1F2".into(),
+            z7_fill: "#FFF2CC".into(),
+            column_widths: Vec::new(),
+            row_height: 0.0,
+            header_text: String::new(),
+            logo: LogoPlacement::None,
+            page: PageLayout::default(),
+        }
+    }
+}
+
+impl ExportTemplate {
+    /// Ширина колонки в символах
+    pub fn column_width(&self, col: usize) -> f64 {
+        self.column_widths
+            .get(col)
+            .copied()
+            .filter(|w| *w > 0.0)
+            .unwrap_or(COLUMN_WIDTHS[col])
+    }
+
+    /// Все ширины колонок в символах
+    pub fn column_widths(&self) -> [f64; COLUMN_COUNT] {
+        std::array::from_fn(|col| self.column_width(col))
+    }
+
+    /// Цвет заливки заголовков как число 0xRRGGBB
+    pub fn header_rgb(&self) -> u32 {
+        parse_color(&self.header_fill).unwrap_or(0xD9E1F2)
+    }
+
+    /// Цвет заливки блока Z7 как число 0xRRGGBB
+    pub fn z7_rgb(&self) -> u32 {
+        parse_color(&self.z7_fill).unwrap_or(0xFFF2CC)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let name = self.name.trim();
+        if name.is_empty() || name.chars().count() > MAX_NAME_CHARS {
+            return Err(format!("Название шаблона должно быть от 1 до {} символов", MAX_NAME_CHARS));
+        }
+        if name == DEFAULT_TEMPLATE {
+            return Err("Встроенный шаблон нельзя перезаписать, выберите другое название".into());
+        }
+        if parse_color(&self.header_fill).is_none() || parse_color(&self.z7_fill).is_none() {
+            return Err("Цвета шаблона задаются в формате #RRGGBB".into());
+        }
+        if !(6.0..=36.0).contains(&self.font_size) {
+            return Err("Размер шрифта должен быть от 6 до 36".into());
+        }
+        if self.column_widths.len() > COLUMN_COUNT || self.column_widths.iter().any(|w| !(0.0..=255.0).contains(w)) {
+            return Err(format!("Ширины колонок: не больше {} значений от 0 до 255", COLUMN_COUNT));
+        }
+        if !(0.0..=409.0).contains(&self.row_height) {
+            return Err("Высота строки должна быть от 0 до 409".into());
+        }
+        self.page.validate()
+    }
+}
+
+/// Разбирает цвет #RRGGBB
+fn parse_color(raw: &str) -> Option<u32> {
+    let hex = raw.trim().strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok()
+}
+
+/// Цвет 0xRRGGBB в виде #rrggbb для CSS и SVG
+pub fn css_color(rgb: u32) -> String {
+    format!("#{:06x}", rgb)
+}
+
+fn user_templates() -> Vec<ExportTemplate> {
+    let Some(file) = paths::app_config_dir().map(|dir| dir.join(TEMPLATES_FILE)) else {
+        return Vec::new();
+    };
+    std::fs::read_to_string(file)
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+fn save_user_templates(list: &[ExportTemplate]) -> Result<(), String> {
+    let dir = paths::app_config_dir().ok_or("Не удалось определить папку настроек")?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| paths::io_error_message("Ошибка создания папки настроек", &e))?;
+    let content = serde_json::to_string_pretty(list)
+        .map_err(|e| format!("Ошибка сохранения шаблонов: {}", e))?;
+    std::fs::write(dir.join(TEMPLATES_FILE), content)
+        .map_err(|e| paths::io_error_message("Ошибка сохранения шаблонов", &e))
+}
+
+/// Все шаблоны: встроенный первым, затем пользовательские
+pub fn list() -> Vec<ExportTemplate> {
+    std::iter::once(ExportTemplate::default())
+        .chain(user_templates())
+        .collect()
+}
+
+/// Сохраняет шаблон (с тем же названием - заменяет)
+pub fn save(template: ExportTemplate) -> Result<(), String> {
+    template.validate()?;
+    let template = ExportTemplate { name: template.name.trim().to_string(), ..template };
+    let mut list = user_templates();
+    match list.iter_mut().find(|t| t.name == template.name) {
+        Some(existing) => *existing = template,
+        None => list.push(template),
+    }
+    save_user_templates(&list)
+}
+
+/// Удаляет пользовательский шаблон
+pub fn delete(name: &str) -> Result<(), String> {
+    let mut list = user_templates();
+    let before = list.len();
+    list.retain(|t| t.name != name);
+    if list.len() == before {
+        return Err("Шаблон не найден".into());
+    }
+    save_user_templates(&list)
+}
+
+/// Шаблон по названию; None - встроенный
+pub fn find(name: Option<&str>) -> Result<ExportTemplate, String> {
+    match name.map(str::trim).filter(|n| !n.is_empty() && *n != DEFAULT_TEMPLATE) {
+        None => Ok(ExportTemplate::default()),
+        Some(name) => user_templates()
+            .into_iter()
+            .find(|t| t.name == name)
+            .ok_or_else(|| format!("Шаблон «{}» не найден", name)),
+    }
+}
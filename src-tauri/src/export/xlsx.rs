@@ -0,0 +1,393 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Выгрузка расписания в .xlsx через rust_xlsxwriter. В отличие от выгрузки из фронтенда
+// здесь нет формул и защиты листа: это отчёт только для просмотра и печати. На листе
+// «Сводная» - нагрузка исполнителей с диаграммой часов и, если заданы рабочие места,
+// таблица их занятости с гистограммой в ячейках.
+
+use rust_xlsxwriter::{
+    Chart, ChartType, Color, ConditionalFormatDataBar, ConditionalFormatType, DocProperties, Format, FormatAlign,
+    FormatBorder, Image, Workbook, Worksheet, XlsxError,
+};
+
+use super::logo::{self, Logo};
+use super::provenance::Provenance;
+use super::templates::{ExportTemplate, LogoPlacement};
+use super::{cells, entry_title, headers, COLUMN_COUNT, WORK_COLUMN};
+use crate::model::{Schedule, ScheduleEntry};
+use crate::schedule::utilization::{self, UtilizationOptions, UtilizationReport};
+use crate::schedule::workload::{self, WorkloadReport};
+
+const SHEET_NAME: &str = "История";
+const SUMMARY_SHEET_NAME: &str = "Сводная";
+
+// Excel ограничивает имя листа 31 символом и запрещает символы []:*?/\
+const MAX_SHEET_NAME_CHARS: usize = 31;
+const SHEET_NAME_FORBIDDEN: &[char] = &['[', ']', ':', '*', '?', '/', '\\'];
+
+const SUMMARY_WIDTHS: [f64; 5] = [30.0, 14.0, 16.0, 16.0, 16.0];
+
+// Диаграмма нагрузки справа от таблиц сводной: колонка привязки и размер в пикселях
+const CHART_COLUMN: u16 = 6;
+const CHART_WIDTH_PX: u32 = 560;
+const CHART_ROW_PX: u32 = 24;
+const CHART_MIN_HEIGHT_PX: u32 = 288;
+
+const LAST_COLUMN: u16 = COLUMN_COUNT as u16 - 1;
+
+// Высота строки шапки; с логотипом строка выше, чтобы он был читаемым
+const HEADER_ROW_HEIGHT: f64 = 30.0;
+const LOGO_ROW_HEIGHT: f64 = 48.0;
+// Размер логотипа в пикселях и отступ от края строки
+const LOGO_HEIGHT_PX: f64 = 56.0;
+const LOGO_MAX_WIDTH_PX: f64 = 240.0;
+const LOGO_OFFSET_PX: u32 = 4;
+
+// Ширина колонки в пикселях для ширины в символах (шрифт Calibri 11)
+fn column_px(chars: f64) -> f64 {
+    chars * 7.0 + 5.0
+}
+
+struct Styles {
+    widths: [f64; COLUMN_COUNT],
+    // Высота строк данных; 0 - автоматически
+    row_height: f64,
+    header_text: String,
+    logo: Option<(Logo, LogoPlacement)>,
+    title: Format,
+    header: Format,
+    cell: Format,
+    name: Format,
+    number: Format,
+    z7_header: Format,
+    z7_line: Format,
+}
+
+impl Styles {
+    fn new(template: &ExportTemplate) -> Self {
+        let cell = Format::new()
+            .set_font_name(template.font_name.as_str())
+            .set_font_size(template.font_size)
+            .set_border(FormatBorder::Thin)
+            .set_align(FormatAlign::Center)
+            .set_align(FormatAlign::VerticalCenter);
+        let header = cell
+            .clone()
+            .set_bold()
+            .set_background_color(Color::RGB(template.header_rgb()))
+            .set_text_wrap();
+        Styles {
+            widths: template.column_widths(),
+            row_height: template.row_height,
+            header_text: template.header_text.trim().to_string(),
+            logo: logo::for_template(template),
+            title: header.clone().set_font_size(template.font_size + 2.0),
+            name: cell.clone().set_bold().set_text_wrap(),
+            number: cell.clone().set_num_format("0.###"),
+            z7_header: header.clone().set_background_color(Color::RGB(template.z7_rgb())),
+            z7_line: cell.clone().set_align(FormatAlign::Left).set_text_wrap(),
+            header,
+            cell,
+        }
+    }
+}
+
+/// Формирует книгу Excel со всеми записями расписания. split_sheets - каждая запись
+/// на своём листе, первым идёт лист «Сводная» с загрузкой исполнителей
+pub fn render(
+    schedule: &Schedule,
+    split_sheets: bool,
+    template: &ExportTemplate,
+    provenance: &Provenance,
+) -> Result<Vec<u8>, String> {
+    render_with_progress(schedule, split_sheets, None, template, provenance, &mut |_, _, _| Ok(()))
+}
+
+/// То же, что render; progress вызывается после каждой записи (сделано, всего, запись),
+/// его ошибка (отмена) прерывает формирование книги. places - рабочие места для таблицы
+/// занятости на листе «Сводная» (только с split_sheets). Сведения о происхождении
+/// записываются в пользовательские свойства книги
+pub fn render_with_progress(
+    schedule: &Schedule,
+    split_sheets: bool,
+    places: Option<&UtilizationOptions>,
+    template: &ExportTemplate,
+    provenance: &Provenance,
+    progress: &mut dyn FnMut(usize, usize, &str) -> Result<(), String>,
+) -> Result<Vec<u8>, String> {
+    let summary = if split_sheets {
+        let usage = places.map(|options| utilization::report(schedule, options)).transpose()?;
+        // Без операций с корректным временем нагрузку не посчитать, сводная - без неё
+        Some(Summary { workload: workload::report(schedule, &Default::default()).ok(), usage })
+    } else {
+        None
+    };
+    build(schedule, summary.as_ref(), template, provenance, progress).map_err(|stop| match stop {
+        Stop::Xlsx(e) => format!("Ошибка формирования Excel: {}", e),
+        Stop::Progress(message) => message,
+    })
+}
+
+// Причина остановки формирования книги
+enum Stop {
+    Xlsx(XlsxError),
+    Progress(String),
+}
+
+impl From<XlsxError> for Stop {
+    fn from(e: XlsxError) -> Self {
+        Stop::Xlsx(e)
+    }
+}
+
+// Отчёты для листа «Сводная»
+struct Summary {
+    workload: Option<WorkloadReport>,
+    usage: Option<UtilizationReport>,
+}
+
+fn build(
+    schedule: &Schedule,
+    summary: Option<&Summary>,
+    template: &ExportTemplate,
+    provenance: &Provenance,
+    progress: &mut dyn FnMut(usize, usize, &str) -> Result<(), String>,
+) -> Result<Vec<u8>, Stop> {
+    let mut workbook = Workbook::new();
+    let properties = provenance
+        .properties()
+        .into_iter()
+        .fold(DocProperties::new().set_comment(provenance.creator()), |props, (name, value)| {
+            props.set_custom_property(name, value)
+        });
+    workbook.set_properties(&properties);
+    let styles = Styles::new(template);
+    let total = schedule.entries.len();
+
+    let Some(summary) = summary else {
+        let sheet = table_sheet(&mut workbook, &styles, SHEET_NAME.to_string())?;
+        let mut row = write_sheet_header(sheet, &styles)?;
+        for (i, entry) in schedule.entries.iter().enumerate() {
+            // Пустая строка между записями
+            row = write_entry(sheet, &styles, entry, row)? + 1;
+            progress(i + 1, total, entry.card_name()).map_err(Stop::Progress)?;
+        }
+        return Ok(workbook.save_to_buffer()?);
+    };
+
+    write_summary(workbook.add_worksheet(), &styles, schedule, summary)?;
+    let mut used = vec![SUMMARY_SHEET_NAME.to_string()];
+    for (i, entry) in schedule.entries.iter().enumerate() {
+        let name = sheet_name(&format!("{} {}", i + 1, entry.card_name()), &used);
+        used.push(name.clone());
+        let sheet = table_sheet(&mut workbook, &styles, name)?;
+        let row = write_sheet_header(sheet, &styles)?;
+        write_entry(sheet, &styles, entry, row)?;
+        progress(i + 1, total, entry.card_name()).map_err(Stop::Progress)?;
+    }
+
+    Ok(workbook.save_to_buffer()?)
+}
+
+fn table_sheet<'a>(workbook: &'a mut Workbook, styles: &Styles, name: String) -> Result<&'a mut Worksheet, XlsxError> {
+    let sheet = workbook.add_worksheet();
+    sheet.set_name(name)?;
+    for (col, width) in styles.widths.iter().enumerate() {
+        sheet.set_column_width(col as u16, *width)?;
+    }
+    Ok(sheet)
+}
+
+/// Шапка из шаблона над таблицами листа: текст и логотип, возвращает следующую свободную строку
+fn write_sheet_header(sheet: &mut Worksheet, styles: &Styles) -> Result<u32, XlsxError> {
+    if styles.header_text.is_empty() && styles.logo.is_none() {
+        return Ok(0);
+    }
+    sheet.merge_range(0, 0, 0, LAST_COLUMN, &styles.header_text, &styles.title)?;
+
+    let Some((logo, placement)) = &styles.logo else {
+        sheet.set_row_height(0, HEADER_ROW_HEIGHT)?;
+        return Ok(2);
+    };
+    sheet.set_row_height(0, LOGO_ROW_HEIGHT)?;
+    let width = (LOGO_HEIGHT_PX * logo.aspect()).min(LOGO_MAX_WIDTH_PX);
+    let height = width / logo.aspect();
+    let image = Image::new_from_buffer(&logo.bytes)?.set_scale_to_size(width, height, true);
+    // Изображение привязывается к первой ячейке строки, справа - со смещением до правого края
+    let x_offset = match placement {
+        LogoPlacement::Right => {
+            let row_width: f64 = styles.widths.iter().map(|w| column_px(*w)).sum();
+            (row_width - width) as u32 - LOGO_OFFSET_PX
+        }
+        _ => LOGO_OFFSET_PX,
+    };
+    sheet.insert_image_with_offset(0, 0, &image, x_offset, LOGO_OFFSET_PX)?;
+    Ok(2)
+}
+
+/// Лист «Сводная»: число операций и суммарная работа каждого исполнителя, нагрузка
+/// по неделям с диаграммой и занятость рабочих мест
+fn write_summary(
+    sheet: &mut Worksheet,
+    styles: &Styles,
+    schedule: &Schedule,
+    summary: &Summary,
+) -> Result<(), XlsxError> {
+    sheet.set_name(SUMMARY_SHEET_NAME)?;
+    for (col, width) in SUMMARY_WIDTHS.iter().enumerate() {
+        sheet.set_column_width(col as u16, *width)?;
+    }
+
+    for (col, label) in ["Исполнитель", "Операций", "Работа (мин)", "Работа (час)"].iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, *label, &styles.header)?;
+    }
+
+    let mut row = 1;
+    for load in schedule.worker_loads() {
+        sheet.write_string_with_format(row, 0, &load.worker, &styles.name)?;
+        sheet.write_number_with_format(row, 1, load.operations as f64, &styles.number)?;
+        sheet.write_number_with_format(row, 2, load.minutes, &styles.number)?;
+        sheet.write_number_with_format(row, 3, load.minutes / 60.0, &styles.number)?;
+        row += 1;
+    }
+
+    if let Some(workload) = &summary.workload {
+        row = write_workload(sheet, styles, workload, row + 1)?;
+    }
+    if let Some(usage) = &summary.usage {
+        write_usage(sheet, styles, usage, row + 1)?;
+    }
+    Ok(())
+}
+
+/// Нагрузка исполнителей из отчёта workload и диаграмма часов каждого; возвращает следующую строку
+fn write_workload(
+    sheet: &mut Worksheet,
+    styles: &Styles,
+    report: &WorkloadReport,
+    mut row: u32,
+) -> Result<u32, XlsxError> {
+    let labels = ["Исполнитель", "Часов всего", "Часов в неделю", "Ставка, ч/нед", "Нагрузка"];
+    for (col, label) in labels.iter().enumerate() {
+        sheet.write_string_with_format(row, col as u16, *label, &styles.header)?;
+    }
+    let first = row + 1;
+    for worker in &report.workers {
+        row += 1;
+        sheet.write_string_with_format(row, 0, &worker.worker, &styles.name)?;
+        sheet.write_number_with_format(row, 1, worker.total_hours, &styles.number)?;
+        sheet.write_number_with_format(row, 2, worker.average_weekly_hours, &styles.number)?;
+        match worker.contract_hours {
+            Some(hours) => sheet.write_number_with_format(row, 3, hours, &styles.number)?,
+            None => sheet.write_blank(row, 3, &styles.cell)?,
+        };
+        sheet.write_string_with_format(row, 4, worker.status.label(), &styles.cell)?;
+    }
+    if report.workers.is_empty() {
+        return Ok(row + 1);
+    }
+
+    let mut chart = Chart::new(ChartType::Bar);
+    chart
+        .add_series()
+        .set_name("Часов всего")
+        .set_categories((SUMMARY_SHEET_NAME, first, 0, row, 0))
+        .set_values((SUMMARY_SHEET_NAME, first, 1, row, 1));
+    chart.title().set_name("Нагрузка исполнителей, часов");
+    chart.legend().set_hidden();
+    chart.set_width(CHART_WIDTH_PX);
+    chart.set_height((report.workers.len() as u32 * CHART_ROW_PX).max(CHART_MIN_HEIGHT_PX));
+    sheet.insert_chart(0, CHART_COLUMN, &chart)?;
+    Ok(row + 1)
+}
+
+/// Занятость рабочих мест; процент выделен гистограммой в ячейке
+fn write_usage(
+    sheet: &mut Worksheet,
+    styles: &Styles,
+    report: &UtilizationReport,
+    mut row: u32,
+) -> Result<(), XlsxError> {
+    let title = format!("Занятость рабочих мест {} - {}", report.from, report.to);
+    sheet.merge_range(row, 0, row, SUMMARY_WIDTHS.len() as u16 - 1, &title, &styles.header)?;
+    row += 1;
+    for (col, label) in ["Рабочее место", "Операций", "Занято, ч", "Доступно, ч", "Занятость, %"].iter().enumerate() {
+        sheet.write_string_with_format(row, col as u16, *label, &styles.header)?;
+    }
+    let first = row + 1;
+    for place in &report.workplaces {
+        row += 1;
+        sheet.write_string_with_format(row, 0, &place.name, &styles.name)?;
+        sheet.write_number_with_format(row, 1, place.operations as f64, &styles.number)?;
+        sheet.write_number_with_format(row, 2, place.busy_minutes as f64 / 60.0, &styles.number)?;
+        sheet.write_number_with_format(row, 3, place.available_minutes as f64 / 60.0, &styles.number)?;
+        sheet.write_number_with_format(row, 4, place.percent, &styles.number)?;
+    }
+    if row >= first {
+        let bar = ConditionalFormatDataBar::new()
+            .set_minimum(ConditionalFormatType::Number, 0)
+            .set_maximum(ConditionalFormatType::Number, 100);
+        sheet.add_conditional_format(first, 4, row, 4, &bar)?;
+    }
+    Ok(())
+}
+
+/// Допустимое и уникальное имя листа
+pub(super) fn sheet_name(raw: &str, used: &[String]) -> String {
+    let clean: String = raw
+        .chars()
+        .map(|c| if SHEET_NAME_FORBIDDEN.contains(&c) { '_' } else { c })
+        .collect();
+    // Имя не может начинаться или заканчиваться апострофом
+    let clean = clean.trim().trim_matches('\'').to_string();
+    let base: String = clean.chars().take(MAX_SHEET_NAME_CHARS).collect();
+
+    let mut name = base.clone();
+    let mut n = 2;
+    while used.iter().any(|u| u.to_lowercase() == name.to_lowercase()) {
+        let suffix = format!(" ({})", n);
+        let keep = MAX_SHEET_NAME_CHARS - suffix.chars().count();
+        name = format!("{}{}", base.chars().take(keep).collect::<String>(), suffix);
+        n += 1;
+    }
+    name
+}
+
+/// Пишет запись начиная со строки row, возвращает следующую свободную строку
+fn write_entry(sheet: &mut Worksheet, styles: &Styles, entry: &ScheduleEntry, mut row: u32) -> Result<u32, XlsxError> {
+    sheet.merge_range(row, 0, row, LAST_COLUMN, &entry_title(entry), &styles.title)?;
+    sheet.set_row_height(row, 24)?;
+    row += 1;
+
+    for (col, label) in headers(entry).iter().enumerate() {
+        sheet.write_string_with_format(row, col as u16, label, &styles.header)?;
+    }
+    row += 1;
+
+    for op in entry.sorted_rows() {
+        for (col, value) in cells(op).iter().enumerate() {
+            let format = if col == 2 { &styles.name } else { &styles.cell };
+            if col == WORK_COLUMN {
+                sheet.write_number_with_format(row, col as u16, op.dur_val, &styles.number)?;
+            } else {
+                sheet.write_string_with_format(row, col as u16, value, format)?;
+            }
+        }
+        if styles.row_height > 0.0 {
+            sheet.set_row_height(row, styles.row_height)?;
+        }
+        row += 1;
+    }
+
+    if !entry.z7.is_empty() {
+        sheet.merge_range(row, 0, row, LAST_COLUMN, "Z7", &styles.z7_header)?;
+        row += 1;
+        for line in &entry.z7 {
+            sheet.merge_range(row, 0, row, LAST_COLUMN, line, &styles.z7_line)?;
+            row += 1;
+        }
+    }
+
+    Ok(row)
+}
@@ -0,0 +1,229 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Операции с файлами пользователя внутри разрешённых папок. Пути проверяются
+// в командах (lib.rs), здесь - только сами операции.
+
+use std::io::Read;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::{cloud, compression, crypto, paths, streams};
+
+/// Расширение собственных файлов расписания. Внутри та же резервная копия в JSON,
+/// но расширение связано с приложением в системе: двойной щелчок открывает файл в нём
+pub const NATIVE_EXTENSION: &str = "ttable";
+
+/// Расширения, которые показываются в списке файлов (в том числе сжатые и зашифрованные: .json.gz, .json.enc)
+pub const LISTED_EXTENSIONS: [&str; 4] = ["json", NATIVE_EXTENSION, "xml", "xlsx"];
+
+/// Расширения рабочих файлов для команд блокировок, копий и передачи частями
+pub const SCHEDULE_EXTENSIONS: [&str; 6] = ["json", NATIVE_EXTENSION, "xml", "xlsx", "gz", "enc"];
+
+/// Расширения файлов, которыми можно управлять из приложения: рабочие файлы и экспорт
+pub const USER_EXTENSIONS: [&str; 18] = [
+    "json", NATIVE_EXTENSION, "xml", "xlsx", "gz", "enc", "pdf", "csv", "ods", "ics", "html", "htm", "md", "docx", "svg",
+    "png", "jpg", "jpeg",
+];
+
+/// Содержимое файла в JSON: .json или .ttable (путь без .gz и .enc)
+pub fn is_json_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json") || ext.eq_ignore_ascii_case(NATIVE_EXTENSION))
+}
+
+// Наибольшее число файлов в ответе
+const MAX_ENTRIES: usize = 1000;
+
+/// Файл в списке
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileEntry {
+    pub path: String,
+    pub name: String,
+    pub size: u64,
+    /// Время изменения, миллисекунды с 1970 года
+    pub modified: u64,
+}
+
+/// Сведения о файле для проверки «файл изменён на диске после открытия»
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileInfo {
+    pub size: u64,
+    /// Время изменения, миллисекунды с 1970 года
+    pub modified: u64,
+    pub readonly: bool,
+    /// SHA-256 содержимого; None - файл только в облаке и не загружен
+    pub hash: Option<String>,
+}
+
+/// Время изменения в миллисекундах с 1970 года
+pub fn modified_ms(metadata: &std::fs::Metadata) -> u64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Сравнение имени с шаблоном: * - любые символы, ? - один символ
+fn matches(pattern: &[char], name: &[char]) -> bool {
+    // Жадный проход с возвратом к последней звёздочке: без экспоненты на шаблонах вида «*a*a*a»
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((sp, sn)) => {
+                    p = sp + 1;
+                    n = sn + 1;
+                    star = Some((sp, sn + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Файлы .json, .xml и .xlsx (и сжатые .gz, зашифрованные .enc) в папке (без вложенных), новые первыми.
+/// Служебные файлы (резервные копии, недописанные части) и ссылки пропускаются
+pub fn list(dir: &Path, pattern: Option<&str>) -> Result<Vec<FileEntry>, String> {
+    let pattern: Option<Vec<char>> = pattern
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(|p| paths::nfc(Path::new(&p.to_lowercase())).to_string_lossy().chars().collect());
+    if pattern.as_ref().is_some_and(|p| p.len() > 255) {
+        return Err("Слишком длинный шаблон имени".into());
+    }
+
+    let entries = std::fs::read_dir(paths::to_fs_path(dir))
+        .map_err(|e| paths::io_error_message("Ошибка чтения папки", &e))?;
+    let mut list = Vec::new();
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else { continue };
+        if !file_type.is_file() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') {
+            continue;
+        }
+        let path = dir.join(&name);
+        let listed = crypto::inner_path(&compression::inner_path(&path))
+            .extension()
+            .is_some_and(|ext| LISTED_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str()));
+        if !listed {
+            continue;
+        }
+        if let Some(pattern) = &pattern {
+            let lower: Vec<char> = paths::nfc(Path::new(&name.to_lowercase())).to_string_lossy().chars().collect();
+            if !matches(pattern, &lower) {
+                continue;
+            }
+        }
+        let Ok(metadata) = entry.metadata() else { continue };
+        list.push(FileEntry {
+            path: path.to_string_lossy().to_string(),
+            name,
+            size: metadata.len(),
+            modified: modified_ms(&metadata),
+        });
+    }
+
+    list.sort_by_key(|f| std::cmp::Reverse(f.modified));
+    list.truncate(MAX_ENTRIES);
+    Ok(list)
+}
+
+/// Перемещает файл в корзину системы. Если корзина недоступна (сетевая папка,
+/// некоторые съёмные носители), файл не удаляется безвозвратно, а остаётся на месте
+pub fn delete(path: &Path) -> Result<(), String> {
+    let target = paths::to_fs_path(path);
+    let metadata = std::fs::symlink_metadata(&target)
+        .map_err(|e| paths::io_error_message("Ошибка получения информации о файле", &e))?;
+    if !metadata.is_file() {
+        return Err("Удалять можно только файлы".into());
+    }
+    trash::delete(&target).map_err(|e| format!("Не удалось переместить файл в корзину: {}", e))
+}
+
+/// Перемещает или переименовывает файл. Существующий файл не перезаписывается;
+/// между дисками (например, на флешку) файл копируется, а затем удаляется исходный
+pub fn move_file(from: &Path, to: &Path) -> Result<(), String> {
+    let source = paths::to_fs_path(from);
+    let target = paths::to_fs_path(to);
+    let metadata = std::fs::symlink_metadata(&source)
+        .map_err(|e| paths::io_error_message("Ошибка получения информации о файле", &e))?;
+    if !metadata.is_file() {
+        return Err("Перемещать можно только файлы".into());
+    }
+    // Переименование только регистром букв (plan.json -> Plan.json) на Windows и macOS
+    // указывает на тот же файл, поэтому не считается занятым именем
+    let same_file = matches!((source.canonicalize(), target.canonicalize()), (Ok(a), Ok(b)) if a == b);
+    if target.exists() && !same_file {
+        return Err("Файл с таким именем уже существует".into());
+    }
+
+    match std::fs::rename(&source, &target) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+            std::fs::copy(&source, &target).map_err(|e| {
+                let _ = std::fs::remove_file(&target);
+                paths::io_error_message("Ошибка копирования файла", &e)
+            })?;
+            std::fs::remove_file(&source)
+                .map_err(|e| paths::io_error_message("Файл скопирован, но исходный не удалось удалить", &e))
+        }
+        Err(e) => Err(paths::io_error_message("Ошибка перемещения файла", &e)),
+    }
+}
+
+/// Размер, время изменения, признак «только чтение» и хеш содержимого
+pub fn info(path: &Path) -> Result<FileInfo, String> {
+    let target = paths::to_fs_path(path);
+    let metadata = std::fs::metadata(&target)
+        .map_err(|e| paths::io_error_message("Ошибка получения информации о файле", &e))?;
+    if !metadata.is_file() {
+        return Err("Указан не файл".into());
+    }
+    if metadata.len() > streams::MAX_STREAM_SIZE {
+        return Err(format!("Размер файла превышает максимальный ({} МБ)", streams::MAX_STREAM_SIZE / 1024 / 1024));
+    }
+
+    // Хеш заглушки запустил бы загрузку всего файла из облака
+    let hash = if cloud::is_placeholder(&metadata) {
+        None
+    } else {
+        let mut file = std::fs::File::open(&target).map_err(|e| paths::io_error_message("Ошибка чтения", &e))?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0u8; 64 * 1024];
+        loop {
+            let read = file.read(&mut buffer).map_err(|e| paths::io_error_message("Ошибка чтения", &e))?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+        Some(format!("{:x}", hasher.finalize()))
+    };
+
+    Ok(FileInfo {
+        size: metadata.len(),
+        modified: modified_ms(&metadata),
+        readonly: metadata.permissions().readonly(),
+        hash,
+    })
+}
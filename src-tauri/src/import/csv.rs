@@ -0,0 +1,490 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Импорт CSV с сопоставлением колонок. Кодировка и разделитель определяются здесь:
+// Excel с русской локалью сохраняет CSV в Windows-1251 с «;», выгрузка приложения -
+// в UTF-8 с BOM, другие программы - в UTF-8 или UTF-16 с «,» или табуляцией.
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::{OperationRow, Schedule, ScheduleEntry};
+
+// Сколько строк показываем в предпросмотре
+const PREVIEW_ROWS: usize = 10;
+
+// По скольким строкам определяется разделитель
+const SNIFF_LINES: usize = 20;
+
+const DELIMITERS: [char; 4] = [';', ',', '\t', '|'];
+
+/// Наибольший размер импортируемого файла
+pub const MAX_CSV_SIZE: usize = 5 * 1024 * 1024;
+
+// Наибольшее число строк и колонок. В расписании их намного меньше, а таблица из
+// миллиона коротких строк заняла бы в памяти в десятки раз больше самого файла
+const MAX_ROWS: usize = 20_000;
+const MAX_COLUMNS: usize = 100;
+
+// Название записи, если в файле нет колонки записи
+const DEFAULT_ENTRY_TITLE: &str = "Импорт из CSV";
+
+// Windows-1251: символы 0x80..0xBF (0x98 не определён); 0xC0..0xFF - «А».. «я» подряд
+const CP1251_HIGH: [char; 64] = [
+    'Ђ', 'Ѓ', '‚', 'ѓ', '„', '…', '†', '‡', '€', '‰', 'Љ', '‹', 'Њ', 'Ќ', 'Ћ', 'Џ',
+    'ђ', '‘', '’', '“', '”', '•', '–', '—', '\u{FFFD}', '™', 'љ', '›', 'њ', 'ќ', 'ћ', 'џ',
+    '\u{A0}', 'Ў', 'ў', 'Ј', '¤', 'Ґ', '¦', '§', 'Ё', '©', 'Є', '«', '¬', '\u{AD}', '®', 'Ї',
+    '°', '±', 'І', 'і', 'ґ', 'µ', '¶', '·', 'ё', '№', 'є', '»', 'ј', 'Ѕ', 'ѕ', 'ї',
+];
+
+/// Кодировка файла
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Windows1251,
+}
+
+/// Поле строки расчёта, в которое импортируется колонка
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Field {
+    /// Запись истории: строки с одинаковым значением попадают в одну запись
+    Entry,
+    OpIndex,
+    Pdtv,
+    Name,
+    Lunch,
+    Pause,
+    Duration,
+    Unit,
+    PostingDate,
+    Worker,
+    StartDate,
+    StartTime,
+    EndDate,
+    EndTime,
+}
+
+impl Field {
+    // Заголовки, по которым колонка сопоставляется автоматически (в нижнем регистре)
+    fn aliases(self) -> &'static [&'static str] {
+        match self {
+            Field::Entry => &["запись", "техкарта", "entry"],
+            Field::OpIndex => &["№", "номер", "n"],
+            Field::Pdtv => &["пдтв"],
+            Field::Name => &["операция", "наименование", "название", "name"],
+            Field::Lunch => &["обед?", "обед"],
+            Field::Pause => &["пауза", "pause"],
+            Field::Duration => &["работа", "длительность", "работа (мин)", "работа (час)", "duration"],
+            Field::Unit => &["ед. изм.", "единица", "ед.", "unit"],
+            Field::PostingDate => &["дата проводки"],
+            Field::Worker => &["исполнитель", "работник", "worker"],
+            Field::StartDate => &["дата начала"],
+            Field::StartTime => &["время начала"],
+            Field::EndDate => &["дата конца", "дата окончания"],
+            Field::EndTime => &["время конца", "время окончания"],
+        }
+    }
+
+    const ALL: [Field; 14] = [
+        Field::Entry,
+        Field::OpIndex,
+        Field::Pdtv,
+        Field::Name,
+        Field::Lunch,
+        Field::Pause,
+        Field::Duration,
+        Field::Unit,
+        Field::PostingDate,
+        Field::Worker,
+        Field::StartDate,
+        Field::StartTime,
+        Field::EndDate,
+        Field::EndTime,
+    ];
+}
+
+/// Параметры чтения; незаданные определяются по содержимому файла
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CsvImportOptions {
+    pub encoding: Option<Encoding>,
+    pub delimiter: Option<char>,
+    /// Первая строка - заголовки
+    pub has_header: bool,
+}
+
+impl Default for CsvImportOptions {
+    fn default() -> Self {
+        CsvImportOptions { encoding: None, delimiter: None, has_header: true }
+    }
+}
+
+/// Предпросмотр файла для окна сопоставления колонок
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CsvPreview {
+    pub encoding: Encoding,
+    pub delimiter: char,
+    /// Заголовки колонок (без строки заголовков - «Колонка 1», «Колонка 2»…)
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    pub total_rows: usize,
+    /// Предлагаемое сопоставление: поле для каждой колонки или null
+    pub mapping: Vec<Option<Field>>,
+}
+
+/// Определяет кодировку по BOM и корректности UTF-8
+fn detect_encoding(bytes: &[u8]) -> Encoding {
+    if bytes.starts_with(&[0xFF, 0xFE]) {
+        Encoding::Utf16Le
+    } else if bytes.starts_with(&[0xFE, 0xFF]) {
+        Encoding::Utf16Be
+    } else if std::str::from_utf8(bytes).is_ok() {
+        Encoding::Utf8
+    } else {
+        Encoding::Windows1251
+    }
+}
+
+fn decode(bytes: &[u8], encoding: Encoding) -> Result<String, String> {
+    let utf16 = |units: Vec<u16>| String::from_utf16(&units).map_err(|_| "Файл повреждён: некорректные данные UTF-16".to_string());
+    let text = match encoding {
+        Encoding::Utf8 => String::from_utf8(bytes.to_vec()).map_err(|_| "Файл не в кодировке UTF-8".to_string())?,
+        Encoding::Utf16Le => utf16(bytes.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect())?,
+        Encoding::Utf16Be => utf16(bytes.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect())?,
+        Encoding::Windows1251 => bytes
+            .iter()
+            .map(|&b| match b {
+                0x00..=0x7F => b as char,
+                0x80..=0xBF => CP1251_HIGH[usize::from(b - 0x80)],
+                _ => char::from_u32(0x0410 + u32::from(b - 0xC0)).unwrap_or('\u{FFFD}'),
+            })
+            .collect(),
+    };
+    Ok(text.strip_prefix('\u{feff}').map(str::to_string).unwrap_or(text))
+}
+
+/// Разделитель, который встречается во всех первых строках одинаковое ненулевое число раз;
+/// если такого нет - самый частый
+fn detect_delimiter(text: &str) -> char {
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).take(SNIFF_LINES).collect();
+    let counts = |delimiter: char| -> Vec<usize> {
+        lines.iter().map(|line| split_line(line, delimiter).len() - 1).collect()
+    };
+    let consistent = DELIMITERS.iter().copied().filter_map(|d| {
+        let c = counts(d);
+        let first = *c.first()?;
+        (first > 0 && c.iter().all(|n| *n == first)).then_some((d, first))
+    });
+    if let Some((d, _)) = consistent.max_by_key(|(_, n)| *n) {
+        return d;
+    }
+    DELIMITERS
+        .iter()
+        .copied()
+        .max_by_key(|d| counts(*d).iter().sum::<usize>())
+        .unwrap_or(';')
+}
+
+// Разбивка одной физической строки - только для определения разделителя
+fn split_line(line: &str, delimiter: char) -> Vec<&str> {
+    let mut fields = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in line.char_indices() {
+        if c == '"' {
+            quoted = !quoted;
+        } else if c == delimiter && !quoted {
+            fields.push(&line[start..i]);
+            start = i + c.len_utf8();
+        }
+    }
+    fields.push(&line[start..]);
+    fields
+}
+
+// Добавляет строку таблицы; пустые строки (в том числе в конце файла) пропускаются
+fn push_record(records: &mut Vec<Vec<String>>, record: Vec<String>) -> Result<(), String> {
+    if record.iter().all(|v| v.trim().is_empty()) {
+        return Ok(());
+    }
+    if records.len() >= MAX_ROWS {
+        return Err(format!("В файле больше {} строк. Разделите его на несколько файлов", MAX_ROWS));
+    }
+    records.push(record);
+    Ok(())
+}
+
+/// Разбирает CSV по RFC 4180: поля в кавычках могут содержать разделитель и переводы строк.
+/// Разбор прерывается, как только таблица выходит за MAX_ROWS строк или MAX_COLUMNS колонок
+fn parse(text: &str, delimiter: char) -> Result<Vec<Vec<String>>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => quoted = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => quoted = true,
+            '\r' => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                push_record(&mut records, std::mem::take(&mut record))?;
+            }
+            c if c == delimiter => {
+                record.push(std::mem::take(&mut field));
+                if record.len() >= MAX_COLUMNS {
+                    return Err(format!("В файле больше {} колонок. Проверьте разделитель", MAX_COLUMNS));
+                }
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        push_record(&mut records, record)?;
+    }
+    Ok(records)
+}
+
+/// Прочитанная таблица: строки данных одинаковой ширины
+struct Table {
+    encoding: Encoding,
+    delimiter: char,
+    headers: Vec<String>,
+    records: Vec<Vec<String>>,
+}
+
+fn read_table(bytes: &[u8], options: CsvImportOptions) -> Result<Table, String> {
+    // Проверяется до декодирования: UTF-16 и Windows-1251 в памяти занимают больше файла
+    if bytes.len() > MAX_CSV_SIZE {
+        return Err(format!("Файл CSV больше {} МБ. Разделите его на несколько файлов", MAX_CSV_SIZE / 1024 / 1024));
+    }
+    let encoding = options.encoding.unwrap_or_else(|| detect_encoding(bytes));
+    let text = decode(bytes, encoding)?;
+    let delimiter = options.delimiter.unwrap_or_else(|| detect_delimiter(&text));
+    if !DELIMITERS.contains(&delimiter) {
+        return Err("Разделитель CSV должен быть «;», «,», «|» или табуляцией".into());
+    }
+
+    let mut records = parse(&text, delimiter)?;
+    if records.is_empty() {
+        return Err("Файл не содержит данных".into());
+    }
+    let width = records.iter().map(Vec::len).max().unwrap_or(0);
+    for record in &mut records {
+        record.resize(width, String::new());
+    }
+
+    let headers = if options.has_header {
+        records.remove(0).into_iter().map(|h| h.trim().to_string()).collect()
+    } else {
+        (1..=width).map(|n| format!("Колонка {}", n)).collect()
+    };
+    Ok(Table { encoding, delimiter, headers, records })
+}
+
+/// Предлагаемое сопоставление по заголовкам; каждое поле назначается не больше одной колонке
+fn suggest_mapping(headers: &[String]) -> Vec<Option<Field>> {
+    let mut used = Vec::new();
+    headers
+        .iter()
+        .map(|header| {
+            let header = header.to_lowercase();
+            let field = Field::ALL
+                .into_iter()
+                .find(|f| !used.contains(f) && f.aliases().contains(&header.as_str()))?;
+            used.push(field);
+            Some(field)
+        })
+        .collect()
+}
+
+/// Предпросмотр: определённые кодировка и разделитель, заголовки, первые строки и сопоставление
+pub fn preview(bytes: &[u8], options: CsvImportOptions) -> Result<CsvPreview, String> {
+    let table = read_table(bytes, options)?;
+    Ok(CsvPreview {
+        encoding: table.encoding,
+        delimiter: table.delimiter,
+        total_rows: table.records.len(),
+        rows: table.records.into_iter().take(PREVIEW_ROWS).collect(),
+        mapping: suggest_mapping(&table.headers),
+        headers: table.headers,
+    })
+}
+
+/// Импортирует строки по сопоставлению колонок (mapping[i] - поле колонки i).
+/// progress вызывается после каждой строки (сделано, всего, операция), его ошибка (отмена) прерывает импорт
+pub fn import(
+    bytes: &[u8],
+    mapping: &[Option<Field>],
+    options: CsvImportOptions,
+    progress: &mut dyn FnMut(usize, usize, &str) -> Result<(), String>,
+) -> Result<Schedule, String> {
+    let table = read_table(bytes, options)?;
+    if mapping.len() > table.headers.len() {
+        return Err(format!("В сопоставлении {} колонок, а в файле {}", mapping.len(), table.headers.len()));
+    }
+    for field in Field::ALL {
+        if mapping.iter().filter(|m| **m == Some(field)).count() > 1 {
+            return Err("Одно поле сопоставлено нескольким колонкам".into());
+        }
+    }
+    if !mapping.contains(&Some(Field::Name)) {
+        return Err("Не выбрана колонка с названием операции".into());
+    }
+
+    let mut schedule = Schedule::default();
+    for (line, record) in table.records.iter().enumerate() {
+        let mut title = DEFAULT_ENTRY_TITLE.to_string();
+        let mut row = OperationRow::default();
+        for (value, field) in record.iter().zip(mapping) {
+            let Some(field) = field else { continue };
+            let value = unsanitize(value.trim());
+            match field {
+                Field::Entry if !value.is_empty() => title = value.to_string(),
+                Field::Entry => {}
+                Field::OpIndex => {
+                    row.original_op_index = value.to_string();
+                    row.op_numeric = parse_number(value);
+                }
+                Field::Pdtv => row.op_idx = value.to_string(),
+                Field::Name => row.name = value.to_string(),
+                Field::Lunch => row.crossed_lunch = matches!(value.to_lowercase().as_str(), "да" | "yes" | "true" | "1" | "+"),
+                Field::Pause => row.pause_text = value.to_string(),
+                Field::Duration if value.is_empty() => {}
+                Field::Duration => {
+                    row.dur_val = parse_number(value)
+                        .ok_or_else(|| format!("Строка {}: длительность «{}» не число", line + 1, value))?;
+                    row.dur_text = value.to_string();
+                }
+                Field::Unit => {
+                    row.unit = match value.to_lowercase().as_str() {
+                        "час" | "ч" | "ч." | "hour" | "h" => "hour".into(),
+                        _ => "min".into(),
+                    }
+                }
+                Field::PostingDate => row.posting_date = value.to_string(),
+                Field::Worker => row.worker = value.to_string(),
+                Field::StartDate => row.start_date = value.to_string(),
+                Field::StartTime => row.start_time = value.to_string(),
+                Field::EndDate => row.end_date = value.to_string(),
+                Field::EndTime => row.end_time = value.to_string(),
+            }
+        }
+        progress(line + 1, table.records.len(), &row.name)?;
+        if row.name.is_empty() {
+            continue;
+        }
+        push_row(&mut schedule, title, row);
+    }
+
+    if schedule.entries.is_empty() {
+        return Err("В файле нет строк с названием операции".into());
+    }
+    Ok(schedule)
+}
+
+/// Добавляет строку в запись с тем же названием; номер исполнителя - по порядку внутри операции
+fn push_row(schedule: &mut Schedule, title: String, mut row: OperationRow) {
+    let index = match schedule.entries.iter().position(|e| e.title == title) {
+        Some(index) => index,
+        None => {
+            schedule.entries.push(ScheduleEntry { title, ..Default::default() });
+            schedule.entries.len() - 1
+        }
+    };
+    let entry = &mut schedule.entries[index];
+    let same_op = entry.rows.iter().filter(|r| r.original_op_index == row.original_op_index).count();
+    row.worker_index = Some(same_op as u32 + 1);
+    entry.rows.push(row);
+}
+
+/// Снимает апостроф, которым выгрузка защищает ячейки от формул
+fn unsanitize(value: &str) -> &str {
+    match value.strip_prefix('\'') {
+        Some(rest) if rest.trim_start().starts_with(['=', '+', '-', '@']) => rest,
+        _ => value,
+    }
+}
+
+// Число с точкой или запятой в качестве десятичного разделителя
+fn parse_number(value: &str) -> Option<f64> {
+    value.replace(',', ".").replace([' ', '\u{A0}'], "").parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(text: &str, big_endian: bool) -> Vec<u8> {
+        let bom = if big_endian { [0xFE, 0xFF] } else { [0xFF, 0xFE] };
+        let units = text.encode_utf16().flat_map(|u| if big_endian { u.to_be_bytes() } else { u.to_le_bytes() });
+        bom.into_iter().chain(units).collect()
+    }
+
+    #[test]
+    fn quoted_delimiters() {
+        let text = "a;\"b;c\";\"d \"\"e\"\"\"\r\n\"две\nстроки\";x\r\n\r\n";
+        assert_eq!(parse(text, ';').unwrap(), vec![vec!["a", "b;c", "d \"e\""], vec!["две\nстроки", "x"]]);
+    }
+
+    // Разделитель внутри кавычек не учитывается при определении
+    #[test]
+    fn delimiter_inside_quotes() {
+        assert_eq!(detect_delimiter("name,\"a;b;c\"\nx,\"d;e;f\"\n"), ',');
+        assert_eq!(detect_delimiter("a\tb\tc\n1\t2\t3\n"), '\t');
+    }
+
+    #[test]
+    fn utf16_bom() {
+        let text = "Операция;Исполнитель\r\n\"Сварка; шов\";Иванов\r\n";
+        for (big_endian, encoding) in [(false, Encoding::Utf16Le), (true, Encoding::Utf16Be)] {
+            let preview = preview(&utf16(text, big_endian), CsvImportOptions::default()).unwrap();
+            assert_eq!(preview.encoding, encoding);
+            assert_eq!(preview.delimiter, ';');
+            assert_eq!(preview.headers, vec!["Операция", "Исполнитель"]);
+            assert_eq!(preview.rows, vec![vec!["Сварка; шов", "Иванов"]]);
+            assert_eq!(preview.mapping, vec![Some(Field::Name), Some(Field::Worker)]);
+        }
+    }
+
+    #[test]
+    fn utf8_bom_and_windows1251() {
+        let preview = preview("\u{feff}a,b\n1,2\n".as_bytes(), CsvImportOptions::default()).unwrap();
+        assert_eq!(preview.encoding, Encoding::Utf8);
+        assert_eq!(preview.headers, vec!["a", "b"]);
+
+        // «Сварка;Ёж» в Windows-1251
+        let bytes = [0xD1, 0xE2, 0xE0, 0xF0, 0xEA, 0xE0, b';', 0xA8, 0xE6];
+        assert_eq!(detect_encoding(&bytes), Encoding::Windows1251);
+        assert_eq!(decode(&bytes, Encoding::Windows1251).unwrap(), "Сварка;Ёж");
+    }
+
+    #[test]
+    fn row_and_column_limits() {
+        let rows = "a;b\n".repeat(MAX_ROWS);
+        assert_eq!(parse(&rows, ';').unwrap().len(), MAX_ROWS);
+        assert!(parse(&format!("{}a;b\n", rows), ';').unwrap_err().contains("строк"));
+        // Пустые строки в лимит не входят
+        assert_eq!(parse(&format!("{}\n\n;\n", rows), ';').unwrap().len(), MAX_ROWS);
+
+        assert!(parse(&";".repeat(MAX_COLUMNS), ';').unwrap_err().contains("колонок"));
+        assert!(preview(&vec![b'a'; MAX_CSV_SIZE + 1], CsvImportOptions::default()).unwrap_err().contains("МБ"));
+    }
+}
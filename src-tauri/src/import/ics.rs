@@ -0,0 +1,331 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Импорт внешних событий из iCalendar (.ics) как занятого времени исполнителей.
+// Часовые пояса (TZID) не пересчитываются: время считается местным, как в расчётах;
+// время в UTC (с суффиксом Z) переводится в местное.
+
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use serde::Serialize;
+
+use crate::model::BlockedSlot;
+
+// Ограничение числа повторений одного события
+const MAX_OCCURRENCES: usize = 500;
+
+/// Наибольший размер импортируемого календаря
+pub const MAX_ICS_SIZE: usize = 5 * 1024 * 1024;
+
+// Наибольшее число интервалов занятости после разворачивания повторений
+const MAX_SLOTS: usize = 20_000;
+
+// Повторения без COUNT и UNTIL разворачиваются на год вперёд
+const HORIZON_DAYS: i64 = 366;
+
+const DATE_FORMAT: &str = "%d.%m.%Y";
+const TIME_FORMAT: &str = "%H:%M:%S";
+
+/// Результат импорта: интервалы занятости и предупреждения о пропущенном
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IcsImport {
+    pub slots: Vec<BlockedSlot>,
+    pub warnings: Vec<String>,
+}
+
+// Свойство iCalendar: имя, параметры и значение
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Default)]
+struct Event {
+    summary: String,
+    start: Option<(NaiveDateTime, bool)>,
+    end: Option<NaiveDateTime>,
+    duration: Option<Duration>,
+    rrule: Option<String>,
+    exdates: Vec<NaiveDateTime>,
+    // Отменённые и «свободные» (TRANSP:TRANSPARENT) события время не занимают
+    skip: bool,
+}
+
+/// Разбирает календарь. worker - исполнитель, которому назначаются события
+/// (пустая строка - все исполнители)
+pub fn parse(text: &str, worker: &str) -> Result<IcsImport, String> {
+    if text.len() > MAX_ICS_SIZE {
+        return Err(format!("Календарь больше {} МБ. Выгрузите события за меньший период", MAX_ICS_SIZE / 1024 / 1024));
+    }
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    if !text.trim_start().to_uppercase().starts_with("BEGIN:VCALENDAR") {
+        return Err("Файл не является календарём iCalendar".into());
+    }
+
+    let mut result = IcsImport { slots: Vec::new(), warnings: Vec::new() };
+    let mut event: Option<Event> = None;
+    // Вложенные компоненты события (VALARM) пропускаются
+    let mut nested = 0;
+
+    for line in unfold(text) {
+        let Some(prop) = property(&line) else { continue };
+        match (prop.name.as_str(), prop.value.to_uppercase().as_str()) {
+            ("BEGIN", "VEVENT") => event = Some(Event::default()),
+            ("BEGIN", _) if event.is_some() => nested += 1,
+            ("END", "VEVENT") => {
+                if let Some(done) = event.take() {
+                    add_event(done, worker, &mut result)?;
+                }
+                nested = 0;
+            }
+            ("END", _) if nested > 0 => nested -= 1,
+            _ if nested > 0 => {}
+            _ => {
+                if let Some(event) = event.as_mut() {
+                    apply(event, &prop);
+                }
+            }
+        }
+    }
+
+    if result.slots.is_empty() {
+        return Err("В календаре нет событий с датой и временем".into());
+    }
+    result.slots.sort_by_key(|slot| slot.start());
+    Ok(result)
+}
+
+/// Склеивает перенесённые строки (RFC 5545, 3.1): продолжение начинается с пробела или табуляции
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for raw in text.split('\n') {
+        let raw = raw.strip_suffix('\r').unwrap_or(raw);
+        match (raw.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(raw.to_string()),
+        }
+    }
+    lines
+}
+
+/// Разбирает «ИМЯ;ПАРАМЕТР=ЗНАЧЕНИЕ:значение»; двоеточие в кавычках параметра не разделяет
+fn property(line: &str) -> Option<Property> {
+    let mut quoted = false;
+    let split = line.char_indices().find(|(_, c)| {
+        if *c == '"' {
+            quoted = !quoted;
+        }
+        *c == ':' && !quoted
+    })?;
+    let (head, value) = (&line[..split.0], &line[split.0 + 1..]);
+    let mut parts = head.split(';');
+    let name = parts.next()?.trim().to_uppercase();
+    let params = parts
+        .filter_map(|p| p.split_once('='))
+        .map(|(k, v)| (k.trim().to_uppercase(), v.trim_matches('"').to_string()))
+        .collect();
+    Some(Property { name, params, value: value.to_string() })
+}
+
+fn apply(event: &mut Event, prop: &Property) {
+    match prop.name.as_str() {
+        "SUMMARY" => event.summary = unescape(&prop.value),
+        "DTSTART" => event.start = date_time(&prop.value, is_date(prop)),
+        "DTEND" => event.end = date_time(&prop.value, is_date(prop)).map(|(dt, _)| dt),
+        "DURATION" => event.duration = duration(&prop.value),
+        "RRULE" => event.rrule = Some(prop.value.clone()),
+        "EXDATE" => event.exdates.extend(
+            prop.value
+                .split(',')
+                .filter_map(|v| date_time(v, is_date(prop)).map(|(dt, _)| dt)),
+        ),
+        "STATUS" if prop.value.eq_ignore_ascii_case("CANCELLED") => event.skip = true,
+        "TRANSP" if prop.value.eq_ignore_ascii_case("TRANSPARENT") => event.skip = true,
+        _ => {}
+    }
+}
+
+fn is_date(prop: &Property) -> bool {
+    prop.param("VALUE").is_some_and(|v| v.eq_ignore_ascii_case("DATE"))
+}
+
+/// Дата или дата-время; второй элемент - событие на весь день
+fn date_time(value: &str, date_only: bool) -> Option<(NaiveDateTime, bool)> {
+    let value = value.trim();
+    if date_only || value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        return Some((date.and_hms_opt(0, 0, 0)?, true));
+    }
+    if let Some(utc) = value.strip_suffix('Z') {
+        let naive = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some((Utc.from_utc_datetime(&naive).with_timezone(&Local).naive_local(), false));
+    }
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok().map(|dt| (dt, false))
+}
+
+/// Длительность вида P1D, PT1H30M, P2W
+fn duration(value: &str) -> Option<Duration> {
+    let rest = value.trim().strip_prefix('P')?;
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in rest.chars() {
+        match c {
+            'T' => in_time = true,
+            '0'..='9' => number.push(c),
+            _ => {
+                let n: i64 = std::mem::take(&mut number).parse().ok()?;
+                total += match (c, in_time) {
+                    ('W', false) => Duration::weeks(n),
+                    ('D', false) => Duration::days(n),
+                    ('H', true) => Duration::hours(n),
+                    ('M', true) => Duration::minutes(n),
+                    ('S', true) => Duration::seconds(n),
+                    _ => return None,
+                };
+            }
+        }
+    }
+    Some(total)
+}
+
+/// Снимает экранирование текста (RFC 5545, 3.3.11)
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => out.push(' '),
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out.trim().to_string()
+}
+
+// Добавляет интервалы события; ошибка - календарь вышел за MAX_SLOTS интервалов
+fn add_event(event: Event, worker: &str, result: &mut IcsImport) -> Result<(), String> {
+    let Some((start, all_day)) = event.start else { return Ok(()) };
+    if event.skip {
+        return Ok(());
+    }
+    let length = match (event.end, event.duration) {
+        (Some(end), _) if end > start => end - start,
+        (_, Some(duration)) if duration > Duration::zero() => duration,
+        _ if all_day => Duration::days(1),
+        _ => return Ok(()),
+    };
+    let title = if event.summary.is_empty() { "Внешнее событие".to_string() } else { event.summary };
+
+    let starts = match &event.rrule {
+        None => vec![start],
+        Some(rule) => occurrences(start, rule).unwrap_or_else(|| {
+            result
+                .warnings
+                .push(format!("«{}»: правило повторения не поддерживается, импортировано первое событие", title));
+            vec![start]
+        }),
+    };
+
+    for begin in starts.into_iter().filter(|s| !event.exdates.contains(s)) {
+        if result.slots.len() >= MAX_SLOTS {
+            return Err(format!(
+                "В календаре больше {} событий с учётом повторений. Выгрузите события за меньший период",
+                MAX_SLOTS
+            ));
+        }
+        let end = begin + length;
+        result.slots.push(BlockedSlot {
+            title: title.clone(),
+            worker: worker.trim().to_string(),
+            start_date: begin.format(DATE_FORMAT).to_string(),
+            start_time: begin.format(TIME_FORMAT).to_string(),
+            end_date: end.format(DATE_FORMAT).to_string(),
+            end_time: end.format(TIME_FORMAT).to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Начала повторений по RRULE; поддерживаются FREQ=DAILY и WEEKLY с INTERVAL,
+/// COUNT, UNTIL и BYDAY. None - правило не поддерживается
+fn occurrences(start: NaiveDateTime, rule: &str) -> Option<Vec<NaiveDateTime>> {
+    let parts: Vec<(String, String)> = rule
+        .split(';')
+        .filter_map(|p| p.split_once('='))
+        .map(|(k, v)| (k.trim().to_uppercase(), v.trim().to_uppercase()))
+        .collect();
+    let get = |key: &str| parts.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+
+    let interval: i64 = get("INTERVAL").map_or(Some(1), |v| v.parse().ok())?.max(1);
+    let count: usize = get("COUNT").map_or(Some(MAX_OCCURRENCES), |v| v.parse().ok())?.min(MAX_OCCURRENCES);
+    let until = match get("UNTIL") {
+        Some(v) => date_time(v, false)?.0,
+        None => start + Duration::days(HORIZON_DAYS),
+    };
+    // Дата без времени в UNTIL включает весь последний день
+    let until = if until.time() == chrono::NaiveTime::MIN { until + Duration::days(1) - Duration::seconds(1) } else { until };
+
+    let mut days: Vec<Weekday> = match get("BYDAY") {
+        Some(list) => list.split(',').map(weekday).collect::<Option<_>>()?,
+        None => vec![start.weekday()],
+    };
+    days.sort_by_key(|d| d.num_days_from_monday());
+
+    let mut list = Vec::new();
+    match get("FREQ")? {
+        "DAILY" if get("BYDAY").is_none() => {
+            let mut current = start;
+            while current <= until && list.len() < count {
+                list.push(current);
+                current += Duration::days(interval);
+            }
+        }
+        "WEEKLY" => {
+            let mut monday = start - Duration::days(i64::from(start.weekday().num_days_from_monday()));
+            'weeks: while monday <= until {
+                for day in &days {
+                    let current = monday + Duration::days(i64::from(day.num_days_from_monday()));
+                    if current < start {
+                        continue;
+                    }
+                    if current > until || list.len() >= count {
+                        break 'weeks;
+                    }
+                    list.push(current);
+                }
+                monday += Duration::weeks(interval);
+            }
+        }
+        _ => return None,
+    }
+    Some(list)
+}
+
+fn weekday(code: &str) -> Option<Weekday> {
+    // Порядковые префиксы (1MO, -1FR) имеют смысл только для помесячных правил
+    match code.trim() {
+        "MO" => Some(Weekday::Mon),
+        "TU" => Some(Weekday::Tue),
+        "WE" => Some(Weekday::Wed),
+        "TH" => Some(Weekday::Thu),
+        "FR" => Some(Weekday::Fri),
+        "SA" => Some(Weekday::Sat),
+        "SU" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
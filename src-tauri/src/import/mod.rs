@@ -0,0 +1,7 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Импорт расписаний из сторонних форматов в модель записей истории.
+
+pub mod csv;
+pub mod ics;
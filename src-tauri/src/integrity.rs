@@ -0,0 +1,90 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Контрольные суммы сохранённых файлов. Рядом с файлом пишется «.имя.sha256»
+// в формате sha256sum («хеш  имя»), при чтении содержимое сверяется с ним.
+// Несовпадение - оборванная запись, сбой синхронизации или носителя; ошибка
+// начинается с кода CORRUPTED, по которому интерфейс предлагает восстановить
+// файл из резервной копии. Файлы без контрольной суммы (старые, присланные)
+// читаются как раньше.
+
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::paths;
+
+/// Код ошибки «файл повреждён» в начале сообщения
+pub const CORRUPTED: &str = "FILE_CORRUPTED";
+
+fn sidecar_path(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?.to_string_lossy().to_string();
+    Some(path.with_file_name(format!(".{}.sha256", name)))
+}
+
+/// SHA-256 содержимого в hex
+pub fn digest(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// Удаляет контрольную сумму. Вызывается перед записью: если запись оборвётся,
+/// не останется старой суммы, из-за которой новый файл выглядел бы повреждённым
+pub fn forget(path: &Path) {
+    if let Some(sidecar) = sidecar_path(path) {
+        let _ = std::fs::remove_file(paths::to_fs_path(&sidecar));
+    }
+}
+
+/// Записывает контрольную сумму после успешного сохранения. Ошибка записи суммы
+/// не отменяет сохранение: файл без суммы просто не проверяется
+pub fn record(path: &Path, hash: &str) {
+    let (Some(sidecar), Some(name)) = (sidecar_path(path), path.file_name()) else {
+        return;
+    };
+    let line = format!("{}  {}\n", hash, name.to_string_lossy());
+    if std::fs::write(paths::to_fs_path(&sidecar), line).is_err() {
+        forget(path);
+    }
+}
+
+/// Сохранённая контрольная сумма файла
+fn recorded(path: &Path) -> Option<String> {
+    sidecar_path(path)
+        .and_then(|sidecar| std::fs::read_to_string(paths::to_fs_path(&sidecar)).ok())
+        .and_then(|raw| raw.split_whitespace().next().map(str::to_lowercase))
+}
+
+/// Есть ли у файла контрольная сумма
+pub fn has_checksum(path: &Path) -> bool {
+    recorded(path).is_some()
+}
+
+/// Копирует контрольную сумму файла from для файла to (копии с тем же содержимым)
+pub fn copy(from: &Path, to: &Path) {
+    match recorded(from) {
+        Some(hash) => record(to, &hash),
+        None => forget(to),
+    }
+}
+
+/// Переносит контрольную сумму вслед за файлом
+pub fn rename(from: &Path, to: &Path) {
+    copy(from, to);
+    forget(from);
+}
+
+/// Сверяет содержимое с сохранённой контрольной суммой
+pub fn verify(path: &Path, content: &[u8]) -> Result<(), String> {
+    let Some(expected) = recorded(path) else {
+        return Ok(());
+    };
+    if expected == digest(content) {
+        Ok(())
+    } else {
+        Err(format!(
+            "{}: файл повреждён - содержимое не совпадает с контрольной суммой, записанной при сохранении. \
+             Восстановите его из резервной копии. Если файл изменяли вручную, откройте его без проверки",
+            CORRUPTED
+        ))
+    }
+}
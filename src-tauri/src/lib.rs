@@ -3,42 +3,270 @@
 
 // Подробнее о командах Tauri: https://tauri.app/develop/calling-rust/
 
-use std::path::PathBuf;
+mod allowlist;
+mod appdata;
+mod archive;
+mod autosave;
+mod backups;
+mod bells;
+mod cloud;
+mod compression;
+mod crypto;
+mod drives;
+mod edits;
+mod events;
+mod export;
+mod files;
+mod import;
+mod integrity;
+mod locks;
+mod maintenance;
+mod migrate;
+mod model;
+mod opening;
+mod recents;
+mod recovery;
+mod paths;
+mod progress;
+mod salvage;
+mod schedule;
+mod schema;
+mod settings;
+mod snapshots;
+mod streams;
+mod transfer;
+mod validation;
+mod watcher;
+mod workspace;
+mod xml;
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::{Mutex, LazyLock};
 use std::time::{Duration, Instant};
 use std::collections::HashMap;
+use serde::Deserialize;
+use serde_json::json;
 use sha2::{Digest, Sha256};
+use tauri::Manager;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use export::provenance::Provenance;
+
+// Rate limiting по умолчанию: максимум 10 операций в секунду на команду
+const DEFAULT_RATE_POLICY: RatePolicy = RatePolicy { max_calls: 10, window_ms: 1000 };
+
+// Политики по командам. None - команда не ограничивается (дешёвые запросы только для чтения).
+// Команды, которых нет в таблице, получают DEFAULT_RATE_POLICY
+const RATE_POLICIES: &[(&str, Option<RatePolicy>)] = &[
+    ("save_file_secure", Some(DEFAULT_RATE_POLICY)),
+    ("save_file_binary", Some(DEFAULT_RATE_POLICY)),
+    ("read_file_secure", Some(DEFAULT_RATE_POLICY)),
+    ("read_file_binary", Some(DEFAULT_RATE_POLICY)),
+    // Вывод ключа Argon2 занимает 64 МБ памяти и заметное время
+    ("save_file_encrypted", Some(RatePolicy { max_calls: 2, window_ms: 1000 })),
+    ("read_file_encrypted", Some(RatePolicy { max_calls: 2, window_ms: 1000 })),
+    ("unlock_with_recovery_key", Some(RatePolicy { max_calls: 2, window_ms: 1000 })),
+    // Читает копию целиком и при переданном пароле выводит ключ Argon2
+    ("verify_backup", Some(RatePolicy { max_calls: 2, window_ms: 1000 })),
+    ("open_write_session", Some(DEFAULT_RATE_POLICY)),
+    ("open_read_session", Some(DEFAULT_RATE_POLICY)),
+    ("list_files_secure", Some(DEFAULT_RATE_POLICY)),
+    ("delete_file_secure", Some(DEFAULT_RATE_POLICY)),
+    ("move_file_secure", Some(DEFAULT_RATE_POLICY)),
+    // Хеширует файл целиком
+    ("get_file_info", Some(RatePolicy { max_calls: 5, window_ms: 1000 })),
+    ("watch_file", Some(DEFAULT_RATE_POLICY)),
+    ("acquire_lock", Some(DEFAULT_RATE_POLICY)),
+    ("release_lock", Some(DEFAULT_RATE_POLICY)),
+    ("get_lock_owner", Some(DEFAULT_RATE_POLICY)),
+    ("list_backups", Some(DEFAULT_RATE_POLICY)),
+    ("write_app_data", Some(DEFAULT_RATE_POLICY)),
+    ("read_app_data", Some(DEFAULT_RATE_POLICY)),
+    ("delete_app_data", Some(DEFAULT_RATE_POLICY)),
+    ("add_recent", Some(DEFAULT_RATE_POLICY)),
+    ("save_project_db", Some(DEFAULT_RATE_POLICY)),
+    ("open_project_db", Some(DEFAULT_RATE_POLICY)),
+    ("delete_project_db", Some(DEFAULT_RATE_POLICY)),
+    // Читают и пишут все файлы папок приложения
+    ("backup_everything", Some(RatePolicy { max_calls: 2, window_ms: 1000 })),
+    ("restore_everything", Some(RatePolicy { max_calls: 2, window_ms: 1000 })),
+    // Сжимает архив и обходит папки с резервными копиями
+    ("run_maintenance", Some(RatePolicy { max_calls: 2, window_ms: 1000 })),
+    ("set_monthly_maintenance", Some(DEFAULT_RATE_POLICY)),
+    ("set_backup_limit", Some(DEFAULT_RATE_POLICY)),
+    ("set_backup_verification", Some(DEFAULT_RATE_POLICY)),
+    ("restore_backup", Some(DEFAULT_RATE_POLICY)),
+    ("validate_schedule_file", Some(DEFAULT_RATE_POLICY)),
+    ("validate_xml", Some(DEFAULT_RATE_POLICY)),
+    ("validate_schedule", Some(DEFAULT_RATE_POLICY)),
+    ("edit_open", Some(DEFAULT_RATE_POLICY)),
+    ("edit_close", Some(DEFAULT_RATE_POLICY)),
+    // Отмена и повтор - по удержанию Ctrl+Z, с частотой автоповтора клавиатуры
+    ("edit_apply", Some(RatePolicy { max_calls: 50, window_ms: 1000 })),
+    ("edit_undo", Some(RatePolicy { max_calls: 50, window_ms: 1000 })),
+    ("edit_redo", Some(RatePolicy { max_calls: 50, window_ms: 1000 })),
+    ("edit_replay", Some(DEFAULT_RATE_POLICY)),
+    ("create_snapshot", Some(DEFAULT_RATE_POLICY)),
+    ("load_snapshot", Some(DEFAULT_RATE_POLICY)),
+    ("delete_snapshot", Some(DEFAULT_RATE_POLICY)),
+    ("diff_snapshots", Some(DEFAULT_RATE_POLICY)),
+    ("set_autosave_interval", Some(DEFAULT_RATE_POLICY)),
+    ("edit_saved", Some(DEFAULT_RATE_POLICY)),
+    ("recover_session", Some(DEFAULT_RATE_POLICY)),
+    ("discard_recovery", Some(DEFAULT_RATE_POLICY)),
+    ("salvage_recovery", Some(RatePolicy { max_calls: 2, window_ms: 1000 })),
+    ("set_setting", Some(DEFAULT_RATE_POLICY)),
+    ("reset_settings", Some(DEFAULT_RATE_POLICY)),
+    ("open_document", Some(DEFAULT_RATE_POLICY)),
+    ("close_document", Some(DEFAULT_RATE_POLICY)),
+    ("check_conflicts", Some(DEFAULT_RATE_POLICY)),
+    ("set_constraints", Some(DEFAULT_RATE_POLICY)),
+    ("mark_absence", Some(DEFAULT_RATE_POLICY)),
+    ("suggest_substitutions", Some(DEFAULT_RATE_POLICY)),
+    ("analyze_gaps", Some(DEFAULT_RATE_POLICY)),
+    ("workload_report", Some(DEFAULT_RATE_POLICY)),
+    ("room_utilization", Some(DEFAULT_RATE_POLICY)),
+    ("bell_times", Some(DEFAULT_RATE_POLICY)),
+    ("bell_days", Some(DEFAULT_RATE_POLICY)),
+    // Вызывается при перетаскивании операции, пока пользователь выбирает место
+    ("preview_change", Some(RatePolicy { max_calls: 20, window_ms: 1000 })),
+    // Пересчитывает конфликты всего расписания для каждой операции недели
+    ("suggest_swaps", Some(RatePolicy { max_calls: 2, window_ms: 1000 })),
+    // Десятки проходов построения по всем операциям
+    ("solve_schedule", Some(RatePolicy { max_calls: 2, window_ms: 1000 })),
+    ("salvage_file_secure", Some(RatePolicy { max_calls: 2, window_ms: 1000 })),
+    ("import_csv_preview", Some(DEFAULT_RATE_POLICY)),
+    ("import_csv", Some(DEFAULT_RATE_POLICY)),
+    ("import_ics", Some(DEFAULT_RATE_POLICY)),
+    ("export_xlsx", Some(DEFAULT_RATE_POLICY)),
+    ("export_csv", Some(DEFAULT_RATE_POLICY)),
+    ("export_ods", Some(DEFAULT_RATE_POLICY)),
+    ("export_ics", Some(DEFAULT_RATE_POLICY)),
+    ("export_html", Some(DEFAULT_RATE_POLICY)),
+    ("export_markdown", Some(DEFAULT_RATE_POLICY)),
+    ("export_docx", Some(DEFAULT_RATE_POLICY)),
+    // Растеризация большой сетки заметно нагружает процессор
+    ("export_image", Some(RatePolicy { max_calls: 2, window_ms: 1000 })),
+    ("export_svg_pages", Some(RatePolicy { max_calls: 2, window_ms: 1000 })),
+    ("export_door_signs", Some(RatePolicy { max_calls: 2, window_ms: 1000 })),
+    ("export_room_schedules", Some(RatePolicy { max_calls: 2, window_ms: 1000 })),
+    ("export_worker_schedule", Some(DEFAULT_RATE_POLICY)),
+    // Все исполнители одним PDF - тяжёлая выгрузка
+    ("export_all_worker_schedules", Some(RatePolicy { max_calls: 2, window_ms: 1000 })),
+    ("export_substitutions", Some(DEFAULT_RATE_POLICY)),
+    ("export_workload_report", Some(DEFAULT_RATE_POLICY)),
+    ("export_bells", Some(DEFAULT_RATE_POLICY)),
+    // Один вызов пишет много файлов, лимит считается на вызов
+    ("batch_export", Some(RatePolicy { max_calls: 2, window_ms: 1000 })),
+    ("resume_export", Some(RatePolicy { max_calls: 2, window_ms: 1000 })),
+    ("discard_export", Some(DEFAULT_RATE_POLICY)),
+    // Загружает системный шрифт и раскладывает страницы
+    ("export_pdf", Some(RatePolicy { max_calls: 2, window_ms: 1000 })),
+    // Распаковывает изображение целиком для проверки
+    ("register_export_logo", Some(RatePolicy { max_calls: 2, window_ms: 1000 })),
+    ("remove_export_logo", Some(DEFAULT_RATE_POLICY)),
+    ("save_export_template", Some(DEFAULT_RATE_POLICY)),
+    ("delete_export_template", Some(DEFAULT_RATE_POLICY)),
+    // Меняют список папок, в которые разрешена запись
+    ("grant_removable_drive", Some(DEFAULT_RATE_POLICY)),
+    ("remove_allowed_dir", Some(DEFAULT_RATE_POLICY)),
+    ("revoke_network_dir", Some(DEFAULT_RATE_POLICY)),
+    // Читает и хеширует весь exe
+    ("get_exe_hash", Some(RatePolicy { max_calls: 2, window_ms: 5000 })),
+    ("get_allowed_dirs", None),
+    ("get_backup_limit", None),
+    ("get_backup_verification", None),
+    ("list_export_templates", None),
+    ("list_network_dirs", None),
+    ("list_added_dirs", None),
+    ("list_app_data", None),
+    ("get_recents", None),
+    ("edit_state", None),
+    ("list_snapshots", None),
+    ("get_autosave_state", None),
+    ("get_recovery_candidates", None),
+    ("get_setting", None),
+    ("get_constraints", None),
+    ("list_documents", None),
+    ("list_projects_db", None),
+    ("list_project_revisions", None),
+    ("list_removable_drives", None),
+    ("get_maintenance_settings", None),
+];
+
+// Файл с пользовательскими переопределениями политик в папке настроек:
+// { "команда": { "maxCalls": 20, "windowMs": 1000 } } или { "команда": null } для снятия ограничения
+const RATE_LIMITS_FILE: &str = "rate_limits.json";
 
-// Rate limiting: максимум 10 операций в секунду на команду
-const MAX_CALLS_PER_SECOND: usize = 10;
-const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(1);
+// Повторная запись того же содержимого в тот же файл в течение этого окна пропускается
+const DEDUP_WINDOW: Duration = Duration::from_secs(2);
 
 // Максимальный размер файла: 10MB
 const MAX_FILE_SIZE: usize = 10 * 1024 * 1024;
 
+// Предупреждение при разрешении сетевой папки
+const NETWORK_DIR_WARNING: &str = "Файлы в сетевой папке могут одновременно открывать несколько человек. \
+Открытый для изменения файл помечается блокировкой, и другой пользователь увидит, кем он занят; \
+но программы, которые не знают о блокировках, могут перезаписать файл. \
+Также при обрыве сети сохранение может завершиться ошибкой.\n\nРазрешить чтение и запись в папке";
+
+/// Ограничение частоты вызовов: не больше max_calls за window_ms
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RatePolicy {
+    max_calls: usize,
+    window_ms: u64,
+}
+
+/// Счётчики вызовов команд. Хранится в состоянии приложения (manage) и передаётся
+/// командам через tauri::State
 struct RateLimiter {
-    calls: HashMap<String, Vec<Instant>>,
+    calls: Mutex<HashMap<String, Vec<Instant>>>,
+    overrides: HashMap<String, Option<RatePolicy>>,
 }
 
 impl RateLimiter {
     fn new() -> Self {
         RateLimiter {
-            calls: HashMap::new(),
+            calls: Mutex::new(HashMap::new()),
+            overrides: Self::load_overrides(),
+        }
+    }
+
+    fn load_overrides() -> HashMap<String, Option<RatePolicy>> {
+        paths::app_config_dir()
+            .and_then(|dir| std::fs::read_to_string(dir.join(RATE_LIMITS_FILE)).ok())
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default()
+    }
+
+    fn policy(&self, command: &str) -> Option<RatePolicy> {
+        if let Some(policy) = self.overrides.get(command) {
+            return *policy;
         }
+        RATE_POLICIES
+            .iter()
+            .find(|(name, _)| *name == command)
+            .map_or(Some(DEFAULT_RATE_POLICY), |(_, policy)| *policy)
     }
 
-    fn check_rate_limit(&mut self, command: &str) -> Result<(), String> {
+    fn check_rate_limit(&self, command: &str) -> Result<(), String> {
+        let Some(policy) = self.policy(command) else {
+            return Ok(());
+        };
+        let window = Duration::from_millis(policy.window_ms);
         let now = Instant::now();
         let key = command.to_string();
         
+        let mut calls = self.calls.lock().map_err(|_| "Ошибка доступа к rate limiter".to_string())?;
         // Получаем или создаём список вызовов для этой команды
-        let timestamps = self.calls.entry(key).or_insert_with(Vec::new);
+        let timestamps = calls.entry(key).or_default();
         
-        // Удаляем старые временные метки (старше 1 секунды)
-        timestamps.retain(|&t| now.duration_since(t) < RATE_LIMIT_WINDOW);
+        // Удаляем временные метки, вышедшие за окно
+        timestamps.retain(|&t| now.duration_since(t) < window);
         
         // Проверяем лимит
-        if timestamps.len() >= MAX_CALLS_PER_SECOND {
+        if timestamps.len() >= policy.max_calls {
             return Err("Превышен лимит запросов. Попробуйте позже.".to_string());
         }
         
@@ -48,202 +276,2460 @@ impl RateLimiter {
     }
 }
 
-static RATE_LIMITER: LazyLock<Mutex<RateLimiter>> = LazyLock::new(|| Mutex::new(RateLimiter::new()));
-
-/// Проверяет что путь находится в разрешённой директории
-fn is_path_allowed(path: &PathBuf) -> bool {
-    let allowed_dirs: Vec<PathBuf> = [
-        dirs::download_dir(),
-        dirs::document_dir(),
-        dirs::desktop_dir(),
-    ]
-    .into_iter()
-    .flatten()
-    .collect();
-
-    // Канонизируем путь для защиты от ../ атак
-    let canonical = match path.canonicalize() {
-        Ok(p) => p,
-        Err(_) => {
-            // Если файл ещё не существует, проверяем родительскую директорию
-            if let Some(parent) = path.parent() {
-                match parent.canonicalize() {
-                    Ok(p) => p,
-                    Err(_) => return false,
-                }
-            } else {
-                return false;
-            }
+// Защита от двойного нажатия «Сохранить»: одинаковые записи подряд схлопываются в одну
+struct WriteDeduplicator {
+    recent: HashMap<PathBuf, (String, Instant)>,
+}
+
+impl WriteDeduplicator {
+    fn new() -> Self {
+        WriteDeduplicator {
+            recent: HashMap::new(),
         }
+    }
+
+    /// Такая же запись в этот файл только что выполнена
+    fn is_duplicate(&mut self, path: &Path, key: &str) -> bool {
+        let now = Instant::now();
+        self.recent.retain(|_, (_, at)| now.duration_since(*at) < DEDUP_WINDOW);
+        matches!(self.recent.get(&paths::nfc(path)), Some((last, _)) if last == key)
+    }
+
+    fn record(&mut self, path: &Path, key: String) {
+        self.recent.insert(paths::nfc(path), (key, Instant::now()));
+    }
+
+    fn forget(&mut self, path: &Path) {
+        self.recent.remove(&paths::nfc(path));
+    }
+}
+
+static WRITE_DEDUP: LazyLock<Mutex<WriteDeduplicator>> = LazyLock::new(|| Mutex::new(WriteDeduplicator::new()));
+
+/// Ключ записи: переданный фронтендом ключ идемпотентности или хеш содержимого
+fn write_key(idempotency_key: Option<String>, content: &[u8]) -> String {
+    match idempotency_key {
+        Some(key) => format!("key:{}", key),
+        None => format!("{:x}", Sha256::digest(content)),
+    }
+}
+
+/// Проверяет, не повторяет ли запись только что выполненную
+fn is_duplicate_write(path: &Path, key: &str) -> bool {
+    WRITE_DEDUP
+        .lock()
+        .map(|mut dedup| dedup.is_duplicate(path, key))
+        .unwrap_or(false)
+}
+
+fn record_write(path: &Path, key: String) {
+    if let Ok(mut dedup) = WRITE_DEDUP.lock() {
+        dedup.record(path, key);
+    }
+}
+
+/// Забывает запись в файл (файл удалён или перемещён): следующее сохранение не считается повтором
+fn forget_write(path: &Path) {
+    if let Ok(mut dedup) = WRITE_DEDUP.lock() {
+        dedup.forget(path);
+    }
+}
+
+/// Записывает файл. На съёмный носитель запись идёт с принудительным сбросом на диск,
+/// чтобы флешку можно было сразу извлечь
+fn write_file(path: &Path, content: &[u8]) -> Result<(), String> {
+    let target = paths::to_fs_path(path);
+    let Some(root) = paths::session_root(path) else {
+        std::fs::write(&target, content).map_err(|e| paths::io_error_message("Ошибка записи", &e))?;
+        watcher::note_write(path);
+        return Ok(());
     };
 
-    allowed_dirs.iter().any(|dir| {
-        if let Ok(canonical_dir) = dir.canonicalize() {
-            canonical.starts_with(&canonical_dir)
+    let result = std::fs::File::create(&target).and_then(|mut file| {
+        file.write_all(content)?;
+        file.sync_all()
+    });
+    result.map(|_| watcher::note_write(path)).map_err(|e| {
+        if root.exists() {
+            paths::io_error_message("Ошибка записи", &e)
         } else {
-            false
+            // Носитель извлекли во время записи - разрешение больше не действует
+            paths::revoke_session_dir(&root);
+            format!("Съёмный носитель {} отключён во время записи. Подключите его и сохраните файл заново", root.display())
         }
     })
 }
 
-/// Безопасная запись файла с проверкой пути, размера и rate limiting
-#[tauri::command]
-fn save_file_secure(path: String, content: String) -> Result<String, String> {
-    // Rate limiting
-    if let Ok(mut limiter) = RATE_LIMITER.lock() {
-        limiter.check_rate_limit("save_file_secure")?;
-    } else {
-        return Err("Ошибка доступа к rate limiter".into());
-    }
-    
-    // Проверка размера контента
-    if content.len() > MAX_FILE_SIZE {
+/// Читает файл с проверкой размера. Облачные заглушки (OneDrive, Яндекс.Диск, iCloud)
+/// сначала загружаются из облака, а не падают с общей ошибкой чтения
+fn read_file(path: &Path) -> Result<Vec<u8>, String> {
+    let target = paths::to_fs_path(path);
+    let metadata = match std::fs::metadata(&target) {
+        Ok(m) => m,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && cloud::is_icloud_stub(path) => {
+            return Err(cloud::online_only_message(path));
+        }
+        Err(e) => return Err(paths::io_error_message("Ошибка получения информации о файле", &e)),
+    };
+
+    // Проверяем размер файла перед чтением
+    if metadata.len() > MAX_FILE_SIZE as u64 {
         return Err(format!("Размер файла превышает максимальный ({} МБ)", MAX_FILE_SIZE / 1024 / 1024));
     }
-    
-    let path_buf = PathBuf::from(&path);
-    
-    // Проверка расширения файла (только .json и .xml)
+
+    if cloud::is_placeholder(&metadata) {
+        return cloud::read_placeholder(&target);
+    }
+
+    std::fs::read(&target).map_err(|e| paths::io_error_message("Ошибка чтения", &e))
+}
+
+/// Выполняет файловую операцию в пуле блокирующих потоков: чтение больших файлов
+/// и формирование выгрузок не подвешивают окно и не задерживают другие команды
+async fn run_blocking<T, F>(task: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(task)
+        .await
+        .map_err(|e| format!("Ошибка выполнения операции: {}", e))?
+}
+
+/// Общие проверки команд экспорта: rate limiting, расширение, имя файла и разрешённая папка
+fn check_export_path(limiter: &RateLimiter, command: &str, path: &str, extensions: &[&str]) -> Result<PathBuf, String> {
+    limiter.check_rate_limit(command)?;
+
+    let path_buf = PathBuf::from(path);
+
     if let Some(ext) = path_buf.extension() {
         let ext_str = ext.to_string_lossy().to_lowercase();
-        if ext_str != "json" && ext_str != "xml" {
-            return Err("Разрешена запись только .json и .xml файлов".into());
+        if !extensions.contains(&ext_str.as_str()) {
+            let list: Vec<String> = extensions.iter().map(|e| format!(".{}", e)).collect();
+            return Err(format!("Разрешена запись только {} файлов через эту команду", list.join(", ")));
         }
     } else {
         return Err("Файл должен иметь расширение".into());
     }
-    
-    if !is_path_allowed(&path_buf) {
-        return Err("Сохранение разрешено только в папки: Загрузки, Документы или Рабочий стол".into());
-    }
-    
-    std::fs::write(&path_buf, &content)
-        .map_err(|e| format!("Ошибка записи: {}", e))?;
-    
-    Ok(path)
-}
 
-/// Безопасная запись бинарного файла (для .xlsx) с проверкой пути, размера и rate limiting
-#[tauri::command]
-fn save_file_binary(path: String, content: Vec<u8>) -> Result<String, String> {
-    // Rate limiting
-    if let Ok(mut limiter) = RATE_LIMITER.lock() {
-        limiter.check_rate_limit("save_file_binary")?;
-    } else {
-        return Err("Ошибка доступа к rate limiter".into());
-    }
+    paths::check_file_name(&path_buf)?;
 
-    // Проверка размера контента
-    if content.len() > MAX_FILE_SIZE {
-        return Err(format!("Размер файла превышает максимальный ({} МБ)", MAX_FILE_SIZE / 1024 / 1024));
+    if !paths::is_path_allowed(&path_buf) {
+        return Err("Сохранение разрешено только в папки: Загрузки, Документы, Рабочий стол или разрешённые вами папки".into());
     }
 
-    let path_buf = PathBuf::from(&path);
+    Ok(path_buf)
+}
+
+/// Общие проверки команд, читающих файлы пользователя: rate limiting, расширение,
+/// имя файла и разрешённая папка
+fn check_read_path(limiter: &RateLimiter, command: &str, path: &str, extensions: &[&str]) -> Result<PathBuf, String> {
+    limiter.check_rate_limit(command)?;
+
+    let path_buf = PathBuf::from(path);
 
-    // Проверка расширения файла (только .xlsx)
     if let Some(ext) = path_buf.extension() {
         let ext_str = ext.to_string_lossy().to_lowercase();
-        if ext_str != "xlsx" {
-            return Err("Разрешена запись только .xlsx файлов через эту команду".into());
+        if !extensions.contains(&ext_str.as_str()) {
+            let list: Vec<String> = extensions.iter().map(|e| format!(".{}", e)).collect();
+            return Err(format!("Разрешено чтение только {} файлов через эту команду", list.join(", ")));
         }
     } else {
         return Err("Файл должен иметь расширение".into());
     }
 
-    if !is_path_allowed(&path_buf) {
-        return Err("Сохранение разрешено только в папки: Загрузки, Документы или Рабочий стол".into());
+    paths::check_file_name(&path_buf)?;
+
+    if !paths::is_path_allowed(&path_buf) {
+        return Err("Чтение разрешено только из папок: Загрузки, Документы, Рабочий стол или разрешённых вами папок".into());
     }
 
-    std::fs::write(&path_buf, &content)
-        .map_err(|e| format!("Ошибка записи: {}", e))?;
+    Ok(path_buf)
+}
+
+/// Записывает сформированный файл экспорта с проверкой размера
+fn save_export(path: &Path, content: &[u8]) -> Result<(), String> {
+    if content.len() > MAX_FILE_SIZE {
+        return Err(format!("Размер файла превышает максимальный ({} МБ)", MAX_FILE_SIZE / 1024 / 1024));
+    }
+    write_file(path, content)
+}
 
-    Ok(path)
+/// Записывает файл экспорта и, если это включено в настройках, файл сведений
+/// о происхождении рядом с ним
+fn save_traced(path: &Path, content: &[u8], provenance: &Provenance) -> Result<(), String> {
+    save_export(path, content)?;
+    if let Some((sidecar, manifest)) = provenance.sidecar(path) {
+        paths::check_file_name(&sidecar)?;
+        write_file(&sidecar, &manifest)?;
+    }
+    Ok(())
 }
 
-/// Безопасное чтение файла с проверкой пути, размера и rate limiting
+/// Безопасная запись файла с проверкой пути, размера и rate limiting.
+/// compress - сохранить сжатым в gzip (к имени добавляется .gz); путь .json.gz или .xml.gz
+/// сжимается всегда. Возвращает путь сохранённого файла
 #[tauri::command]
-fn read_file_secure(path: String) -> Result<String, String> {
-    // Rate limiting
-    if let Ok(mut limiter) = RATE_LIMITER.lock() {
-        limiter.check_rate_limit("read_file_secure")?;
-    } else {
-        return Err("Ошибка доступа к rate limiter".into());
+async fn save_file_secure(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    content: String,
+    idempotency_key: Option<String>,
+    compress: Option<bool>,
+) -> Result<String, String> {
+    // Сжатие меняет имя файла, поэтому итоговый путь известен до проверки повтора
+    let compress = compress.unwrap_or(false) || compression::is_gz_path(Path::new(&path));
+    let path_buf = if compress { compression::gz_path(Path::new(&path)) } else { PathBuf::from(&path) };
+
+    // Повторная запись того же содержимого (двойной клик) в тот же файл и с тем же
+    // сжатием не пишет файл заново и не расходует лимит
+    let key = format!("{}:{}", if compress { "gz" } else { "raw" }, write_key(idempotency_key, content.as_bytes()));
+    if is_duplicate_write(&path_buf, &key) {
+        return Ok(path_buf.to_string_lossy().to_string());
     }
-    
-    let path_buf = PathBuf::from(&path);
-    
-    // Проверка расширения файла
-    if let Some(ext) = path_buf.extension() {
-        let ext_str = ext.to_string_lossy().to_lowercase();
-        if ext_str != "json" && ext_str != "xml" {
-            return Err("Разрешено чтение только .json и .xml файлов".into());
+
+    limiter.check_rate_limit("save_file_secure")?;
+    run_blocking(move || {
+        // Проверка размера контента; сжатый файл проверяется после сжатия
+        let limit = if compress { compression::MAX_UNPACKED_SIZE } else { MAX_FILE_SIZE };
+        if content.len() > limit {
+            return Err(format!("Размер файла превышает максимальный ({} МБ)", limit / 1024 / 1024));
         }
-    } else {
-        return Err("Файл должен иметь расширение".into());
+
+        // Формат содержимого определяется по имени без .gz
+        let inner = compression::inner_path(&path_buf);
+
+        // Проверка расширения файла (только .json, .ttable и .xml)
+        if let Some(ext) = inner.extension() {
+            let ext_str = ext.to_string_lossy().to_lowercase();
+            if ext_str != "json" && ext_str != files::NATIVE_EXTENSION && ext_str != "xml" {
+                return Err("Разрешена запись только .json, .ttable и .xml файлов".into());
+            }
+        } else {
+            return Err("Файл должен иметь расширение".into());
+        }
+
+        paths::check_file_name(&path_buf)?;
+
+        if !paths::is_path_allowed(&path_buf) {
+            return Err("Сохранение разрешено только в папки: Загрузки, Документы, Рабочий стол или разрешённые вами папки".into());
+        }
+
+        // Резервная копия техкарт сохраняется с версией формата
+        let content = if files::is_json_path(&inner) {
+            migrate::stamp(&content)
+        } else {
+            content
+        };
+        let bytes = if compress { compression::compress(content.as_bytes())? } else { content.into_bytes() };
+        if bytes.len() > MAX_FILE_SIZE {
+            return Err(format!("Размер файла превышает максимальный ({} МБ) даже после сжатия", MAX_FILE_SIZE / 1024 / 1024));
+        }
+
+        locks::check_write(&path_buf)?;
+        backups::rotate(&path_buf)?;
+        integrity::forget(&path_buf);
+        write_file(&path_buf, &bytes)?;
+        integrity::record(&path_buf, &integrity::digest(&bytes));
+        record_write(&path_buf, key);
+
+        Ok(path_buf.to_string_lossy().to_string())
+    })
+    .await
+}
+
+/// Безопасная запись бинарного файла (для .xlsx) с проверкой пути, размера и rate limiting
+#[tauri::command]
+async fn save_file_binary(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    content: Vec<u8>,
+    idempotency_key: Option<String>,
+) -> Result<String, String> {
+    // Повторная запись того же содержимого (двойной клик) не пишет файл заново и не расходует лимит
+    let key = write_key(idempotency_key, &content);
+    if is_duplicate_write(Path::new(&path), &key) {
+        return Ok(path);
     }
-    
-    if !is_path_allowed(&path_buf) {
-        return Err("Чтение разрешено только из папок: Загрузки, Документы или Рабочий стол".into());
+
+    limiter.check_rate_limit("save_file_binary")?;
+    run_blocking(move || {
+        // Проверка размера контента
+        if content.len() > MAX_FILE_SIZE {
+            return Err(format!("Размер файла превышает максимальный ({} МБ)", MAX_FILE_SIZE / 1024 / 1024));
+        }
+
+        let path_buf = PathBuf::from(&path);
+
+        // Проверка расширения файла (только .xlsx)
+        if let Some(ext) = path_buf.extension() {
+            let ext_str = ext.to_string_lossy().to_lowercase();
+            if ext_str != "xlsx" {
+                return Err("Разрешена запись только .xlsx файлов через эту команду".into());
+            }
+        } else {
+            return Err("Файл должен иметь расширение".into());
+        }
+
+        paths::check_file_name(&path_buf)?;
+
+        if !paths::is_path_allowed(&path_buf) {
+            return Err("Сохранение разрешено только в папки: Загрузки, Документы, Рабочий стол или разрешённые вами папки".into());
+        }
+
+        locks::check_write(&path_buf)?;
+        backups::rotate(&path_buf)?;
+        integrity::forget(&path_buf);
+        write_file(&path_buf, &content)?;
+        integrity::record(&path_buf, &integrity::digest(&content));
+        record_write(&path_buf, key);
+
+        Ok(path)
+    })
+    .await
+}
+
+/// Безопасное чтение бинарного файла (.xlsx и изображения) с проверкой пути, размера и rate limiting.
+/// ignore_checksum - открыть файл, не совпадающий с контрольной суммой
+#[tauri::command]
+async fn read_file_binary(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    ignore_checksum: Option<bool>,
+) -> Result<Vec<u8>, String> {
+    let path_buf = check_read_path(&limiter, "read_file_binary", &path, &["xlsx", "png", "jpg", "jpeg"])?;
+    run_blocking(move || {
+        let bytes = read_file(&path_buf)?;
+        if !ignore_checksum.unwrap_or(false) {
+            integrity::verify(&path_buf, &bytes)?;
+        }
+        Ok(bytes)
+    })
+    .await
+}
+
+/// Сохраняет файл зашифрованным паролем (Argon2id + AES-256-GCM). Путь - .json.enc
+/// или .xml.enc (к .json и .xml расширение .enc добавляется). При первом шифровании
+/// и смене пароля возвращается новый ключ восстановления для печати
+#[tauri::command]
+async fn save_file_encrypted(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    content: String,
+    password: String,
+) -> Result<crypto::EncryptedFile, String> {
+    limiter.check_rate_limit("save_file_encrypted")?;
+    run_blocking(move || {
+        if content.len() > MAX_FILE_SIZE {
+            return Err(format!("Размер файла превышает максимальный ({} МБ)", MAX_FILE_SIZE / 1024 / 1024));
+        }
+
+        let path_buf = PathBuf::from(&path);
+        let path_buf = if crypto::is_enc_path(&path_buf) {
+            path_buf
+        } else {
+            let mut name = path_buf.into_os_string();
+            name.push(".enc");
+            PathBuf::from(name)
+        };
+        let inner = crypto::inner_path(&path_buf);
+
+        match inner.extension().map(|ext| ext.to_string_lossy().to_lowercase()) {
+            Some(ext) if ext == "json" || ext == files::NATIVE_EXTENSION || ext == "xml" => {}
+            Some(_) => return Err("Шифровать можно только .json, .ttable и .xml файлы".into()),
+            None => return Err("Файл должен иметь расширение".into()),
+        }
+
+        paths::check_file_name(&path_buf)?;
+
+        if !paths::is_path_allowed(&path_buf) {
+            return Err("Сохранение разрешено только в папки: Загрузки, Документы, Рабочий стол или разрешённые вами папки".into());
+        }
+
+        let content = if files::is_json_path(&inner) {
+            migrate::stamp(&content)
+        } else {
+            content
+        };
+        // Из прежней версии файла переносится ключ восстановления
+        let previous = read_file(&path_buf).ok();
+        let sealed = crypto::encrypt(content.as_bytes(), &password, previous.as_deref())?;
+
+        locks::check_write(&path_buf)?;
+        backups::rotate(&path_buf)?;
+        integrity::forget(&path_buf);
+        write_file(&path_buf, &sealed.bytes)?;
+        integrity::record(&path_buf, &integrity::digest(&sealed.bytes));
+
+        Ok(crypto::EncryptedFile { path: path_buf.to_string_lossy().to_string(), recovery_key: sealed.recovery_key })
+    })
+    .await
+}
+
+/// Открывает зашифрованный файл ключом восстановления, если пароль забыт, и сразу
+/// задаёт новый пароль; ключ восстановления остаётся прежним. Неверный ключ - ошибка
+/// с кодом WRONG_PASSWORD, повреждённый файл - FILE_CORRUPTED
+#[tauri::command]
+async fn unlock_with_recovery_key(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    recovery_key: String,
+    new_password: String,
+) -> Result<String, String> {
+    // Файл сначала читается: проверки и сообщения - как у чтения
+    let path_buf = check_read_path(&limiter, "unlock_with_recovery_key", &path, &["enc"])?;
+    run_blocking(move || {
+        let inner = crypto::inner_path(&path_buf);
+        if !files::is_json_path(&inner) && !inner.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("xml")) {
+            return Err("Разрешено чтение только .json.enc, .ttable.enc и .xml.enc файлов".into());
+        }
+        // Новый пароль записывается в тот же файл: файл только для чтения или занятый
+        // другим пользователем отклоняется до вывода ключа, а не после
+        let metadata = std::fs::metadata(paths::to_fs_path(&path_buf))
+            .map_err(|e| paths::io_error_message("Ошибка чтения файла", &e))?;
+        if metadata.permissions().readonly() {
+            return Err("Файл доступен только для чтения: новый пароль сохранить нельзя".into());
+        }
+        locks::check_write(&path_buf)?;
+
+        let bytes = read_file(&path_buf)?;
+        integrity::verify(&path_buf, &bytes)?;
+        let (plain, resealed) = crypto::recover(&bytes, &recovery_key, &new_password)?;
+        let text = check_schedule_text(&inner, plain)?;
+
+        backups::rotate(&path_buf)?;
+        integrity::forget(&path_buf);
+        write_file(&path_buf, &resealed)?;
+        integrity::record(&path_buf, &integrity::digest(&resealed));
+        Ok(text)
+    })
+    .await
+}
+
+/// Читает зашифрованный файл. Неверный пароль - ошибка с кодом WRONG_PASSWORD,
+/// повреждённый файл - FILE_CORRUPTED
+#[tauri::command]
+async fn read_file_encrypted(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    password: String,
+) -> Result<String, String> {
+    let path_buf = check_read_path(&limiter, "read_file_encrypted", &path, &["enc"])?;
+    run_blocking(move || {
+        let inner = crypto::inner_path(&path_buf);
+        if !files::is_json_path(&inner) && !inner.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("xml")) {
+            return Err("Разрешено чтение только .json.enc, .ttable.enc и .xml.enc файлов".into());
+        }
+
+        let bytes = read_file(&path_buf)?;
+        // Контрольная сумма отличает повреждение файла от неверного пароля
+        integrity::verify(&path_buf, &bytes)?;
+        let plain = crypto::decrypt(&bytes, &password)?;
+        check_schedule_text(&inner, plain)
+    })
+    .await
+}
+
+/// Открывает запись большого файла частями (.json, .xml, .xlsx), возвращает идентификатор сеанса
+#[tauri::command]
+fn open_write_session(limiter: tauri::State<'_, RateLimiter>, path: String) -> Result<String, String> {
+    let path_buf = check_export_path(&limiter, "open_write_session", &path, &files::SCHEDULE_EXTENSIONS)?;
+    locks::check_write(&path_buf)?;
+    streams::open_write(&path_buf)
+}
+
+/// Дописывает часть (не больше 4 МБ), возвращает общий записанный объём
+#[tauri::command]
+async fn write_chunk(session: String, chunk: Vec<u8>) -> Result<u64, String> {
+    run_blocking(move || streams::write_chunk(&session, &chunk)).await
+}
+
+/// Завершает запись частями: файл заменяется целиком только сейчас
+#[tauri::command]
+async fn finish_write(session: String) -> Result<String, String> {
+    run_blocking(move || {
+        let (path, hash) = streams::finish_write(&session)?;
+        integrity::record(&path, &hash);
+        watcher::note_write(&path);
+        Ok(path.to_string_lossy().to_string())
+    })
+    .await
+}
+
+/// Отменяет запись частями, прежний файл не меняется
+#[tauri::command]
+fn abort_write(session: String) -> Result<(), String> {
+    streams::abort_write(&session)
+}
+
+/// Открывает чтение большого файла частями: идентификатор сеанса и размер файла
+#[tauri::command]
+fn open_read_session(limiter: tauri::State<'_, RateLimiter>, path: String) -> Result<streams::ReadSessionInfo, String> {
+    let path_buf = check_read_path(&limiter, "open_read_session", &path, &files::SCHEDULE_EXTENSIONS)?;
+    streams::open_read(&path_buf)
+}
+
+/// Читает часть файла с позиции offset (не больше 4 МБ); пустой ответ - конец файла
+#[tauri::command]
+async fn read_chunk(session: String, offset: u64, length: usize) -> Result<Vec<u8>, String> {
+    run_blocking(move || streams::read_chunk(&session, offset, length)).await
+}
+
+/// Закрывает сеанс чтения частями
+#[tauri::command]
+fn close_read_session(session: String) -> Result<(), String> {
+    streams::close_read(&session)
+}
+
+/// Файлы .json, .xml и .xlsx в разрешённой папке с размером и временем изменения,
+/// новые первыми. pattern - шаблон имени (* и ?), без учёта регистра
+#[tauri::command]
+async fn list_files_secure(
+    limiter: tauri::State<'_, RateLimiter>,
+    dir: String,
+    pattern: Option<String>,
+) -> Result<Vec<files::FileEntry>, String> {
+    limiter.check_rate_limit("list_files_secure")?;
+    run_blocking(move || {
+        let dir_buf = PathBuf::from(&dir);
+        if !dir_buf.is_dir() {
+            return Err("Папка не найдена".into());
+        }
+        if !paths::is_path_allowed(&dir_buf) {
+            return Err("Просмотр разрешён только в папках: Загрузки, Документы, Рабочий стол или разрешённых вами папках".into());
+        }
+
+        files::list(&dir_buf, pattern.as_deref())
+    })
+    .await
+}
+
+/// Перемещает файл из разрешённой папки в корзину системы
+#[tauri::command]
+async fn delete_file_secure(limiter: tauri::State<'_, RateLimiter>, path: String) -> Result<(), String> {
+    let path_buf = check_export_path(&limiter, "delete_file_secure", &path, &files::USER_EXTENSIONS)?;
+    run_blocking(move || {
+        files::delete(&path_buf)?;
+        integrity::forget(&path_buf);
+        forget_write(&path_buf);
+        Ok(())
+    })
+    .await
+}
+
+/// Перемещает или переименовывает файл внутри разрешённых папок. Расширение
+/// менять нельзя, существующий файл не перезаписывается
+#[tauri::command]
+async fn move_file_secure(limiter: tauri::State<'_, RateLimiter>, from: String, to: String) -> Result<String, String> {
+    limiter.check_rate_limit("move_file_secure")?;
+    run_blocking(move || {
+        let from_buf = PathBuf::from(&from);
+        let to_buf = PathBuf::from(&to);
+
+        let extension = |path: &Path| path.extension().map(|e| e.to_string_lossy().to_lowercase());
+        match (extension(&from_buf), extension(&to_buf)) {
+            (Some(a), Some(b)) if a == b && files::USER_EXTENSIONS.contains(&a.as_str()) => {}
+            (Some(a), Some(b)) if a == b => return Err(format!("Файлы .{} нельзя перемещать через эту команду", a)),
+            (Some(_), Some(_)) => return Err("При перемещении нельзя менять расширение файла".into()),
+            _ => return Err("Файл должен иметь расширение".into()),
+        }
+
+        paths::check_file_name(&from_buf)?;
+        paths::check_file_name(&to_buf)?;
+
+        if !paths::is_path_allowed(&from_buf) || !paths::is_path_allowed(&to_buf) {
+            return Err("Перемещение разрешено только между папками: Загрузки, Документы, Рабочий стол или разрешёнными вами папками".into());
+        }
+
+        // Перемещение меняет оба пути: файл, занятый другим пользователем, не уводится из-под него
+        locks::check_write(&from_buf)?;
+        locks::check_write(&to_buf)?;
+
+        files::move_file(&from_buf, &to_buf)?;
+        integrity::rename(&from_buf, &to_buf);
+        watcher::note_write(&from_buf);
+        watcher::note_write(&to_buf);
+        forget_write(&from_buf);
+        forget_write(&to_buf);
+        Ok(to)
+    })
+    .await
+}
+
+/// Размер, время изменения, «только чтение» и SHA-256 файла: перед сохранением
+/// фронтенд сверяет их со значениями на момент открытия
+#[tauri::command]
+async fn get_file_info(limiter: tauri::State<'_, RateLimiter>, path: String) -> Result<files::FileInfo, String> {
+    let path_buf = check_read_path(&limiter, "get_file_info", &path, &files::USER_EXTENSIONS)?;
+    run_blocking(move || files::info(&path_buf)).await
+}
+
+/// Начинает слежение за файлом: при изменении другой программой фронтенд получает
+/// событие «file-changed» с путём и видом изменения (modified или removed)
+#[tauri::command]
+fn watch_file(app: tauri::AppHandle, limiter: tauri::State<'_, RateLimiter>, path: String) -> Result<(), String> {
+    let path_buf = check_read_path(&limiter, "watch_file", &path, &files::USER_EXTENSIONS)?;
+    watcher::watch(&app, &path_buf)
+}
+
+/// Прекращает слежение за файлом
+#[tauri::command]
+fn unwatch_file(path: String) -> Result<(), String> {
+    watcher::unwatch(Path::new(&path))
+}
+
+/// Занимает файл для изменения (файл «.имя.lock» рядом с ним). Если файл занят
+/// другим пользователем, ошибка сообщает кем и с какого времени; force - перехватить
+#[tauri::command]
+fn acquire_lock(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    force: Option<bool>,
+) -> Result<locks::LockOwner, String> {
+    let path_buf = check_export_path(&limiter, "acquire_lock", &path, &files::SCHEDULE_EXTENSIONS)?;
+    locks::acquire(&path_buf, force.unwrap_or(false))
+}
+
+/// Снимает свою блокировку файла
+#[tauri::command]
+fn release_lock(limiter: tauri::State<'_, RateLimiter>, path: String) -> Result<(), String> {
+    let path_buf = check_export_path(&limiter, "release_lock", &path, &files::SCHEDULE_EXTENSIONS)?;
+    locks::release(&path_buf)
+}
+
+/// Кем занят файл; null - свободен
+#[tauri::command]
+fn get_lock_owner(limiter: tauri::State<'_, RateLimiter>, path: String) -> Result<Option<locks::LockOwner>, String> {
+    let path_buf = check_read_path(&limiter, "get_lock_owner", &path, &files::SCHEDULE_EXTENSIONS)?;
+    Ok(locks::owner(&path_buf))
+}
+
+/// Резервные копии файла из папки .backups, новые первыми
+#[tauri::command]
+async fn list_backups(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+) -> Result<Vec<backups::BackupInfo>, String> {
+    let path_buf = check_read_path(&limiter, "list_backups", &path, &files::SCHEDULE_EXTENSIONS)?;
+    run_blocking(move || Ok(backups::list(&path_buf))).await
+}
+
+/// Восстанавливает файл из резервной копии; текущая версия сохраняется в копии
+#[tauri::command]
+async fn restore_backup(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    backup: String,
+) -> Result<String, String> {
+    let path_buf = check_export_path(&limiter, "restore_backup", &path, &files::SCHEDULE_EXTENSIONS)?;
+    run_blocking(move || {
+        locks::check_write(&path_buf)?;
+        // Контрольная сумма относилась к заменённому содержимому
+        integrity::forget(&path_buf);
+        backups::restore(&path_buf, Path::new(&backup))?;
+        watcher::note_write(&path_buf);
+        Ok(path)
+    })
+    .await
+}
+
+/// Проверяет копию: контрольная сумма, расшифровка, распаковка и формат содержимого.
+/// Копия зашифрованного файла без пароля проверяется только по контрольной сумме
+fn check_backup(
+    path: &Path,
+    backup: &Path,
+    password: Option<&str>,
+    check: &mut backups::BackupCheck,
+) -> Result<(), String> {
+    let bytes = read_file(backup)?;
+    integrity::verify(backup, &bytes)?;
+    check.checksum_verified = integrity::has_checksum(backup);
+
+    let (inner, bytes) = if crypto::is_enc_path(path) {
+        let Some(password) = password else {
+            check.needs_password = true;
+            return Ok(());
+        };
+        (crypto::inner_path(path), crypto::decrypt(&bytes, password)?)
+    } else {
+        (path.to_path_buf(), bytes)
+    };
+    let bytes = if compression::is_gzip(&bytes) { compression::decompress(&bytes)? } else { bytes };
+    let inner = compression::inner_path(&inner);
+
+    // Содержимое .xlsx не проверяется: его читает только фронтенд
+    let is_xml = inner.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("xml"));
+    if files::is_json_path(&inner) {
+        let text = String::from_utf8_lossy(&bytes);
+        let from_version = migrate::upgrade(&text)?.from_version;
+        check.migrated_from = (from_version < migrate::CURRENT_VERSION).then_some(from_version);
     }
-    
-    // Проверяем размер файла перед чтением
-    let metadata = std::fs::metadata(&path_buf)
-        .map_err(|e| format!("Ошибка получения информации о файле: {}", e))?;
-    
-    if metadata.len() > MAX_FILE_SIZE as u64 {
-        return Err(format!("Размер файла превышает максимальный ({} МБ)", MAX_FILE_SIZE / 1024 / 1024));
+    if files::is_json_path(&inner) || is_xml {
+        check_schedule_text(&inner, bytes)?;
     }
-    
-    std::fs::read_to_string(&path_buf)
-        .map_err(|e| format!("Ошибка чтения: {}", e))
+    // Восстановление перезаписывает файл: занятый коллегой файл не восстановится
+    locks::check_write(path)
 }
 
-/// Вычисляет SHA-256 хеш исполняемого файла приложения
+/// Проверяет, пройдёт ли восстановление из резервной копии, не восстанавливая её:
+/// копия читается отдельно от файла, сверяется с контрольной суммой, расшифровывается
+/// (если передан пароль), распаковывается и проверяется по схеме
 #[tauri::command]
-fn get_exe_hash() -> Result<String, String> {
-    let exe_path = std::env::current_exe()
-        .map_err(|e| format!("Не удалось получить путь к exe: {}", e))?;
-    let bytes = std::fs::read(&exe_path)
-        .map_err(|e| format!("Ошибка чтения exe: {}", e))?;
-    let hash = Sha256::digest(&bytes);
-    Ok(format!("{:x}", hash))
+async fn verify_backup(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    backup: String,
+    password: Option<String>,
+) -> Result<backups::BackupCheck, String> {
+    let path_buf = check_read_path(&limiter, "verify_backup", &path, &files::SCHEDULE_EXTENSIONS)?;
+    run_blocking(move || {
+        let backup = backups::find(&path_buf, Path::new(&backup))?;
+        let mut check = backups::BackupCheck { path: backup.to_string_lossy().to_string(), ..Default::default() };
+        if let Err(e) = check_backup(&path_buf, &backup, password.as_deref(), &mut check) {
+            check.error = Some(e);
+        }
+        check.restorable = check.error.is_none() && !check.needs_password;
+        Ok(check)
+    })
+    .await
 }
 
-/// Возвращает список разрешённых директорий
+/// Сколько резервных копий каждого файла хранится
 #[tauri::command]
-fn get_allowed_dirs() -> Vec<String> {
-    [
-        dirs::download_dir(),
-        dirs::document_dir(), 
-        dirs::desktop_dir(),
-    ]
-    .into_iter()
-    .flatten()
-    .map(|p| p.to_string_lossy().to_string())
-    .collect()
+fn get_backup_limit() -> usize {
+    backups::keep()
 }
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    tauri::Builder::default()
-        .plugin(tauri_plugin_opener::init())
-        .plugin(tauri_plugin_dialog::init())
-        .plugin(tauri_plugin_fs::init())
-        .invoke_handler(tauri::generate_handler![
-            save_file_secure,
-            save_file_binary,
-            read_file_secure,
-            get_allowed_dirs,
-            get_exe_hash
-        ])
-        .setup(|_app| {
-            // DevTools только в debug режиме
-            #[cfg(debug_assertions)]
-            {
-                
-            }
-            Ok(())
-        })
-        .run(tauri::generate_context!())
-        .expect("ошибка при запуске приложения Tauri");
+/// Задаёт число хранимых копий (0 - не создавать)
+#[tauri::command]
+fn set_backup_limit(limiter: tauri::State<'_, RateLimiter>, keep: usize) -> Result<(), String> {
+    limiter.check_rate_limit("set_backup_limit")?;
+    backups::set_keep(keep)
+}
+
+/// Сверяется ли каждая новая резервная копия с исходным файлом
+#[tauri::command]
+fn get_backup_verification() -> bool {
+    backups::verify_enabled()
+}
+
+/// Включает проверку каждой новой копии: испорченная копия отменяет перезапись файла
+#[tauri::command]
+fn set_backup_verification(limiter: tauri::State<'_, RateLimiter>, enabled: bool) -> Result<(), String> {
+    limiter.check_rate_limit("set_backup_verification")?;
+    backups::set_verify(enabled)
+}
+
+/// Проверяет прочитанный рабочий файл перед передачей во фронтенд. inner - путь
+/// без .gz и .enc, по его расширению выбирается проверка
+fn check_schedule_text(inner: &Path, bytes: Vec<u8>) -> Result<String, String> {
+    let text = String::from_utf8(bytes).map_err(|_| "Ошибка чтения: файл не в кодировке UTF-8".to_string())?;
+
+    // Файл, не соответствующий схеме, не передаём во фронтенд
+    // Резервные копии старых форматов обновляются до текущего
+    if files::is_json_path(inner) {
+        let text = migrate::upgrade(&text)?.text;
+        schema::validate_backup(&text).summary().map_or(Ok(text), Err)
+    } else {
+        xml::check(&text).summary().map_or(Ok(text), Err)
+    }
+}
+
+/// Читает рабочий .json или .xml (в том числе сжатый) по уже проверенному пути:
+/// контрольная сумма, распаковка и проверка содержимого
+fn load_schedule(path: &Path, ignore_checksum: bool) -> Result<String, String> {
+    let bytes = read_file(path)?;
+    if !ignore_checksum {
+        integrity::verify(path, &bytes)?;
+    }
+    let bytes = if compression::is_gzip(&bytes) { compression::decompress(&bytes)? } else { bytes };
+    check_schedule_text(&compression::inner_path(path), bytes)
+}
+
+/// Безопасное чтение файла с проверкой пути, размера и rate limiting.
+/// Повреждённый файл (не совпадает контрольная сумма) даёт ошибку с кодом FILE_CORRUPTED;
+/// ignore_checksum - открыть его всё равно
+#[tauri::command]
+async fn read_file_secure(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    ignore_checksum: Option<bool>,
+) -> Result<String, String> {
+    limiter.check_rate_limit("read_file_secure")?;
+    run_blocking(move || {
+        let path_buf = PathBuf::from(&path);
+        // Сжатые .json.gz и .xml.gz проверяются по имени без .gz
+        let inner = compression::inner_path(&path_buf);
+
+        // Проверка расширения файла
+        if let Some(ext) = inner.extension() {
+            let ext_str = ext.to_string_lossy().to_lowercase();
+            if ext_str != "json" && ext_str != files::NATIVE_EXTENSION && ext_str != "xml" {
+                return Err("Разрешено чтение только .json, .ttable и .xml файлов".into());
+            }
+        } else {
+            return Err("Файл должен иметь расширение".into());
+        }
+
+        paths::check_file_name(&path_buf)?;
+
+        if !paths::is_path_allowed(&path_buf) {
+            return Err("Чтение разрешено только из папок: Загрузки, Документы, Рабочий стол или разрешённых вами папок".into());
+        }
+
+        load_schedule(&path_buf, ignore_checksum.unwrap_or(false))
+    })
+    .await
+}
+
+/// Проверка XML-файла: корректность и отсутствие DTD и внешних сущностей,
+/// первое нарушение - со строкой и колонкой
+#[tauri::command]
+async fn validate_xml(limiter: tauri::State<'_, RateLimiter>, path: String) -> Result<xml::XmlReport, String> {
+    let path_buf = check_read_path(&limiter, "validate_xml", &path, &["xml"])?;
+    run_blocking(move || {
+        let text = String::from_utf8(read_file(&path_buf)?).map_err(|_| "Ошибка чтения: файл не в кодировке UTF-8".to_string())?;
+        Ok(xml::check(&text))
+    })
+    .await
+}
+
+/// Проверка JSON-файла по схеме: список ошибок с указанием места в документе
+#[tauri::command]
+async fn validate_schedule_file(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+) -> Result<schema::ValidationReport, String> {
+    let path_buf = check_read_path(&limiter, "validate_schedule_file", &path, &["json", files::NATIVE_EXTENSION])?;
+    run_blocking(move || {
+        let text = String::from_utf8(read_file(&path_buf)?).map_err(|_| "Ошибка чтения: файл не в кодировке UTF-8".to_string())?;
+        let upgraded = migrate::upgrade(&text)?;
+        let mut report = schema::validate_backup(&upgraded.text);
+        if upgraded.from_version < migrate::CURRENT_VERSION {
+            report.migrated_from = Some(upgraded.from_version);
+        }
+        Ok(report)
+    })
+    .await
+}
+
+/// Смысловая проверка расписания: исполнители, время операций, пересечения
+/// операций одного исполнителя и операции на время внешних событий
+#[tauri::command]
+async fn validate_schedule(
+    limiter: tauri::State<'_, RateLimiter>,
+    schedule: serde_json::Value,
+    workers: Option<Vec<String>>,
+) -> Result<validation::ScheduleReport, String> {
+    limiter.check_rate_limit("validate_schedule")?;
+    run_blocking(move || Ok(validation::validate(schedule, workers.as_deref()))).await
+}
+
+/// Двойные назначения: пересекающиеся операции одного исполнителя, в том числе из
+/// разных записей истории, операции на время внешних событий и нарушения ограничений
+/// (без списка ограничений - сохранённых)
+#[tauri::command]
+async fn check_conflicts(
+    limiter: tauri::State<'_, RateLimiter>,
+    schedule: model::Schedule,
+    constraints: Option<Vec<schedule::constraints::Constraint>>,
+) -> Result<Vec<schedule::conflicts::Conflict>, String> {
+    limiter.check_rate_limit("check_conflicts")?;
+    run_blocking(move || {
+        let constraints = constraints.unwrap_or_else(schedule::constraints::load);
+        schedule::constraints::check(&constraints)?;
+        Ok(schedule::conflicts::find_with(&schedule, &constraints))
+    })
+    .await
+}
+
+/// Записывает отсутствие исполнителя событием в расписании и возвращает расписание
+#[tauri::command]
+fn mark_absence(
+    limiter: tauri::State<'_, RateLimiter>,
+    schedule: model::Schedule,
+    absence: schedule::substitution::Absence,
+) -> Result<model::Schedule, String> {
+    limiter.check_rate_limit("mark_absence")?;
+    schedule::substitution::mark_absent(schedule, &absence)
+}
+
+/// Операции отсутствующего исполнителя и подходящие замены: свободные в это время,
+/// с опытом такой операции и меньшей загрузкой
+#[tauri::command]
+async fn suggest_substitutions(
+    limiter: tauri::State<'_, RateLimiter>,
+    schedule: model::Schedule,
+    absence: schedule::substitution::Absence,
+    workers: Option<Vec<String>>,
+    constraints: Option<Vec<schedule::constraints::Constraint>>,
+) -> Result<Vec<schedule::substitution::Substitution>, String> {
+    limiter.check_rate_limit("suggest_substitutions")?;
+    run_blocking(move || {
+        let constraints = constraints.unwrap_or_else(schedule::constraints::load);
+        schedule::constraints::check(&constraints)?;
+        schedule::substitution::suggest(&schedule, &absence, workers.as_deref(), &constraints)
+    })
+    .await
+}
+
+/// Окна: простои исполнителей и перерывы между операциями техкарт внутри дня
+#[tauri::command]
+async fn analyze_gaps(
+    limiter: tauri::State<'_, RateLimiter>,
+    schedule: model::Schedule,
+    options: Option<schedule::gaps::GapOptions>,
+) -> Result<schedule::gaps::GapReport, String> {
+    limiter.check_rate_limit("analyze_gaps")?;
+    run_blocking(move || schedule::gaps::analyze(&schedule, &options.unwrap_or_default())).await
+}
+
+/// Нагрузка исполнителей по неделям, операциям и техкартам в сравнении со ставкой
+#[tauri::command]
+async fn workload_report(
+    limiter: tauri::State<'_, RateLimiter>,
+    schedule: model::Schedule,
+    options: Option<schedule::workload::WorkloadOptions>,
+) -> Result<schedule::workload::WorkloadReport, String> {
+    limiter.check_rate_limit("workload_report")?;
+    run_blocking(move || schedule::workload::report(&schedule, &options.unwrap_or_default())).await
+}
+
+/// Занятость рабочих мест (аналог кабинетов): процент занятости, часы пик и
+/// свободные промежутки. Место задаётся операциями, которые на нём выполняются
+#[tauri::command]
+async fn room_utilization(
+    limiter: tauri::State<'_, RateLimiter>,
+    schedule: model::Schedule,
+    options: schedule::utilization::UtilizationOptions,
+) -> Result<schedule::utilization::UtilizationReport, String> {
+    limiter.check_rate_limit("room_utilization")?;
+    run_blocking(move || schedule::utilization::report(&schedule, &options)).await
+}
+
+/// Начало и окончание периодов каждого варианта дня графика звонков
+#[tauri::command]
+fn bell_times(
+    limiter: tauri::State<'_, RateLimiter>,
+    bells: bells::BellSchedule,
+) -> Result<Vec<bells::VariantTimes>, String> {
+    limiter.check_rate_limit("bell_times")?;
+    bells.times()
+}
+
+/// Звонки каждого дня диапазона дд.мм.гггг по сменам; сокращённые дни - по своему варианту
+#[tauri::command]
+async fn bell_days(
+    limiter: tauri::State<'_, RateLimiter>,
+    bells: bells::BellSchedule,
+    from: String,
+    to: String,
+) -> Result<Vec<bells::DayTimes>, String> {
+    limiter.check_rate_limit("bell_days")?;
+    run_blocking(move || bells.days(&from, &to)).await
+}
+
+/// Пробное изменение: применяет перенос, обмен или удаление к копии расписания и
+/// возвращает появившиеся и устранённые конфликты и показатели до и после
+#[tauri::command]
+async fn preview_change(
+    limiter: tauri::State<'_, RateLimiter>,
+    schedule: model::Schedule,
+    change: schedule::preview::Change,
+    constraints: Option<Vec<schedule::constraints::Constraint>>,
+) -> Result<schedule::preview::Preview, String> {
+    limiter.check_rate_limit("preview_change")?;
+    run_blocking(move || {
+        let constraints = constraints.unwrap_or_else(schedule::constraints::load);
+        schedule::constraints::check(&constraints)?;
+        schedule::preview::preview(&schedule, &change, &constraints)
+    })
+    .await
+}
+
+/// Варианты обмена операции временем с операциями той же недели без новых конфликтов,
+/// лучшие по окнам и равномерности нагрузки первыми
+#[tauri::command]
+async fn suggest_swaps(
+    limiter: tauri::State<'_, RateLimiter>,
+    schedule: model::Schedule,
+    target: schedule::preview::RowRef,
+    constraints: Option<Vec<schedule::constraints::Constraint>>,
+) -> Result<Vec<schedule::swaps::SwapSuggestion>, String> {
+    limiter.check_rate_limit("suggest_swaps")?;
+    run_blocking(move || {
+        let constraints = constraints.unwrap_or_else(schedule::constraints::load);
+        schedule::constraints::check(&constraints)?;
+        schedule::swaps::suggest(&schedule, target, &constraints)
+    })
+    .await
+}
+
+/// Сохранённые ограничения расписания
+#[tauri::command]
+async fn get_constraints() -> Result<Vec<schedule::constraints::Constraint>, String> {
+    run_blocking(|| Ok(schedule::constraints::load())).await
+}
+
+/// Заменяет сохранённые ограничения расписания
+#[tauri::command]
+async fn set_constraints(
+    limiter: tauri::State<'_, RateLimiter>,
+    constraints: Vec<schedule::constraints::Constraint>,
+) -> Result<(), String> {
+    limiter.check_rate_limit("set_constraints")?;
+    run_blocking(move || schedule::constraints::save(&constraints)).await
+}
+
+/// Строит расписание: ставит операции исполнителям с учётом порядка, рабочего
+/// календаря и занятого времени. Ход поиска отправляется событиями прогресса
+#[tauri::command]
+async fn solve_schedule(
+    app: tauri::AppHandle,
+    limiter: tauri::State<'_, RateLimiter>,
+    request: schedule::solver::SolveRequest,
+    operation_id: Option<String>,
+) -> Result<schedule::solver::SolveResult, String> {
+    limiter.check_rate_limit("solve_schedule")?;
+    run_blocking(move || {
+        let mut request = request;
+        if request.constraints.is_none() {
+            request.constraints = Some(schedule::constraints::load());
+        }
+        let mut reporter = progress::Reporter::new(&app, operation_id);
+        schedule::solver::solve(&request, &mut |done, total, current| reporter.report(done, total, current))
+    })
+    .await
+}
+
+/// Восстановление повреждённого JSON-файла: возвращает уцелевшие записи и отчёт о потерянных
+#[tauri::command]
+async fn salvage_file_secure(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+) -> Result<salvage::SalvageReport, String> {
+    limiter.check_rate_limit("salvage_file_secure")?;
+    run_blocking(move || {
+        let path_buf = PathBuf::from(&path);
+
+        // Проверка расширения файла (XML приложение не создаёт, восстанавливаем только JSON)
+        if let Some(ext) = path_buf.extension() {
+            let ext_str = ext.to_string_lossy().to_lowercase();
+            if ext_str != "json" && ext_str != files::NATIVE_EXTENSION {
+                return Err("Восстановление поддерживается только для .json и .ttable файлов".into());
+            }
+        } else {
+            return Err("Файл должен иметь расширение".into());
+        }
+
+        paths::check_file_name(&path_buf)?;
+
+        if !paths::is_path_allowed(&path_buf) {
+            return Err("Чтение разрешено только из папок: Загрузки, Документы, Рабочий стол или разрешённых вами папок".into());
+        }
+
+        // Читаем байты, а не строку: битые последовательности UTF-8 не должны обрывать чтение
+        let bytes = read_file(&path_buf)?;
+
+        salvage::salvage_json(&bytes)
+    })
+    .await
+}
+
+/// Выгрузка расписания в Excel: книга формируется на стороне Rust.
+/// split_sheets - лист на каждую запись истории и первый лист «Сводная» с нагрузкой
+/// исполнителей; workplaces - рабочие места, как у room_utilization, для таблицы занятости в сводной.
+/// Ход по записям - события «export://progress» с operation_id, отмена - cancel_operation
+#[tauri::command]
+// Аргументы команды - поля объекта в invoke, структура изменила бы вызов из фронтенда
+#[allow(clippy::too_many_arguments)]
+async fn export_xlsx(
+    app: tauri::AppHandle,
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    schedule: model::Schedule,
+    split_sheets: Option<bool>,
+    workplaces: Option<schedule::utilization::UtilizationOptions>,
+    template: Option<String>,
+    operation_id: Option<String>,
+) -> Result<String, String> {
+    let path_buf = check_export_path(&limiter, "export_xlsx", &path, &["xlsx"])?;
+    run_blocking(move || {
+        let template = export::templates::find(template.as_deref())?;
+        let split_sheets = split_sheets.unwrap_or(false);
+        let options = json!({
+            "template": template.name,
+            "splitSheets": split_sheets,
+            "workplaces": workplaces.is_some(),
+        });
+        let provenance = Provenance::new(export::Format::Xlsx, &schedule, options);
+        let mut reporter = progress::Reporter::new(&app, operation_id);
+        let content = export::xlsx::render_with_progress(
+            &schedule,
+            split_sheets,
+            workplaces.as_ref(),
+            &template,
+            &provenance,
+            &mut |done, total, current| reporter.report(done, total, current),
+        )?;
+        save_traced(&path_buf, &content, &provenance)?;
+        Ok(path)
+    })
+    .await
+}
+
+/// Выгрузка расписания в PDF для печати. organization - название организации для шапки страниц,
+/// page - формат бумаги, поля и ориентация (по умолчанию - из шаблона оформления)
+#[tauri::command]
+async fn export_pdf(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    schedule: model::Schedule,
+    organization: Option<String>,
+    page: Option<export::pdf::PageSetup>,
+    template: Option<String>,
+) -> Result<String, String> {
+    let path_buf = check_export_path(&limiter, "export_pdf", &path, &["pdf"])?;
+    run_blocking(move || {
+        let template = export::templates::find(template.as_deref())?;
+        let page = page.unwrap_or_else(|| export::pdf::PageSetup::for_template(&template));
+        let options = json!({ "template": template.name, "organization": organization });
+        let provenance = Provenance::new(export::Format::Pdf, &schedule, options);
+        let content = export::pdf::render(&schedule, organization.as_deref(), &page, &template, &provenance)?;
+        save_traced(&path_buf, &content, &provenance)?;
+        Ok(path)
+    })
+    .await
+}
+
+/// Выгрузка расписания в CSV (разделитель и BOM задаются параметрами)
+#[tauri::command]
+async fn export_csv(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    schedule: model::Schedule,
+    options: Option<export::csv::CsvOptions>,
+) -> Result<String, String> {
+    let path_buf = check_export_path(&limiter, "export_csv", &path, &["csv"])?;
+    run_blocking(move || {
+        let provenance = Provenance::new(export::Format::Csv, &schedule, json!({}));
+        let content = export::csv::render(&schedule, options.unwrap_or_default())?;
+        save_traced(&path_buf, content.as_bytes(), &provenance)?;
+        Ok(path)
+    })
+    .await
+}
+
+/// Выгрузка расписания в OpenDocument (.ods) для LibreOffice Calc
+#[tauri::command]
+async fn export_ods(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    schedule: model::Schedule,
+    template: Option<String>,
+) -> Result<String, String> {
+    let path_buf = check_export_path(&limiter, "export_ods", &path, &["ods"])?;
+    run_blocking(move || {
+        let template = export::templates::find(template.as_deref())?;
+        let provenance = Provenance::new(export::Format::Ods, &schedule, json!({ "template": template.name }));
+        let content = export::ods::render(&schedule, &template)?;
+        save_traced(&path_buf, &content, &provenance)?;
+        Ok(path)
+    })
+    .await
+}
+
+/// Выгрузка операций в календарь (.ics): всех, одного исполнителя или одной записи истории
+#[tauri::command]
+async fn export_ics(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    schedule: model::Schedule,
+    worker: Option<String>,
+    entry: Option<usize>,
+) -> Result<String, String> {
+    let path_buf = check_export_path(&limiter, "export_ics", &path, &["ics"])?;
+    run_blocking(move || {
+        let provenance = Provenance::new(export::Format::Ics, &schedule, json!({ "worker": worker, "entry": entry }));
+        let content = export::ics::render(&schedule, worker.as_deref(), entry)?;
+        save_traced(&path_buf, content.as_bytes(), &provenance)?;
+        Ok(path)
+    })
+    .await
+}
+
+/// Выгрузка расписания в один HTML-файл со встроенными стилями (dark - тёмная тема)
+#[tauri::command]
+async fn export_html(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    schedule: model::Schedule,
+    dark: Option<bool>,
+    template: Option<String>,
+) -> Result<String, String> {
+    let path_buf = check_export_path(&limiter, "export_html", &path, &["html", "htm"])?;
+    run_blocking(move || {
+        let template = export::templates::find(template.as_deref())?;
+        let dark = dark.unwrap_or(false);
+        let options = json!({ "template": template.name, "dark": dark });
+        let provenance = Provenance::new(export::Format::Html, &schedule, options);
+        let content = export::html::render(&schedule, dark, &template, &provenance);
+        save_traced(&path_buf, content.as_bytes(), &provenance)?;
+        Ok(path)
+    })
+    .await
+}
+
+/// Выгрузка расписания в Markdown: таблица на запись истории или на день
+#[tauri::command]
+async fn export_markdown(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    schedule: model::Schedule,
+    grouping: Option<export::markdown::Grouping>,
+) -> Result<String, String> {
+    let path_buf = check_export_path(&limiter, "export_markdown", &path, &["md"])?;
+    run_blocking(move || {
+        let provenance = Provenance::new(export::Format::Markdown, &schedule, json!({}));
+        let content = export::markdown::render(&schedule, grouping.unwrap_or_default());
+        save_traced(&path_buf, content.as_bytes(), &provenance)?;
+        Ok(path)
+    })
+    .await
+}
+
+/// Выгрузка расписания в Word с шапкой организации и строками подписей
+#[tauri::command]
+async fn export_docx(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    schedule: model::Schedule,
+    options: Option<export::docx::DocxOptions>,
+    template: Option<String>,
+) -> Result<String, String> {
+    let path_buf = check_export_path(&limiter, "export_docx", &path, &["docx"])?;
+    run_blocking(move || {
+        let template = export::templates::find(template.as_deref())?;
+        let provenance = Provenance::new(export::Format::Docx, &schedule, json!({ "template": template.name }));
+        let content = export::docx::render(&schedule, &options.unwrap_or_default(), &template)?;
+        save_traced(&path_buf, &content, &provenance)?;
+        Ok(path)
+    })
+    .await
+}
+
+/// Выгрузка сетки расписания в картинку: формат по расширению (.svg, .png, .jpg),
+/// options - масштаб, качество JPEG, палитра PNG и предельный размер файла;
+/// scale, если задан, заменяет масштаб из options
+#[tauri::command]
+async fn export_image(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    schedule: model::Schedule,
+    scale: Option<f32>,
+    options: Option<export::image::ImageOptions>,
+    template: Option<String>,
+) -> Result<String, String> {
+    let path_buf = check_export_path(&limiter, "export_image", &path, &["svg", "png", "jpg", "jpeg"])?;
+    run_blocking(move || {
+        let template = export::templates::find(template.as_deref())?;
+        let mut options = options.unwrap_or_default();
+        if let Some(scale) = scale {
+            options.scale = scale;
+        }
+        let extension = path_buf
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase)
+            .unwrap_or_default();
+        let (format, content) = match extension.as_str() {
+            "svg" => (export::Format::Svg, export::image::render_svg(&schedule, &template).into_bytes()),
+            "png" => (export::Format::Png, export::image::render_png(&schedule, &options, &template)?),
+            _ => (export::Format::Jpeg, export::image::render_jpeg(&schedule, &options, &template)?),
+        };
+        let options = json!({ "template": template.name, "scale": options.scale });
+        let provenance = Provenance::new(format, &schedule, options);
+        save_traced(&path_buf, &content, &provenance)?;
+        Ok(path)
+    })
+    .await
+}
+
+/// Постраничная выгрузка в SVG с разбиением как у PDF (organization и page - как у export_pdf):
+/// страницы записываются рядом с path в файлы «имя-1.svg», «имя-2.svg»... Возвращает их пути
+#[tauri::command]
+async fn export_svg_pages(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    schedule: model::Schedule,
+    organization: Option<String>,
+    page: Option<export::pdf::PageSetup>,
+    template: Option<String>,
+) -> Result<Vec<String>, String> {
+    let path_buf = check_export_path(&limiter, "export_svg_pages", &path, &["svg"])?;
+    run_blocking(move || {
+        let template = export::templates::find(template.as_deref())?;
+        let page = page.unwrap_or_else(|| export::pdf::PageSetup::for_template(&template));
+        let pages = export::image::render_svg_pages(&schedule, organization.as_deref(), &page, &template)?;
+        let stem = path_buf.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        let digits = pages.len().to_string().len();
+        let mut written = Vec::with_capacity(pages.len());
+        for (i, content) in pages.iter().enumerate() {
+            let page_path = path_buf.with_file_name(format!("{}-{:0digits$}.svg", stem, i + 1));
+            paths::check_file_name(&page_path)?;
+            save_export(&page_path, content.as_bytes())?;
+            written.push(page_path.to_string_lossy().into_owned());
+        }
+        Ok(written)
+    })
+    .await
+}
+
+/// Таблички на дверь рабочих мест в PDF: страница на место с сеткой недели по часам.
+/// options - рабочие места, часы и дни, как у room_utilization, и неделя
+#[tauri::command]
+async fn export_door_signs(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    schedule: model::Schedule,
+    options: export::doorsigns::DoorSignOptions,
+    template: Option<String>,
+) -> Result<String, String> {
+    let path_buf = check_export_path(&limiter, "export_door_signs", &path, &["pdf"])?;
+    run_blocking(move || {
+        let template = export::templates::find(template.as_deref())?;
+        let content = export::doorsigns::render(&schedule, &options, &template)?;
+        save_export(&path_buf, &content)?;
+        Ok(path)
+    })
+    .await
+}
+
+/// Расписание рабочих мест по периодам графика звонков в PDF: страница на место,
+/// в ячейке - техкарты, занимающие место в этот период. shift - смена графика
+#[tauri::command]
+async fn export_room_schedules(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    schedule: model::Schedule,
+    options: export::doorsigns::DoorSignOptions,
+    bells: bells::BellSchedule,
+    shift: Option<String>,
+    template: Option<String>,
+) -> Result<String, String> {
+    let path_buf = check_export_path(&limiter, "export_room_schedules", &path, &["pdf"])?;
+    run_blocking(move || {
+        let template = export::templates::find(template.as_deref())?;
+        let shift = shift.unwrap_or_default();
+        let content = export::doorsigns::render_periods(&schedule, &options, &bells, &shift, &template)?;
+        save_export(&path_buf, &content)?;
+        Ok(path)
+    })
+    .await
+}
+
+/// Пакетная выгрузка: файл на каждую запись истории или на каждого исполнителя в папку dir.
+/// Существующие файлы не перезаписываются - новый получает номер «(2)».
+/// Возвращает список записанных файлов; ход по файлам - события «export://progress» с operation_id,
+/// отмена - cancel_operation. Выгрузку с operation_id, прерванную ошибкой или отменой, продолжает
+/// resume_export (записанные файлы остаются), а discard_export удаляет её файлы
+#[tauri::command]
+// Аргументы команды - поля объекта в invoke, структура изменила бы вызов из фронтенда
+#[allow(clippy::too_many_arguments)]
+async fn batch_export(
+    app: tauri::AppHandle,
+    limiter: tauri::State<'_, RateLimiter>,
+    dir: String,
+    schedule: model::Schedule,
+    format: export::Format,
+    split: Option<export::batch::Split>,
+    template: Option<String>,
+    operation_id: Option<String>,
+) -> Result<Vec<String>, String> {
+    limiter.check_rate_limit("batch_export")?;
+    run_blocking(move || {
+        let dir_buf = PathBuf::from(&dir);
+        if !dir_buf.is_dir() {
+            return Err("Папка для выгрузки не найдена".into());
+        }
+        if !paths::is_path_allowed(&dir_buf) {
+            return Err("Сохранение разрешено только в папки: Загрузки, Документы, Рабочий стол или разрешённые вами папки".into());
+        }
+
+        let style = export::templates::find(template.as_deref())?;
+        let mut batch = export::batch::Batch::new(
+            &dir_buf,
+            &schedule,
+            format,
+            split.unwrap_or_default(),
+            template,
+            operation_id.clone(),
+        )?;
+        let mut reporter = progress::Reporter::new(&app, operation_id);
+        batch.run(&style, &save_export, &mut |done, total, current| reporter.report(done, total, current))
+    })
+    .await
+}
+
+/// Продолжает пакетную выгрузку, прерванную ошибкой или отменой: записываются
+/// файлы, которых нет или которые изменились после выгрузки. Возвращает все файлы
+/// выгрузки; ход - события «export://progress» с тем же operation_id
+#[tauri::command]
+async fn resume_export(
+    app: tauri::AppHandle,
+    limiter: tauri::State<'_, RateLimiter>,
+    operation_id: String,
+) -> Result<Vec<String>, String> {
+    limiter.check_rate_limit("resume_export")?;
+    run_blocking(move || {
+        let mut batch = export::batch::Batch::load(&operation_id)?;
+        if !batch.dir().is_dir() {
+            return Err("Папка выгрузки недоступна: подключите диск или сетевую папку".into());
+        }
+        if !paths::is_path_allowed(batch.dir()) {
+            return Err("Сохранение разрешено только в папки: Загрузки, Документы, Рабочий стол или разрешённые вами папки".into());
+        }
+        let template = export::templates::find(batch.template())?;
+        let mut reporter = progress::Reporter::new(&app, Some(operation_id));
+        batch.run(&template, &save_export, &mut |done, total, current| reporter.report(done, total, current))
+    })
+    .await
+}
+
+/// Отказ от продолжения выгрузки: записанные ею файлы удаляются
+#[tauri::command]
+async fn discard_export(limiter: tauri::State<'_, RateLimiter>, operation_id: String) -> Result<(), String> {
+    limiter.check_rate_limit("discard_export")?;
+    run_blocking(move || {
+        export::batch::Batch::load(&operation_id)?.discard();
+        Ok(())
+    })
+    .await
+}
+
+/// Пакетные выгрузки, прерванные ошибкой, отменой или закрытием программы
+#[tauri::command]
+async fn list_unfinished_exports() -> Result<Vec<export::batch::UnfinishedExport>, String> {
+    run_blocking(|| Ok(export::batch::unfinished())).await
+}
+
+/// Личное расписание исполнителя (его операции и простои между ними) в .xlsx или .pdf.
+/// duplex - для двусторонней печати PDF дополняется до чётного числа страниц
+#[tauri::command]
+async fn export_worker_schedule(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    schedule: model::Schedule,
+    worker: String,
+    duplex: Option<bool>,
+    template: Option<String>,
+) -> Result<String, String> {
+    let path_buf = check_export_path(&limiter, "export_worker_schedule", &path, &["xlsx", "pdf"])?;
+    run_blocking(move || {
+        let template = export::templates::find(template.as_deref())?;
+        let personal = export::personal::worker_schedule(&schedule, &worker)?;
+        let is_pdf = path_buf
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"));
+        let format = if is_pdf { export::Format::Pdf } else { export::Format::Xlsx };
+        let duplex = duplex.unwrap_or(false);
+        let options = json!({ "template": template.name, "worker": worker, "duplex": duplex });
+        let provenance = Provenance::new(format, &schedule, options);
+        let content = if is_pdf {
+            let page = export::pdf::PageSetup { duplex, ..export::pdf::PageSetup::for_template(&template) };
+            export::pdf::render(&personal, None, &page, &template, &provenance)?
+        } else {
+            export::xlsx::render(&personal, false, &template, &provenance)?
+        };
+        save_traced(&path_buf, &content, &provenance)?;
+        Ok(path)
+    })
+    .await
+}
+
+/// Личные расписания всех исполнителей одним PDF по алфавиту, каждый с новой страницы.
+/// duplex - для двусторонней печати: каждый исполнитель начинается с лицевой стороны
+/// листа, после нечётного числа страниц вставляется пустая
+#[tauri::command]
+async fn export_all_worker_schedules(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    schedule: model::Schedule,
+    duplex: Option<bool>,
+    template: Option<String>,
+) -> Result<String, String> {
+    let path_buf = check_export_path(&limiter, "export_all_worker_schedules", &path, &["pdf"])?;
+    run_blocking(move || {
+        let template = export::templates::find(template.as_deref())?;
+        let personal = export::personal::all_workers(&schedule)?;
+        let duplex = duplex.unwrap_or(false);
+        let options = json!({ "template": template.name, "duplex": duplex });
+        let provenance = Provenance::new(export::Format::Pdf, &schedule, options);
+        let page = export::pdf::PageSetup { duplex, ..export::pdf::PageSetup::for_template(&template) };
+        let content = export::pdf::render(&personal, None, &page, &template, &provenance)?;
+        save_traced(&path_buf, &content, &provenance)?;
+        Ok(path)
+    })
+    .await
+}
+
+/// Лист замен на день в .xlsx или .pdf
+#[tauri::command]
+async fn export_substitutions(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    schedule: model::Schedule,
+    absent: String,
+    date: String,
+    assignments: Vec<schedule::substitution::Assignment>,
+    template: Option<String>,
+) -> Result<String, String> {
+    let path_buf = check_export_path(&limiter, "export_substitutions", &path, &["xlsx", "pdf"])?;
+    run_blocking(move || {
+        let template = export::templates::find(template.as_deref())?;
+        let sheet = schedule::substitution::sheet(&schedule, &absent, &date, &assignments)?;
+        let is_pdf = path_buf
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"));
+        let format = if is_pdf { export::Format::Pdf } else { export::Format::Xlsx };
+        let provenance = Provenance::new(format, &schedule, json!({ "template": template.name, "absent": absent }));
+        let content = if is_pdf {
+            let page = export::pdf::PageSetup::for_template(&template);
+            export::pdf::render(&sheet, None, &page, &template, &provenance)?
+        } else {
+            export::xlsx::render(&sheet, false, &template, &provenance)?
+        };
+        save_traced(&path_buf, &content, &provenance)?;
+        Ok(path)
+    })
+    .await
+}
+
+/// Отчёт о нагрузке исполнителей в .xlsx
+#[tauri::command]
+async fn export_workload_report(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    schedule: model::Schedule,
+    options: Option<schedule::workload::WorkloadOptions>,
+    template: Option<String>,
+) -> Result<String, String> {
+    let path_buf = check_export_path(&limiter, "export_workload_report", &path, &["xlsx"])?;
+    run_blocking(move || {
+        let template = export::templates::find(template.as_deref())?;
+        let report = schedule::workload::report(&schedule, &options.unwrap_or_default())?;
+        let content = export::report::render(&report.tables(), &template)?;
+        save_export(&path_buf, &content)?;
+        Ok(path)
+    })
+    .await
+}
+
+/// Таблица звонков в .xlsx: лист на каждый вариант дня
+#[tauri::command]
+async fn export_bells(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    bells: bells::BellSchedule,
+    template: Option<String>,
+) -> Result<String, String> {
+    let path_buf = check_export_path(&limiter, "export_bells", &path, &["xlsx"])?;
+    run_blocking(move || {
+        let template = export::templates::find(template.as_deref())?;
+        let content = export::report::render(&bells.tables()?, &template)?;
+        save_export(&path_buf, &content)?;
+        Ok(path)
+    })
+    .await
+}
+
+/// Подключает логотип для шапки выгрузок: PNG или JPEG не больше 2 МБ
+#[tauri::command]
+async fn register_export_logo(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+) -> Result<export::logo::LogoInfo, String> {
+    let path_buf = check_read_path(&limiter, "register_export_logo", &path, &["png", "jpg", "jpeg"])?;
+    run_blocking(move || export::logo::register(read_file(&path_buf)?)).await
+}
+
+/// Отключает логотип выгрузок
+#[tauri::command]
+fn remove_export_logo(limiter: tauri::State<'_, RateLimiter>) -> Result<(), String> {
+    limiter.check_rate_limit("remove_export_logo")?;
+    export::logo::remove()
+}
+
+/// Предпросмотр CSV перед импортом: кодировка, разделитель, заголовки, первые строки
+/// и предлагаемое сопоставление колонок
+#[tauri::command]
+async fn import_csv_preview(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    options: Option<import::csv::CsvImportOptions>,
+) -> Result<import::csv::CsvPreview, String> {
+    let path_buf = check_read_path(&limiter, "import_csv_preview", &path, &["csv", "txt"])?;
+    run_blocking(move || import::csv::preview(&read_file(&path_buf)?, options.unwrap_or_default())).await
+}
+
+/// Импорт CSV: mapping - поле для каждой колонки файла (null - колонка пропускается).
+/// Ход по строкам - события «export://progress» с operation_id, отмена - cancel_operation
+#[tauri::command]
+async fn import_csv(
+    app: tauri::AppHandle,
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    mapping: Vec<Option<import::csv::Field>>,
+    options: Option<import::csv::CsvImportOptions>,
+    operation_id: Option<String>,
+) -> Result<model::Schedule, String> {
+    let path_buf = check_read_path(&limiter, "import_csv", &path, &["csv", "txt"])?;
+    run_blocking(move || {
+        let mut reporter = progress::Reporter::new(&app, operation_id);
+        import::csv::import(
+            &read_file(&path_buf)?,
+            &mapping,
+            options.unwrap_or_default(),
+            &mut |done, total, current| reporter.report(done, total, current),
+        )
+    })
+    .await
+}
+
+/// Импорт внешних событий из календаря (.ics) как занятого времени. worker - исполнитель,
+/// которого касаются события (не указан - все исполнители)
+#[tauri::command]
+async fn import_ics(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+    worker: Option<String>,
+) -> Result<import::ics::IcsImport, String> {
+    let path_buf = check_read_path(&limiter, "import_ics", &path, &["ics"])?;
+    run_blocking(move || {
+        let text = String::from_utf8(read_file(&path_buf)?).map_err(|_| "Ошибка чтения: файл не в кодировке UTF-8".to_string())?;
+        import::ics::parse(&text, worker.as_deref().unwrap_or(""))
+    })
+    .await
+}
+
+/// Шаблоны оформления выгрузок: встроенный и пользовательские
+#[tauri::command]
+fn list_export_templates() -> Vec<export::templates::ExportTemplate> {
+    export::templates::list()
+}
+
+/// Сохраняет шаблон оформления (шаблон с тем же названием заменяется)
+#[tauri::command]
+fn save_export_template(
+    limiter: tauri::State<'_, RateLimiter>,
+    template: export::templates::ExportTemplate,
+) -> Result<(), String> {
+    limiter.check_rate_limit("save_export_template")?;
+    export::templates::save(template)
+}
+
+/// Удаляет пользовательский шаблон оформления
+#[tauri::command]
+fn delete_export_template(limiter: tauri::State<'_, RateLimiter>, name: String) -> Result<(), String> {
+    limiter.check_rate_limit("delete_export_template")?;
+    export::templates::delete(&name)
+}
+
+/// Записывает файл рабочих данных (настройки, автосохранения, кэш) в папку приложения
+#[tauri::command]
+async fn write_app_data(
+    limiter: tauri::State<'_, RateLimiter>,
+    area: appdata::Area,
+    name: String,
+    content: String,
+) -> Result<(), String> {
+    limiter.check_rate_limit("write_app_data")?;
+    run_blocking(move || appdata::write(area, &name, &content)).await
+}
+
+/// Читает файл рабочих данных; null - файла нет
+#[tauri::command]
+async fn read_app_data(
+    limiter: tauri::State<'_, RateLimiter>,
+    area: appdata::Area,
+    name: String,
+) -> Result<Option<String>, String> {
+    limiter.check_rate_limit("read_app_data")?;
+    run_blocking(move || appdata::read(area, &name)).await
+}
+
+/// Файлы рабочих данных области, новые первыми
+#[tauri::command]
+fn list_app_data(area: appdata::Area) -> Vec<files::FileEntry> {
+    appdata::list(area)
+}
+
+/// Удаляет файл рабочих данных
+#[tauri::command]
+fn delete_app_data(limiter: tauri::State<'_, RateLimiter>, area: appdata::Area, name: String) -> Result<(), String> {
+    limiter.check_rate_limit("delete_app_data")?;
+
+    appdata::delete(area, &name)
+}
+
+/// Добавляет файл в список недавних (или поднимает его наверх)
+#[tauri::command]
+fn add_recent(limiter: tauri::State<'_, RateLimiter>, path: String) -> Result<(), String> {
+    let path_buf = check_read_path(&limiter, "add_recent", &path, &files::SCHEDULE_EXTENSIONS)?;
+    recents::add(&path_buf)
+}
+
+/// Недавние файлы: закреплённые первыми, затем по времени открытия. Удалённые файлы
+/// и файлы вне разрешённых папок не возвращаются
+#[tauri::command]
+fn get_recents() -> Vec<recents::RecentFile> {
+    recents::list()
+}
+
+/// Закрепляет файл в списке недавних или снимает закрепление
+#[tauri::command]
+fn pin_recent(path: String, pinned: bool) -> Result<(), String> {
+    recents::pin(Path::new(&path), pinned)
+}
+
+/// Сохраняет расписание в архив SQLite новой ревизией проекта, возвращает её идентификатор
+#[tauri::command]
+async fn save_project_db(
+    limiter: tauri::State<'_, RateLimiter>,
+    project: String,
+    schedule: model::Schedule,
+    label: Option<String>,
+) -> Result<i64, String> {
+    limiter.check_rate_limit("save_project_db")?;
+    run_blocking(move || archive::save(&project, &schedule, label.as_deref().unwrap_or(""))).await
+}
+
+/// Открывает проект из архива: указанную ревизию или последнюю
+#[tauri::command]
+async fn open_project_db(
+    limiter: tauri::State<'_, RateLimiter>,
+    project: String,
+    revision: Option<i64>,
+) -> Result<model::Schedule, String> {
+    limiter.check_rate_limit("open_project_db")?;
+    run_blocking(move || archive::load(&project, revision)).await
+}
+
+/// Проекты архива с числом ревизий, недавно изменённые первыми
+#[tauri::command]
+async fn list_projects_db() -> Result<Vec<archive::ProjectInfo>, String> {
+    run_blocking(archive::projects).await
+}
+
+/// Ревизии проекта в архиве, новые первыми
+#[tauri::command]
+async fn list_project_revisions(project: String) -> Result<Vec<archive::RevisionInfo>, String> {
+    run_blocking(move || archive::revisions(&project)).await
+}
+
+/// Удаляет проект из архива со всеми ревизиями
+#[tauri::command]
+async fn delete_project_db(limiter: tauri::State<'_, RateLimiter>, project: String) -> Result<(), String> {
+    limiter.check_rate_limit("delete_project_db")?;
+    run_blocking(move || archive::delete(&project)).await
+}
+
+/// Полная резервная копия приложения в zip для переноса на другой компьютер: настройки,
+/// шаблоны, автосохранения, снимки и архив расписаний
+#[tauri::command]
+async fn backup_everything(
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+) -> Result<transfer::BackupReport, String> {
+    let path_buf = check_export_path(&limiter, "backup_everything", &path, &["zip"])?;
+    run_blocking(move || {
+        let (bytes, report) = transfer::backup()?;
+        write_file(&path_buf, &bytes)?;
+        Ok(report)
+    })
+    .await
+}
+
+/// Восстанавливает полную резервную копию. Разрешённые папки из копии, которые есть
+/// на этом компьютере, добавляются только после подтверждения в системном диалоге
+#[tauri::command]
+async fn restore_everything(
+    app: tauri::AppHandle,
+    limiter: tauri::State<'_, RateLimiter>,
+    path: String,
+) -> Result<transfer::RestoreReport, String> {
+    let path_buf = check_read_path(&limiter, "restore_everything", &path, &["zip"])?;
+    let restored = run_blocking(move || transfer::restore(&path_buf)).await?;
+
+    let present = restored.present_dirs();
+    let confirmed = !present.is_empty() && {
+        let list: Vec<String> = present.iter().map(|dir| dir.display().to_string()).collect();
+        let mut message = format!(
+            "В копии есть папки, разрешённые на прежнем компьютере:\n{}\n\nРазрешить чтение и запись в них?",
+            list.join("\n")
+        );
+        if !restored.network_dirs.is_empty() {
+            message.push_str("\n\nСреди них есть сетевые: файлы в них могут одновременно открывать несколько человек");
+        }
+        app.dialog()
+            .message(message)
+            .title("Разрешённые папки")
+            .kind(MessageDialogKind::Warning)
+            .buttons(MessageDialogButtons::OkCancel)
+            .blocking_show()
+    };
+    Ok(transfer::grant(restored, confirmed))
+}
+
+/// Обслуживание хранилища: сжимает архив, удаляет данные, на которые ничего не
+/// ссылается, и сообщает, сколько места освобождено
+#[tauri::command]
+async fn run_maintenance(limiter: tauri::State<'_, RateLimiter>) -> Result<maintenance::MaintenanceReport, String> {
+    limiter.check_rate_limit("run_maintenance")?;
+    run_blocking(maintenance::run).await
+}
+
+/// Настройки обслуживания: ежемесячный запуск и время последнего обслуживания
+#[tauri::command]
+fn get_maintenance_settings() -> maintenance::Settings {
+    maintenance::settings()
+}
+
+/// Включает или выключает ежемесячное обслуживание при запуске
+#[tauri::command]
+fn set_monthly_maintenance(limiter: tauri::State<'_, RateLimiter>, enabled: bool) -> Result<(), String> {
+    limiter.check_rate_limit("set_monthly_maintenance")?;
+    maintenance::set_monthly(enabled)
+}
+
+/// Открывает расписание как документ рабочей области: бэкенд выдаёт идентификатор,
+/// по которому документ правится командами edit_*. Уже открытый файл не дублируется
+#[tauri::command]
+fn open_document(
+    limiter: tauri::State<'_, RateLimiter>,
+    workspace: tauri::State<'_, workspace::Workspace>,
+    edits: tauri::State<'_, edits::Edits>,
+    path: Option<String>,
+    schedule: model::Schedule,
+    window: Option<String>,
+) -> Result<workspace::OpenedDocument, String> {
+    limiter.check_rate_limit("open_document")?;
+    let path = path.map(PathBuf::from);
+    workspace.open(&edits, path.as_deref(), schedule, window)
+}
+
+/// Закрывает документ рабочей области; с несохранёнными изменениями - только с force
+#[tauri::command]
+fn close_document(
+    limiter: tauri::State<'_, RateLimiter>,
+    workspace: tauri::State<'_, workspace::Workspace>,
+    edits: tauri::State<'_, edits::Edits>,
+    autosave: tauri::State<'_, autosave::Autosave>,
+    id: String,
+    force: Option<bool>,
+) -> Result<(), String> {
+    limiter.check_rate_limit("close_document")?;
+    workspace.close(&edits, &id, force.unwrap_or(false))?;
+    autosave::discard(&autosave, &id)
+}
+
+/// Открытые документы: путь, название, окно и признак несохранённых изменений
+#[tauri::command]
+fn list_documents(
+    workspace: tauri::State<'_, workspace::Workspace>,
+    edits: tauri::State<'_, edits::Edits>,
+) -> Vec<workspace::DocumentInfo> {
+    workspace.list(&edits)
+}
+
+/// Начинает историю правок документа с его текущим расписанием
+#[tauri::command]
+fn edit_open(
+    limiter: tauri::State<'_, RateLimiter>,
+    edits: tauri::State<'_, edits::Edits>,
+    document: String,
+    schedule: model::Schedule,
+) -> Result<edits::EditState, String> {
+    limiter.check_rate_limit("edit_open")?;
+    edits.open(document, schedule)
+}
+
+/// Закрывает документ, забывает его историю правок и удаляет автосохранение
+#[tauri::command]
+fn edit_close(
+    limiter: tauri::State<'_, RateLimiter>,
+    edits: tauri::State<'_, edits::Edits>,
+    autosave: tauri::State<'_, autosave::Autosave>,
+    document: String,
+) -> Result<(), String> {
+    limiter.check_rate_limit("edit_close")?;
+    edits.close(&document)?;
+    autosave::discard(&autosave, &document)
+}
+
+/// Документ сохранён пользователем: его автосохранение больше не нужно
+#[tauri::command]
+fn edit_saved(
+    limiter: tauri::State<'_, RateLimiter>,
+    edits: tauri::State<'_, edits::Edits>,
+    autosave: tauri::State<'_, autosave::Autosave>,
+    document: String,
+) -> Result<(), String> {
+    limiter.check_rate_limit("edit_saved")?;
+    edits.mark_saved(&document)?;
+    autosave::discard(&autosave, &document)
+}
+
+/// Текущее расписание документа и состояние отмены (после перезагрузки фронтенда)
+#[tauri::command]
+fn edit_state(edits: tauri::State<'_, edits::Edits>, document: String) -> Result<edits::EditState, String> {
+    edits.state(&document)
+}
+
+/// Применяет правку к документу
+#[tauri::command]
+fn edit_apply(
+    limiter: tauri::State<'_, RateLimiter>,
+    edits: tauri::State<'_, edits::Edits>,
+    document: String,
+    edit: edits::Edit,
+) -> Result<edits::EditState, String> {
+    limiter.check_rate_limit("edit_apply")?;
+    edits.apply(&document, edit)
+}
+
+/// Отменяет последнюю правку
+#[tauri::command]
+fn edit_undo(
+    limiter: tauri::State<'_, RateLimiter>,
+    edits: tauri::State<'_, edits::Edits>,
+    document: String,
+) -> Result<edits::EditState, String> {
+    limiter.check_rate_limit("edit_undo")?;
+    edits.undo(&document)
+}
+
+/// Возвращает отменённую правку
+#[tauri::command]
+fn edit_redo(
+    limiter: tauri::State<'_, RateLimiter>,
+    edits: tauri::State<'_, edits::Edits>,
+    document: String,
+) -> Result<edits::EditState, String> {
+    limiter.check_rate_limit("edit_redo")?;
+    edits.redo(&document)
+}
+
+/// Повторяет правки документа на новой версии расписания (например, перечитанной с диска)
+#[tauri::command]
+fn edit_replay(
+    limiter: tauri::State<'_, RateLimiter>,
+    edits: tauri::State<'_, edits::Edits>,
+    document: String,
+    schedule: model::Schedule,
+) -> Result<edits::EditState, String> {
+    limiter.check_rate_limit("edit_replay")?;
+    edits.replay(&document, schedule)
+}
+
+/// Сохраняет снимок расписания с меткой (например, перед публикацией)
+#[tauri::command]
+async fn create_snapshot(
+    limiter: tauri::State<'_, RateLimiter>,
+    label: String,
+    schedule: model::Schedule,
+) -> Result<snapshots::SnapshotInfo, String> {
+    limiter.check_rate_limit("create_snapshot")?;
+    run_blocking(move || snapshots::create(&label, schedule)).await
+}
+
+/// Снимки расписания, новые первыми
+#[tauri::command]
+async fn list_snapshots() -> Result<Vec<snapshots::SnapshotInfo>, String> {
+    run_blocking(|| Ok(snapshots::list())).await
+}
+
+/// Снимок целиком, с расписанием
+#[tauri::command]
+async fn load_snapshot(limiter: tauri::State<'_, RateLimiter>, id: String) -> Result<snapshots::Snapshot, String> {
+    limiter.check_rate_limit("load_snapshot")?;
+    run_blocking(move || snapshots::load(&id)).await
+}
+
+/// Удаляет снимок; отсутствующий снимок - ошибка
+#[tauri::command]
+async fn delete_snapshot(limiter: tauri::State<'_, RateLimiter>, id: String) -> Result<(), String> {
+    limiter.check_rate_limit("delete_snapshot")?;
+    run_blocking(move || snapshots::delete(&id)).await
+}
+
+/// Изменения от снимка a к снимку b: добавленные, снятые, перенесённые и переданные
+/// другому исполнителю операции
+#[tauri::command]
+async fn diff_snapshots(
+    limiter: tauri::State<'_, RateLimiter>,
+    a: String,
+    b: String,
+) -> Result<snapshots::SnapshotDiff, String> {
+    limiter.check_rate_limit("diff_snapshots")?;
+    run_blocking(move || snapshots::diff(&a, &b)).await
+}
+
+/// Задаёт интервал автосохранения в секундах; 0 выключает автосохранение
+#[tauri::command]
+fn set_autosave_interval(
+    limiter: tauri::State<'_, RateLimiter>,
+    autosave: tauri::State<'_, autosave::Autosave>,
+    edits: tauri::State<'_, edits::Edits>,
+    seconds: u64,
+) -> Result<autosave::AutosaveState, String> {
+    limiter.check_rate_limit("set_autosave_interval")?;
+    autosave.set_interval(seconds)?;
+    autosave.state(&edits)
+}
+
+/// Интервал, время последнего автосохранения, последняя ошибка и документы,
+/// изменения которых ещё не записаны
+#[tauri::command]
+fn get_autosave_state(
+    autosave: tauri::State<'_, autosave::Autosave>,
+    edits: tauri::State<'_, edits::Edits>,
+) -> Result<autosave::AutosaveState, String> {
+    autosave.state(&edits)
+}
+
+/// Значение настройки: theme, locale, defaultExportFormat, autosaveIntervalSecs
+/// или allowedDirs (только для чтения)
+#[tauri::command]
+fn get_setting(key: String) -> Result<serde_json::Value, String> {
+    settings::get(&key)
+}
+
+/// Меняет настройку и возвращает все настройки
+#[tauri::command]
+fn set_setting(
+    limiter: tauri::State<'_, RateLimiter>,
+    autosave: tauri::State<'_, autosave::Autosave>,
+    key: String,
+    value: serde_json::Value,
+) -> Result<settings::Settings, String> {
+    limiter.check_rate_limit("set_setting")?;
+    let settings = settings::set(&key, value)?;
+    autosave.apply(&settings);
+    Ok(settings)
+}
+
+/// Возвращает настройки к значениям по умолчанию; добавленные папки остаются
+#[tauri::command]
+fn reset_settings(
+    limiter: tauri::State<'_, RateLimiter>,
+    autosave: tauri::State<'_, autosave::Autosave>,
+) -> Result<settings::Settings, String> {
+    limiter.check_rate_limit("reset_settings")?;
+    let settings = settings::reset()?;
+    autosave.apply(&settings);
+    Ok(settings)
+}
+
+/// Несохранённые изменения прошлых сеансов, оставшиеся после сбоя
+#[tauri::command]
+async fn get_recovery_candidates() -> Result<Vec<recovery::RecoveryCandidate>, String> {
+    run_blocking(|| Ok(recovery::candidates())).await
+}
+
+/// Восстанавливает несохранённые изменения: документ открывается в рабочей области
+/// как изменённый и возвращается фронтенду. Файл восстановления удаляется только
+/// после этого: при ошибке несохранённая работа не теряется
+#[tauri::command]
+async fn recover_session(
+    limiter: tauri::State<'_, RateLimiter>,
+    workspace: tauri::State<'_, workspace::Workspace>,
+    edits: tauri::State<'_, edits::Edits>,
+    id: String,
+) -> Result<workspace::OpenedDocument, String> {
+    limiter.check_rate_limit("recover_session")?;
+    let file = run_blocking({
+        let id = id.clone();
+        move || recovery::load(&id)
+    })
+    .await?;
+    let state = edits.restore(file.document.clone(), file.schedule)?;
+    let document = match workspace.adopt(&edits, &file.document, file.path) {
+        Ok(document) => document,
+        Err(e) => {
+            let _ = edits.close(&file.document);
+            return Err(e);
+        }
+    };
+    // Документ уже открыт; не удалённый файл только предложит восстановление ещё раз
+    let _ = run_blocking(move || recovery::discard(&id)).await;
+    Ok(workspace::OpenedDocument { document, state, already_open: false })
+}
+
+/// Отказ от восстановления: изменения прошлого сеанса удаляются
+#[tauri::command]
+async fn discard_recovery(limiter: tauri::State<'_, RateLimiter>, id: String) -> Result<(), String> {
+    limiter.check_rate_limit("discard_recovery")?;
+    run_blocking(move || recovery::discard(&id)).await
+}
+
+/// Достаёт уцелевшие записи из повреждённого файла восстановления (damaged: true)
+#[tauri::command]
+async fn salvage_recovery(limiter: tauri::State<'_, RateLimiter>, id: String) -> Result<salvage::SalvageReport, String> {
+    limiter.check_rate_limit("salvage_recovery")?;
+    run_blocking(move || recovery::salvage(&id)).await
+}
+
+/// Отменяет выгрузку или импорт, запущенные с этим operation_id. Операция завершается
+/// ошибкой с кодом OPERATION_CANCELLED
+#[tauri::command]
+fn cancel_operation(id: String) -> Result<(), String> {
+    progress::cancel(&id)
+}
+
+/// Фронтенд готов принимать «file-opened»: открывает файлы, с которыми запущено приложение
+#[tauri::command]
+fn open_pending_files(app: tauri::AppHandle) {
+    opening::frontend_ready(&app);
+}
+
+/// Возвращает список подключённых съёмных носителей
+#[tauri::command]
+fn list_removable_drives() -> Vec<drives::RemovableDrive> {
+    drives::removable_drives()
+}
+
+/// Разрешает запись на съёмный носитель до закрытия приложения.
+/// Принимается только корень носителя из list_removable_drives
+#[tauri::command]
+fn grant_removable_drive(limiter: tauri::State<'_, RateLimiter>, path: String) -> Result<String, String> {
+    limiter.check_rate_limit("grant_removable_drive")?;
+    let drive = drives::removable_drives()
+        .into_iter()
+        .find(|d| paths::same_path(Path::new(&d.path), Path::new(&path)))
+        .ok_or("Съёмный носитель не найден. Проверьте, что он подключён")?;
+    paths::grant_session_dir(PathBuf::from(&drive.path));
+    Ok(drive.path)
+}
+
+/// Отзывает разрешение на съёмный носитель (перед извлечением)
+#[tauri::command]
+fn revoke_removable_drive(path: String) {
+    paths::revoke_session_dir(Path::new(&path));
+}
+
+/// Вычисляет SHA-256 хеш исполняемого файла приложения
+#[tauri::command]
+async fn get_exe_hash(limiter: tauri::State<'_, RateLimiter>) -> Result<String, String> {
+    limiter.check_rate_limit("get_exe_hash")?;
+    run_blocking(move || {
+        let exe_path = std::env::current_exe()
+            .map_err(|e| format!("Не удалось получить путь к exe: {}", e))?;
+        let bytes = std::fs::read(&exe_path)
+            .map_err(|e| format!("Ошибка чтения exe: {}", e))?;
+        let hash = Sha256::digest(&bytes);
+        Ok(format!("{:x}", hash))
+    })
+    .await
+}
+
+/// Возвращает список разрешённых директорий (включая разрешённые сетевые папки)
+#[tauri::command]
+fn get_allowed_dirs() -> Vec<String> {
+    paths::allowed_dirs()
+        .into_iter()
+        .chain(allowlist::network_dirs())
+        .chain(allowlist::dirs())
+        .chain(paths::session_dirs())
+        .map(|p| p.to_string_lossy().to_string())
+        .collect()
+}
+
+/// Добавляет папку к разрешённым: пользователь выбирает её в системном диалоге,
+/// путь из фронтенда не принимается. Список хранится подписанным
+#[tauri::command]
+async fn add_allowed_dir(app: tauri::AppHandle) -> Result<Option<String>, String> {
+    let Some(picked) = app
+        .dialog()
+        .file()
+        .set_title("Выберите папку для файлов расписания")
+        .blocking_pick_folder()
+    else {
+        return Ok(None);
+    };
+    let dir = picked
+        .into_path()
+        .map_err(|e| format!("Некорректный путь: {}", e))?;
+
+    if paths::is_network_path(&dir) {
+        let confirmed = app
+            .dialog()
+            .message(format!("{} {}?", NETWORK_DIR_WARNING, dir.display()))
+            .title("Сетевая папка")
+            .kind(MessageDialogKind::Warning)
+            .buttons(MessageDialogButtons::OkCancel)
+            .blocking_show();
+        if !confirmed {
+            return Ok(None);
+        }
+    }
+
+    let granted = allowlist::add(&dir)?;
+    Ok(Some(granted.to_string_lossy().to_string()))
+}
+
+/// Папки, добавленные пользователем к разрешённым
+#[tauri::command]
+fn list_added_dirs() -> Vec<String> {
+    allowlist::dirs()
+        .into_iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect()
+}
+
+/// Убирает добавленную папку из разрешённых
+#[tauri::command]
+fn remove_allowed_dir(limiter: tauri::State<'_, RateLimiter>, path: String) -> Result<(), String> {
+    limiter.check_rate_limit("remove_allowed_dir")?;
+    allowlist::remove(&PathBuf::from(path))
+}
+
+/// Разрешает сетевую папку: пользователь сам выбирает её в системном диалоге
+/// и подтверждает предупреждение, путь из фронтенда не принимается. Список хранится подписанным
+#[tauri::command]
+async fn grant_network_dir(app: tauri::AppHandle) -> Result<Option<String>, String> {
+    let Some(picked) = app
+        .dialog()
+        .file()
+        .set_title("Выберите сетевую папку")
+        .blocking_pick_folder()
+    else {
+        return Ok(None);
+    };
+    let dir = picked
+        .into_path()
+        .map_err(|e| format!("Некорректный путь: {}", e))?;
+
+    if !paths::is_network_path(&dir) {
+        return Err("Выбранная папка не является сетевой. Укажите путь вида \\\\сервер\\папка или сетевой диск".into());
+    }
+
+    let confirmed = app
+        .dialog()
+        .message(format!("{} {}?", NETWORK_DIR_WARNING, dir.display()))
+        .title("Сетевая папка")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancel)
+        .blocking_show();
+    if !confirmed {
+        return Ok(None);
+    }
+
+    let granted = allowlist::add_network(&dir)?;
+    Ok(Some(granted.to_string_lossy().to_string()))
+}
+
+/// Возвращает список разрешённых сетевых папок
+#[tauri::command]
+fn list_network_dirs() -> Vec<String> {
+    allowlist::network_dirs()
+        .into_iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect()
+}
+
+/// Отзывает разрешение на сетевую папку
+#[tauri::command]
+fn revoke_network_dir(limiter: tauri::State<'_, RateLimiter>, path: String) -> Result<(), String> {
+    limiter.check_rate_limit("revoke_network_dir")?;
+    allowlist::remove_network(&PathBuf::from(path))
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    let builder = tauri::Builder::default();
+    // Второй запуск (двойной щелчок по файлу .ttable) не открывает новое окно:
+    // аргументы передаются уже запущенному экземпляру. Плагин подключается первым
+    #[cfg(not(mobile))]
+    let builder = builder.plugin(tauri_plugin_single_instance::init(|app, args, cwd| {
+        if let Some(window) = app.get_webview_window("main") {
+            let _ = window.unminimize();
+            let _ = window.show();
+            let _ = window.set_focus();
+        }
+        opening::request(app, opening::launch_paths(args, Path::new(&cwd)));
+    }));
+    builder
+        .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_fs::init())
+        .manage(RateLimiter::new())
+        .manage(edits::Edits::default())
+        .manage(autosave::Autosave::default())
+        .manage(workspace::Workspace::default())
+        .on_window_event(|window, event| {
+            // Перетаскивание файлов в окно: проверка и чтение в бэкенде, не в цикле событий
+            if let tauri::WindowEvent::DragDrop(tauri::DragDropEvent::Drop { paths, .. }) = event {
+                opening::request(window.app_handle(), paths.clone());
+            }
+        })
+        .invoke_handler(tauri::generate_handler![
+            save_file_secure,
+            save_file_binary,
+            read_file_binary,
+            save_file_encrypted,
+            unlock_with_recovery_key,
+            read_file_encrypted,
+            open_write_session,
+            write_chunk,
+            finish_write,
+            abort_write,
+            open_read_session,
+            read_chunk,
+            close_read_session,
+            list_files_secure,
+            delete_file_secure,
+            move_file_secure,
+            get_file_info,
+            watch_file,
+            unwatch_file,
+            acquire_lock,
+            release_lock,
+            get_lock_owner,
+            list_backups,
+            restore_backup,
+            verify_backup,
+            get_backup_limit,
+            set_backup_limit,
+            get_backup_verification,
+            set_backup_verification,
+            write_app_data,
+            read_app_data,
+            list_app_data,
+            delete_app_data,
+            add_recent,
+            get_recents,
+            pin_recent,
+            open_pending_files,
+            cancel_operation,
+            edit_open,
+            edit_close,
+            edit_state,
+            edit_apply,
+            edit_undo,
+            edit_redo,
+            edit_replay,
+            create_snapshot,
+            list_snapshots,
+            load_snapshot,
+            delete_snapshot,
+            diff_snapshots,
+            set_autosave_interval,
+            get_autosave_state,
+            edit_saved,
+            get_recovery_candidates,
+            recover_session,
+            discard_recovery,
+            salvage_recovery,
+            get_setting,
+            set_setting,
+            reset_settings,
+            open_document,
+            close_document,
+            list_documents,
+            save_project_db,
+            open_project_db,
+            list_projects_db,
+            list_project_revisions,
+            delete_project_db,
+            backup_everything,
+            restore_everything,
+            run_maintenance,
+            get_maintenance_settings,
+            set_monthly_maintenance,
+            read_file_secure,
+            salvage_file_secure,
+            validate_schedule_file,
+            validate_xml,
+            validate_schedule,
+            check_conflicts,
+            get_constraints,
+            set_constraints,
+            mark_absence,
+            suggest_substitutions,
+            analyze_gaps,
+            workload_report,
+            room_utilization,
+            bell_times,
+            bell_days,
+            preview_change,
+            suggest_swaps,
+            solve_schedule,
+            export_xlsx,
+            export_pdf,
+            export_csv,
+            export_ods,
+            export_ics,
+            export_html,
+            export_markdown,
+            export_docx,
+            export_image,
+            export_svg_pages,
+            export_door_signs,
+            export_room_schedules,
+            export_worker_schedule,
+            export_all_worker_schedules,
+            export_substitutions,
+            export_workload_report,
+            export_bells,
+            batch_export,
+            resume_export,
+            discard_export,
+            list_unfinished_exports,
+            list_export_templates,
+            save_export_template,
+            delete_export_template,
+            register_export_logo,
+            remove_export_logo,
+            import_csv_preview,
+            import_csv,
+            import_ics,
+            get_allowed_dirs,
+            grant_network_dir,
+            list_network_dirs,
+            revoke_network_dir,
+            add_allowed_dir,
+            list_added_dirs,
+            remove_allowed_dir,
+            list_removable_drives,
+            grant_removable_drive,
+            revoke_removable_drive,
+            get_exe_hash
+        ])
+        .setup(|app| {
+            // DevTools только в debug режиме
+            #[cfg(debug_assertions)]
+            {
+                
+            }
+            // «Открыть с помощью» в Windows и Linux: файлы приходят аргументами запуска
+            let cwd = std::env::current_dir().unwrap_or_default();
+            opening::request(app.handle(), opening::launch_paths(std::env::args(), &cwd));
+            // Файлы автосохранения прошлого сеанса откладываются до того, как
+            // автосохранение нового начнёт писать
+            recovery::collect();
+            autosave::start(app.handle().clone());
+            maintenance::start();
+            Ok(())
+        })
+        .build(tauri::generate_context!())
+        .expect("ошибка при запуске приложения Tauri")
+        .run(|_app, _event| {
+            // В macOS файлы приходят событием, в том числе при запуске приложения
+            #[cfg(any(target_os = "macos", target_os = "ios"))]
+            if let tauri::RunEvent::Opened { urls } = _event {
+                let files = urls.iter().filter_map(|url| url.to_file_path().ok()).collect();
+                opening::request(_app, files);
+            }
+        });
 }
@@ -0,0 +1,235 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Рекомендательные блокировки файлов в общих папках. При открытии рядом с файлом
+// создаётся «.имя.lock» с пользователем, компьютером и временем; второй экземпляр
+// приложения (у другого секретаря) видит блокировку и не перезаписывает чужие
+// правки молча. Системные блокировки не используются: на сетевых дисках они
+// работают ненадёжно и не дают понятного «кем занят».
+//
+// Блокировка того же пользователя на том же компьютере перехватывается без
+// вопросов - это остаток после аварийного закрытия. Пока файл открыт, экземпляр
+// раз в REFRESH_EVERY обновляет время в своих блокировках (refresh вызывается из
+// потока автосохранения); блокировка, не обновлявшаяся дольше STALE_AFTER, брошена.
+//
+// Время пишется в UTC (RFC 3339) и сравнивается в UTC: у компьютеров в общей папке
+// могут быть разные часовые пояса, а местное время без пояса при переходе на летнее
+// время и обратно сдвигается на час.
+
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::paths;
+
+// Блокировка без обновления дольше этого срока считается брошенной
+const STALE_AFTER_MINUTES: i64 = 10;
+
+// Как часто обновляются свои блокировки; с запасом меньше STALE_AFTER_MINUTES
+const REFRESH_EVERY: Duration = Duration::from_secs(60);
+
+// Местное время без пояса в блокировках прежних версий
+const LEGACY_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// Кем занят файл
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockOwner {
+    pub user: String,
+    pub host: String,
+    /// Время блокировки в UTC, RFC 3339
+    pub since: String,
+    /// Последнее обновление блокировки в UTC, RFC 3339; пусто - не обновлялась
+    #[serde(default)]
+    pub refreshed: String,
+    // Экземпляр приложения, взявший блокировку
+    #[serde(default)]
+    instance: String,
+}
+
+// Файлы, которые занял этот экземпляр, и время последнего обновления блокировок
+struct Held {
+    paths: HashSet<PathBuf>,
+    refreshed_at: Option<Instant>,
+}
+
+static HELD: LazyLock<Mutex<Held>> = LazyLock::new(|| Mutex::new(Held { paths: HashSet::new(), refreshed_at: None }));
+
+// Идентификатор этого экземпляра приложения
+static INSTANCE: LazyLock<String> = LazyLock::new(|| {
+    let mut bytes = [0u8; 8];
+    let _ = getrandom::getrandom(&mut bytes);
+    format!("{}-{}", std::process::id(), bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>())
+});
+
+fn user_name() -> String {
+    std::env::var("USERNAME")
+        .or_else(|_| std::env::var("USER"))
+        .unwrap_or_else(|_| "неизвестный пользователь".into())
+}
+
+fn host_name() -> String {
+    if let Ok(host) = std::env::var("COMPUTERNAME").or_else(|_| std::env::var("HOSTNAME")) {
+        return host;
+    }
+    std::fs::read_to_string("/etc/hostname")
+        .ok()
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "неизвестный компьютер".into())
+}
+
+fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+// Время из блокировки; блокировки прежних версий - в местном времени без пояса
+fn parse_time(raw: &str) -> Option<DateTime<Utc>> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(raw) {
+        return Some(at.with_timezone(&Utc));
+    }
+    let naive = NaiveDateTime::parse_from_str(raw, LEGACY_TIME_FORMAT).ok()?;
+    Local.from_local_datetime(&naive).earliest().map(|at| at.with_timezone(&Utc))
+}
+
+fn lock_path(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?.to_string_lossy().to_string();
+    Some(path.with_file_name(format!(".{}.lock", name)))
+}
+
+fn read_owner(lock: &Path) -> Option<LockOwner> {
+    let raw = std::fs::read_to_string(paths::to_fs_path(lock)).ok()?;
+    serde_json::from_str(&raw).ok()
+}
+
+impl LockOwner {
+    fn current() -> Self {
+        LockOwner {
+            user: user_name(),
+            host: host_name(),
+            since: now(),
+            refreshed: String::new(),
+            instance: INSTANCE.clone(),
+        }
+    }
+
+    fn is_ours(&self) -> bool {
+        self.instance == *INSTANCE
+    }
+
+    /// Блокировку можно перехватить: наш пользователь на этом компьютере или брошенная
+    fn can_take_over(&self) -> bool {
+        let same_person = self.user.eq_ignore_ascii_case(&user_name()) && self.host.eq_ignore_ascii_case(&host_name());
+        let alive = if self.refreshed.is_empty() { &self.since } else { &self.refreshed };
+        let stale = parse_time(alive).is_none_or(|at| Utc::now() - at > chrono::Duration::minutes(STALE_AFTER_MINUTES));
+        same_person || stale
+    }
+
+    fn message(&self) -> String {
+        // В сообщении - местное время этого компьютера
+        let since = parse_time(&self.since)
+            .map(|t| t.with_timezone(&Local).format("%d.%m.%Y %H:%M").to_string())
+            .unwrap_or_else(|| self.since.clone());
+        format!(
+            "Файл открыт для изменения пользователем {} на компьютере {} с {}. \
+             Дождитесь, пока он закроет файл, или откройте копию",
+            self.user, self.host, since
+        )
+    }
+}
+
+/// Занимает файл. force - перехватить чужую блокировку (пользователь подтвердил)
+pub fn acquire(path: &Path, force: bool) -> Result<LockOwner, String> {
+    let lock = lock_path(path).ok_or("Не указано имя файла")?;
+    if let Some(owner) = read_owner(&lock) {
+        if !owner.is_ours() && !owner.can_take_over() && !force {
+            return Err(owner.message());
+        }
+        let _ = std::fs::remove_file(paths::to_fs_path(&lock));
+    }
+
+    let owner = LockOwner::current();
+    let content = serde_json::to_string_pretty(&owner).map_err(|e| format!("Ошибка блокировки файла: {}", e))?;
+    // create_new: если другой экземпляр успел создать блокировку между проверкой и записью, выигрывает он
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(paths::to_fs_path(&lock))
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .map_err(|e| match read_owner(&lock) {
+            Some(other) if e.kind() == std::io::ErrorKind::AlreadyExists => other.message(),
+            _ => paths::io_error_message("Не удалось заблокировать файл", &e),
+        })?;
+    if let Ok(mut held) = HELD.lock() {
+        held.paths.insert(path.to_path_buf());
+    }
+    Ok(owner)
+}
+
+/// Снимает свою блокировку; чужую не трогает
+pub fn release(path: &Path) -> Result<(), String> {
+    let lock = lock_path(path).ok_or("Не указано имя файла")?;
+    if let Ok(mut held) = HELD.lock() {
+        held.paths.remove(path);
+    }
+    match read_owner(&lock) {
+        Some(owner) if owner.is_ours() => std::fs::remove_file(paths::to_fs_path(&lock))
+            .map_err(|e| paths::io_error_message("Не удалось снять блокировку", &e)),
+        Some(_) => Err("Файл заблокирован другим экземпляром приложения".into()),
+        None => Ok(()),
+    }
+}
+
+/// Проверка перед записью: файл не занят другим пользователем
+pub fn check_write(path: &Path) -> Result<(), String> {
+    let Some(owner) = lock_path(path).and_then(|lock| read_owner(&lock)) else {
+        return Ok(());
+    };
+    if owner.is_ours() || owner.can_take_over() {
+        Ok(())
+    } else {
+        Err(owner.message())
+    }
+}
+
+/// Кем занят файл; None - свободен
+pub fn owner(path: &Path) -> Option<LockOwner> {
+    lock_path(path).and_then(|lock| read_owner(&lock))
+}
+
+/// Обновляет время в своих блокировках, если с прошлого обновления прошло REFRESH_EVERY.
+/// Перехваченные другими блокировки забываются
+pub fn refresh() {
+    let Ok(mut held) = HELD.lock() else {
+        return;
+    };
+    if held.paths.is_empty() || held.refreshed_at.is_some_and(|at| at.elapsed() < REFRESH_EVERY) {
+        return;
+    }
+    held.refreshed_at = Some(Instant::now());
+    held.paths.retain(|path| {
+        let Some(lock) = lock_path(path) else {
+            return false;
+        };
+        let Some(mut owner) = read_owner(&lock).filter(LockOwner::is_ours) else {
+            return false;
+        };
+        owner.refreshed = now();
+        // Через временный файл: читающий не увидит блокировку записанной наполовину.
+        // Если запись не удалась (например, сеть недоступна), попробуем в следующий раз
+        if let Ok(content) = serde_json::to_string_pretty(&owner) {
+            let tmp = lock.with_extension("lock.tmp");
+            let written = std::fs::write(paths::to_fs_path(&tmp), content)
+                .and_then(|_| std::fs::rename(paths::to_fs_path(&tmp), paths::to_fs_path(&lock)));
+            if written.is_err() {
+                let _ = std::fs::remove_file(paths::to_fs_path(&tmp));
+            }
+        }
+        true
+    });
+}
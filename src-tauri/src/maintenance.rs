@@ -0,0 +1,140 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Обслуживание хранилища: сжатие архива расписаний (VACUUM), удаление данных, на которые
+// больше ничего не ссылается, и отчёт об освобождённом месте. Удаляются:
+// - исполнители архива, оставшиеся от удалённых проектов;
+// - логотип выгрузок, который не размещает ни один шаблон;
+// - остатки оборванной записи снимков;
+// - резервные копии в .backups, исходного файла которых больше нет. Проверяются папки
+//   недавних файлов и разрешённые папки (без вложенных): обход всего диска занял бы минуты.
+//
+// Запускается командой вручную или раз в месяц при старте программы, если это включено
+// в настройках (maintenance.json в папке настроек).
+
+use std::path::PathBuf;
+
+use chrono::{Duration, Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::export::logo;
+use crate::{allowlist, archive, backups, paths, recents, snapshots};
+
+const SETTINGS_FILE: &str = "maintenance.json";
+
+const TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+// Период автоматического обслуживания
+const MONTHLY: Duration = Duration::days(30);
+
+/// Настройки обслуживания
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Settings {
+    /// Обслуживать раз в месяц при запуске
+    #[serde(default)]
+    pub monthly: bool,
+    /// Время последнего обслуживания: ГГГГ-ММ-ДДTЧЧ:ММ:СС
+    #[serde(default)]
+    pub last_run: Option<String>,
+}
+
+/// Итог обслуживания
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceReport {
+    /// Освобождено сжатием архива, байт
+    pub archive_bytes: u64,
+    pub removed_workers: usize,
+    pub logo_bytes: u64,
+    pub snapshot_files: usize,
+    pub snapshot_bytes: u64,
+    pub backup_files: usize,
+    pub backup_bytes: u64,
+    /// Освобождено всего, байт
+    pub reclaimed_bytes: u64,
+}
+
+/// Текущие настройки
+pub fn settings() -> Settings {
+    paths::app_config_dir()
+        .and_then(|dir| std::fs::read_to_string(dir.join(SETTINGS_FILE)).ok())
+        .and_then(|raw| serde_json::from_str::<Settings>(&raw).ok())
+        .unwrap_or_default()
+}
+
+fn save_settings(settings: &Settings) -> Result<(), String> {
+    let dir = paths::app_config_dir().ok_or("Не удалось определить папку настроек")?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| paths::io_error_message("Ошибка создания папки настроек", &e))?;
+    let content = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Ошибка сохранения настроек: {}", e))?;
+    std::fs::write(dir.join(SETTINGS_FILE), content)
+        .map_err(|e| paths::io_error_message("Ошибка сохранения настроек", &e))
+}
+
+/// Включает или выключает ежемесячное обслуживание
+pub fn set_monthly(monthly: bool) -> Result<(), String> {
+    save_settings(&Settings { monthly, ..settings() })
+}
+
+// Папки, в которых ищутся осиротевшие резервные копии
+fn backup_folders() -> Vec<PathBuf> {
+    let mut folders: Vec<PathBuf> = recents::list()
+        .into_iter()
+        .filter_map(|recent| recent.path.parent().map(|dir| dir.to_path_buf()))
+        .chain(paths::allowed_dirs())
+        .chain(allowlist::dirs())
+        .chain(allowlist::network_dirs())
+        .collect();
+    folders.sort_by_key(|dir| paths::nfc(dir));
+    folders.dedup_by(|a, b| paths::same_path(a, b));
+    folders
+}
+
+/// Обслуживает хранилище и запоминает время обслуживания
+pub fn run() -> Result<MaintenanceReport, String> {
+    let compaction = archive::compact()?;
+    let (snapshot_files, snapshot_bytes) = snapshots::prune_leftovers();
+    let (backup_files, backup_bytes) = backup_folders()
+        .iter()
+        .map(|folder| backups::prune_orphans(folder))
+        .fold((0, 0), |(files, bytes), (f, b)| (files + f, bytes + b));
+
+    let mut report = MaintenanceReport {
+        archive_bytes: compaction.reclaimed,
+        removed_workers: compaction.workers,
+        logo_bytes: logo::prune_unused(),
+        snapshot_files,
+        snapshot_bytes,
+        backup_files,
+        backup_bytes,
+        reclaimed_bytes: 0,
+    };
+    report.reclaimed_bytes = report.archive_bytes + report.logo_bytes + report.snapshot_bytes + report.backup_bytes;
+
+    let last_run = Some(Local::now().format(TIME_FORMAT).to_string());
+    save_settings(&Settings { last_run, ..settings() })?;
+    Ok(report)
+}
+
+// Пора ли ежемесячное обслуживание
+fn due(settings: &Settings) -> bool {
+    if !settings.monthly {
+        return false;
+    }
+    settings
+        .last_run
+        .as_deref()
+        .and_then(|raw| NaiveDateTime::parse_from_str(raw, TIME_FORMAT).ok())
+        .is_none_or(|last| Local::now().naive_local() - last >= MONTHLY)
+}
+
+/// При запуске: если включено и прошёл месяц, обслуживает хранилище в фоне
+pub fn start() {
+    if due(&settings()) {
+        std::thread::spawn(|| {
+            let _ = run();
+        });
+    }
+}
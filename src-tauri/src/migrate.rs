@@ -0,0 +1,127 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Версии формата резервной копии техкарт и переход между ними. Поле schemaVersion
+// пишется при сохранении; файл без него - формат 1. При чтении старые файлы по
+// цепочке миграций (1 -> 2 -> …) приводятся к текущему формату, поэтому обновление
+// приложения не ломает копии, уже лежащие в Документах у пользователей.
+//
+// Формат 1: техкарта хранится строкой JSON или (в ранних версиях) массивом,
+// у операций может не быть полей перерыва.
+// Формат 2: поле schemaVersion, техкарта - строка JSON, у каждой операции есть
+// hasBreak, breakVal и breakUnit.
+
+use serde_json::{Map, Value};
+
+/// Текущая версия формата
+pub const CURRENT_VERSION: u32 = 2;
+
+const VERSION_FIELD: &str = "schemaVersion";
+const CARD_PREFIX: &str = "z7_card_";
+
+type Migration = fn(&mut Map<String, Value>) -> Result<(), String>;
+
+// Миграция из версии N в N + 1, по порядку
+const MIGRATIONS: &[(u32, Migration)] = &[(1, v1_to_v2)];
+
+/// Результат чтения: содержимое в текущем формате и исходная версия файла
+pub struct Upgraded {
+    pub text: String,
+    pub from_version: u32,
+}
+
+/// Файл похож на резервную копию: объект, все ключи которого - техкарты или версия
+fn backup_map(value: &mut Value) -> Option<&mut Map<String, Value>> {
+    let map = value.as_object_mut()?;
+    map.keys()
+        .all(|k| k == VERSION_FIELD || k.starts_with(CARD_PREFIX))
+        .then_some(map)
+}
+
+fn version(map: &Map<String, Value>) -> Result<u32, String> {
+    match map.get(VERSION_FIELD) {
+        None => Ok(1),
+        Some(v) => v
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .filter(|v| *v >= 1)
+            .ok_or_else(|| "Поле schemaVersion должно быть целым числом от 1".to_string()),
+    }
+}
+
+/// Приводит резервную копию к текущему формату. Текст, который не разбирается
+/// или не похож на резервную копию, возвращается как есть - его оценит проверка по схеме
+pub fn upgrade(text: &str) -> Result<Upgraded, String> {
+    let unchanged = |from_version| Upgraded { text: text.to_string(), from_version };
+    let Ok(mut value) = serde_json::from_str::<Value>(text.strip_prefix('\u{feff}').unwrap_or(text)) else {
+        return Ok(unchanged(CURRENT_VERSION));
+    };
+    let Some(map) = backup_map(&mut value) else {
+        return Ok(unchanged(CURRENT_VERSION));
+    };
+
+    let from_version = version(map)?;
+    if from_version > CURRENT_VERSION {
+        return Err(format!(
+            "Файл сохранён более новой версией приложения (формат {}), обновите программу",
+            from_version
+        ));
+    }
+    if from_version == CURRENT_VERSION {
+        return Ok(unchanged(from_version));
+    }
+
+    for (from, migration) in MIGRATIONS {
+        if *from >= from_version {
+            migration(map).map_err(|e| format!("Не удалось обновить файл формата {}: {}", from, e))?;
+            map.insert(VERSION_FIELD.into(), Value::from(from + 1));
+        }
+    }
+
+    let text = serde_json::to_string_pretty(&value).map_err(|e| format!("Ошибка обновления файла: {}", e))?;
+    Ok(Upgraded { text, from_version })
+}
+
+/// Проставляет текущую версию в сохраняемую резервную копию
+pub fn stamp(text: &str) -> String {
+    let Ok(mut value) = serde_json::from_str::<Value>(text) else {
+        return text.to_string();
+    };
+    match backup_map(&mut value) {
+        Some(map) if !map.contains_key(VERSION_FIELD) => {
+            map.insert(VERSION_FIELD.into(), Value::from(CURRENT_VERSION));
+            serde_json::to_string_pretty(&value).unwrap_or_else(|_| text.to_string())
+        }
+        _ => text.to_string(),
+    }
+}
+
+/// 1 -> 2: техкарты-массивы сохраняются строкой, операциям добавляются поля перерыва
+fn v1_to_v2(map: &mut Map<String, Value>) -> Result<(), String> {
+    for (key, card) in map.iter_mut().filter(|(k, _)| k.starts_with(CARD_PREFIX)) {
+        let mut steps = match card.take() {
+            Value::String(raw) => serde_json::from_str::<Value>(&raw)
+                .map_err(|_| format!("техкарта «{}» повреждена", &key[CARD_PREFIX.len()..]))?,
+            other => other,
+        };
+        if let Some(list) = steps.as_array_mut() {
+            for step in list.iter_mut().filter_map(Value::as_object_mut) {
+                let break_val = step.get("breakVal").and_then(number).unwrap_or(0.0);
+                step.entry("breakVal").or_insert(Value::from(break_val));
+                step.entry("breakUnit").or_insert(Value::from("min"));
+                step.entry("hasBreak").or_insert(Value::from(break_val > 0.0));
+            }
+        }
+        *card = Value::String(steps.to_string());
+    }
+    Ok(())
+}
+
+// Числа в старых файлах встречаются и строкой
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().replace(',', ".").parse().ok(),
+        _ => None,
+    }
+}
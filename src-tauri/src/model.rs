@@ -0,0 +1,254 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Модель расписания, которую фронтенд передаёт в команды экспорта:
+// записи истории расчётов в том же виде, в каком они хранятся в z7_history_session.
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+/// Расписание: записи истории в порядке отображения
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Schedule {
+    pub entries: Vec<ScheduleEntry>,
+    /// Занятое внешними событиями время, в которое операции не назначаются
+    #[serde(default)]
+    pub blocked: Vec<BlockedSlot>,
+}
+
+/// Одна запись истории (один расчёт техкарты)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ScheduleEntry {
+    /// Заголовок: «техкарта | Сформировано: дата; время»
+    pub title: String,
+    pub rows: Vec<OperationRow>,
+    /// Строки блока Z7
+    pub z7: Vec<String>,
+    /// Режим цепочки: запись продолжает предыдущую
+    pub chain: bool,
+    /// Режим времени: total, per_worker или individual
+    pub time_mode: String,
+}
+
+/// Строка расчёта: операция, выполняемая одним исполнителем
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct OperationRow {
+    /// Номер операции в техкарте
+    #[serde(deserialize_with = "text")]
+    pub original_op_index: String,
+    /// ПДТВ
+    #[serde(deserialize_with = "text")]
+    pub op_idx: String,
+    /// Порядковый номер операции для сортировки
+    pub op_numeric: Option<f64>,
+    pub name: String,
+    #[serde(deserialize_with = "text")]
+    pub worker: String,
+    /// Номер исполнителя внутри операции (с 1)
+    pub worker_index: Option<u32>,
+    /// Длительность в единицах unit
+    pub dur_val: f64,
+    pub dur_text: String,
+    /// min или hour
+    pub unit: String,
+    /// Дата и время в формате дд.мм.гггг и ЧЧ:ММ:СС
+    pub start_date: String,
+    pub start_time: String,
+    pub end_date: String,
+    pub end_time: String,
+    /// Операция захватывает обед
+    pub crossed_lunch: bool,
+    pub pause_text: String,
+    pub posting_date: String,
+    pub pdtv_auto_mode: bool,
+}
+
+/// Интервал, занятый внешним событием (совещание, обучение, выезд)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BlockedSlot {
+    pub title: String,
+    /// Исполнитель, которого касается событие; пустая строка - все исполнители
+    pub worker: String,
+    /// Дата и время в том же формате, что и у строк расчёта
+    pub start_date: String,
+    pub start_time: String,
+    pub end_date: String,
+    pub end_time: String,
+}
+
+/// Суммарная загрузка исполнителя
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkerLoad {
+    pub worker: String,
+    /// Количество операций
+    pub operations: usize,
+    /// Суммарная работа в минутах
+    pub minutes: f64,
+}
+
+impl Schedule {
+    /// Исполнители в порядке первого появления
+    pub fn workers(&self) -> Vec<String> {
+        let mut workers: Vec<String> = Vec::new();
+        for row in self.entries.iter().flat_map(|e| &e.rows) {
+            let worker = row.worker.trim();
+            if !worker.is_empty() && !workers.iter().any(|w| w == worker) {
+                workers.push(worker.to_string());
+            }
+        }
+        workers
+    }
+
+    /// Загрузка исполнителей в порядке первого появления
+    pub fn worker_loads(&self) -> Vec<WorkerLoad> {
+        let mut loads: Vec<WorkerLoad> = self
+            .workers()
+            .into_iter()
+            .map(|worker| WorkerLoad { worker, operations: 0, minutes: 0.0 })
+            .collect();
+        for row in self.entries.iter().flat_map(|e| &e.rows) {
+            if let Some(load) = loads.iter_mut().find(|l| l.worker == row.worker.trim()) {
+                load.operations += 1;
+                load.minutes += row.duration_minutes();
+            }
+        }
+        loads
+    }
+
+    /// Расписание только с операциями исполнителя; записи без его операций пропускаются
+    pub fn for_worker(&self, worker: &str) -> Schedule {
+        let entries = self
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                let rows: Vec<OperationRow> = entry
+                    .rows
+                    .iter()
+                    .filter(|r| r.worker.trim() == worker.trim())
+                    .cloned()
+                    .collect();
+                (!rows.is_empty()).then(|| ScheduleEntry { rows, ..entry.clone() })
+            })
+            .collect();
+        let blocked = self
+            .blocked
+            .iter()
+            .filter(|slot| slot.applies_to(worker))
+            .cloned()
+            .collect();
+        Schedule { entries, blocked }
+    }
+}
+
+impl BlockedSlot {
+    pub fn start(&self) -> Option<NaiveDateTime> {
+        parse_date_time(&self.start_date, &self.start_time)
+    }
+
+    pub fn end(&self) -> Option<NaiveDateTime> {
+        parse_date_time(&self.end_date, &self.end_time)
+    }
+
+    /// Событие касается исполнителя
+    pub fn applies_to(&self, worker: &str) -> bool {
+        let own = self.worker.trim();
+        own.is_empty() || own == worker.trim()
+    }
+}
+
+impl ScheduleEntry {
+    /// Название техкарты: заголовок до « | Сформировано»
+    pub fn card_name(&self) -> &str {
+        self.title.split(" | ").next().unwrap_or(&self.title).trim()
+    }
+
+    /// Суффикс единицы измерения для заголовка «Работа», как getHeaderUnitSuffix во фронтенде
+    pub fn unit_suffix(&self) -> &'static str {
+        let Some(first) = self.rows.first().map(OperationRow::unit) else {
+            return " (мин)";
+        };
+        if self.rows.iter().any(|r| r.unit() != first) {
+            return "";
+        }
+        match first {
+            "min" => " (мин)",
+            "hour" => " (час)",
+            _ => "",
+        }
+    }
+
+    /// Название режима времени для заголовка записи
+    pub fn time_mode_label(&self) -> &'static str {
+        match self.time_mode.as_str() {
+            "per_worker" => "На Каждого",
+            "individual" => "Индивидуальный",
+            _ => "Общий",
+        }
+    }
+
+    /// Строки в порядке операций, внутри операции - по номеру исполнителя
+    pub fn sorted_rows(&self) -> Vec<&OperationRow> {
+        let mut rows: Vec<&OperationRow> = self.rows.iter().collect();
+        rows.sort_by(|a, b| {
+            a.sort_key()
+                .total_cmp(&b.sort_key())
+                .then(a.worker_index.unwrap_or(1).cmp(&b.worker_index.unwrap_or(1)))
+        });
+        rows
+    }
+}
+
+impl OperationRow {
+    /// Единица длительности (по умолчанию минуты)
+    pub fn unit(&self) -> &str {
+        if self.unit.is_empty() { "min" } else { &self.unit }
+    }
+
+    /// Начало операции
+    pub fn start(&self) -> Option<NaiveDateTime> {
+        parse_date_time(&self.start_date, &self.start_time)
+    }
+
+    /// Окончание операции
+    pub fn end(&self) -> Option<NaiveDateTime> {
+        parse_date_time(&self.end_date, &self.end_time)
+    }
+
+    /// Длительность в минутах
+    pub fn duration_minutes(&self) -> f64 {
+        if self.unit() == "hour" { self.dur_val * 60.0 } else { self.dur_val }
+    }
+
+    /// Подпись единицы длительности
+    pub fn unit_label(&self) -> &'static str {
+        if self.unit() == "hour" { "час" } else { "мин" }
+    }
+
+    fn sort_key(&self) -> f64 {
+        self.op_numeric
+            .or_else(|| self.op_idx.parse().ok())
+            .unwrap_or(0.0)
+    }
+}
+
+fn parse_date_time(date: &str, time: &str) -> Option<NaiveDateTime> {
+    let raw = format!("{} {}", date.trim(), time.trim());
+    NaiveDateTime::parse_from_str(&raw, "%d.%m.%Y %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(&raw, "%d.%m.%Y %H:%M"))
+        .ok()
+}
+
+// Номера и исполнители приходят из фронтенда то строкой, то числом
+fn text<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(s) => s,
+        Value::Null => String::new(),
+        other => other.to_string(),
+    })
+}
@@ -0,0 +1,140 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Открытие файлов в обход диалога: перетаскивание в окно и «Открыть с помощью»
+// в системе. Файл проходит те же проверки, что и в read_file_secure (расширение,
+// разрешённая папка, размер, контрольная сумма, схема), после чего фронтенд
+// получает событие «file-opened» с содержимым или «file-open-failed» с причиной отказа
+// (каналы событий и их данные - в events.rs).
+//
+// Файлы из командной строки (и перетащенные до загрузки интерфейса) приходят, когда
+// событие было бы потеряно. Они откладываются, пока фронтенд не подпишется на
+// события и не вызовет open_pending_files. Чтение и проверки идут в фоновом потоке:
+// большой файл или медленная сетевая папка не должны останавливать цикл событий окна.
+
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+use base64::Engine as _;
+use serde::Serialize;
+
+use crate::{compression, crypto, events, files, paths};
+
+// Сколько файлов из одного перетаскивания или запуска обрабатывается
+const MAX_FILES: usize = 10;
+
+// Отложенные файлы; None - фронтенд уже готов принимать события
+static PENDING: LazyLock<Mutex<Option<Vec<PathBuf>>>> = LazyLock::new(|| Mutex::new(Some(Vec::new())));
+
+/// Формат открытого файла
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OpenedFormat {
+    Json,
+    Xml,
+    Xlsx,
+    /// Зашифрованный файл: содержимое не передаётся, фронтенд запрашивает пароль
+    /// и вызывает read_file_encrypted
+    Encrypted,
+}
+
+/// Открытый файл
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenedFile {
+    pub path: String,
+    pub format: OpenedFormat,
+    /// Текст .json и .xml
+    pub text: Option<String>,
+    /// Содержимое .xlsx в base64
+    pub base64: Option<String>,
+}
+
+/// Отказ открыть файл
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenFailed {
+    pub path: String,
+    pub message: String,
+}
+
+/// Проверяет и читает файл
+pub fn open(path: &Path) -> Result<OpenedFile, String> {
+    let inner = crypto::inner_path(&compression::inner_path(path));
+    let format = match inner.extension().map(|e| e.to_string_lossy().to_lowercase()).as_deref() {
+        _ if crypto::is_enc_path(path) => OpenedFormat::Encrypted,
+        Some(ext) if ext == "json" || ext == files::NATIVE_EXTENSION => OpenedFormat::Json,
+        Some("xml") => OpenedFormat::Xml,
+        Some("xlsx") if !compression::is_gz_path(path) => OpenedFormat::Xlsx,
+        _ => return Err("Открыть можно только файлы .ttable, .json, .xml и .xlsx".into()),
+    };
+    paths::check_file_name(path)?;
+    if !paths::is_path_allowed(path) {
+        return Err("Файл лежит вне разрешённых папок. Перенесите его в Документы или добавьте его папку к разрешённым".into());
+    }
+
+    let (text, base64) = match format {
+        OpenedFormat::Json | OpenedFormat::Xml => (Some(crate::load_schedule(path, false)?), None),
+        OpenedFormat::Xlsx => {
+            let bytes = crate::read_file(path)?;
+            crate::integrity::verify(path, &bytes)?;
+            (None, Some(base64::engine::general_purpose::STANDARD.encode(bytes)))
+        }
+        OpenedFormat::Encrypted => (None, None),
+    };
+    Ok(OpenedFile { path: path.to_string_lossy().to_string(), format, text, base64 })
+}
+
+/// Открывает файлы и сообщает фронтенду результат по каждому
+fn open_and_emit(app: &tauri::AppHandle, files: &[PathBuf]) {
+    for path in files.iter().take(MAX_FILES) {
+        match open(path) {
+            Ok(opened) => events::emit(app, opened),
+            Err(message) => events::emit(app, OpenFailed { path: path.to_string_lossy().to_string(), message }),
+        }
+    }
+}
+
+/// Файлы из аргументов запуска. Первый аргумент - сама программа, ключи
+/// (начинаются с «-») пропускаются, относительные пути отсчитываются от cwd
+pub fn launch_paths(args: impl IntoIterator<Item = String>, cwd: &Path) -> Vec<PathBuf> {
+    args.into_iter()
+        .skip(1)
+        .filter(|arg| !arg.starts_with('-'))
+        .map(|arg| {
+            let path = PathBuf::from(arg);
+            if path.is_absolute() {
+                path
+            } else {
+                cwd.join(path)
+            }
+        })
+        .collect()
+}
+
+/// Открывает файлы в фоновом потоке
+fn spawn_open(app: &tauri::AppHandle, files: Vec<PathBuf>) {
+    if files.is_empty() {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || open_and_emit(&app, &files));
+}
+
+/// Открывает файлы, пришедшие от системы или перетащенные в окно. Пока фронтенд
+/// не готов, они откладываются
+pub fn request(app: &tauri::AppHandle, files: Vec<PathBuf>) {
+    if let Ok(mut pending) = PENDING.lock() {
+        if let Some(queue) = pending.as_mut() {
+            queue.extend(files);
+            return;
+        }
+    }
+    spawn_open(app, files);
+}
+
+/// Фронтенд подписался на события: открывает отложенные файлы, следующие - сразу
+pub fn frontend_ready(app: &tauri::AppHandle) {
+    let files = PENDING.lock().ok().and_then(|mut pending| pending.take()).unwrap_or_default();
+    spawn_open(app, files);
+}
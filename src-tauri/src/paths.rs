@@ -0,0 +1,284 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Проверки путей: разрешённые директории, имена файлов, длинные пути Windows.
+
+use std::path::{Component, Path, PathBuf, Prefix};
+use std::sync::{LazyLock, Mutex};
+use unicode_normalization::UnicodeNormalization;
+
+// Папка приложения в системной директории настроек (совпадает с identifier из tauri.conf.json)
+const APP_DIR_NAME: &str = "com.timetotable.calculator";
+
+// Имена устройств, которые Windows не даёт использовать как имя файла (в том числе с расширением: CON.json)
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+// Символы, запрещённые в именах файлов Windows
+const FORBIDDEN_CHARS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+// Классический лимит MAX_PATH (260 символов вместе с завершающим нулём)
+#[cfg(windows)]
+const WINDOWS_MAX_PATH: usize = 259;
+
+/// Приводит путь к NFC. Диалоги macOS отдают кириллицу в NFD («й» = «и» + кратка),
+/// и без нормализации один и тот же файл выглядит как два разных пути.
+/// Используется только для сравнения: сами файловые операции идут по исходному пути
+pub fn nfc(path: &Path) -> PathBuf {
+    match path.to_str() {
+        Some(s) => PathBuf::from(s.nfc().collect::<String>()),
+        None => path.to_path_buf(),
+    }
+}
+
+/// Сравнение путей без учёта формы нормализации Unicode
+pub fn same_path(a: &Path, b: &Path) -> bool {
+    nfc(a) == nfc(b)
+}
+
+/// Путь лежит внутри директории (без учёта формы нормализации Unicode)
+pub fn is_within(path: &Path, dir: &Path) -> bool {
+    nfc(path).starts_with(nfc(dir))
+}
+
+/// Директория настроек приложения
+pub fn app_config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_DIR_NAME))
+}
+
+/// Директория рабочих данных приложения (автосохранения)
+pub fn app_data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(APP_DIR_NAME))
+}
+
+/// Директория кэша приложения: система может её очищать
+pub fn app_cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join(APP_DIR_NAME))
+}
+
+/// Список разрешённых директорий: Загрузки, Документы, Рабочий стол
+pub fn allowed_dirs() -> Vec<PathBuf> {
+    [
+        dirs::download_dir(),
+        dirs::document_dir(),
+        dirs::desktop_dir(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+// Съёмные носители, разрешённые пользователем до закрытия приложения
+static SESSION_DIRS: LazyLock<Mutex<Vec<PathBuf>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+
+/// Разрешает папку до конца сеанса (без сохранения в настройках)
+pub fn grant_session_dir(dir: PathBuf) {
+    if let Ok(mut dirs) = SESSION_DIRS.lock() {
+        if !dirs.iter().any(|d| same_path(d, &dir)) {
+            dirs.push(dir);
+        }
+    }
+}
+
+/// Отзывает разрешение, выданное на сеанс
+pub fn revoke_session_dir(dir: &Path) {
+    if let Ok(mut dirs) = SESSION_DIRS.lock() {
+        dirs.retain(|d| !same_path(d, dir));
+    }
+}
+
+/// Папки, разрешённые на текущий сеанс
+pub fn session_dirs() -> Vec<PathBuf> {
+    SESSION_DIRS.lock().map(|dirs| dirs.clone()).unwrap_or_default()
+}
+
+/// Разрешённая на сеанс папка (съёмный носитель), в которой лежит путь
+pub fn session_root(path: &Path) -> Option<PathBuf> {
+    session_dirs().into_iter().find(|dir| is_within(path, dir))
+}
+
+/// Сетевой путь: UNC (\\сервер\папка) или подключённый сетевой диск Windows
+pub fn is_network_path(path: &Path) -> bool {
+    match path.components().next() {
+        Some(Component::Prefix(prefix)) => match prefix.kind() {
+            Prefix::UNC(..) | Prefix::VerbatimUNC(..) => true,
+            Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) => crate::drives::is_remote_drive(letter),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Убирает verbatim-префикс, который добавляет canonicalize в Windows:
+/// \\?\UNC\сервер\папка -> \\сервер\папка, \\?\C:\папка -> C:\папка
+pub fn strip_verbatim(path: &Path) -> PathBuf {
+    let raw = path.to_string_lossy();
+    if let Some(unc) = raw.strip_prefix(r"\\?\UNC\") {
+        PathBuf::from(format!(r"\\{}", unc))
+    } else if let Some(disk) = raw.strip_prefix(r"\\?\").filter(|rest| rest.as_bytes().get(1) == Some(&b':')) {
+        PathBuf::from(disk)
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// Проверяет что путь находится в разрешённой директории
+pub fn is_path_allowed(path: &Path) -> bool {
+    // Канонизируем путь для защиты от ../ атак
+    let canonical = match path.canonicalize() {
+        Ok(p) => p,
+        Err(_) => {
+            // Если файл ещё не существует, проверяем родительскую директорию
+            if let Some(parent) = path.parent() {
+                match parent.canonicalize() {
+                    Ok(p) => p,
+                    Err(_) => return false,
+                }
+            } else {
+                return false;
+            }
+        }
+    };
+    // Сравниваем без verbatim-префиксов, иначе \\?\UNC\... не совпадёт с \\сервер\...
+    let canonical = strip_verbatim(&canonical);
+
+    allowed_dirs()
+        .into_iter()
+        .chain(crate::allowlist::network_dirs())
+        .chain(crate::allowlist::dirs())
+        .chain(session_dirs())
+        .any(|dir| {
+            if let Ok(canonical_dir) = dir.canonicalize() {
+                is_within(&canonical, &strip_verbatim(&canonical_dir))
+            } else {
+                false
+            }
+        })
+}
+
+/// Проверяет имя файла на совместимость с Windows: зарезервированные имена устройств,
+/// запрещённые символы, точка или пробел в конце
+pub fn check_file_name(path: &Path) -> Result<(), String> {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| "Не указано имя файла".to_string())?;
+
+    if name.chars().any(|c| FORBIDDEN_CHARS.contains(&c) || c.is_control()) {
+        return Err(format!("Имя файла «{}» содержит недопустимые символы", name));
+    }
+
+    if name.ends_with('.') || name.ends_with(' ') {
+        return Err("Имя файла не может заканчиваться точкой или пробелом".into());
+    }
+
+    // Windows сравнивает по части до первой точки, без учёта регистра и хвостовых пробелов
+    let stem = name.split('.').next().unwrap_or("").trim_end().to_uppercase();
+    if RESERVED_NAMES.contains(&stem.as_str()) {
+        return Err(format!("Имя «{}» зарезервировано Windows, выберите другое имя файла", name));
+    }
+
+    Ok(())
+}
+
+/// Возвращает путь, пригодный для файловых операций. В Windows пути длиннее MAX_PATH
+/// (глубокие папки OneDrive) получают префикс \\?\ (или \\?\UNC\ для сетевых путей).
+#[cfg(windows)]
+pub fn to_fs_path(path: &Path) -> PathBuf {
+    let raw = path.as_os_str().to_string_lossy();
+    if raw.len() <= WINDOWS_MAX_PATH || raw.starts_with(r"\\?\") || !path.is_absolute() {
+        return path.to_path_buf();
+    }
+
+    // Verbatim-пути не нормализуются системой: приводим разделители и убираем . и ..
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    let normalized = normalized.to_string_lossy().replace('/', r"\");
+
+    if let Some(unc) = normalized.strip_prefix(r"\\") {
+        PathBuf::from(format!(r"\\?\UNC\{}", unc))
+    } else {
+        PathBuf::from(format!(r"\\?\{}", normalized))
+    }
+}
+
+#[cfg(not(windows))]
+pub fn to_fs_path(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+/// Формирует понятное сообщение об ошибке ввода-вывода вместо системного кода
+pub fn io_error_message(action: &str, err: &std::io::Error) -> String {
+    if err.kind() == std::io::ErrorKind::InvalidFilename {
+        return format!("{}: путь или имя файла слишком длинные", action);
+    }
+
+    // ERROR_PATH_NOT_FOUND и ERROR_INVALID_NAME
+    #[cfg(windows)]
+    match err.raw_os_error() {
+        Some(3) => return format!("{}: папка не найдена (возможно, путь слишком длинный)", action),
+        Some(123) => return format!("{}: недопустимое имя файла или папки", action),
+        _ => {}
+    }
+
+    format!("{}: {}", action, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reserved_and_trailing_names() {
+        for name in ["CON.txt", "con .json", "Lpt1.tar.gz", "name.", "name ", "a:b.json", "a\u{1}.json"] {
+            assert!(check_file_name(Path::new(name)).is_err(), "{}", name);
+        }
+        for name in ["console.txt", "COM10.json", "name.json", ".hidden", "расписание 1.json"] {
+            assert!(check_file_name(Path::new(name)).is_ok(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn verbatim_prefix() {
+        assert_eq!(strip_verbatim(Path::new(r"\\?\UNC\server\share\a.json")), PathBuf::from(r"\\server\share\a.json"));
+        assert_eq!(strip_verbatim(Path::new(r"\\?\C:\Users\a.json")), PathBuf::from(r"C:\Users\a.json"));
+        // Verbatim-путь без буквы диска оставляется как есть
+        assert_eq!(strip_verbatim(Path::new(r"\\?\Volume{1}\a")), PathBuf::from(r"\\?\Volume{1}\a"));
+    }
+
+    // Длинный путь получает префикс \\?\, а strip_verbatim возвращает исходный
+    #[cfg(windows)]
+    #[test]
+    fn long_path_round_trip() {
+        let deep = "папка\\".repeat(40);
+        for path in [format!(r"C:\{}a.json", deep), format!(r"\\server\share\{}a.json", deep)] {
+            let verbatim = to_fs_path(Path::new(&path));
+            assert!(verbatim.to_string_lossy().starts_with(r"\\?\"), "{}", verbatim.display());
+            assert_eq!(strip_verbatim(&verbatim), PathBuf::from(&path));
+        }
+        assert_eq!(to_fs_path(Path::new(r"C:\a.json")), PathBuf::from(r"C:\a.json"));
+    }
+
+    // «й» из диалога macOS (NFD: «и» + кратка) и набранное (NFC) - один путь
+    #[test]
+    fn unicode_normalization() {
+        let path = "/Users/u/Документы/Зайцев.json";
+        let composed: String = path.nfc().collect();
+        let decomposed: String = path.nfd().collect();
+        assert_ne!(decomposed, composed);
+        assert!(same_path(Path::new(&decomposed), Path::new(&composed)));
+        assert!(is_within(Path::new(&decomposed), Path::new("/Users/u/Документы")));
+        assert!(!same_path(Path::new(&composed), Path::new("/Users/u/Документы/Заицев.json")));
+    }
+}
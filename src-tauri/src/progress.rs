@@ -0,0 +1,105 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Ход длительных операций (пакетная выгрузка, формирование Excel, импорт CSV).
+// Фронтенд получает события «export://progress» с процентом и текущим элементом
+// и показывает настоящую полосу вместо индикатора ожидания. Идентификатор операции
+// передаёт фронтенд, по нему он отличает события одновременных операций. Канал и
+// данные события описаны в events.rs.
+//
+// Модули выгрузки и импорта о Tauri не знают: они принимают функцию
+// (сделано, всего, текущий элемент), а события отправляет Reporter.
+//
+// Операцию с идентификатором можно отменить командой cancel_operation: Reporter
+// проверяет флаг отмены при каждом вызове и возвращает ошибку с кодом CANCELLED,
+// на которой выгрузка или импорт останавливаются. Уже записанные файлы пакетной
+// выгрузки остаются на месте, и её можно продолжить (export/batch.rs).
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::events;
+
+/// Код ошибки «операция отменена» в начале сообщения
+pub const CANCELLED: &str = "OPERATION_CANCELLED";
+
+// Не чаще одного события за этот интервал, если процент не изменился
+const MIN_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Событие о ходе операции
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressEvent {
+    pub operation: Option<String>,
+    pub percent: u8,
+    /// Текущий элемент: имя файла, запись истории, строка CSV
+    pub current: String,
+    pub done: usize,
+    pub total: usize,
+}
+
+// Флаги отмены выполняющихся операций по идентификатору
+static RUNNING: LazyLock<Mutex<HashMap<String, Arc<AtomicBool>>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Отменяет выполняющуюся операцию
+pub fn cancel(operation: &str) -> Result<(), String> {
+    let running = RUNNING.lock().map_err(|_| "Ошибка доступа к списку операций".to_string())?;
+    let flag = running.get(operation).ok_or("Операция не найдена или уже завершена")?;
+    flag.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Отправляет события о ходе одной операции и следит за её отменой
+pub struct Reporter {
+    app: tauri::AppHandle,
+    operation: Option<String>,
+    cancelled: Arc<AtomicBool>,
+    last_percent: Option<u8>,
+    last_emit: Instant,
+}
+
+impl Reporter {
+    pub fn new(app: &tauri::AppHandle, operation: Option<String>) -> Self {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        if let (Some(id), Ok(mut running)) = (&operation, RUNNING.lock()) {
+            running.insert(id.clone(), cancelled.clone());
+        }
+        Reporter { app: app.clone(), operation, cancelled, last_percent: None, last_emit: Instant::now() }
+    }
+
+    /// Сообщает, что сделано done из total. Событие отправляется, когда меняется
+    /// процент или прошло MIN_INTERVAL: строки большого CSV не засыпают фронтенд событиями.
+    /// Ошибка - операцию отменили, её нужно прервать
+    pub fn report(&mut self, done: usize, total: usize, current: &str) -> Result<(), String> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Err(format!("{}: операция отменена", CANCELLED));
+        }
+        let percent = (done.min(total) * 100).checked_div(total).map_or(100, |p| p as u8);
+        if self.last_percent == Some(percent) && self.last_emit.elapsed() < MIN_INTERVAL {
+            return Ok(());
+        }
+        self.last_percent = Some(percent);
+        self.last_emit = Instant::now();
+        events::emit(
+            &self.app,
+            ProgressEvent { operation: self.operation.clone(), percent, current: current.to_string(), done, total },
+        );
+        Ok(())
+    }
+}
+
+impl Drop for Reporter {
+    fn drop(&mut self) {
+        let (Some(id), Ok(mut running)) = (&self.operation, RUNNING.lock()) else {
+            return;
+        };
+        // Тот же идентификатор мог занять более новый запуск
+        if running.get(id).is_some_and(|flag| Arc::ptr_eq(flag, &self.cancelled)) {
+            running.remove(id);
+        }
+    }
+}
@@ -0,0 +1,105 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Список недавних файлов. Хранится в настройках приложения (appdata); при выдаче
+// файлы, которых больше нет или которые оказались вне разрешённых папок, не
+// показываются, но из списка не удаляются: флешка или сетевой диск могут быть
+// просто не подключены.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::appdata::{self, Area};
+use crate::paths;
+
+const RECENTS_FILE: &str = "recent_files.json";
+
+// Сколько незакреплённых файлов помнить
+const MAX_RECENTS: usize = 20;
+
+/// Недавний файл
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentFile {
+    pub path: PathBuf,
+    pub name: String,
+    #[serde(default)]
+    pub pinned: bool,
+    /// Время последнего открытия, миллисекунды с 1970 года
+    pub opened_at: u64,
+}
+
+fn load() -> Vec<RecentFile> {
+    appdata::read(Area::Settings, RECENTS_FILE)
+        .ok()
+        .flatten()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+fn save(list: &[RecentFile]) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(list)
+        .map_err(|e| format!("Ошибка сохранения списка недавних файлов: {}", e))?;
+    appdata::write(Area::Settings, RECENTS_FILE, &content)
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Закреплённые первыми, затем по времени открытия
+fn sort(list: &mut [RecentFile]) {
+    list.sort_by(|a, b| b.pinned.cmp(&a.pinned).then(b.opened_at.cmp(&a.opened_at)));
+}
+
+/// Добавляет файл (или поднимает наверх уже известный)
+pub fn add(path: &Path) -> Result<(), String> {
+    let mut list = load();
+    let opened_at = now_ms();
+    match list.iter_mut().find(|r| paths::same_path(&r.path, path)) {
+        Some(existing) => existing.opened_at = opened_at,
+        None => list.push(RecentFile {
+            path: path.to_path_buf(),
+            name: path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+            pinned: false,
+            opened_at,
+        }),
+    }
+    sort(&mut list);
+
+    // Лишние незакреплённые отбрасываются, закреплённые остаются всегда
+    let mut unpinned = 0;
+    list.retain(|r| {
+        if r.pinned {
+            return true;
+        }
+        unpinned += 1;
+        unpinned <= MAX_RECENTS
+    });
+    save(&list)
+}
+
+/// Недавние файлы, которые существуют и лежат в разрешённых папках
+pub fn list() -> Vec<RecentFile> {
+    let mut list: Vec<RecentFile> = load()
+        .into_iter()
+        .filter(|r| paths::to_fs_path(&r.path).is_file() && paths::is_path_allowed(&r.path))
+        .collect();
+    sort(&mut list);
+    list
+}
+
+/// Закрепляет файл в списке или снимает закрепление
+pub fn pin(path: &Path, pinned: bool) -> Result<(), String> {
+    let mut list = load();
+    let item = list
+        .iter_mut()
+        .find(|r| paths::same_path(&r.path, path))
+        .ok_or("Файла нет в списке недавних")?;
+    item.pinned = pinned;
+    sort(&mut list);
+    save(&list)
+}
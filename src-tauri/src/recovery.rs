@@ -0,0 +1,151 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Восстановление несохранённой работы после сбоя. Автосохранение удаляет свой файл,
+// когда документ сохраняют или закрывают, поэтому файл, оставшийся к запуску, - это
+// изменения, которых нет в сохранённом документе. При запуске такие файлы
+// переименовываются из session-*.json в recovery-*.json, чтобы автосохранение
+// нового сеанса их не перезаписало, и ждут решения пользователя: восстановить или
+// отказаться.
+//
+// Если документ - файл, изменённый позже автосохранения (например, сохранённый
+// другой программой), автосохранение устарело и удаляется без вопроса. Файл, который
+// не удалось разобрать, не удаляется: он переименовывается в damaged-*.json и тоже
+// предлагается пользователю - из него можно достать уцелевшее через salvage.
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, NaiveDateTime};
+use serde::Serialize;
+
+use crate::appdata::{self, Area};
+use crate::autosave::AutosaveFile;
+use crate::paths;
+use crate::salvage::{self, SalvageReport};
+
+const SESSION_PREFIX: &str = "session-";
+const RECOVERY_PREFIX: &str = "recovery-";
+const DAMAGED_PREFIX: &str = "damaged-";
+
+/// Несохранённая работа прошлого сеанса
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryCandidate {
+    /// Идентификатор для recover_session
+    pub id: String,
+    pub document: String,
+    pub path: Option<PathBuf>,
+    pub saved_at: String,
+    pub entries: usize,
+    pub operations: usize,
+    /// Файл не разбирается: восстановить можно только через salvage_recovery
+    pub damaged: bool,
+}
+
+// Идентификатор - имя файла recovery-<хеш>.json или damaged-<хеш>.json
+fn check_id(id: &str) -> Result<(), String> {
+    let valid = [RECOVERY_PREFIX, DAMAGED_PREFIX]
+        .iter()
+        .find_map(|prefix| id.strip_prefix(prefix))
+        .and_then(|rest| rest.strip_suffix(".json"))
+        .is_some_and(|hash| !hash.is_empty() && hash.chars().all(|c| c.is_ascii_hexdigit()));
+    if valid { Ok(()) } else { Err("Недопустимый идентификатор восстановления".into()) }
+}
+
+fn read(name: &str) -> Option<AutosaveFile> {
+    appdata::read(Area::Autosave, name)
+        .ok()
+        .flatten()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+}
+
+// Файл документа изменён позже автосохранения. У документа рабочей области путь
+// записан отдельно, у документа edit_open путь - сам идентификатор
+fn is_stale(file: &AutosaveFile) -> bool {
+    let path = file.path.as_deref().unwrap_or(Path::new(&file.document));
+    let Ok(saved_at) = NaiveDateTime::parse_from_str(&file.saved_at, "%Y-%m-%dT%H:%M:%S") else {
+        return false;
+    };
+    std::fs::metadata(paths::to_fs_path(path))
+        .and_then(|m| m.modified())
+        .is_ok_and(|modified| DateTime::<Local>::from(modified).naive_local() > saved_at)
+}
+
+/// Вызывается при запуске до автосохранения: откладывает файлы прошлого сеанса
+pub fn collect() {
+    for entry in appdata::list(Area::Autosave) {
+        let Some(hash) = entry.name.strip_prefix(SESSION_PREFIX) else {
+            continue;
+        };
+        // Неразобранный файл не удаляется, а откладывается для salvage
+        let Some(file) = read(&entry.name) else {
+            let _ = appdata::rename(Area::Autosave, &entry.name, &format!("{}{}", DAMAGED_PREFIX, hash));
+            continue;
+        };
+        if !is_stale(&file) {
+            // Если отложить не удалось, файл сеанса остаётся: лучше так, чем потерять правки
+            let recovery = format!("{}{}", RECOVERY_PREFIX, hash);
+            let content = serde_json::to_string(&file).map_err(|e| e.to_string());
+            if content.and_then(|c| appdata::write(Area::Autosave, &recovery, &c)).is_err() {
+                continue;
+            }
+        }
+        let _ = appdata::delete(Area::Autosave, &entry.name);
+    }
+}
+
+/// Несохранённая работа прошлых сеансов, новые первыми
+pub fn candidates() -> Vec<RecoveryCandidate> {
+    appdata::list(Area::Autosave)
+        .into_iter()
+        .filter_map(|entry| {
+            if entry.name.starts_with(DAMAGED_PREFIX) {
+                let saved_at = DateTime::from_timestamp_millis(entry.modified as i64)
+                    .map(|t| t.with_timezone(&Local).format("%Y-%m-%dT%H:%M:%S").to_string())
+                    .unwrap_or_default();
+                return Some(RecoveryCandidate {
+                    id: entry.name,
+                    document: String::new(),
+                    path: None,
+                    saved_at,
+                    entries: 0,
+                    operations: 0,
+                    damaged: true,
+                });
+            }
+            if !entry.name.starts_with(RECOVERY_PREFIX) {
+                return None;
+            }
+            let file = read(&entry.name)?;
+            Some(RecoveryCandidate {
+                id: entry.name,
+                entries: file.schedule.entries.len(),
+                operations: file.schedule.entries.iter().map(|e| e.rows.len()).sum(),
+                document: file.document,
+                path: file.path,
+                saved_at: file.saved_at,
+                damaged: false,
+            })
+        })
+        .collect()
+}
+
+/// Достаёт уцелевшее из повреждённого файла восстановления; файл остаётся до discard
+pub fn salvage(id: &str) -> Result<SalvageReport, String> {
+    check_id(id)?;
+    let bytes = appdata::read_bytes(Area::Autosave, id)?.ok_or("Данные для восстановления не найдены")?;
+    salvage::salvage_json(&bytes)
+}
+
+/// Читает несохранённую работу. Файл восстановления остаётся, пока работа не открыта:
+/// удаляет его discard
+pub fn load(id: &str) -> Result<AutosaveFile, String> {
+    check_id(id)?;
+    read(id).ok_or_else(|| "Данные для восстановления не найдены или повреждены".into())
+}
+
+/// Отказ от восстановления
+pub fn discard(id: &str) -> Result<(), String> {
+    check_id(id)?;
+    appdata::delete(Area::Autosave, id)
+}
@@ -0,0 +1,343 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Восстановление повреждённых JSON-файлов (обрезанных, с битыми байтами).
+// Из файла достаются все записи верхнего уровня, которые удалось разобрать целиком,
+// остальные попадают в отчёт как потерянные.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Результат восстановления файла
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SalvageReport {
+    /// Восстановленное содержимое (валидный JSON)
+    pub content: String,
+    /// Ключи (или индексы массива) восстановленных записей
+    pub recovered: Vec<String>,
+    /// Ключи записей, которые восстановить не удалось
+    pub lost: Vec<String>,
+    /// Файл обрывается посередине записи
+    pub truncated: bool,
+    /// Количество недопустимых последовательностей UTF-8
+    pub invalid_bytes: usize,
+    /// Результат открывается только для чтения
+    pub read_only: bool,
+}
+
+/// Пытается восстановить как можно больше записей из повреждённого JSON
+pub fn salvage_json(bytes: &[u8]) -> Result<SalvageReport, String> {
+    let (text, invalid_bytes) = decode_lossy(bytes);
+    // Обрезанные при сбое питания файлы часто добиты нулевыми байтами
+    let text = text.trim_start_matches('\u{feff}').trim_end_matches('\0');
+
+    // Структура файла цела - проверяем только отдельные записи
+    if let Ok(value) = serde_json::from_str::<Value>(text) {
+        let mut recovered = Vec::new();
+        let mut lost = Vec::new();
+        let content = match value {
+            Value::Object(map) => {
+                let mut kept = Map::new();
+                for (key, value) in map {
+                    if is_record_intact(&value) {
+                        recovered.push(key.clone());
+                        kept.insert(key, value);
+                    } else {
+                        lost.push(key);
+                    }
+                }
+                Value::Object(kept)
+            }
+            Value::Array(items) => {
+                let mut kept = Vec::new();
+                for (i, value) in items.into_iter().enumerate() {
+                    if is_record_intact(&value) {
+                        recovered.push(format!("#{}", i));
+                        kept.push(value);
+                    } else {
+                        lost.push(format!("#{}", i));
+                    }
+                }
+                Value::Array(kept)
+            }
+            other => other,
+        };
+        let damaged = !lost.is_empty() || invalid_bytes > 0;
+        let content = if lost.is_empty() {
+            text.to_string()
+        } else {
+            serde_json::to_string_pretty(&content)
+                .map_err(|e| format!("Ошибка формирования результата: {}", e))?
+        };
+        return Ok(SalvageReport {
+            content,
+            recovered,
+            lost,
+            truncated: false,
+            invalid_bytes,
+            read_only: damaged,
+        });
+    }
+
+    let mut scanner = Scanner::new(text);
+    scanner.skip_ws();
+    let (content, recovered, lost, truncated) = match scanner.peek() {
+        Some('{') => {
+            scanner.pos += 1;
+            let (map, recovered, lost, truncated) = salvage_object(&mut scanner);
+            (Value::Object(map), recovered, lost, truncated)
+        }
+        Some('[') => {
+            scanner.pos += 1;
+            let (items, recovered, lost, truncated) = salvage_array(&mut scanner);
+            (Value::Array(items), recovered, lost, truncated)
+        }
+        _ => return Err("Не удалось распознать структуру файла: ожидался объект или массив JSON".into()),
+    };
+
+    if recovered.is_empty() {
+        return Err("Не удалось восстановить ни одной записи".into());
+    }
+
+    let content = serde_json::to_string_pretty(&content)
+        .map_err(|e| format!("Ошибка формирования результата: {}", e))?;
+
+    Ok(SalvageReport {
+        content,
+        recovered,
+        lost,
+        truncated,
+        invalid_bytes,
+        read_only: true,
+    })
+}
+
+/// Декодирует UTF-8, заменяя битые последовательности, и считает их количество
+fn decode_lossy(bytes: &[u8]) -> (String, usize) {
+    let mut text = String::with_capacity(bytes.len());
+    let mut invalid = 0;
+    for chunk in bytes.utf8_chunks() {
+        text.push_str(chunk.valid());
+        if !chunk.invalid().is_empty() {
+            invalid += 1;
+            text.push('\u{fffd}');
+        }
+    }
+    (text, invalid)
+}
+
+type Salvaged<T> = (T, Vec<String>, Vec<String>, bool);
+
+fn salvage_object(scanner: &mut Scanner) -> Salvaged<Map<String, Value>> {
+    let mut map = Map::new();
+    let mut recovered = Vec::new();
+    let mut lost = Vec::new();
+
+    loop {
+        scanner.skip_ws_and_commas();
+        match scanner.peek() {
+            None => return (map, recovered, lost, true),
+            Some('}') => return (map, recovered, lost, false),
+            Some('"') => {}
+            Some(_) => {
+                // Мусор между записями - ищем начало следующей
+                if !scanner.resync() {
+                    return (map, recovered, lost, true);
+                }
+                continue;
+            }
+        }
+
+        let key_start = scanner.pos;
+        let Some(key_end) = scanner.scan_string() else {
+            return (map, recovered, lost, true);
+        };
+        let key: String = match serde_json::from_str(&scanner.text[key_start..key_end]) {
+            Ok(k) => k,
+            Err(_) => {
+                if !scanner.resync() {
+                    return (map, recovered, lost, true);
+                }
+                continue;
+            }
+        };
+
+        scanner.skip_ws();
+        if scanner.peek() != Some(':') {
+            lost.push(key);
+            if !scanner.resync() {
+                return (map, recovered, lost, true);
+            }
+            continue;
+        }
+        scanner.pos += 1;
+        scanner.skip_ws();
+
+        let value_start = scanner.pos;
+        let Some(value_end) = scanner.scan_value() else {
+            lost.push(key);
+            return (map, recovered, lost, true);
+        };
+        match parse_record(&scanner.text[value_start..value_end]) {
+            Some(value) => {
+                recovered.push(key.clone());
+                map.insert(key, value);
+            }
+            None => {
+                lost.push(key);
+                if !scanner.resync() {
+                    return (map, recovered, lost, true);
+                }
+            }
+        }
+    }
+}
+
+fn salvage_array(scanner: &mut Scanner) -> Salvaged<Vec<Value>> {
+    let mut items = Vec::new();
+    let mut recovered = Vec::new();
+    let mut lost = Vec::new();
+    let mut index = 0;
+
+    loop {
+        scanner.skip_ws_and_commas();
+        match scanner.peek() {
+            None => return (items, recovered, lost, true),
+            Some(']') => return (items, recovered, lost, false),
+            Some(_) => {}
+        }
+
+        let start = scanner.pos;
+        let Some(end) = scanner.scan_value() else {
+            lost.push(format!("#{}", index));
+            return (items, recovered, lost, true);
+        };
+        match parse_record(&scanner.text[start..end]) {
+            Some(value) => {
+                recovered.push(format!("#{}", index));
+                items.push(value);
+            }
+            None => lost.push(format!("#{}", index)),
+        }
+        index += 1;
+    }
+}
+
+/// Разбирает одну запись и проверяет её целостность
+fn parse_record(raw: &str) -> Option<Value> {
+    let value: Value = serde_json::from_str(raw).ok()?;
+    is_record_intact(&value).then_some(value)
+}
+
+/// Техкарты хранятся как JSON внутри строки, поэтому такие строки тоже должны разбираться целиком
+fn is_record_intact(value: &Value) -> bool {
+    match value {
+        Value::String(s) => {
+            let inner = s.trim_start();
+            !(inner.starts_with('{') || inner.starts_with('[')) || serde_json::from_str::<Value>(s).is_ok()
+        }
+        _ => true,
+    }
+}
+
+struct Scanner<'a> {
+    text: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(text: &'a str) -> Self {
+        Scanner { text, bytes: text.as_bytes(), pos: 0 }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.bytes.get(self.pos).map(|&b| b as char)
+    }
+
+    fn skip_ws(&mut self) {
+        while matches!(self.bytes.get(self.pos), Some(b' ' | b'\t' | b'\r' | b'\n')) {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_ws_and_commas(&mut self) {
+        while matches!(self.bytes.get(self.pos), Some(b' ' | b'\t' | b'\r' | b'\n' | b',')) {
+            self.pos += 1;
+        }
+    }
+
+    /// Пропускает строку, начинающуюся в текущей позиции. Возвращает позицию после закрывающей кавычки.
+    fn scan_string(&mut self) -> Option<usize> {
+        let mut i = self.pos + 1;
+        while i < self.bytes.len() {
+            match self.bytes[i] {
+                b'\\' => i += 2,
+                b'"' => {
+                    self.pos = i + 1;
+                    return Some(self.pos);
+                }
+                _ => i += 1,
+            }
+        }
+        self.pos = self.bytes.len();
+        None
+    }
+
+    /// Пропускает значение любого типа. None - файл оборвался внутри значения.
+    fn scan_value(&mut self) -> Option<usize> {
+        match self.peek()? {
+            '"' => self.scan_string(),
+            '{' | '[' => {
+                let mut depth = 0usize;
+                while self.pos < self.bytes.len() {
+                    match self.bytes[self.pos] {
+                        b'"' => {
+                            self.scan_string()?;
+                            continue;
+                        }
+                        b'{' | b'[' => depth += 1,
+                        b'}' | b']' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                self.pos += 1;
+                                return Some(self.pos);
+                            }
+                        }
+                        _ => {}
+                    }
+                    self.pos += 1;
+                }
+                None
+            }
+            _ => {
+                while let Some(&b) = self.bytes.get(self.pos) {
+                    if matches!(b, b',' | b'}' | b']') {
+                        return Some(self.pos);
+                    }
+                    self.pos += 1;
+                }
+                None
+            }
+        }
+    }
+
+    /// Переходит к началу следующей записи объекта (запятая, затем кавычка ключа)
+    fn resync(&mut self) -> bool {
+        while self.pos < self.bytes.len() {
+            if self.bytes[self.pos] == b',' {
+                let mut j = self.pos + 1;
+                while matches!(self.bytes.get(j), Some(b' ' | b'\t' | b'\r' | b'\n')) {
+                    j += 1;
+                }
+                if self.bytes.get(j) == Some(&b'"') {
+                    self.pos = j;
+                    return true;
+                }
+            }
+            self.pos += 1;
+        }
+        false
+    }
+}
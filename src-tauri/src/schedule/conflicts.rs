@@ -0,0 +1,204 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Двойные назначения: исполнитель занят двумя операциями одновременно или операция
+// попадает на время внешнего события. Сравниваются все операции исполнителя, в том
+// числе из разных записей истории (разных смен и расчётов) и операции, которые
+// переходят через полночь в следующую смену.
+
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+use super::constraints::{self, Constraint};
+use super::{format_time, Slot};
+use crate::model::Schedule;
+
+/// Вид конфликта
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    /// Две операции одного исполнителя пересекаются
+    DoubleBooked,
+    /// Операция на время внешнего события
+    Blocked,
+    /// Нарушено жёсткое ограничение (constraints.rs)
+    Constraint,
+    /// Нарушено мягкое ограничение
+    Preference,
+}
+
+/// Операция или событие, участвующие в конфликте
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictSide {
+    /// Запись истории и строка; для события - номер события, для ограничения - его номер
+    pub entry: Option<usize>,
+    pub row: Option<usize>,
+    pub blocked: Option<usize>,
+    pub constraint: Option<usize>,
+    /// Техкарта, название события или описание ограничения
+    pub card: String,
+    pub operation: String,
+}
+
+/// Конфликт в расписании
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Conflict {
+    pub kind: ConflictKind,
+    pub worker: String,
+    /// Общий интервал: дд.мм.гггг ЧЧ:ММ
+    pub start: String,
+    pub end: String,
+    pub minutes: i64,
+    pub first: ConflictSide,
+    pub second: ConflictSide,
+    /// Операции из разных записей истории
+    pub cross_entry: bool,
+    pub message: String,
+}
+
+pub(super) fn side(slot: &Slot) -> ConflictSide {
+    ConflictSide {
+        entry: Some(slot.entry),
+        row: Some(slot.row),
+        blocked: None,
+        constraint: None,
+        card: slot.card.to_string(),
+        operation: slot.operation.name.clone(),
+    }
+}
+
+pub(super) fn conflict(
+    kind: ConflictKind,
+    worker: &str,
+    (start, end): (NaiveDateTime, NaiveDateTime),
+    first: ConflictSide,
+    second: ConflictSide,
+    message: String,
+) -> Conflict {
+    Conflict {
+        kind,
+        worker: worker.to_string(),
+        start: format_time(start),
+        end: format_time(end),
+        minutes: (end - start).num_minutes(),
+        cross_entry: kind == ConflictKind::DoubleBooked && first.entry != second.entry,
+        first,
+        second,
+        message,
+    }
+}
+
+/// Все конфликты расписания: каждая пара пересекающихся операций исполнителя
+/// и каждая операция на время события, которое касается исполнителя
+pub fn find(schedule: &Schedule) -> Vec<Conflict> {
+    let mut slots = super::slots(schedule);
+    slots.sort_by(|a, b| a.worker.cmp(b.worker).then(a.start.cmp(&b.start)));
+    let mut conflicts = Vec::new();
+
+    // Операции исполнителя по времени начала; active - начатые и ещё не закончившиеся
+    let mut active: Vec<&Slot> = Vec::new();
+    for slot in &slots {
+        active.retain(|prev| prev.worker == slot.worker && prev.end > slot.start);
+        for prev in &active {
+            let end = prev.end.min(slot.end);
+            let message = format!(
+                "{}: «{}» и «{}» пересекаются с {} до {}",
+                slot.worker,
+                prev.operation.name,
+                slot.operation.name,
+                format_time(slot.start),
+                format_time(end)
+            );
+            conflicts.push(conflict(
+                ConflictKind::DoubleBooked,
+                slot.worker,
+                (slot.start, end),
+                side(prev),
+                side(slot),
+                message,
+            ));
+        }
+        active.push(slot);
+    }
+
+    for (b, blocked) in schedule.blocked.iter().enumerate() {
+        let (Some(from), Some(to)) = (blocked.start(), blocked.end()) else {
+            continue;
+        };
+        if to < from {
+            continue;
+        }
+        for slot in slots.iter().filter(|s| blocked.applies_to(s.worker) && s.start < to && from < s.end) {
+            let (start, end) = (slot.start.max(from), slot.end.min(to));
+            let event = ConflictSide {
+                entry: None,
+                row: None,
+                blocked: Some(b),
+                constraint: None,
+                card: blocked.title.clone(),
+                operation: String::new(),
+            };
+            let message = format!(
+                "{}: «{}» попадает на событие «{}» ({} - {})",
+                slot.worker,
+                slot.operation.name,
+                blocked.title,
+                format_time(start),
+                format_time(end)
+            );
+            conflicts.push(conflict(ConflictKind::Blocked, slot.worker, (start, end), side(slot), event, message));
+        }
+    }
+    conflicts
+}
+
+/// Конфликты и нарушения ограничений
+pub fn find_with(schedule: &Schedule, constraints: &[Constraint]) -> Vec<Conflict> {
+    let mut conflicts = find(schedule);
+    conflicts.extend(constraints::violations(schedule, constraints));
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{OperationRow, ScheduleEntry};
+
+    fn row(name: &str, worker: &str, start: &str, end: &str) -> OperationRow {
+        OperationRow {
+            name: name.into(),
+            worker: worker.into(),
+            start_date: "05.01.2026".into(),
+            start_time: start.into(),
+            end_date: "05.01.2026".into(),
+            end_time: end.into(),
+            ..Default::default()
+        }
+    }
+
+    fn schedule(entries: Vec<Vec<OperationRow>>) -> Schedule {
+        let entries = entries.into_iter().map(|rows| ScheduleEntry { rows, ..Default::default() }).collect();
+        Schedule { entries, ..Default::default() }
+    }
+
+    // Пересечение операций одного исполнителя из разных записей; смежные операции
+    // и операции другого исполнителя конфликтом не считаются
+    #[test]
+    fn overlap_detected() {
+        let schedule = schedule(vec![
+            vec![row("Резка", "Иванов", "08:00:00", "10:00:00"), row("Сварка", "Петров", "08:00:00", "12:00:00")],
+            vec![row("Правка", "Иванов", "09:30:00", "11:00:00"), row("Сборка", "Иванов", "11:00:00", "12:00:00")],
+        ]);
+        let conflicts = find(&schedule);
+        assert_eq!(conflicts.len(), 1);
+        let conflict = &conflicts[0];
+        assert_eq!(conflict.kind, ConflictKind::DoubleBooked);
+        assert_eq!(conflict.worker, "Иванов");
+        assert_eq!((conflict.start.as_str(), conflict.end.as_str()), ("05.01.2026 09:30", "05.01.2026 10:00"));
+        assert_eq!(conflict.minutes, 30);
+        assert!(conflict.cross_entry);
+        assert_eq!((conflict.first.operation.as_str(), conflict.second.operation.as_str()), ("Резка", "Правка"));
+    }
+}
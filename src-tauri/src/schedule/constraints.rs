@@ -0,0 +1,297 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Ограничения расписания, которые пользователь задаёт сам: исполнитель не работает
+// по понедельникам, операцию выполняют только определённые исполнители (у кого есть
+// станок или допуск - аналог специализированного кабинета), не больше N операций в
+// день, операция не ставится первой в дне. Жёсткое ограничение нарушать нельзя,
+// мягкое - нежелательно: построение (solver.rs) обходит его, если это не задерживает
+// работы больше чем на вес ограничения в часах.
+//
+// Одна и та же проверка (Rule::violated_by) используется при построении и при поиске
+// конфликтов в готовом расписании. Список ограничений хранится в папке настроек
+// (constraints.json).
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use super::conflicts::{conflict, side, Conflict, ConflictKind, ConflictSide};
+use super::format_time;
+use crate::model::Schedule;
+use crate::paths;
+
+const CONSTRAINTS_FILE: &str = "constraints.json";
+
+const MAX_CONSTRAINTS: usize = 500;
+
+const WEEKDAYS: [&str; 7] = ["пн", "вт", "ср", "чт", "пт", "сб", "вс"];
+
+fn default_hard() -> bool {
+    true
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+/// Правило ограничения
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", rename_all_fields = "camelCase")]
+pub enum Rule {
+    /// Исполнитель не работает в эти дни недели (1 - понедельник) и часы ЧЧ:ММ;
+    /// без дней - каждый день, без часов - весь день
+    Unavailable {
+        worker: String,
+        #[serde(default)]
+        weekdays: Vec<u8>,
+        from: Option<String>,
+        to: Option<String>,
+    },
+    /// Операцию выполняют только эти исполнители
+    RequiredWorkers { operation: String, workers: Vec<String> },
+    /// Не больше count операций в день; пустой worker - у каждого исполнителя
+    MaxPerDay {
+        #[serde(default)]
+        worker: String,
+        count: u32,
+    },
+    /// Операция не ставится первой в дне исполнителя
+    NotFirst { operation: String },
+}
+
+/// Ограничение
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Constraint {
+    #[serde(flatten)]
+    pub rule: Rule,
+    #[serde(default = "default_hard")]
+    pub hard: bool,
+    /// Для мягкого: на сколько часов построение готово задержать работы, чтобы его выполнить
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    /// Пояснение пользователя
+    #[serde(default)]
+    pub note: String,
+}
+
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(value.trim(), "%H:%M:%S"))
+        .ok()
+}
+
+fn same_name(a: &str, b: &str) -> bool {
+    a.trim().to_lowercase() == b.trim().to_lowercase()
+}
+
+impl Rule {
+    /// Время, когда исполнитель недоступен в этот день
+    pub fn unavailable_on(&self, worker: &str, date: NaiveDate) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        let Rule::Unavailable { worker: own, weekdays, from, to } = self else {
+            return None;
+        };
+        let weekday = date.weekday().number_from_monday() as u8;
+        if own.trim() != worker.trim() || !(weekdays.is_empty() || weekdays.contains(&weekday)) {
+            return None;
+        }
+        let start = from.as_deref().and_then(parse_time).map_or(date.and_time(NaiveTime::MIN), |t| date.and_time(t));
+        let end = to
+            .as_deref()
+            .and_then(parse_time)
+            .map_or((date + Duration::days(1)).and_time(NaiveTime::MIN), |t| date.and_time(t));
+        Some((start, end))
+    }
+
+    /// Нарушает ли операция name исполнителя worker с start по end правило;
+    /// day - начала остальных операций исполнителя в тот же день
+    pub fn violated_by(
+        &self,
+        worker: &str,
+        name: &str,
+        (start, end): (NaiveDateTime, NaiveDateTime),
+        day: &[NaiveDateTime],
+    ) -> bool {
+        match self {
+            // Многодневная операция проверяется по каждому дню, а не только по первому и последнему
+            Rule::Unavailable { .. } => start
+                .date()
+                .iter_days()
+                .take_while(|date| *date <= end.date())
+                .filter_map(|date| self.unavailable_on(worker, date))
+                .any(|(from, to)| start < to && from < end),
+            Rule::RequiredWorkers { operation, workers } => {
+                same_name(operation, name) && !workers.iter().any(|w| w.trim() == worker.trim())
+            }
+            Rule::MaxPerDay { worker: own, count } => {
+                (own.trim().is_empty() || own.trim() == worker.trim()) && day.len() >= *count as usize
+            }
+            Rule::NotFirst { operation } => same_name(operation, name) && !day.iter().any(|&s| s < start),
+        }
+    }
+
+    /// Описание для сообщений
+    pub fn describe(&self) -> String {
+        match self {
+            Rule::Unavailable { worker, weekdays, from, to } => {
+                let days = if weekdays.is_empty() {
+                    "каждый день".to_string()
+                } else {
+                    let names: Vec<&str> =
+                        weekdays.iter().filter_map(|&d| WEEKDAYS.get(d as usize - 1)).copied().collect();
+                    names.join(", ")
+                };
+                match (from, to) {
+                    (None, None) => format!("{} не работает: {}", worker, days),
+                    _ => format!(
+                        "{} не работает: {} с {} до {}",
+                        worker,
+                        days,
+                        from.as_deref().unwrap_or("00:00"),
+                        to.as_deref().unwrap_or("24:00")
+                    ),
+                }
+            }
+            Rule::RequiredWorkers { operation, workers } => {
+                format!("«{}» выполняют только: {}", operation, workers.join(", "))
+            }
+            Rule::MaxPerDay { worker, count } if worker.trim().is_empty() => {
+                format!("Не больше {} операций в день у исполнителя", count)
+            }
+            Rule::MaxPerDay { worker, count } => format!("{}: не больше {} операций в день", worker, count),
+            Rule::NotFirst { operation } => format!("«{}» не ставится первой в дне", operation),
+        }
+    }
+}
+
+impl Constraint {
+    fn check(&self, index: usize) -> Result<(), String> {
+        let prefix = format!("Ограничение {}", index + 1);
+        match &self.rule {
+            Rule::Unavailable { worker, weekdays, from, to } => {
+                if worker.trim().is_empty() {
+                    return Err(format!("{}: не указан исполнитель", prefix));
+                }
+                if weekdays.iter().any(|d| !(1..=7).contains(d)) {
+                    return Err(format!("{}: дни недели - числа от 1 (понедельник) до 7", prefix));
+                }
+                if let Some(t) = [from, to].into_iter().flatten().find(|t| parse_time(t).is_none()) {
+                    return Err(format!("{}: время «{}» не в формате ЧЧ:ММ", prefix, t));
+                }
+                let (from, to) = (from.as_deref().and_then(parse_time), to.as_deref().and_then(parse_time));
+                if let (Some(from), Some(to)) = (from, to) {
+                    if to <= from {
+                        return Err(format!("{}: окончание раньше начала", prefix));
+                    }
+                }
+            }
+            Rule::RequiredWorkers { operation, workers } => {
+                if operation.trim().is_empty() || workers.iter().all(|w| w.trim().is_empty()) {
+                    return Err(format!("{}: нужны операция и хотя бы один исполнитель", prefix));
+                }
+            }
+            Rule::MaxPerDay { count, .. } => {
+                if *count == 0 {
+                    return Err(format!("{}: число операций в день должно быть больше нуля", prefix));
+                }
+            }
+            Rule::NotFirst { operation } => {
+                if operation.trim().is_empty() {
+                    return Err(format!("{}: не указана операция", prefix));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Проверяет список ограничений
+pub fn check(constraints: &[Constraint]) -> Result<(), String> {
+    if constraints.len() > MAX_CONSTRAINTS {
+        return Err(format!("Слишком много ограничений (не больше {})", MAX_CONSTRAINTS));
+    }
+    constraints.iter().enumerate().try_for_each(|(i, c)| c.check(i))
+}
+
+/// Сохранённые ограничения; без файла или при ошибке чтения - пустой список
+pub fn load() -> Vec<Constraint> {
+    paths::app_config_dir()
+        .and_then(|dir| std::fs::read_to_string(dir.join(CONSTRAINTS_FILE)).ok())
+        .and_then(|raw| serde_json::from_str::<Vec<Constraint>>(&raw).ok())
+        .filter(|list| check(list).is_ok())
+        .unwrap_or_default()
+}
+
+/// Заменяет сохранённые ограничения
+pub fn save(constraints: &[Constraint]) -> Result<(), String> {
+    check(constraints)?;
+    let dir = paths::app_config_dir().ok_or("Не удалось определить папку настроек")?;
+    std::fs::create_dir_all(&dir).map_err(|e| paths::io_error_message("Ошибка создания папки настроек", &e))?;
+    let content = serde_json::to_string_pretty(constraints)
+        .map_err(|e| format!("Ошибка сохранения ограничений: {}", e))?;
+    std::fs::write(dir.join(CONSTRAINTS_FILE), content)
+        .map_err(|e| paths::io_error_message("Ошибка сохранения ограничений", &e))
+}
+
+/// Нарушения ограничений в расписании. Превышение числа операций в день
+/// показывается один раз, на первой лишней операции
+pub fn violations(schedule: &Schedule, constraints: &[Constraint]) -> Vec<Conflict> {
+    let mut slots = super::slots(schedule);
+    slots.sort_by(|a, b| a.worker.cmp(b.worker).then(a.start.cmp(&b.start)));
+    let mut conflicts = Vec::new();
+
+    for (i, slot) in slots.iter().enumerate() {
+        let date = slot.start.date();
+        let day: Vec<NaiveDateTime> = slots
+            .iter()
+            .enumerate()
+            .filter(|&(j, other)| j != i && other.worker == slot.worker && other.start.date() == date)
+            .map(|(_, other)| other.start)
+            .collect();
+        for (c, constraint) in constraints.iter().enumerate() {
+            if !constraint.rule.violated_by(slot.worker, &slot.operation.name, (slot.start, slot.end), &day) {
+                continue;
+            }
+            let earlier = day.iter().filter(|&&s| s < slot.start).count();
+            let message = match &constraint.rule {
+                Rule::MaxPerDay { count, .. } if earlier != *count as usize => continue,
+                Rule::MaxPerDay { count, .. } => format!(
+                    "{}: {} операций {} при пределе {}",
+                    slot.worker,
+                    day.len() + 1,
+                    date.format("%d.%m.%Y"),
+                    count
+                ),
+                rule => format!(
+                    "{}: «{}» ({}) нарушает ограничение «{}»",
+                    slot.worker,
+                    slot.operation.name,
+                    format_time(slot.start),
+                    rule.describe()
+                ),
+            };
+            let (start, end) = match &constraint.rule {
+                Rule::Unavailable { .. } => [slot.start.date(), slot.end.date()]
+                    .into_iter()
+                    .filter_map(|d| constraint.rule.unavailable_on(slot.worker, d))
+                    .find(|&(from, to)| slot.start < to && from < slot.end)
+                    .map_or((slot.start, slot.end), |(from, to)| (slot.start.max(from), slot.end.min(to))),
+                _ => (slot.start, slot.end),
+            };
+            let rule = ConflictSide {
+                entry: None,
+                row: None,
+                blocked: None,
+                constraint: Some(c),
+                card: match constraint.note.trim() {
+                    "" => constraint.rule.describe(),
+                    note => note.to_string(),
+                },
+                operation: String::new(),
+            };
+            let kind = if constraint.hard { ConflictKind::Constraint } else { ConflictKind::Preference };
+            conflicts.push(conflict(kind, slot.worker, (start, end), side(slot), rule, message));
+        }
+    }
+    conflicts
+}
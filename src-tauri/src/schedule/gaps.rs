@@ -0,0 +1,203 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Окна: простои внутри рабочего дня между операциями исполнителя и перерывы между
+// операциями одной техкарты (изделие ждёт следующей операции - аналог окон у класса).
+// Считаются только перерывы внутри дня: время до первой и после последней операции
+// окном не считается. Обед по желанию вычитается из окон.
+
+use std::collections::BTreeMap;
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use super::{format_time, Slot};
+use crate::model::Schedule;
+
+// Столько худших дней попадает в отчёт
+const MAX_WORST: usize = 10;
+
+/// Параметры подсчёта окон
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GapOptions {
+    /// Перерывы короче этого окном не считаются, минуты
+    pub min_minutes: i64,
+    /// Обед ЧЧ:ММ, который не считается окном
+    pub lunch_start: Option<String>,
+    pub lunch_end: Option<String>,
+}
+
+impl Default for GapOptions {
+    fn default() -> Self {
+        GapOptions { min_minutes: 1, lunch_start: None, lunch_end: None }
+    }
+}
+
+/// Окно
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Gap {
+    /// дд.мм.гггг ЧЧ:ММ
+    pub start: String,
+    pub end: String,
+    pub minutes: i64,
+}
+
+/// Окна одного дня
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DayGaps {
+    /// дд.мм.гггг
+    pub date: String,
+    pub minutes: i64,
+    pub gaps: Vec<Gap>,
+}
+
+/// Окна исполнителя или техкарты
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GapSummary {
+    pub name: String,
+    pub total_minutes: i64,
+    pub count: usize,
+    pub longest_minutes: i64,
+    pub days: Vec<DayGaps>,
+}
+
+/// Вид того, у кого окно
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GapOwner {
+    Worker,
+    Card,
+}
+
+/// День с наибольшими окнами
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorstDay {
+    pub owner: GapOwner,
+    pub name: String,
+    pub date: String,
+    pub minutes: i64,
+}
+
+/// Отчёт об окнах; списки - от больших окон к меньшим
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GapReport {
+    pub workers: Vec<GapSummary>,
+    pub cards: Vec<GapSummary>,
+    pub worker_minutes: i64,
+    pub card_minutes: i64,
+    pub worst: Vec<WorstDay>,
+}
+
+fn parse_time(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(value.trim(), "%H:%M:%S"))
+        .map_err(|_| format!("Время обеда «{}» не в формате ЧЧ:ММ", value))
+}
+
+// Длина перерыва без обеда
+fn idle_minutes(start: NaiveDateTime, end: NaiveDateTime, lunch: Option<(NaiveTime, NaiveTime)>) -> i64 {
+    let total = (end - start).num_minutes();
+    let Some((from, to)) = lunch else {
+        return total;
+    };
+    let date = start.date();
+    let (from, to) = (date.and_time(from), date.and_time(to));
+    let overlap = (end.min(to) - start.max(from)).num_minutes().max(0);
+    total - overlap
+}
+
+fn summary(name: &str, slots: &[&Slot], options: &GapOptions, lunch: Option<(NaiveTime, NaiveTime)>) -> GapSummary {
+    let mut by_day: BTreeMap<NaiveDate, Vec<&Slot>> = BTreeMap::new();
+    for slot in slots {
+        by_day.entry(slot.start.date()).or_default().push(slot);
+    }
+    let mut days = Vec::new();
+    for (date, mut list) in by_day {
+        list.sort_by_key(|s| s.start);
+        let mut gaps = Vec::new();
+        let mut busy_until = list[0].end;
+        for slot in &list[1..] {
+            if slot.start > busy_until {
+                let minutes = idle_minutes(busy_until, slot.start, lunch);
+                if minutes >= options.min_minutes {
+                    gaps.push(Gap { start: format_time(busy_until), end: format_time(slot.start), minutes });
+                }
+            }
+            busy_until = busy_until.max(slot.end);
+        }
+        if !gaps.is_empty() {
+            let minutes = gaps.iter().map(|g| g.minutes).sum();
+            days.push(DayGaps { date: date.format("%d.%m.%Y").to_string(), minutes, gaps });
+        }
+    }
+    let all = days.iter().flat_map(|d| &d.gaps);
+    GapSummary {
+        name: name.to_string(),
+        total_minutes: days.iter().map(|d| d.minutes).sum(),
+        count: all.clone().count(),
+        longest_minutes: all.map(|g| g.minutes).max().unwrap_or(0),
+        days,
+    }
+}
+
+fn summaries<'a>(
+    slots: &[Slot<'a>],
+    key: impl Fn(&Slot<'a>) -> &'a str,
+    options: &GapOptions,
+    lunch: Option<(NaiveTime, NaiveTime)>,
+) -> Vec<GapSummary> {
+    let mut groups: BTreeMap<&str, Vec<&Slot>> = BTreeMap::new();
+    for slot in slots {
+        groups.entry(key(slot)).or_default().push(slot);
+    }
+    let mut list: Vec<GapSummary> = groups.iter().map(|(name, s)| summary(name, s, options, lunch)).collect();
+    list.sort_by(|a, b| b.total_minutes.cmp(&a.total_minutes).then(a.name.cmp(&b.name)));
+    list
+}
+
+/// Окна исполнителей и техкарт по дням
+pub fn analyze(schedule: &Schedule, options: &GapOptions) -> Result<GapReport, String> {
+    let lunch = match (&options.lunch_start, &options.lunch_end) {
+        (Some(from), Some(to)) => {
+            let (from, to) = (parse_time(from)?, parse_time(to)?);
+            if to <= from {
+                return Err("Конец обеда раньше начала".into());
+            }
+            Some((from, to))
+        }
+        _ => None,
+    };
+    let slots = super::slots(schedule);
+    let workers = summaries(&slots, |s| s.worker, options, lunch);
+    let cards = summaries(&slots, |s| s.card, options, lunch);
+
+    let mut worst: Vec<WorstDay> = [(GapOwner::Worker, &workers), (GapOwner::Card, &cards)]
+        .into_iter()
+        .flat_map(|(owner, list)| {
+            list.iter().flat_map(move |s| {
+                s.days.iter().map(move |d| WorstDay {
+                    owner,
+                    name: s.name.clone(),
+                    date: d.date.clone(),
+                    minutes: d.minutes,
+                })
+            })
+        })
+        .collect();
+    worst.sort_by_key(|d| std::cmp::Reverse(d.minutes));
+    worst.truncate(MAX_WORST);
+
+    Ok(GapReport {
+        worker_minutes: workers.iter().map(|s| s.total_minutes).sum(),
+        card_minutes: cards.iter().map(|s| s.total_minutes).sum(),
+        workers,
+        cards,
+        worst,
+    })
+}
@@ -0,0 +1,64 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Анализ и построение расписания на стороне Rust: общие для команд и экспорта
+// проверки и поиск, которые во фронтенде для больших расписаний работают слишком медленно.
+//
+// Школьные понятия переносятся на модель так: учитель - исполнитель, урок - строка
+// расчёта (операция), смена - запись истории. Классов в модели нет; кабинет - рабочее
+// место (utilization::Workplace), которое задаётся списком выполняемых на нём операций.
+
+pub mod conflicts;
+pub mod constraints;
+pub mod gaps;
+pub mod preview;
+pub mod solver;
+pub mod substitution;
+pub mod swaps;
+pub mod utilization;
+pub mod workload;
+
+use chrono::NaiveDateTime;
+
+use crate::model::{OperationRow, Schedule};
+
+/// Операция с разобранным временем и местом в расписании
+#[derive(Debug, Clone)]
+pub struct Slot<'a> {
+    pub entry: usize,
+    pub row: usize,
+    pub card: &'a str,
+    pub operation: &'a OperationRow,
+    pub worker: &'a str,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+}
+
+/// Операции с исполнителем и корректным временем; остальные пропускаются
+pub fn slots(schedule: &Schedule) -> Vec<Slot<'_>> {
+    timed_slots(schedule).into_iter().filter(|s| !s.worker.is_empty()).collect()
+}
+
+/// Операции с корректным временем, в том числе без исполнителя (worker пустой):
+/// место и время операция занимает, даже если её ещё никому не назначили
+pub fn timed_slots(schedule: &Schedule) -> Vec<Slot<'_>> {
+    let mut slots = Vec::new();
+    for (e, entry) in schedule.entries.iter().enumerate() {
+        for (r, operation) in entry.rows.iter().enumerate() {
+            let (Some(start), Some(end)) = (operation.start(), operation.end()) else {
+                continue;
+            };
+            if end < start {
+                continue;
+            }
+            let worker = operation.worker.trim();
+            slots.push(Slot { entry: e, row: r, card: entry.card_name(), operation, worker, start, end });
+        }
+    }
+    slots
+}
+
+/// Время для сообщений: дд.мм.гггг ЧЧ:ММ
+pub fn format_time(time: NaiveDateTime) -> String {
+    time.format("%d.%m.%Y %H:%M").to_string()
+}
@@ -0,0 +1,189 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Пробное изменение: перенос, обмен или удаление операции применяется к копии
+// расписания, и фронтенд видит последствия до того, как правка попадёт в историю
+// (edits.rs): какие конфликты появятся и исчезнут, как изменятся окна и
+// равномерность нагрузки.
+//
+// Неравномерность нагрузки - сумма по исполнителям разницы между самым загруженным и
+// самым свободным днём; учитываются дни, в которые в расписании есть хоть одна
+// операция.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use super::conflicts::{self, Conflict, ConflictKind};
+use super::constraints::Constraint;
+use super::gaps::{self, GapOptions};
+use crate::edits::{self, Edit};
+use crate::model::Schedule;
+
+const DATE_FORMAT: &str = "%d.%m.%Y";
+const TIME_FORMAT: &str = "%H:%M:%S";
+
+/// Строка расписания
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RowRef {
+    pub entry: usize,
+    pub row: usize,
+}
+
+/// Пробное изменение, type - move, swap или delete
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", rename_all_fields = "camelCase")]
+pub enum Change {
+    /// Перенести операцию на другое время и, если указан, к другому исполнителю
+    Move {
+        entry: usize,
+        row: usize,
+        worker: Option<String>,
+        start_date: String,
+        start_time: String,
+        end_date: String,
+        end_time: String,
+    },
+    /// Обменять операции временем; исполнители и длительности остаются прежними
+    Swap { first: RowRef, second: RowRef },
+    Delete { entry: usize, row: usize },
+}
+
+/// Показатели расписания
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Metrics {
+    pub conflicts: usize,
+    pub worker_gap_minutes: i64,
+    pub card_gap_minutes: i64,
+    pub imbalance_minutes: i64,
+}
+
+/// Результат пробного изменения
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Preview {
+    /// Расписание после изменения
+    pub schedule: Schedule,
+    /// Конфликты, которых до изменения не было
+    pub added: Vec<Conflict>,
+    /// Конфликты, которые изменение устраняет
+    pub resolved: Vec<Conflict>,
+    pub before: Metrics,
+    pub after: Metrics,
+}
+
+impl Change {
+    /// Правки истории, которыми выполняется изменение
+    pub fn edits(&self, schedule: &Schedule) -> Result<Vec<Edit>, String> {
+        match self {
+            Change::Move { entry, row, worker, start_date, start_time, end_date, end_time } => Ok(vec![Edit::Move {
+                entry: *entry,
+                row: *row,
+                worker: worker.clone(),
+                start_date: start_date.clone(),
+                start_time: start_time.clone(),
+                end_date: end_date.clone(),
+                end_time: end_time.clone(),
+            }]),
+            Change::Swap { first, second } => {
+                if first == second {
+                    return Err("Операцию нельзя обменять с ней самой".into());
+                }
+                let times = |at: &RowRef| {
+                    let row = schedule
+                        .entries
+                        .get(at.entry)
+                        .and_then(|e| e.rows.get(at.row))
+                        .ok_or_else(|| format!("Нет операции {} в записи {}", at.row + 1, at.entry + 1))?;
+                    match (row.start(), row.end()) {
+                        (Some(start), Some(end)) => Ok((start, end - start)),
+                        _ => Err(format!("У операции «{}» не разобрано время", row.name)),
+                    }
+                };
+                let ((a_start, a_length), (b_start, b_length)) = (times(first)?, times(second)?);
+                let moved = |at: &RowRef, start: NaiveDateTime, length: Duration| {
+                    let end = start + length;
+                    Edit::Move {
+                        entry: at.entry,
+                        row: at.row,
+                        worker: None,
+                        start_date: start.format(DATE_FORMAT).to_string(),
+                        start_time: start.format(TIME_FORMAT).to_string(),
+                        end_date: end.format(DATE_FORMAT).to_string(),
+                        end_time: end.format(TIME_FORMAT).to_string(),
+                    }
+                };
+                Ok(vec![moved(first, b_start, a_length), moved(second, a_start, b_length)])
+            }
+            Change::Delete { entry, row } => Ok(vec![Edit::Delete { entry: *entry, row: *row }]),
+        }
+    }
+
+    /// Применяет изменение к копии расписания
+    pub fn apply(&self, schedule: &Schedule) -> Result<Schedule, String> {
+        let mut changed = schedule.clone();
+        for edit in self.edits(schedule)? {
+            edits::apply(&mut changed, &edit)?;
+        }
+        Ok(changed)
+    }
+}
+
+/// Неравномерность нагрузки, минуты
+fn imbalance(schedule: &Schedule) -> i64 {
+    let slots = super::slots(schedule);
+    let days: BTreeSet<NaiveDate> = slots.iter().map(|s| s.start.date()).collect();
+    let mut loads: BTreeMap<&str, BTreeMap<NaiveDate, f64>> = BTreeMap::new();
+    for slot in &slots {
+        let day = loads.entry(slot.worker).or_default().entry(slot.start.date()).or_default();
+        *day += slot.operation.duration_minutes();
+    }
+    loads
+        .values()
+        .map(|by_day| {
+            let day_loads = days.iter().map(|d| by_day.get(d).copied().unwrap_or(0.0));
+            let max = day_loads.clone().fold(0.0, f64::max);
+            let min = day_loads.fold(f64::INFINITY, f64::min);
+            (max - min).round() as i64
+        })
+        .sum()
+}
+
+/// Показатели расписания; conflicts - его конфликты
+pub fn metrics(schedule: &Schedule, conflicts: &[Conflict]) -> Metrics {
+    let gaps = gaps::analyze(schedule, &GapOptions::default()).ok();
+    Metrics {
+        conflicts: conflicts.len(),
+        worker_gap_minutes: gaps.as_ref().map_or(0, |g| g.worker_minutes),
+        card_gap_minutes: gaps.as_ref().map_or(0, |g| g.card_minutes),
+        imbalance_minutes: imbalance(schedule),
+    }
+}
+
+// Конфликты сравниваются по виду и тексту: номера строк после удаления сдвигаются
+pub(super) fn key(conflict: &Conflict) -> (ConflictKind, &str) {
+    (conflict.kind, conflict.message.as_str())
+}
+
+/// Применяет изменение к копии расписания и сравнивает до и после
+pub fn preview(schedule: &Schedule, change: &Change, constraints: &[Constraint]) -> Result<Preview, String> {
+    let changed = change.apply(schedule)?;
+    let before = conflicts::find_with(schedule, constraints);
+    let after = conflicts::find_with(&changed, constraints);
+
+    let before_keys: HashSet<_> = before.iter().map(key).collect();
+    let after_keys: HashSet<_> = after.iter().map(key).collect();
+    let added = after.iter().filter(|c| !before_keys.contains(&key(c))).cloned().collect();
+    let resolved = before.iter().filter(|c| !after_keys.contains(&key(c))).cloned().collect();
+
+    Ok(Preview {
+        before: metrics(schedule, &before),
+        after: metrics(&changed, &after),
+        schedule: changed,
+        added,
+        resolved,
+    })
+}
@@ -0,0 +1,675 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Автоматическое построение расписания. На входе - операции с длительностью, кто
+// может их выполнять и порядок (операция начинается после окончания указанных),
+// рабочий календарь и занятое время; на выходе - расписание с назначенными
+// исполнителями и временем.
+//
+// Поиск эвристический. Расписание строится жадно: из готовых операций берётся
+// операция с наибольшим приоритетом и ставится на самое раннее время к тому
+// исполнителю, у которого она раньше закончится. Первый проход идёт по длине
+// цепочки до конца работ, следующие - со случайными возмущениями приоритетов;
+// остаётся вариант, в котором размещено больше операций и все работы кончаются раньше.
+//
+// Ограничения пользователя (constraints.rs) проверяются при выборе времени: жёсткое
+// не нарушается никогда, мягкое нарушается, только если иначе работы кончатся позже
+// больше чем на вес ограничения в часах.
+//
+// Одна операция - один исполнитель; операцию нескольких исполнителей задают
+// несколькими операциями.
+
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use super::conflicts::{Conflict, ConflictKind};
+use super::constraints::{self, Constraint};
+use crate::model::{BlockedSlot, OperationRow, Schedule, ScheduleEntry};
+
+const DATE_FORMAT: &str = "%d.%m.%Y";
+const TIME_FORMAT: &str = "%H:%M:%S";
+
+const DEFAULT_HORIZON_DAYS: u32 = 60;
+const MAX_HORIZON_DAYS: u32 = 366;
+const DEFAULT_ITERATIONS: u32 = 50;
+const MAX_ITERATIONS: u32 = 1000;
+const MAX_TASKS: usize = 5000;
+
+// Насколько случайно меняется приоритет в проходах после первого
+const JITTER: f64 = 0.3;
+
+const DEFAULT_ENTRY_TITLE: &str = "Автоматическое расписание";
+
+/// Операция, которую нужно поставить в расписание
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Task {
+    /// Техкарта: операции одной техкарты попадают в одну запись истории
+    #[serde(default)]
+    pub card: String,
+    /// Номер операции в техкарте; по умолчанию - порядковый
+    #[serde(default)]
+    pub number: String,
+    pub name: String,
+    pub minutes: u32,
+    /// Кто может выполнять; пустой список - любой исполнитель
+    #[serde(default)]
+    pub workers: Vec<String>,
+    /// Индексы операций, после окончания которых можно начинать
+    #[serde(default)]
+    pub after: Vec<usize>,
+}
+
+/// Рабочий день: ЧЧ:ММ
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WorkDay {
+    pub start: String,
+    pub end: String,
+    /// Обед; без него день не прерывается
+    pub lunch_start: Option<String>,
+    pub lunch_end: Option<String>,
+}
+
+impl Default for WorkDay {
+    fn default() -> Self {
+        WorkDay {
+            start: "08:00".into(),
+            end: "17:00".into(),
+            lunch_start: Some("12:00".into()),
+            lunch_end: Some("13:00".into()),
+        }
+    }
+}
+
+/// Задача построения
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SolveRequest {
+    pub tasks: Vec<Task>,
+    pub workers: Vec<String>,
+    /// Первый день: дд.мм.гггг
+    pub start_date: String,
+    pub horizon_days: Option<u32>,
+    /// Рабочие дни недели: 1 - понедельник, 7 - воскресенье; по умолчанию пн-пт
+    #[serde(default)]
+    pub weekdays: Vec<u8>,
+    #[serde(default)]
+    pub day: WorkDay,
+    /// Занятое время: совещания, обучение, отпуск
+    #[serde(default)]
+    pub blocked: Vec<BlockedSlot>,
+    /// Ограничения; None - сохранённые
+    pub constraints: Option<Vec<Constraint>>,
+    pub iterations: Option<u32>,
+}
+
+/// Операция, которую не удалось поставить
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Unplaced {
+    pub task: usize,
+    pub name: String,
+    pub reason: String,
+}
+
+/// Результат построения
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SolveResult {
+    pub schedule: Schedule,
+    pub placed: usize,
+    pub unplaced: Vec<Unplaced>,
+    /// Окончание последней операции: дд.мм.гггг ЧЧ:ММ
+    pub finish: Option<String>,
+    /// Нарушенные мягкие ограничения
+    pub violations: Vec<Conflict>,
+    pub iterations: u32,
+}
+
+// Рабочий день календаря
+#[derive(Debug, Clone, Copy)]
+struct Day {
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+    lunch: Option<(NaiveDateTime, NaiveDateTime)>,
+}
+
+impl Day {
+    /// Начало не раньше t и окончание операции длиной minutes; None - не помещается в день
+    fn layout(&self, t: NaiveDateTime, minutes: i64) -> Option<(NaiveDateTime, NaiveDateTime, bool)> {
+        let mut start = t.max(self.start);
+        let mut crossed = false;
+        let mut end = start + Duration::minutes(minutes);
+        if let Some((from, to)) = self.lunch {
+            if start >= from && start < to {
+                start = to;
+                end = start + Duration::minutes(minutes);
+            } else if start < from && end > from {
+                end += to - from;
+                crossed = true;
+            }
+        }
+        (end <= self.end).then_some((start, end, crossed))
+    }
+
+    fn capacity(&self) -> i64 {
+        let lunch = self.lunch.map_or(0, |(from, to)| (to - from).num_minutes());
+        (self.end - self.start).num_minutes() - lunch
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Placement {
+    worker: usize,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+    crossed_lunch: bool,
+}
+
+// Один вариант расписания
+struct Plan {
+    placements: Vec<Option<Placement>>,
+    reasons: Vec<Option<String>>,
+    // Сумма весов нарушенных мягких ограничений
+    penalty: u32,
+}
+
+impl Plan {
+    // Больше размещённых, затем меньше нарушений, затем раньше конец всех работ,
+    // затем меньше сумма окончаний
+    fn score(&self) -> (usize, u32, Option<NaiveDateTime>, i64) {
+        let placed = self.placements.iter().flatten();
+        let unplaced = self.placements.iter().filter(|p| p.is_none()).count();
+        let finish = placed.clone().map(|p| p.end).max();
+        let total = placed.map(|p| p.end.and_utc().timestamp() / 60).sum();
+        (unplaced, self.penalty, finish, total)
+    }
+}
+
+fn parse_time(value: &str, field: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(value.trim(), TIME_FORMAT))
+        .map_err(|_| format!("{}: время «{}» не в формате ЧЧ:ММ", field, value))
+}
+
+fn calendar(request: &SolveRequest) -> Result<Vec<Day>, String> {
+    let first = NaiveDate::parse_from_str(request.start_date.trim(), DATE_FORMAT)
+        .map_err(|_| format!("Дата начала «{}» не в формате дд.мм.гггг", request.start_date))?;
+    let horizon = request.horizon_days.unwrap_or(DEFAULT_HORIZON_DAYS);
+    if horizon == 0 || horizon > MAX_HORIZON_DAYS {
+        return Err(format!("Срок планирования - от 1 до {} дней", MAX_HORIZON_DAYS));
+    }
+    let weekdays: Vec<u8> = if request.weekdays.is_empty() { vec![1, 2, 3, 4, 5] } else { request.weekdays.clone() };
+    if weekdays.iter().any(|d| !(1..=7).contains(d)) {
+        return Err("Дни недели задаются числами от 1 (понедельник) до 7 (воскресенье)".into());
+    }
+
+    let start = parse_time(&request.day.start, "Начало дня")?;
+    let end = parse_time(&request.day.end, "Конец дня")?;
+    if end <= start {
+        return Err("Конец рабочего дня раньше начала".into());
+    }
+    let lunch = match (&request.day.lunch_start, &request.day.lunch_end) {
+        (Some(from), Some(to)) => {
+            let (from, to) = (parse_time(from, "Начало обеда")?, parse_time(to, "Конец обеда")?);
+            if !(start < from && from < to && to < end) {
+                return Err("Обед должен быть внутри рабочего дня".into());
+            }
+            Some((from, to))
+        }
+        _ => None,
+    };
+
+    let days = (0..horizon as i64)
+        .map(|n| first + Duration::days(n))
+        .filter(|date| weekdays.contains(&(date.weekday().number_from_monday() as u8)))
+        .map(|date| Day {
+            start: date.and_time(start),
+            end: date.and_time(end),
+            lunch: lunch.map(|(from, to)| (date.and_time(from), date.and_time(to))),
+        })
+        .collect();
+    Ok(days)
+}
+
+/// Проверяет задачу: индексы в after, циклы, длительности
+fn check(request: &SolveRequest) -> Result<(), String> {
+    if request.tasks.is_empty() {
+        return Err("Нет операций для планирования".into());
+    }
+    if request.tasks.len() > MAX_TASKS {
+        return Err(format!("Слишком много операций (не больше {})", MAX_TASKS));
+    }
+    for (i, task) in request.tasks.iter().enumerate() {
+        if task.minutes == 0 {
+            return Err(format!("Операция {}: длительность должна быть больше нуля", i + 1));
+        }
+        if task.workers.is_empty() && request.workers.is_empty() {
+            return Err(format!("Операция {}: некому выполнять - список исполнителей пуст", i + 1));
+        }
+        if let Some(bad) = task.after.iter().find(|&&a| a >= request.tasks.len() || a == i) {
+            return Err(format!("Операция {}: неверная ссылка на предшествующую операцию {}", i + 1, bad + 1));
+        }
+    }
+    // Цикл в порядке операций: топологическая сортировка не доходит до конца
+    let mut indegree: Vec<usize> = request.tasks.iter().map(|t| t.after.len()).collect();
+    let mut ready: Vec<usize> = (0..request.tasks.len()).filter(|&i| indegree[i] == 0).collect();
+    let mut seen = 0;
+    while let Some(i) = ready.pop() {
+        seen += 1;
+        for (j, task) in request.tasks.iter().enumerate() {
+            for _ in task.after.iter().filter(|&&a| a == i) {
+                indegree[j] -= 1;
+                if indegree[j] == 0 {
+                    ready.push(j);
+                }
+            }
+        }
+    }
+    if seen < request.tasks.len() {
+        return Err("Порядок операций замкнут в цикл".into());
+    }
+    Ok(())
+}
+
+// Длина цепочки от начала операции до конца работ, в минутах
+fn priorities(tasks: &[Task]) -> Vec<f64> {
+    let mut tail: Vec<Option<f64>> = vec![None; tasks.len()];
+    fn visit(i: usize, tasks: &[Task], tail: &mut Vec<Option<f64>>) -> f64 {
+        if let Some(v) = tail[i] {
+            return v;
+        }
+        let next = (0..tasks.len())
+            .filter(|&j| tasks[j].after.contains(&i))
+            .map(|j| visit(j, tasks, tail))
+            .fold(0.0, f64::max);
+        let v = tasks[i].minutes as f64 + next;
+        tail[i] = Some(v);
+        v
+    }
+    (0..tasks.len()).map(|i| visit(i, tasks, &mut tail)).collect()
+}
+
+// xorshift64*: воспроизводимость не нужна, качества для возмущений хватает
+struct Rng(u64);
+
+impl Rng {
+    fn new() -> Self {
+        let mut seed = [0u8; 8];
+        let _ = getrandom::getrandom(&mut seed);
+        Rng(u64::from_le_bytes(seed) | 1)
+    }
+
+    fn next(&mut self) -> f64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+struct Solver<'a> {
+    request: &'a SolveRequest,
+    days: Vec<Day>,
+    workers: Vec<String>,
+    constraints: &'a [Constraint],
+    // Занятое каждым исполнителем время до начала построения: события и жёсткие ограничения
+    blocked: Vec<Vec<(NaiveDateTime, NaiveDateTime)>>,
+    // Время, которое исполнителю лучше оставить свободным (мягкие ограничения)
+    avoid: Vec<Vec<(NaiveDateTime, NaiveDateTime)>>,
+}
+
+impl<'a> Solver<'a> {
+    fn new(request: &'a SolveRequest) -> Result<Self, String> {
+        let days = calendar(request)?;
+        if days.is_empty() {
+            return Err("В сроке планирования нет рабочих дней".into());
+        }
+        let mut workers: Vec<String> = Vec::new();
+        for name in request.workers.iter().chain(request.tasks.iter().flat_map(|t| &t.workers)) {
+            let name = name.trim();
+            if !name.is_empty() && !workers.iter().any(|w| w == name) {
+                workers.push(name.to_string());
+            }
+        }
+        let constraints = request.constraints.as_deref().unwrap_or_default();
+        constraints::check(constraints)?;
+        let unavailable = |worker: &str, hard: bool| -> Vec<(NaiveDateTime, NaiveDateTime)> {
+            constraints
+                .iter()
+                .filter(|c| c.hard == hard)
+                .flat_map(|c| days.iter().filter_map(|day| c.rule.unavailable_on(worker, day.start.date())))
+                .collect()
+        };
+        let blocked = workers
+            .iter()
+            .map(|worker| {
+                let mut busy: Vec<(NaiveDateTime, NaiveDateTime)> = request
+                    .blocked
+                    .iter()
+                    .filter(|slot| slot.applies_to(worker))
+                    .filter_map(|slot| Some((slot.start()?, slot.end()?)))
+                    .filter(|(from, to)| from < to)
+                    .collect();
+                busy.extend(unavailable(worker, true));
+                busy
+            })
+            .collect();
+        let avoid = workers.iter().map(|worker| unavailable(worker, false)).collect();
+        Ok(Solver { request, days, workers, constraints, blocked, avoid })
+    }
+
+    // Сумма весов мягких ограничений, которые нарушает операция; day - начала
+    // остальных операций исполнителя в тот же день
+    fn penalty(&self, worker: usize, name: &str, time: (NaiveDateTime, NaiveDateTime), day: &[NaiveDateTime]) -> u32 {
+        self.constraints
+            .iter()
+            .filter(|c| !c.hard && c.rule.violated_by(&self.workers[worker], name, time, day))
+            .map(|c| c.weight)
+            .sum()
+    }
+
+    fn candidates(&self, task: &Task) -> Vec<usize> {
+        if task.workers.is_empty() {
+            return (0..self.workers.len())
+                .filter(|&w| self.request.workers.iter().any(|n| n.trim() == self.workers[w]))
+                .collect();
+        }
+        task.workers
+            .iter()
+            .filter_map(|name| self.workers.iter().position(|w| w == name.trim()))
+            .collect()
+    }
+
+    /// Самое раннее время у исполнителя не раньше not_before; strict - без нарушения
+    /// мягких ограничений. ops - уже поставленные операции исполнителя
+    #[allow(clippy::too_many_arguments)]
+    fn fit(
+        &self,
+        worker: usize,
+        name: &str,
+        busy: &[(NaiveDateTime, NaiveDateTime)],
+        ops: &[NaiveDateTime],
+        not_before: NaiveDateTime,
+        minutes: i64,
+        strict: bool,
+    ) -> Option<(NaiveDateTime, NaiveDateTime, bool)> {
+        for day in self.days.iter().filter(|d| d.end > not_before) {
+            let from = not_before.max(day.start);
+            let mut starts: Vec<NaiveDateTime> = std::iter::once(from)
+                .chain(busy.iter().map(|&(_, end)| end).filter(|&end| end > from && end < day.end))
+                .collect();
+            starts.sort();
+            for t in starts {
+                let Some((start, end, crossed)) = day.layout(t, minutes) else {
+                    continue;
+                };
+                if !busy.iter().all(|&(b_start, b_end)| end <= b_start || b_end <= start) {
+                    continue;
+                }
+                let day: Vec<NaiveDateTime> = ops.iter().copied().filter(|s| s.date() == start.date()).collect();
+                let violated = self
+                    .constraints
+                    .iter()
+                    .filter(|c| c.hard || strict)
+                    .any(|c| c.rule.violated_by(&self.workers[worker], name, (start, end), &day));
+                if !violated {
+                    return Some((start, end, crossed));
+                }
+            }
+        }
+        None
+    }
+
+    fn build(&self, priority: &[f64]) -> Plan {
+        let tasks = &self.request.tasks;
+        let mut placements: Vec<Option<Placement>> = vec![None; tasks.len()];
+        let mut reasons: Vec<Option<String>> = vec![None; tasks.len()];
+        let mut done = vec![false; tasks.len()];
+        let mut busy = self.blocked.clone();
+        let mut busy_strict: Vec<Vec<(NaiveDateTime, NaiveDateTime)>> =
+            busy.iter().zip(&self.avoid).map(|(b, a)| b.iter().chain(a).copied().collect()).collect();
+        let mut ops: Vec<Vec<NaiveDateTime>> = vec![Vec::new(); self.workers.len()];
+        let mut penalty = 0;
+        let mut load = vec![0i64; self.workers.len()];
+        let capacity = self.days.iter().map(Day::capacity).max().unwrap_or(0);
+
+        while let Some(i) = (0..tasks.len())
+            .filter(|&i| !done[i] && tasks[i].after.iter().all(|&a| done[a]))
+            .max_by(|&a, &b| priority[a].total_cmp(&priority[b]).then(b.cmp(&a)))
+        {
+            done[i] = true;
+            let task = &tasks[i];
+            let minutes = task.minutes as i64;
+            if task.after.iter().any(|&a| placements[a].is_none()) {
+                reasons[i] = Some("Не размещена предшествующая операция".into());
+                continue;
+            }
+            if minutes > capacity {
+                reasons[i] = Some("Операция длиннее рабочего дня".into());
+                continue;
+            }
+            let not_before = task
+                .after
+                .iter()
+                .filter_map(|&a| placements[a].map(|p| p.end))
+                .max()
+                .unwrap_or(self.days[0].start);
+
+            // У каждого исполнителя - лучшее время без нарушений и лучшее вообще; нарушение
+            // мягкого ограничения засчитывается как задержка на его вес в часах
+            let best = self
+                .candidates(task)
+                .into_iter()
+                .flat_map(|w| {
+                    let strict = self.fit(w, &task.name, &busy_strict[w], &ops[w], not_before, minutes, true);
+                    let relaxed = self.fit(w, &task.name, &busy[w], &ops[w], not_before, minutes, false);
+                    let strict = strict.map(|fit| (w, fit, 0));
+                    let relaxed = relaxed.map(|fit @ (start, end, _)| {
+                        let day: Vec<NaiveDateTime> =
+                            ops[w].iter().copied().filter(|s| s.date() == start.date()).collect();
+                        (w, fit, self.penalty(w, &task.name, (start, end), &day))
+                    });
+                    strict.into_iter().chain(relaxed)
+                })
+                .min_by_key(|&(w, (_, end, _), cost)| (end + Duration::hours(cost as i64), load[w]));
+            match best {
+                Some((worker, (start, end, crossed_lunch), cost)) => {
+                    busy[worker].push((start, end));
+                    busy_strict[worker].push((start, end));
+                    ops[worker].push(start);
+                    load[worker] += minutes;
+                    penalty += cost;
+                    placements[i] = Some(Placement { worker, start, end, crossed_lunch });
+                }
+                None if self.candidates(task).is_empty() => {
+                    reasons[i] = Some("Нет подходящего исполнителя".into());
+                }
+                None if !self.constraints.is_empty() => {
+                    reasons[i] = Some("Не хватает свободного времени в сроке планирования с учётом ограничений".into());
+                }
+                None => reasons[i] = Some("Не хватает свободного времени в сроке планирования".into()),
+            }
+        }
+        Plan { placements, reasons, penalty }
+    }
+
+    fn schedule(&self, plan: &Plan) -> Schedule {
+        let now = Local::now();
+        let stamp = format!("Сформировано: {}; {}", now.format(DATE_FORMAT), now.format(TIME_FORMAT));
+        // Записи в порядке первого появления техкарты
+        let mut order: Vec<String> = Vec::new();
+        let mut rows: BTreeMap<usize, Vec<(NaiveDateTime, OperationRow)>> = BTreeMap::new();
+        for (i, (task, placement)) in self.request.tasks.iter().zip(&plan.placements).enumerate() {
+            let Some(p) = placement else { continue };
+            let card = if task.card.trim().is_empty() { DEFAULT_ENTRY_TITLE } else { task.card.trim() };
+            let index = order.iter().position(|c| c == card).unwrap_or_else(|| {
+                order.push(card.to_string());
+                order.len() - 1
+            });
+            let number = match task.number.trim() {
+                "" => (i + 1).to_string(),
+                number => number.to_string(),
+            };
+            let row = OperationRow {
+                op_numeric: number.replace(',', ".").parse().ok(),
+                original_op_index: number.clone(),
+                op_idx: number,
+                name: task.name.clone(),
+                worker: self.workers[p.worker].clone(),
+                worker_index: Some(1),
+                dur_val: task.minutes as f64,
+                dur_text: task.minutes.to_string(),
+                unit: "min".into(),
+                start_date: p.start.format(DATE_FORMAT).to_string(),
+                start_time: p.start.format(TIME_FORMAT).to_string(),
+                end_date: p.end.format(DATE_FORMAT).to_string(),
+                end_time: p.end.format(TIME_FORMAT).to_string(),
+                crossed_lunch: p.crossed_lunch,
+                ..Default::default()
+            };
+            rows.entry(index).or_default().push((p.start, row));
+        }
+        let entries = order
+            .iter()
+            .enumerate()
+            .map(|(index, card)| {
+                let mut list = rows.remove(&index).unwrap_or_default();
+                list.sort_by_key(|(start, _)| *start);
+                ScheduleEntry {
+                    title: format!("{} | {}", card, stamp),
+                    rows: list.into_iter().map(|(_, row)| row).collect(),
+                    time_mode: "individual".into(),
+                    ..Default::default()
+                }
+            })
+            .collect();
+        Schedule { entries, blocked: self.request.blocked.clone() }
+    }
+}
+
+/// Строит расписание. progress получает номер прохода; ошибка из progress
+/// (отмена) останавливает поиск
+pub fn solve(
+    request: &SolveRequest,
+    progress: &mut dyn FnMut(usize, usize, &str) -> Result<(), String>,
+) -> Result<SolveResult, String> {
+    check(request)?;
+    let solver = Solver::new(request)?;
+    let iterations = request.iterations.unwrap_or(DEFAULT_ITERATIONS).clamp(1, MAX_ITERATIONS);
+    let base = priorities(&request.tasks);
+    let mut rng = Rng::new();
+
+    let mut best = solver.build(&base);
+    for n in 1..iterations {
+        progress(n as usize, iterations as usize, "Поиск расписания")?;
+        let jittered: Vec<f64> = base.iter().map(|p| p * (1.0 + JITTER * (rng.next() - 0.5))).collect();
+        let plan = solver.build(&jittered);
+        if plan.score() < best.score() {
+            best = plan;
+        }
+    }
+    progress(iterations as usize, iterations as usize, "Готово")?;
+
+    let unplaced: Vec<Unplaced> = best
+        .reasons
+        .iter()
+        .enumerate()
+        .filter_map(|(i, reason)| {
+            Some(Unplaced { task: i, name: request.tasks[i].name.clone(), reason: reason.clone()? })
+        })
+        .collect();
+    let finish = best.score().2.map(|t| t.format("%d.%m.%Y %H:%M").to_string());
+    let schedule = solver.schedule(&best);
+    // Жёсткие ограничения решатель не нарушает, проверяем только мягкие
+    let violations: Vec<Conflict> = constraints::violations(&schedule, solver.constraints)
+        .into_iter()
+        .filter(|conflict| conflict.kind == ConflictKind::Preference)
+        .collect();
+    Ok(SolveResult {
+        schedule,
+        placed: best.placements.iter().flatten().count(),
+        unplaced,
+        finish,
+        violations,
+        iterations,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schedule::constraints::Rule;
+
+    fn task(name: &str, minutes: u32, after: Vec<usize>) -> Task {
+        Task { card: String::new(), number: String::new(), name: name.into(), minutes, workers: Vec::new(), after }
+    }
+
+    // Рабочая неделя с понедельника 05.01.2026, без сохранённых ограничений
+    fn request(tasks: Vec<Task>, constraints: Vec<Constraint>) -> SolveRequest {
+        SolveRequest {
+            tasks,
+            workers: vec!["Иванов".into()],
+            start_date: "05.01.2026".into(),
+            horizon_days: Some(7),
+            weekdays: Vec::new(),
+            day: WorkDay::default(),
+            blocked: Vec::new(),
+            constraints: Some(constraints),
+            iterations: Some(5),
+        }
+    }
+
+    fn run(request: &SolveRequest) -> Result<SolveResult, String> {
+        solve(request, &mut |_, _, _| Ok(()))
+    }
+
+    #[test]
+    fn precedence_cycle() {
+        let tasks = vec![task("Резка", 60, vec![2]), task("Сварка", 60, vec![0]), task("Покраска", 60, vec![1])];
+        let error = run(&request(tasks, Vec::new())).unwrap_err();
+        assert!(error.contains("цикл"), "{}", error);
+    }
+
+    // В рабочем дне 8 часов без учёта обеда: девятичасовая операция не ставится, следующая за ней - тоже
+    #[test]
+    fn longer_than_day() {
+        let tasks = vec![task("Отжиг", 9 * 60, Vec::new()), task("Правка", 30, vec![0]), task("Резка", 30, Vec::new())];
+        let result = run(&request(tasks, Vec::new())).unwrap();
+        assert_eq!(result.placed, 1);
+        let reasons: Vec<(usize, &str)> = result.unplaced.iter().map(|u| (u.task, u.reason.as_str())).collect();
+        assert_eq!(
+            reasons,
+            vec![(0, "Операция длиннее рабочего дня"), (1, "Не размещена предшествующая операция")]
+        );
+    }
+
+    // Жёсткая недоступность не нарушается, даже если из-за неё работы кончаются позже
+    #[test]
+    fn hard_unavailable_never_violated() {
+        let constraints = vec![Constraint {
+            rule: Rule::Unavailable {
+                worker: "Иванов".into(),
+                weekdays: vec![1, 2],
+                from: Some("10:00".into()),
+                to: None,
+            },
+            hard: true,
+            weight: 1,
+            note: String::new(),
+        }];
+        let tasks = (0..6).map(|i| task(&format!("Операция {}", i + 1), 120, Vec::new())).collect();
+        let result = run(&request(tasks, constraints.clone())).unwrap();
+        assert_eq!(result.placed, 6);
+        assert!(constraints::violations(&result.schedule, &constraints).is_empty());
+        for row in result.schedule.entries.iter().flat_map(|e| &e.rows) {
+            let (start, end) = (row.start().unwrap(), row.end().unwrap());
+            if matches!(start.weekday().number_from_monday(), 1 | 2) {
+                assert!(end.time() <= NaiveTime::from_hms_opt(10, 0, 0).unwrap(), "{} {}", row.name, end);
+            }
+        }
+    }
+}
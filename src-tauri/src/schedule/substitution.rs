@@ -0,0 +1,237 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Замены на время отсутствия исполнителя. Отсутствие записывается в расписание
+// событием (blocked) на этого исполнителя: его операции сразу видны в check_conflicts,
+// а подбор замен учитывает все отсутствия без отдельного хранилища.
+//
+// Для каждой операции отсутствующего подбираются исполнители, которые свободны в это
+// время и не нарушают жёстких ограничений (constraints.rs). Выше в списке те, кто уже
+// выполнял такую операцию, затем - без нарушений мягких ограничений, затем - с
+// меньшей загрузкой в этот день. Лист замен на день - обычное расписание из одной
+// записи, его можно выгрузить любым форматом.
+
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use super::constraints::Constraint;
+use super::{format_time, Slot};
+use crate::model::{BlockedSlot, Schedule, ScheduleEntry};
+
+const DATE_FORMAT: &str = "%d.%m.%Y";
+
+const ABSENCE_TITLE: &str = "Отсутствует";
+
+const MAX_ABSENCE_DAYS: i64 = 366;
+
+// Столько замен предлагается на каждую операцию
+const MAX_CANDIDATES: usize = 5;
+
+const NOT_ASSIGNED: &str = "Замена не назначена";
+
+/// Отсутствие исполнителя: даты дд.мм.гггг включительно
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Absence {
+    pub worker: String,
+    pub from: String,
+    pub to: String,
+    #[serde(default)]
+    pub reason: String,
+}
+
+/// Возможная замена
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Candidate {
+    pub worker: String,
+    /// Уже выполнял операцию с таким названием
+    pub experienced: bool,
+    /// Сумма весов нарушенных мягких ограничений
+    pub penalty: u32,
+    /// Загрузка в день операции до замены, минуты
+    pub day_minutes: f64,
+}
+
+/// Операция отсутствующего и возможные замены
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Substitution {
+    pub entry: usize,
+    pub row: usize,
+    pub card: String,
+    pub operation: String,
+    /// дд.мм.гггг ЧЧ:ММ
+    pub start: String,
+    pub end: String,
+    /// Лучшие первыми; пустой список - свободных исполнителей нет
+    pub candidates: Vec<Candidate>,
+}
+
+/// Выбранная замена для листа замен
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Assignment {
+    pub entry: usize,
+    pub row: usize,
+    pub worker: String,
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value.trim(), DATE_FORMAT)
+        .map_err(|_| format!("Дата «{}» не в формате дд.мм.гггг", value))
+}
+
+impl Absence {
+    // Начало первого дня и начало дня после последнего
+    fn range(&self) -> Result<(NaiveDateTime, NaiveDateTime), String> {
+        if self.worker.trim().is_empty() {
+            return Err("Не указан отсутствующий исполнитель".into());
+        }
+        let (from, to) = (parse_date(&self.from)?, parse_date(&self.to)?);
+        if to < from {
+            return Err("Последний день отсутствия раньше первого".into());
+        }
+        if (to - from).num_days() >= MAX_ABSENCE_DAYS {
+            return Err(format!("Отсутствие не может быть дольше {} дней", MAX_ABSENCE_DAYS));
+        }
+        Ok((from.and_time(Default::default()), (to + Duration::days(1)).and_time(Default::default())))
+    }
+}
+
+/// Добавляет в расписание событие «Отсутствует» на исполнителя
+pub fn mark_absent(mut schedule: Schedule, absence: &Absence) -> Result<Schedule, String> {
+    let (from, to) = absence.range()?;
+    let title = match absence.reason.trim() {
+        "" => ABSENCE_TITLE.to_string(),
+        reason => format!("{}: {}", ABSENCE_TITLE, reason),
+    };
+    schedule.blocked.push(BlockedSlot {
+        title,
+        worker: absence.worker.trim().to_string(),
+        start_date: from.format(DATE_FORMAT).to_string(),
+        start_time: "00:00:00".into(),
+        end_date: to.format(DATE_FORMAT).to_string(),
+        end_time: "00:00:00".into(),
+    });
+    Ok(schedule)
+}
+
+fn minutes(slot: &Slot) -> f64 {
+    (slot.end - slot.start).num_minutes() as f64
+}
+
+/// Операции отсутствующего в эти дни и возможные замены. known_workers - все
+/// исполнители, в том числе без операций в расписании
+pub fn suggest(
+    schedule: &Schedule,
+    absence: &Absence,
+    known_workers: Option<&[String]>,
+    constraints: &[Constraint],
+) -> Result<Vec<Substitution>, String> {
+    let (from, to) = absence.range()?;
+    let absent = absence.worker.trim();
+    let slots = super::slots(schedule);
+
+    let mut pool = schedule.workers();
+    for worker in known_workers.unwrap_or_default() {
+        let worker = worker.trim();
+        if !worker.is_empty() && !pool.iter().any(|w| w == worker) {
+            pool.push(worker.to_string());
+        }
+    }
+    pool.retain(|w| w != absent);
+
+    let mut own: Vec<&Slot> = slots.iter().filter(|s| s.worker == absent && s.start < to && from < s.end).collect();
+    own.sort_by_key(|s| s.start);
+    let mut result = Vec::with_capacity(own.len());
+    for slot in own {
+        let date = slot.start.date();
+        let mut candidates: Vec<Candidate> = pool
+            .iter()
+            .filter_map(|worker| {
+                let theirs: Vec<&Slot> = slots.iter().filter(|s| s.worker == worker).collect();
+                if theirs.iter().any(|s| s.start < slot.end && slot.start < s.end) {
+                    return None;
+                }
+                let blocked = schedule.blocked.iter().filter(|b| b.applies_to(worker)).any(|b| {
+                    matches!((b.start(), b.end()), (Some(f), Some(t)) if slot.start < t && f < slot.end)
+                });
+                if blocked {
+                    return None;
+                }
+                let day: Vec<NaiveDateTime> =
+                    theirs.iter().filter(|s| s.start.date() == date).map(|s| s.start).collect();
+                let violated = |c: &&Constraint| {
+                    c.rule.violated_by(worker, &slot.operation.name, (slot.start, slot.end), &day)
+                };
+                if constraints.iter().filter(|c| c.hard).any(|c| violated(&c)) {
+                    return None;
+                }
+                let experienced = slots
+                    .iter()
+                    .any(|s| s.worker == worker && s.operation.name.trim() == slot.operation.name.trim());
+                Some(Candidate {
+                    worker: worker.clone(),
+                    experienced,
+                    penalty: constraints.iter().filter(|c| !c.hard).filter(violated).map(|c| c.weight).sum(),
+                    day_minutes: theirs.iter().filter(|s| s.start.date() == date).map(|s| minutes(s)).sum(),
+                })
+            })
+            .collect();
+        candidates.sort_by(|a, b| {
+            b.experienced
+                .cmp(&a.experienced)
+                .then(a.penalty.cmp(&b.penalty))
+                .then(a.day_minutes.total_cmp(&b.day_minutes))
+                .then(a.worker.cmp(&b.worker))
+        });
+        candidates.truncate(MAX_CANDIDATES);
+        result.push(Substitution {
+            entry: slot.entry,
+            row: slot.row,
+            card: slot.card.to_string(),
+            operation: slot.operation.name.clone(),
+            start: format_time(slot.start),
+            end: format_time(slot.end),
+            candidates,
+        });
+    }
+    Ok(result)
+}
+
+/// Лист замен на день: операции отсутствующего с назначенными заменами
+pub fn sheet(schedule: &Schedule, absent: &str, date: &str, assignments: &[Assignment]) -> Result<Schedule, String> {
+    let day = parse_date(date)?;
+    let absent = absent.trim();
+    let mut slots: Vec<Slot> =
+        super::slots(schedule).into_iter().filter(|s| s.worker == absent && s.start.date() == day).collect();
+    if slots.is_empty() {
+        return Err(format!("У исполнителя «{}» нет операций {}", absent, day.format(DATE_FORMAT)));
+    }
+    slots.sort_by_key(|s| s.start);
+
+    let rows = slots
+        .iter()
+        .enumerate()
+        .map(|(i, slot)| {
+            let substitute = assignments
+                .iter()
+                .find(|a| a.entry == slot.entry && a.row == slot.row && !a.worker.trim().is_empty())
+                .map_or(NOT_ASSIGNED, |a| a.worker.trim());
+            let mut row = slot.operation.clone();
+            row.name = format!("{} ({}, вместо: {})", row.name, slot.card, absent);
+            row.worker = substitute.to_string();
+            row.op_numeric = Some(i as f64);
+            row
+        })
+        .collect();
+    Ok(Schedule {
+        entries: vec![ScheduleEntry {
+            title: format!("Замены на {}", day.format(DATE_FORMAT)),
+            rows,
+            ..Default::default()
+        }],
+        ..Default::default()
+    })
+}
@@ -0,0 +1,101 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Подбор обмена: для операции, которую нужно передвинуть, перебираются операции той
+// же недели, с которыми её можно обменять временем (preview.rs) без новых конфликтов.
+// Варианты упорядочены по тому, насколько обмен уменьшает окна и неравномерность
+// нагрузки; устранённые конфликты ценятся выше любого выигрыша в окнах.
+
+use std::collections::HashSet;
+
+use chrono::{Datelike, NaiveDateTime};
+use serde::Serialize;
+
+use super::conflicts;
+use super::constraints::Constraint;
+use super::format_time;
+use super::preview::{self, Change, Metrics, RowRef};
+use crate::model::Schedule;
+
+// Столько вариантов возвращается
+const MAX_SUGGESTIONS: usize = 10;
+
+// Больше операций не перебирается: каждая проверка пересчитывает конфликты всего расписания
+const MAX_PARTNERS: usize = 1000;
+
+/// Вариант обмена
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SwapSuggestion {
+    pub partner: RowRef,
+    pub card: String,
+    pub operation: String,
+    pub worker: String,
+    /// Время операции-партнёра, на которое встанет выбранная: дд.мм.гггг ЧЧ:ММ
+    pub start: String,
+    pub resolved_conflicts: usize,
+    /// Насколько уменьшатся окна исполнителей и техкарт, минуты; меньше нуля - увеличатся
+    pub gap_gain_minutes: i64,
+    pub balance_gain_minutes: i64,
+    pub after: Metrics,
+}
+
+fn week(time: NaiveDateTime) -> (i32, u32) {
+    let week = time.iso_week();
+    (week.year(), week.week())
+}
+
+/// Варианты обмена операции target внутри её недели, лучшие первыми
+pub fn suggest(
+    schedule: &Schedule,
+    target: RowRef,
+    constraints: &[Constraint],
+) -> Result<Vec<SwapSuggestion>, String> {
+    let slots = super::slots(schedule);
+    let chosen = slots
+        .iter()
+        .find(|s| s.entry == target.entry && s.row == target.row)
+        .ok_or("У операции не указан исполнитель или не разобрано время")?;
+
+    let before_conflicts = conflicts::find_with(schedule, constraints);
+    let before = preview::metrics(schedule, &before_conflicts);
+    let before_keys: HashSet<_> = before_conflicts.iter().map(preview::key).collect();
+
+    let mut suggestions = Vec::new();
+    let partners = slots
+        .iter()
+        .filter(|s| week(s.start) == week(chosen.start) && s.start != chosen.start)
+        .take(MAX_PARTNERS);
+    for partner in partners {
+        let partner_ref = RowRef { entry: partner.entry, row: partner.row };
+        let change = Change::Swap { first: target, second: partner_ref };
+        let changed = change.apply(schedule)?;
+        let after_conflicts = conflicts::find_with(&changed, constraints);
+        let after_keys: HashSet<_> = after_conflicts.iter().map(preview::key).collect();
+        if after_keys.iter().any(|k| !before_keys.contains(k)) {
+            continue;
+        }
+        let after = preview::metrics(&changed, &after_conflicts);
+        suggestions.push(SwapSuggestion {
+            partner: partner_ref,
+            card: partner.card.to_string(),
+            operation: partner.operation.name.clone(),
+            worker: partner.worker.to_string(),
+            start: format_time(partner.start),
+            resolved_conflicts: before_keys.len() - after_keys.len(),
+            gap_gain_minutes: (before.worker_gap_minutes + before.card_gap_minutes)
+                - (after.worker_gap_minutes + after.card_gap_minutes),
+            balance_gain_minutes: before.imbalance_minutes - after.imbalance_minutes,
+            after,
+        });
+    }
+
+    suggestions.sort_by(|a, b| {
+        b.resolved_conflicts
+            .cmp(&a.resolved_conflicts)
+            .then((b.gap_gain_minutes + b.balance_gain_minutes).cmp(&(a.gap_gain_minutes + a.balance_gain_minutes)))
+            .then((a.partner.entry, a.partner.row).cmp(&(b.partner.entry, b.partner.row)))
+    });
+    suggestions.truncate(MAX_SUGGESTIONS);
+    Ok(suggestions)
+}
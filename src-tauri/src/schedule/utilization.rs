@@ -0,0 +1,274 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Занятость рабочих мест (аналог кабинетов). В расписании нет поля «кабинет», поэтому
+// рабочее место задаётся списком операций, которые на нём выполняются (покраска - в
+// покрасочной камере, сварка - на сварочном посту). Место занято, пока идёт хотя бы
+// одна его операция, в том числе ещё не назначенная исполнителю; одновременные
+// операции время занятости не удваивают.
+//
+// Доступное время - рабочие часы рабочих дней от первой до последней операции
+// расписания. Для каждого часа дня считается средняя занятость за период: часы пик
+// и часы, когда место стоит, видны сразу.
+
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use serde::{Deserialize, Serialize};
+
+use crate::model::Schedule;
+
+const DATE_FORMAT: &str = "%d.%m.%Y";
+
+// Свободные промежутки короче этого не показываются, минуты
+const MIN_UNUSED_MINUTES: i64 = 60;
+
+// Столько свободных промежутков показывается на каждое место
+const MAX_UNUSED: usize = 200;
+
+/// Рабочее место и операции, которые на нём выполняются
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Workplace {
+    pub name: String,
+    pub operations: Vec<String>,
+}
+
+impl Workplace {
+    /// Выполняется ли операция name на этом месте
+    pub fn performs(&self, name: &str) -> bool {
+        let name = name.trim().to_lowercase();
+        self.operations.iter().any(|op| op.trim().to_lowercase() == name)
+    }
+}
+
+/// Параметры подсчёта
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UtilizationOptions {
+    pub workplaces: Vec<Workplace>,
+    /// Рабочие часы ЧЧ:ММ
+    pub day_start: String,
+    pub day_end: String,
+    /// Рабочие дни недели: 1 - понедельник; по умолчанию пн-пт
+    pub weekdays: Vec<u8>,
+}
+
+impl Default for UtilizationOptions {
+    fn default() -> Self {
+        UtilizationOptions {
+            workplaces: Vec::new(),
+            day_start: "08:00".into(),
+            day_end: "17:00".into(),
+            weekdays: vec![1, 2, 3, 4, 5],
+        }
+    }
+}
+
+impl UtilizationOptions {
+    /// Проверяет параметры; возвращает начало и конец рабочего дня
+    pub fn working_hours(&self) -> Result<(NaiveTime, NaiveTime), String> {
+        if self.workplaces.is_empty() {
+            return Err("Не заданы рабочие места".into());
+        }
+        let (day_start, day_end) = (parse_time(&self.day_start)?, parse_time(&self.day_end)?);
+        if day_end <= day_start {
+            return Err("Конец рабочего дня раньше начала".into());
+        }
+        if self.weekdays.iter().any(|d| !(1..=7).contains(d)) {
+            return Err("Дни недели задаются числами от 1 (понедельник) до 7 (воскресенье)".into());
+        }
+        Ok((day_start, day_end))
+    }
+
+    /// Рабочий ли день недели date
+    pub fn is_working_day(&self, date: NaiveDate) -> bool {
+        self.weekdays.is_empty() || self.weekdays.contains(&(date.weekday().number_from_monday() as u8))
+    }
+}
+
+/// Часы рабочего дня с day_start по day_end; крайние - неполные
+pub fn day_hours(day_start: NaiveTime, day_end: NaiveTime) -> Vec<(NaiveTime, NaiveTime)> {
+    (day_start.hour()..=day_end.hour())
+        .filter_map(|h| {
+            let from = NaiveTime::from_hms_opt(h, 0, 0)?.max(day_start);
+            let to = NaiveTime::from_hms_opt(h + 1, 0, 0).map_or(day_end, |t| t.min(day_end));
+            (from < to).then_some((from, to))
+        })
+        .collect()
+}
+
+/// Средняя занятость часа дня
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HourLoad {
+    /// Начало часа: 0-23
+    pub hour: u32,
+    pub percent: f64,
+}
+
+/// Промежуток, когда место свободно
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnusedPeriod {
+    pub date: String,
+    /// ЧЧ:ММ
+    pub start: String,
+    pub end: String,
+    pub minutes: i64,
+}
+
+/// Занятость рабочего места
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkplaceUtilization {
+    pub name: String,
+    pub operations: usize,
+    pub busy_minutes: i64,
+    pub available_minutes: i64,
+    pub percent: f64,
+    /// Часы с наибольшей занятостью
+    pub peak_hours: Vec<u32>,
+    pub hours: Vec<HourLoad>,
+    pub unused: Vec<UnusedPeriod>,
+    /// Показаны не все свободные промежутки
+    pub unused_truncated: bool,
+}
+
+/// Отчёт о занятости
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UtilizationReport {
+    /// Период: дд.мм.гггг
+    pub from: String,
+    pub to: String,
+    pub working_days: usize,
+    pub workplaces: Vec<WorkplaceUtilization>,
+    /// Операции, которые не относятся ни к одному месту
+    pub unassigned_operations: usize,
+}
+
+fn parse_time(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(value.trim(), "%H:%M:%S"))
+        .map_err(|_| format!("Время «{}» не в формате ЧЧ:ММ", value))
+}
+
+fn percent(part: i64, whole: i64) -> f64 {
+    if whole <= 0 {
+        return 0.0;
+    }
+    (part as f64 * 1000.0 / whole as f64).round() / 10.0
+}
+
+// Объединение интервалов, отсортированное по началу
+fn merge(mut intervals: Vec<(NaiveDateTime, NaiveDateTime)>) -> Vec<(NaiveDateTime, NaiveDateTime)> {
+    intervals.sort();
+    let mut merged: Vec<(NaiveDateTime, NaiveDateTime)> = Vec::new();
+    for (start, end) in intervals {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+fn overlap(busy: &[(NaiveDateTime, NaiveDateTime)], from: NaiveDateTime, to: NaiveDateTime) -> i64 {
+    busy.iter()
+        .map(|&(start, end)| (end.min(to) - start.max(from)).num_minutes().max(0))
+        .sum()
+}
+
+/// Занятость рабочих мест за период расписания
+pub fn report(schedule: &Schedule, options: &UtilizationOptions) -> Result<UtilizationReport, String> {
+    let (day_start, day_end) = options.working_hours()?;
+
+    // Занятость не зависит от назначения: операция без исполнителя место тоже занимает
+    let slots = super::timed_slots(schedule);
+    let (Some(first), Some(last)) = (slots.iter().map(|s| s.start).min(), slots.iter().map(|s| s.end).max()) else {
+        return Err("В расписании нет операций с корректным временем".into());
+    };
+    let days: Vec<NaiveDate> = first
+        .date()
+        .iter_days()
+        .take_while(|d| *d <= last.date())
+        .filter(|d| options.is_working_day(*d))
+        .collect();
+    let day_minutes = (day_end - day_start).num_minutes();
+    let hours = day_hours(day_start, day_end);
+
+    let unassigned_operations =
+        slots.iter().filter(|s| !options.workplaces.iter().any(|p| p.performs(&s.operation.name))).count();
+
+    let workplaces = options
+        .workplaces
+        .iter()
+        .map(|place| {
+            let own: Vec<_> = slots.iter().filter(|s| place.performs(&s.operation.name)).collect();
+            let busy = merge(own.iter().map(|s| (s.start, s.end)).collect());
+
+            let mut busy_minutes = 0;
+            let mut unused = Vec::new();
+            let mut by_hour: BTreeMap<u32, i64> = BTreeMap::new();
+            for day in &days {
+                let (open, close) = (day.and_time(day_start), day.and_time(day_end));
+                busy_minutes += overlap(&busy, open, close);
+                for &(from, to) in &hours {
+                    *by_hour.entry(from.hour()).or_default() += overlap(&busy, day.and_time(from), day.and_time(to));
+                }
+                // Свободные промежутки внутри рабочего дня
+                let mut cursor = open;
+                let inside = busy.iter().filter(|&&(s, e)| s < close && e > open);
+                for &(start, end) in inside.chain(std::iter::once(&(close, close))) {
+                    let start = start.max(open);
+                    if (start - cursor).num_minutes() >= MIN_UNUSED_MINUTES {
+                        unused.push(UnusedPeriod {
+                            date: day.format(DATE_FORMAT).to_string(),
+                            start: cursor.format("%H:%M").to_string(),
+                            end: start.format("%H:%M").to_string(),
+                            minutes: (start - cursor).num_minutes(),
+                        });
+                    }
+                    cursor = cursor.max(end.min(close));
+                }
+            }
+
+            let hours: Vec<HourLoad> = hours
+                .iter()
+                .map(|&(from, to)| {
+                    let available = (to - from).num_minutes() * days.len() as i64;
+                    let busy = by_hour.get(&from.hour()).copied().unwrap_or(0);
+                    HourLoad { hour: from.hour(), percent: percent(busy, available) }
+                })
+                .collect();
+            let peak = hours.iter().map(|h| h.percent).fold(0.0, f64::max);
+            let available_minutes = day_minutes * days.len() as i64;
+            let unused_truncated = unused.len() > MAX_UNUSED;
+            unused.truncate(MAX_UNUSED);
+            WorkplaceUtilization {
+                name: place.name.clone(),
+                operations: own.len(),
+                busy_minutes,
+                available_minutes,
+                percent: percent(busy_minutes, available_minutes),
+                peak_hours: if peak > 0.0 {
+                    hours.iter().filter(|h| h.percent == peak).map(|h| h.hour).collect()
+                } else {
+                    Vec::new()
+                },
+                hours,
+                unused,
+                unused_truncated,
+            }
+        })
+        .collect();
+
+    Ok(UtilizationReport {
+        from: first.format(DATE_FORMAT).to_string(),
+        to: last.format(DATE_FORMAT).to_string(),
+        working_days: days.len(),
+        workplaces,
+        unassigned_operations,
+    })
+}
@@ -0,0 +1,279 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Нагрузка исполнителей по неделям: часы работы за каждую календарную неделю периода
+// расписания, в разрезе операций и техкарт, и сравнение со ставкой (договорной
+// нагрузкой в часах в неделю). Работа считается по длительности операции, а не по
+// времени от начала до окончания, поэтому обед в неё не входит.
+//
+// Неделя без операций исполнителя считается неделей с нулевой нагрузкой: иначе
+// исполнитель, занятый одну неделю из четырёх, выглядел бы загруженным полностью.
+
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::export::report::{Cell, Row, Table};
+use crate::model::Schedule;
+
+const DATE_FORMAT: &str = "%d.%m.%Y";
+
+const DEFAULT_TOLERANCE_PERCENT: f64 = 5.0;
+
+/// Ставка исполнителя
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Contract {
+    pub worker: String,
+    pub weekly_hours: f64,
+}
+
+/// Параметры отчёта
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WorkloadOptions {
+    pub contracts: Vec<Contract>,
+    /// Допустимое отклонение от ставки, проценты; по умолчанию 5
+    pub tolerance_percent: Option<f64>,
+}
+
+/// Соответствие ставке
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadStatus {
+    Ok,
+    Over,
+    Under,
+    /// Ставка не задана
+    NoContract,
+}
+
+impl LoadStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            LoadStatus::Ok => "В норме",
+            LoadStatus::Over => "Перегрузка",
+            LoadStatus::Under => "Недогрузка",
+            LoadStatus::NoContract => "Ставка не задана",
+        }
+    }
+}
+
+/// Часы за неделю
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WeekLoad {
+    /// Понедельник недели: дд.мм.гггг
+    pub week: String,
+    pub hours: f64,
+    pub status: LoadStatus,
+}
+
+/// Часы по операции или техкарте за весь период
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareLoad {
+    pub name: String,
+    pub hours: f64,
+}
+
+/// Нагрузка исполнителя
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkerWorkload {
+    pub worker: String,
+    pub total_hours: f64,
+    pub average_weekly_hours: f64,
+    pub contract_hours: Option<f64>,
+    /// Средняя нагрузка минус ставка
+    pub deviation_hours: Option<f64>,
+    /// По средней нагрузке за период
+    pub status: LoadStatus,
+    pub weeks: Vec<WeekLoad>,
+    pub by_operation: Vec<ShareLoad>,
+    pub by_card: Vec<ShareLoad>,
+}
+
+/// Отчёт о нагрузке
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkloadReport {
+    /// Понедельники недель периода
+    pub weeks: Vec<String>,
+    pub workers: Vec<WorkerWorkload>,
+    pub over: usize,
+    pub under: usize,
+}
+
+fn monday(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+fn status(hours: f64, contract: Option<f64>, tolerance: f64) -> LoadStatus {
+    match contract {
+        None => LoadStatus::NoContract,
+        Some(c) if hours > c * (1.0 + tolerance) => LoadStatus::Over,
+        Some(c) if hours < c * (1.0 - tolerance) => LoadStatus::Under,
+        Some(_) => LoadStatus::Ok,
+    }
+}
+
+fn shares(map: BTreeMap<String, f64>) -> Vec<ShareLoad> {
+    let mut list: Vec<ShareLoad> =
+        map.into_iter().map(|(name, minutes)| ShareLoad { name, hours: minutes / 60.0 }).collect();
+    list.sort_by(|a, b| b.hours.total_cmp(&a.hours).then(a.name.cmp(&b.name)));
+    list
+}
+
+/// Считает нагрузку исполнителей расписания и исполнителей со ставкой
+pub fn report(schedule: &Schedule, options: &WorkloadOptions) -> Result<WorkloadReport, String> {
+    let tolerance = options.tolerance_percent.unwrap_or(DEFAULT_TOLERANCE_PERCENT);
+    if !(0.0..=100.0).contains(&tolerance) {
+        return Err("Допустимое отклонение - от 0 до 100 процентов".into());
+    }
+    let tolerance = tolerance / 100.0;
+    if let Some(c) = options.contracts.iter().find(|c| !c.weekly_hours.is_finite() || c.weekly_hours < 0.0) {
+        return Err(format!("Недопустимая ставка у исполнителя «{}»", c.worker));
+    }
+    let contract = |worker: &str| options.contracts.iter().find(|c| c.worker.trim() == worker).map(|c| c.weekly_hours);
+
+    let slots = super::slots(schedule);
+    let (Some(first), Some(last)) = (slots.iter().map(|s| s.start).min(), slots.iter().map(|s| s.start).max()) else {
+        return Err("В расписании нет операций с корректным временем".into());
+    };
+    let mut weeks = Vec::new();
+    let mut week = monday(first.date());
+    while week <= last.date() {
+        weeks.push(week);
+        week += Duration::days(7);
+    }
+
+    let mut names: Vec<String> = schedule.workers();
+    for c in &options.contracts {
+        if !c.worker.trim().is_empty() && !names.iter().any(|n| n == c.worker.trim()) {
+            names.push(c.worker.trim().to_string());
+        }
+    }
+
+    let mut workers: Vec<WorkerWorkload> = names
+        .into_iter()
+        .map(|worker| {
+            let own: Vec<_> = slots.iter().filter(|s| s.worker == worker).collect();
+            let contract_hours = contract(&worker);
+            let mut by_operation: BTreeMap<String, f64> = BTreeMap::new();
+            let mut by_card: BTreeMap<String, f64> = BTreeMap::new();
+            let mut by_week: BTreeMap<NaiveDate, f64> = BTreeMap::new();
+            for slot in &own {
+                let minutes = slot.operation.duration_minutes();
+                *by_operation.entry(slot.operation.name.trim().to_string()).or_default() += minutes;
+                *by_card.entry(slot.card.to_string()).or_default() += minutes;
+                *by_week.entry(monday(slot.start.date())).or_default() += minutes;
+            }
+            let weeks: Vec<WeekLoad> = weeks
+                .iter()
+                .map(|w| {
+                    let hours = by_week.get(w).copied().unwrap_or(0.0) / 60.0;
+                    let status = status(hours, contract_hours, tolerance);
+                    WeekLoad { week: w.format(DATE_FORMAT).to_string(), hours, status }
+                })
+                .collect();
+            let total_hours: f64 = weeks.iter().map(|w| w.hours).sum();
+            let average_weekly_hours = total_hours / weeks.len() as f64;
+            WorkerWorkload {
+                total_hours,
+                average_weekly_hours,
+                contract_hours,
+                deviation_hours: contract_hours.map(|c| average_weekly_hours - c),
+                status: status(average_weekly_hours, contract_hours, tolerance),
+                weeks,
+                by_operation: shares(by_operation),
+                by_card: shares(by_card),
+                worker,
+            }
+        })
+        .collect();
+    workers.sort_by(|a, b| a.worker.cmp(&b.worker));
+
+    Ok(WorkloadReport {
+        weeks: weeks.iter().map(|w| w.format(DATE_FORMAT).to_string()).collect(),
+        over: workers.iter().filter(|w| w.status == LoadStatus::Over).count(),
+        under: workers.iter().filter(|w| w.status == LoadStatus::Under).count(),
+        workers,
+    })
+}
+
+fn number(value: Option<f64>) -> Cell {
+    value.map_or(Cell::Empty, |v| Cell::Number((v * 100.0).round() / 100.0))
+}
+
+impl WorkloadReport {
+    /// Листы отчёта для выгрузки в .xlsx
+    pub fn tables(&self) -> Vec<Table> {
+        let text = |s: &str| Cell::Text(s.to_string());
+        let summary = Table {
+            name: "Нагрузка".into(),
+            title: "Нагрузка исполнителей, часов в неделю".into(),
+            headers: ["Исполнитель", "Ставка", "Средняя", "Отклонение", "Всего за период", "Состояние"]
+                .map(String::from)
+                .to_vec(),
+            widths: vec![30.0, 12.0, 12.0, 12.0, 16.0, 20.0],
+            rows: self
+                .workers
+                .iter()
+                .map(|w| Row {
+                    cells: vec![
+                        text(&w.worker),
+                        number(w.contract_hours),
+                        number(Some(w.average_weekly_hours)),
+                        number(w.deviation_hours),
+                        number(Some(w.total_hours)),
+                        text(w.status.label()),
+                    ],
+                    marked: matches!(w.status, LoadStatus::Over | LoadStatus::Under),
+                })
+                .collect(),
+        };
+
+        let by_week = Table {
+            name: "По неделям".into(),
+            title: "Часы по неделям (неделя с понедельника)".into(),
+            headers: std::iter::once("Исполнитель".to_string()).chain(self.weeks.iter().cloned()).collect(),
+            widths: std::iter::once(30.0).chain(self.weeks.iter().map(|_| 12.0)).collect(),
+            rows: self
+                .workers
+                .iter()
+                .map(|w| Row {
+                    cells: std::iter::once(text(&w.worker))
+                        .chain(w.weeks.iter().map(|wk| number(Some(wk.hours))))
+                        .collect(),
+                    marked: w.weeks.iter().any(|wk| matches!(wk.status, LoadStatus::Over | LoadStatus::Under)),
+                })
+                .collect(),
+        };
+
+        let shares = |name: &str, column: &str, pick: fn(&WorkerWorkload) -> &Vec<ShareLoad>| Table {
+            name: name.into(),
+            title: format!("Часы за период: {}", name.to_lowercase()),
+            headers: vec!["Исполнитель".into(), column.into(), "Часы".into()],
+            widths: vec![30.0, 50.0, 12.0],
+            rows: self
+                .workers
+                .iter()
+                .flat_map(|w| {
+                    pick(w).iter().map(move |s| Row {
+                        cells: vec![text(&w.worker), text(&s.name), number(Some(s.hours))],
+                        marked: false,
+                    })
+                })
+                .collect(),
+        };
+
+        vec![
+            summary,
+            by_week,
+            shares("По операциям", "Операция", |w| &w.by_operation),
+            shares("По техкартам", "Техкарта", |w| &w.by_card),
+        ]
+    }
+}
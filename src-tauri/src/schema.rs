@@ -0,0 +1,289 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Проверка JSON-файлов по JSON Schema до передачи во фронтенд: битый или чужой файл
+// отклоняется с указанием места ошибки, а не роняет интерфейс.
+// Схема лежит в schemas/ и встраивается в сборку. Поддерживается подмножество
+// draft 2020-12, которое используется в наших схемах: type, enum, const, properties,
+// patternProperties, additionalProperties, required, items, min/maxLength, pattern,
+// minimum/maximum, $ref на #/$defs и contentSchema для JSON, сохранённого строкой.
+
+use regex::Regex;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::migrate::CURRENT_VERSION;
+
+// Схема текущего формата; файлы старых форматов перед проверкой обновляются (см. migrate)
+const BACKUP_SCHEMA: &str = include_str!("../schemas/backup.v2.schema.json");
+
+// После стольких ошибок проверка останавливается
+const MAX_ERRORS: usize = 50;
+
+// Глубина вложенности $ref, после которой схема считается зацикленной
+const MAX_REF_DEPTH: usize = 32;
+
+/// Ошибка в файле
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaError {
+    /// Место в документе (JSON Pointer, «» - корень)
+    pub pointer: String,
+    /// Строка и колонка - для синтаксических ошибок
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+/// Результат проверки файла
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationReport {
+    pub valid: bool,
+    pub schema_version: u32,
+    /// Версия формата, из которой файл был обновлён при чтении
+    pub migrated_from: Option<u32>,
+    pub errors: Vec<SchemaError>,
+}
+
+impl ValidationReport {
+    /// Первая ошибка одной строкой для сообщения пользователю
+    pub fn summary(&self) -> Option<String> {
+        let first = self.errors.first()?;
+        let place = match (first.line, first.column) {
+            (Some(line), Some(column)) => format!("строка {}, колонка {}", line, column),
+            _ if first.pointer.is_empty() => "корень документа".to_string(),
+            _ => first.pointer.clone(),
+        };
+        let more = match self.errors.len() {
+            1 => String::new(),
+            n => format!(" (и ещё ошибок: {})", n - 1),
+        };
+        Some(format!("Файл не соответствует формату: {}: {}{}", place, first.message, more))
+    }
+}
+
+/// Проверяет текст резервной копии техкарт
+pub fn validate_backup(text: &str) -> ValidationReport {
+    let schema: Value = serde_json::from_str(BACKUP_SCHEMA).expect("встроенная схема - валидный JSON");
+    validate_text(text, &schema)
+}
+
+/// Разбирает текст и проверяет документ по схеме
+pub fn validate_text(text: &str, schema: &Value) -> ValidationReport {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let errors = match serde_json::from_str::<Value>(text) {
+        Ok(document) => {
+            let mut validator = Validator { root: schema, errors: Vec::new() };
+            validator.check(&document, schema, "", 0);
+            validator.errors
+        }
+        Err(e) => vec![SchemaError {
+            pointer: String::new(),
+            line: Some(e.line()),
+            column: Some(e.column()),
+            message: format!("некорректный JSON: {}", syntax_message(&e)),
+        }],
+    };
+    ValidationReport { valid: errors.is_empty(), schema_version: CURRENT_VERSION, migrated_from: None, errors }
+}
+
+fn syntax_message(e: &serde_json::Error) -> &'static str {
+    match e.classify() {
+        serde_json::error::Category::Eof => "файл обрывается",
+        serde_json::error::Category::Io => "ошибка чтения",
+        _ => "синтаксическая ошибка",
+    }
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+    errors: Vec<SchemaError>,
+}
+
+impl<'a> Validator<'a> {
+    fn fail(&mut self, pointer: &str, message: String) {
+        if self.errors.len() < MAX_ERRORS {
+            self.errors.push(SchemaError { pointer: pointer.to_string(), line: None, column: None, message });
+        }
+    }
+
+    fn check(&mut self, value: &Value, schema: &'a Value, pointer: &str, depth: usize) {
+        if self.errors.len() >= MAX_ERRORS {
+            return;
+        }
+        let Some(schema) = schema.as_object() else { return };
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            match self.resolve(reference) {
+                Some(target) if depth < MAX_REF_DEPTH => self.check(value, target, pointer, depth + 1),
+                _ => self.fail(pointer, format!("не удалось разрешить ссылку схемы {}", reference)),
+            }
+        }
+
+        if let Some(expected) = schema.get("type") {
+            let allowed: Vec<&str> = match expected {
+                Value::String(t) => vec![t.as_str()],
+                Value::Array(list) => list.iter().filter_map(Value::as_str).collect(),
+                _ => Vec::new(),
+            };
+            if !allowed.iter().any(|t| type_matches(value, t)) {
+                self.fail(pointer, format!("ожидается {}, а не {}", type_names(&allowed), type_name(value)));
+                return;
+            }
+        }
+
+        if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+            if !options.contains(value) {
+                let list: Vec<String> = options.iter().map(Value::to_string).collect();
+                self.fail(pointer, format!("допустимые значения: {}", list.join(", ")));
+            }
+        }
+
+        if let Some(expected) = schema.get("const") {
+            if value != expected {
+                self.fail(pointer, format!("ожидается значение {}", expected));
+            }
+        }
+
+        match value {
+            Value::Object(map) => self.check_object(map, schema, pointer, depth),
+            Value::Array(items) => self.check_array(items, schema, pointer, depth),
+            Value::String(text) => self.check_string(text, schema, pointer, depth),
+            Value::Number(n) => {
+                let n = n.as_f64().unwrap_or(0.0);
+                if schema.get("minimum").and_then(Value::as_f64).is_some_and(|min| n < min) {
+                    self.fail(pointer, format!("значение {} меньше допустимого", n));
+                }
+                if schema.get("maximum").and_then(Value::as_f64).is_some_and(|max| n > max) {
+                    self.fail(pointer, format!("значение {} больше допустимого", n));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check_object(&mut self, map: &Map<String, Value>, schema: &'a Map<String, Value>, pointer: &str, depth: usize) {
+        if let Some(required) = schema.get("required").and_then(Value::as_array) {
+            for name in required.iter().filter_map(Value::as_str) {
+                if !map.contains_key(name) {
+                    self.fail(pointer, format!("нет обязательного поля «{}»", name));
+                }
+            }
+        }
+
+        let properties = schema.get("properties").and_then(Value::as_object);
+        let patterns: Vec<(Regex, &'a Value)> = schema
+            .get("patternProperties")
+            .and_then(Value::as_object)
+            .map(|p| p.iter().filter_map(|(re, s)| Regex::new(re).ok().map(|re| (re, s))).collect())
+            .unwrap_or_default();
+        let additional = schema.get("additionalProperties");
+
+        for (key, item) in map {
+            let path = format!("{}/{}", pointer, escape_pointer(key));
+            let mut matched = false;
+            if let Some(sub) = properties.and_then(|p| p.get(key)) {
+                matched = true;
+                self.check(item, sub, &path, depth);
+            }
+            for (re, sub) in &patterns {
+                if re.is_match(key) {
+                    matched = true;
+                    self.check(item, sub, &path, depth);
+                }
+            }
+            match additional {
+                _ if matched => {}
+                Some(Value::Bool(false)) => self.fail(&path, format!("недопустимое поле «{}»", key)),
+                Some(sub @ Value::Object(_)) => self.check(item, sub, &path, depth),
+                _ => {}
+            }
+        }
+    }
+
+    fn check_array(&mut self, items: &[Value], schema: &'a Map<String, Value>, pointer: &str, depth: usize) {
+        if schema.get("minItems").and_then(Value::as_u64).is_some_and(|min| (items.len() as u64) < min) {
+            self.fail(pointer, "слишком мало элементов".into());
+        }
+        if schema.get("maxItems").and_then(Value::as_u64).is_some_and(|max| items.len() as u64 > max) {
+            self.fail(pointer, "слишком много элементов".into());
+        }
+        if let Some(sub) = schema.get("items") {
+            for (i, item) in items.iter().enumerate() {
+                self.check(item, sub, &format!("{}/{}", pointer, i), depth);
+            }
+        }
+    }
+
+    fn check_string(&mut self, text: &str, schema: &'a Map<String, Value>, pointer: &str, depth: usize) {
+        let length = text.chars().count() as u64;
+        if schema.get("minLength").and_then(Value::as_u64).is_some_and(|min| length < min) {
+            self.fail(pointer, "слишком короткая строка".into());
+        }
+        if schema.get("maxLength").and_then(Value::as_u64).is_some_and(|max| length > max) {
+            self.fail(pointer, format!("строка длиннее {} символов", schema["maxLength"]));
+        }
+        if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+            if Regex::new(pattern).is_ok_and(|re| !re.is_match(text)) {
+                self.fail(pointer, format!("значение «{}» имеет неверный формат", text));
+            }
+        }
+        // JSON, сохранённый строкой, проверяется по contentSchema
+        if schema.get("contentMediaType").and_then(Value::as_str) == Some("application/json") {
+            match serde_json::from_str::<Value>(text) {
+                Ok(inner) => {
+                    if let Some(sub) = schema.get("contentSchema") {
+                        self.check(&inner, sub, pointer, depth);
+                    }
+                }
+                Err(e) => self.fail(pointer, format!("вложенный JSON повреждён: {}", syntax_message(&e))),
+            }
+        }
+    }
+
+    /// Ссылка вида #/$defs/имя внутри той же схемы
+    fn resolve(&self, reference: &str) -> Option<&'a Value> {
+        let path = reference.strip_prefix('#')?;
+        self.root.pointer(path)
+    }
+}
+
+fn type_matches(value: &Value, expected: &str) -> bool {
+    match expected {
+        "integer" => value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        other => type_name(value) == other || (other == "number" && value.is_number()),
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn type_names(list: &[&str]) -> String {
+    let names: Vec<&str> = list
+        .iter()
+        .map(|t| match *t {
+            "object" => "объект",
+            "array" => "массив",
+            "string" => "строка",
+            "number" => "число",
+            "integer" => "целое число",
+            "boolean" => "логическое значение",
+            _ => "null",
+        })
+        .collect();
+    names.join(" или ")
+}
+
+// RFC 6901: «~» и «/» в ключах экранируются
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
@@ -0,0 +1,138 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Настройки приложения в папке настроек (settings.json) вместо разрозненных ключей
+// localStorage: тема, язык, формат выгрузки по умолчанию, интервал автосохранения,
+// файл сведений о происхождении рядом с выгрузкой.
+// Фронтенд читает и меняет их по одной через get_setting и set_setting; значение
+// проверяется по типу поля, неизвестные ключи не принимаются.
+//
+// Добавленные пользователем папки показываются как настройка allowedDirs только
+// для чтения: список меняется только через диалог выбора папки (allowlist.rs, там же
+// подпись списка ключом из хранилища секретов ОС), иначе set_setting открыл бы доступ
+// к любой папке без ведома пользователя.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::allowlist;
+use crate::export::Format;
+use crate::paths;
+
+const SETTINGS_FILE: &str = "settings.json";
+
+const ALLOWED_DIRS_KEY: &str = "allowedDirs";
+
+// Интервал автосохранения по умолчанию и допустимые пределы, секунды; 0 - выключено
+const DEFAULT_AUTOSAVE_SECS: u64 = 60;
+const MIN_AUTOSAVE_SECS: u64 = 10;
+const MAX_AUTOSAVE_SECS: u64 = 3600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Theme {
+    Light,
+    Dark,
+}
+
+/// Настройки приложения
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    pub theme: Theme,
+    /// Язык интерфейса (ru, en и т. п.)
+    pub locale: String,
+    pub default_export_format: Format,
+    /// 0 - автосохранение выключено
+    pub autosave_interval_secs: u64,
+    /// Писать рядом с выгрузкой «имя.provenance.json» (export/provenance.rs)
+    pub provenance_sidecar: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            theme: Theme::Light,
+            locale: "ru".into(),
+            default_export_format: Format::Xlsx,
+            autosave_interval_secs: DEFAULT_AUTOSAVE_SECS,
+            provenance_sidecar: false,
+        }
+    }
+}
+
+impl Settings {
+    fn check(&self) -> Result<(), String> {
+        let secs = self.autosave_interval_secs;
+        if secs != 0 && !(MIN_AUTOSAVE_SECS..=MAX_AUTOSAVE_SECS).contains(&secs) {
+            return Err(format!(
+                "Интервал автосохранения - от {} до {} секунд или 0, чтобы выключить",
+                MIN_AUTOSAVE_SECS, MAX_AUTOSAVE_SECS
+            ));
+        }
+        let locale_valid = (2..=16).contains(&self.locale.len())
+            && self.locale.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !locale_valid {
+            return Err("Недопустимый код языка".into());
+        }
+        Ok(())
+    }
+}
+
+/// Настройки из файла; без файла или при ошибке чтения - по умолчанию
+pub fn load() -> Settings {
+    paths::app_config_dir()
+        .and_then(|dir| std::fs::read_to_string(dir.join(SETTINGS_FILE)).ok())
+        .and_then(|raw| serde_json::from_str::<Settings>(&raw).ok())
+        .filter(|s| s.check().is_ok())
+        .unwrap_or_default()
+}
+
+fn save(settings: &Settings) -> Result<(), String> {
+    let dir = paths::app_config_dir().ok_or("Не удалось определить папку настроек")?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| paths::io_error_message("Ошибка создания папки настроек", &e))?;
+    let content = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Ошибка сохранения настроек: {}", e))?;
+    std::fs::write(dir.join(SETTINGS_FILE), content)
+        .map_err(|e| paths::io_error_message("Ошибка сохранения настроек", &e))
+}
+
+fn to_map(settings: &Settings) -> Map<String, Value> {
+    match serde_json::to_value(settings) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    }
+}
+
+/// Значение настройки по ключу (имя поля в camelCase)
+pub fn get(key: &str) -> Result<Value, String> {
+    if key == ALLOWED_DIRS_KEY {
+        return serde_json::to_value(allowlist::dirs()).map_err(|e| e.to_string());
+    }
+    to_map(&load()).remove(key).ok_or_else(|| format!("Неизвестная настройка: {}", key))
+}
+
+/// Меняет одну настройку и возвращает все настройки
+pub fn set(key: &str, value: Value) -> Result<Settings, String> {
+    if key == ALLOWED_DIRS_KEY {
+        return Err("Папки добавляются только через диалог выбора папки".into());
+    }
+    let mut map = to_map(&load());
+    if !map.contains_key(key) {
+        return Err(format!("Неизвестная настройка: {}", key));
+    }
+    map.insert(key.to_string(), value);
+    let settings: Settings = serde_json::from_value(Value::Object(map))
+        .map_err(|e| format!("Недопустимое значение настройки {}: {}", key, e))?;
+    settings.check()?;
+    save(&settings)?;
+    Ok(settings)
+}
+
+/// Возвращает настройки к значениям по умолчанию. Добавленные папки не меняются
+pub fn reset() -> Result<Settings, String> {
+    let settings = Settings::default();
+    save(&settings)?;
+    Ok(settings)
+}
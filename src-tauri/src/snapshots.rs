@@ -0,0 +1,340 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Снимки расписания перед публикацией и смысловое сравнение двух снимков. Снимок -
+// JSON-файл в папке данных приложения (snapshots/<id>.json): метка, время и
+// расписание целиком. Сравнение сопоставляет операции по техкарте, номеру операции
+// и номеру исполнителя и описывает изменения словами («Иванов: «Сборка» пн 12.03
+// 09:00-10:00 → вт 13.03 10:30-11:30»), а не построчной разницей текста.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use chrono::{Datelike, Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::model::{BlockedSlot, OperationRow, Schedule};
+use crate::paths;
+
+const SNAPSHOT_DIR: &str = "snapshots";
+
+const MAX_LABEL_CHARS: usize = 200;
+
+const WEEKDAYS: [&str; 7] = ["пн", "вт", "ср", "чт", "пт", "сб", "вс"];
+
+/// Снимок расписания
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+    pub id: String,
+    pub label: String,
+    /// ГГГГ-ММ-ДДTЧЧ:ММ:СС
+    pub created_at: String,
+    pub schedule: Schedule,
+}
+
+/// Снимок в списке, без расписания
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotInfo {
+    pub id: String,
+    pub label: String,
+    pub created_at: String,
+    pub operations: usize,
+}
+
+/// Вид изменения
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    /// Перенесена на другое время
+    Moved,
+    /// Передана другому исполнителю
+    Reassigned,
+    /// Изменилась длительность или название
+    Changed,
+    BlockedAdded,
+    BlockedRemoved,
+}
+
+/// Одно изменение между снимками
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub worker: String,
+    /// Техкарта и операция; для событий - название события
+    pub card: String,
+    pub operation: String,
+    pub message: String,
+}
+
+/// Разница между снимками a и b
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotDiff {
+    pub from: SnapshotInfo,
+    pub to: SnapshotInfo,
+    pub changes: Vec<Change>,
+}
+
+fn snapshot_dir() -> Result<PathBuf, String> {
+    paths::app_data_dir()
+        .map(|dir| dir.join(SNAPSHOT_DIR))
+        .ok_or_else(|| "Не удалось определить папку данных приложения".into())
+}
+
+// Идентификатор - время создания, он же имя файла; допускаются только цифры и дефис
+fn snapshot_path(id: &str) -> Result<PathBuf, String> {
+    if id.is_empty() || id.len() > 32 || !id.chars().all(|c| c.is_ascii_digit() || c == '-') {
+        return Err("Недопустимый идентификатор снимка".into());
+    }
+    Ok(snapshot_dir()?.join(format!("{}.json", id)))
+}
+
+impl Snapshot {
+    fn info(&self) -> SnapshotInfo {
+        SnapshotInfo {
+            id: self.id.clone(),
+            label: self.label.clone(),
+            created_at: self.created_at.clone(),
+            operations: self.schedule.entries.iter().map(|e| e.rows.len()).sum(),
+        }
+    }
+}
+
+/// Сохраняет снимок расписания с меткой
+pub fn create(label: &str, schedule: Schedule) -> Result<SnapshotInfo, String> {
+    let label = label.trim();
+    if label.chars().count() > MAX_LABEL_CHARS {
+        return Err(format!("Метка снимка длиннее {} символов", MAX_LABEL_CHARS));
+    }
+    let now = Local::now();
+    let mut id = now.format("%Y%m%d-%H%M%S-%3f").to_string();
+    // Два снимка в одну миллисекунду получают разные имена
+    let mut n = 1;
+    while snapshot_path(&id)?.exists() {
+        id = format!("{}-{}", now.format("%Y%m%d-%H%M%S-%3f"), n);
+        n += 1;
+    }
+    let snapshot = Snapshot {
+        id,
+        label: label.to_string(),
+        created_at: now.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        schedule,
+    };
+    let content = serde_json::to_string(&snapshot).map_err(|e| format!("Ошибка сохранения снимка: {}", e))?;
+    let path = snapshot_path(&snapshot.id)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| paths::io_error_message("Ошибка создания папки снимков", &e))?;
+    }
+    let temp = path.with_extension("json.tmp");
+    std::fs::write(&temp, content)
+        .and_then(|_| std::fs::rename(&temp, &path))
+        .map_err(|e| {
+            let _ = std::fs::remove_file(&temp);
+            paths::io_error_message("Ошибка записи снимка", &e)
+        })?;
+    Ok(snapshot.info())
+}
+
+/// Читает снимок
+pub fn load(id: &str) -> Result<Snapshot, String> {
+    let path = snapshot_path(id)?;
+    let raw = match std::fs::read_to_string(&path) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err("Снимок не найден".into()),
+        Err(e) => return Err(paths::io_error_message("Ошибка чтения снимка", &e)),
+    };
+    serde_json::from_str(&raw).map_err(|e| format!("Снимок повреждён: {}", e))
+}
+
+/// Снимки, новые первыми; нечитаемые файлы пропускаются
+pub fn list() -> Vec<SnapshotInfo> {
+    let Some(entries) = snapshot_dir().ok().and_then(|dir| std::fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    let mut list: Vec<SnapshotInfo> = entries
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|e| std::fs::read_to_string(e.path()).ok())
+        .filter_map(|raw| serde_json::from_str::<Snapshot>(&raw).ok())
+        .map(|s| s.info())
+        .collect();
+    list.sort_by(|a, b| b.id.cmp(&a.id));
+    list
+}
+
+/// Удаляет снимок
+pub fn delete(id: &str) -> Result<(), String> {
+    match std::fs::remove_file(snapshot_path(id)?) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err("Снимок не найден".into()),
+        Err(e) => Err(paths::io_error_message("Ошибка удаления снимка", &e)),
+    }
+}
+
+/// Удаляет остатки оборванной записи снимков (.json.tmp). Сами снимки создаёт
+/// пользователь и ни на что не ссылаются, поэтому они не удаляются.
+/// Возвращает число удалённых файлов и их размер
+pub fn prune_leftovers() -> (usize, u64) {
+    let Some(entries) = snapshot_dir().ok().and_then(|dir| std::fs::read_dir(dir).ok()) else {
+        return (0, 0);
+    };
+    let (mut files, mut bytes) = (0, 0);
+    for entry in entries.flatten() {
+        if !entry.file_name().to_string_lossy().ends_with(".json.tmp") {
+            continue;
+        }
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        if std::fs::remove_file(entry.path()).is_ok() {
+            files += 1;
+            bytes += size;
+        }
+    }
+    (files, bytes)
+}
+
+/// Сравнивает два снимка: что изменилось от a к b
+pub fn diff(a: &str, b: &str) -> Result<SnapshotDiff, String> {
+    let from = load(a)?;
+    let to = load(b)?;
+    let changes = diff_schedules(&from.schedule, &to.schedule);
+    Ok(SnapshotDiff { from: from.info(), to: to.info(), changes })
+}
+
+// Операция, сопоставляемая между снимками
+struct Op<'a> {
+    card: &'a str,
+    row: &'a OperationRow,
+}
+
+// Ключ сопоставления: техкарта, номер операции, номер исполнителя и порядковый номер
+// среди строк с тем же ключом (одна техкарта может быть рассчитана несколько раз)
+type OpKey = (String, String, u32, usize);
+
+fn keyed(schedule: &Schedule) -> Vec<(OpKey, Op<'_>)> {
+    let mut seen: HashMap<(String, String, u32), usize> = HashMap::new();
+    let mut ops = Vec::new();
+    for entry in &schedule.entries {
+        let card = entry.card_name();
+        for row in &entry.rows {
+            let number = if row.original_op_index.is_empty() { row.op_idx.clone() } else { row.original_op_index.clone() };
+            let base = (card.to_string(), number, row.worker_index.unwrap_or(1));
+            let n = seen.entry(base.clone()).or_default();
+            *n += 1;
+            ops.push(((base.0, base.1, base.2, *n), Op { card, row }));
+        }
+    }
+    ops
+}
+
+fn diff_schedules(from: &Schedule, to: &Schedule) -> Vec<Change> {
+    let old = keyed(from);
+    let new = keyed(to);
+    let old_index: HashMap<&OpKey, &Op> = old.iter().map(|(k, op)| (k, op)).collect();
+    let new_index: HashMap<&OpKey, &Op> = new.iter().map(|(k, op)| (k, op)).collect();
+    let mut changes = Vec::new();
+
+    for (key, op) in &old {
+        if !new_index.contains_key(key) {
+            changes.push(change(ChangeKind::Removed, op, format!("{}: снята операция {} ({})", worker(op.row), title(op), when(op.row))));
+        }
+    }
+    for (key, op) in &new {
+        let Some(before) = old_index.get(key) else {
+            changes.push(change(ChangeKind::Added, op, format!("{}: добавлена операция {} ({})", worker(op.row), title(op), when(op.row))));
+            continue;
+        };
+        let (a, b) = (before.row, op.row);
+        if a.worker.trim() != b.worker.trim() {
+            changes.push(change(
+                ChangeKind::Reassigned,
+                op,
+                format!("{}: {} → {}", title(op), worker(a), worker(b)),
+            ));
+        }
+        if a.start() != b.start() || a.end() != b.end() {
+            changes.push(change(ChangeKind::Moved, op, format!("{}: {} {} → {}", worker(b), title(op), when(a), when(b))));
+        }
+        if a.name.trim() != b.name.trim() {
+            changes.push(change(ChangeKind::Changed, op, format!("{}: {} переименована в «{}»", worker(b), title(before), b.name)));
+        }
+        if a.duration_minutes() != b.duration_minutes() {
+            changes.push(change(
+                ChangeKind::Changed,
+                op,
+                format!("{}: {} {} {} → {} {}", worker(b), title(op), a.dur_val, a.unit_label(), b.dur_val, b.unit_label()),
+            ));
+        }
+    }
+
+    let slot_key = |s: &BlockedSlot| (s.title.trim().to_string(), s.worker.trim().to_string(), s.start(), s.end());
+    for slot in &from.blocked {
+        if !to.blocked.iter().any(|s| slot_key(s) == slot_key(slot)) {
+            changes.push(slot_change(ChangeKind::BlockedRemoved, slot, "снято событие"));
+        }
+    }
+    for slot in &to.blocked {
+        if !from.blocked.iter().any(|s| slot_key(s) == slot_key(slot)) {
+            changes.push(slot_change(ChangeKind::BlockedAdded, slot, "добавлено событие"));
+        }
+    }
+    changes
+}
+
+fn change(kind: ChangeKind, op: &Op, message: String) -> Change {
+    Change {
+        kind,
+        worker: op.row.worker.trim().to_string(),
+        card: op.card.to_string(),
+        operation: op.row.name.clone(),
+        message,
+    }
+}
+
+fn slot_change(kind: ChangeKind, slot: &BlockedSlot, action: &str) -> Change {
+    let who = if slot.worker.trim().is_empty() { "Все исполнители" } else { slot.worker.trim() };
+    let start = slot.start().map(format_time).unwrap_or_else(|| "время не указано".into());
+    Change {
+        kind,
+        worker: slot.worker.trim().to_string(),
+        card: slot.title.clone(),
+        operation: String::new(),
+        message: format!("{}: {} «{}» ({})", who, action, slot.title, start),
+    }
+}
+
+fn worker(row: &OperationRow) -> &str {
+    match row.worker.trim() {
+        "" => "Без исполнителя",
+        worker => worker,
+    }
+}
+
+// «Сборка» (Техкарта 12)
+fn title(op: &Op) -> String {
+    if op.card.is_empty() {
+        format!("«{}»", op.row.name)
+    } else {
+        format!("«{}» ({})", op.row.name, op.card)
+    }
+}
+
+fn when(row: &OperationRow) -> String {
+    match (row.start(), row.end()) {
+        (Some(start), Some(end)) if start.date() == end.date() => {
+            format!("{}-{}", format_time(start), end.format("%H:%M"))
+        }
+        (Some(start), Some(end)) => format!("{} - {}", format_time(start), format_time(end)),
+        (Some(start), None) => format_time(start),
+        _ => "время не указано".into(),
+    }
+}
+
+// вт 12.03 09:00
+fn format_time(time: NaiveDateTime) -> String {
+    format!("{} {}", WEEKDAYS[time.weekday().num_days_from_monday() as usize], time.format("%d.%m %H:%M"))
+}
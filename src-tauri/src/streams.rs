@@ -0,0 +1,229 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Передача больших файлов частями. Файл больше MAX_FILE_SIZE не помещается в одну
+// строку IPC, поэтому фронтенд открывает сеанс и передаёт или получает его кусками.
+// Запись идёт во временный файл рядом с целевым и заменяет его только после
+// finish_write: оборванная передача не портит уже сохранённый файл.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::{backups, cloud, integrity, paths};
+
+/// Наибольший размер одной части
+pub const MAX_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// Наибольший размер файла, передаваемого частями
+pub const MAX_STREAM_SIZE: u64 = 512 * 1024 * 1024;
+
+// Сеанс без обращений дольше этого времени закрывается
+const SESSION_TTL: Duration = Duration::from_secs(10 * 60);
+
+// Одновременно открытых сеансов каждого вида
+const MAX_SESSIONS: usize = 8;
+
+struct WriteSession {
+    target: PathBuf,
+    temp: PathBuf,
+    file: File,
+    written: u64,
+    // Контрольная сумма считается по ходу записи
+    hasher: Sha256,
+    touched: Instant,
+}
+
+struct ReadSession {
+    file: File,
+    size: u64,
+    touched: Instant,
+}
+
+static WRITES: LazyLock<Mutex<HashMap<String, WriteSession>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+static READS: LazyLock<Mutex<HashMap<String, ReadSession>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Открытый сеанс чтения
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadSessionInfo {
+    pub session: String,
+    pub size: u64,
+}
+
+fn new_id() -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    format!("{:x}-{:08x}", NEXT_ID.fetch_add(1, Ordering::Relaxed), nanos)
+}
+
+fn lock_error() -> String {
+    "Ошибка доступа к сеансам передачи".into()
+}
+
+fn not_found() -> String {
+    "Сеанс передачи не найден или закрыт по таймауту".into()
+}
+
+/// Закрывает сеансы, к которым давно не обращались; недописанные файлы удаляются
+fn expire() {
+    if let Ok(mut writes) = WRITES.lock() {
+        let stale: Vec<String> = writes
+            .iter()
+            .filter(|(_, s)| s.touched.elapsed() > SESSION_TTL)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in stale {
+            if let Some(session) = writes.remove(&id) {
+                drop(session.file);
+                let _ = std::fs::remove_file(paths::to_fs_path(&session.temp));
+            }
+        }
+    }
+    if let Ok(mut reads) = READS.lock() {
+        reads.retain(|_, s| s.touched.elapsed() <= SESSION_TTL);
+    }
+}
+
+/// Открывает запись в target (путь уже проверен), возвращает идентификатор сеанса
+pub fn open_write(target: &Path) -> Result<String, String> {
+    expire();
+    let mut writes = WRITES.lock().map_err(|_| lock_error())?;
+    if writes.len() >= MAX_SESSIONS {
+        return Err("Слишком много одновременных передач, дождитесь окончания предыдущих".into());
+    }
+    if writes.values().any(|s| paths::same_path(&s.target, target)) {
+        return Err("В этот файл уже идёт запись".into());
+    }
+
+    let id = new_id();
+    let name = target.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let temp = target.with_file_name(format!(".{}.{}.part", name, id));
+    let file = File::create(paths::to_fs_path(&temp))
+        .map_err(|e| paths::io_error_message("Ошибка создания временного файла", &e))?;
+    writes.insert(
+        id.clone(),
+        WriteSession { target: target.to_path_buf(), temp, file, written: 0, hasher: Sha256::new(), touched: Instant::now() },
+    );
+    Ok(id)
+}
+
+/// Дописывает часть, возвращает общий записанный объём
+pub fn write_chunk(id: &str, chunk: &[u8]) -> Result<u64, String> {
+    if chunk.len() > MAX_CHUNK_SIZE {
+        return Err(format!("Часть больше {} МБ", MAX_CHUNK_SIZE / 1024 / 1024));
+    }
+    let mut writes = WRITES.lock().map_err(|_| lock_error())?;
+    let session = writes.get_mut(id).ok_or_else(not_found)?;
+    if session.written + chunk.len() as u64 > MAX_STREAM_SIZE {
+        return Err(format!("Размер файла превышает максимальный ({} МБ)", MAX_STREAM_SIZE / 1024 / 1024));
+    }
+    session
+        .file
+        .write_all(chunk)
+        .map_err(|e| paths::io_error_message("Ошибка записи", &e))?;
+    session.written += chunk.len() as u64;
+    session.hasher.update(chunk);
+    session.touched = Instant::now();
+    Ok(session.written)
+}
+
+/// Завершает запись: сбрасывает данные на диск и заменяет целевой файл временным.
+/// Возвращает путь и SHA-256 записанного содержимого
+pub fn finish_write(id: &str) -> Result<(PathBuf, String), String> {
+    let session = WRITES
+        .lock()
+        .map_err(|_| lock_error())?
+        .remove(id)
+        .ok_or_else(not_found)?;
+    let temp = paths::to_fs_path(&session.temp);
+    if let Err(e) = backups::rotate(&session.target) {
+        let _ = std::fs::remove_file(&temp);
+        return Err(e);
+    }
+    integrity::forget(&session.target);
+    let result = session
+        .file
+        .sync_all()
+        .and_then(|_| std::fs::rename(&temp, paths::to_fs_path(&session.target)));
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&temp);
+        return Err(paths::io_error_message("Ошибка записи", &e));
+    }
+    Ok((session.target, format!("{:x}", session.hasher.finalize())))
+}
+
+/// Отменяет запись; целевой файл остаётся прежним
+pub fn abort_write(id: &str) -> Result<(), String> {
+    let session = WRITES
+        .lock()
+        .map_err(|_| lock_error())?
+        .remove(id)
+        .ok_or_else(not_found)?;
+    drop(session.file);
+    std::fs::remove_file(paths::to_fs_path(&session.temp))
+        .map_err(|e| paths::io_error_message("Ошибка удаления временного файла", &e))
+}
+
+/// Открывает чтение файла (путь уже проверен)
+pub fn open_read(path: &Path) -> Result<ReadSessionInfo, String> {
+    expire();
+    let target = paths::to_fs_path(path);
+    let metadata = std::fs::metadata(&target)
+        .map_err(|e| paths::io_error_message("Ошибка получения информации о файле", &e))?;
+    if metadata.len() > MAX_STREAM_SIZE {
+        return Err(format!("Размер файла превышает максимальный ({} МБ)", MAX_STREAM_SIZE / 1024 / 1024));
+    }
+    // Чтение большой заглушки частями зависло бы на загрузке из облака
+    if cloud::is_placeholder(&metadata) {
+        return Err(cloud::online_only_message(path));
+    }
+
+    let mut reads = READS.lock().map_err(|_| lock_error())?;
+    if reads.len() >= MAX_SESSIONS {
+        return Err("Слишком много одновременных передач, дождитесь окончания предыдущих".into());
+    }
+    let file = File::open(&target).map_err(|e| paths::io_error_message("Ошибка чтения", &e))?;
+    let id = new_id();
+    reads.insert(id.clone(), ReadSession { file, size: metadata.len(), touched: Instant::now() });
+    Ok(ReadSessionInfo { session: id, size: metadata.len() })
+}
+
+/// Читает до length байт с позиции offset; пустой результат - конец файла
+pub fn read_chunk(id: &str, offset: u64, length: usize) -> Result<Vec<u8>, String> {
+    let length = length.min(MAX_CHUNK_SIZE);
+    let mut reads = READS.lock().map_err(|_| lock_error())?;
+    let session = reads.get_mut(id).ok_or_else(not_found)?;
+    session.touched = Instant::now();
+    if offset >= session.size {
+        return Ok(Vec::new());
+    }
+
+    let mut buffer = Vec::with_capacity(length);
+    session
+        .file
+        .seek(SeekFrom::Start(offset))
+        .and_then(|_| (&mut session.file).take(length as u64).read_to_end(&mut buffer))
+        .map_err(|e| paths::io_error_message("Ошибка чтения", &e))?;
+    Ok(buffer)
+}
+
+/// Закрывает сеанс чтения
+pub fn close_read(id: &str) -> Result<(), String> {
+    READS
+        .lock()
+        .map_err(|_| lock_error())?
+        .remove(id)
+        .map(|_| ())
+        .ok_or_else(not_found)
+}
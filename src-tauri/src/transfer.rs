@@ -0,0 +1,415 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Полная резервная копия приложения для переноса на другой компьютер: одним zip-файлом
+// сохраняются папка настроек (настройки, шаблоны и логотип выгрузок, календари, данные
+// фронтенда) и папка данных (автосохранения, снимки, архив расписаний).
+//
+// Состав копии:
+// - manifest.json - формат, версия программы, время создания и разрешённые папки;
+// - config/... - файлы папки настроек;
+// - data/... - файлы папки данных; архив записан согласованной копией (VACUUM INTO).
+//
+// Подписанные списки разрешённых папок не копируются: подпись проверяется ключом этого
+// компьютера и на другом не сойдётся. Папки перечислены в манифесте, и при восстановлении
+// пользователь подтверждает доступ к тем из них, что есть на новом компьютере.
+//
+// Восстановление дополняет папки приложения: файлы из копии заменяют одноимённые,
+// остальные файлы остаются. Каждый файл пишется во временный и заменяется переименованием.
+
+use std::io::{Cursor, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::{allowlist, archive, paths};
+
+const FORMAT: &str = "time-to-table-backup";
+const FORMAT_VERSION: u32 = 1;
+
+const MANIFEST_FILE: &str = "manifest.json";
+const CONFIG_PREFIX: &str = "config/";
+const DATA_PREFIX: &str = "data/";
+
+// Наибольший суммарный размер файлов в копии
+const MAX_BACKUP_SIZE: u64 = 512 * 1024 * 1024;
+
+// Наибольшее число файлов в копии
+const MAX_FILES: usize = 20_000;
+
+// Наибольший размер манифеста
+const MAX_MANIFEST_SIZE: u64 = 1024 * 1024;
+
+/// Манифест копии
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    format: String,
+    version: u32,
+    app_version: String,
+    /// ГГГГ-ММ-ДДTЧЧ:ММ:СС
+    created_at: String,
+    #[serde(default)]
+    allowed_dirs: Vec<PathBuf>,
+    #[serde(default)]
+    network_dirs: Vec<PathBuf>,
+}
+
+/// Итог создания копии
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupReport {
+    pub files: usize,
+    /// Суммарный размер файлов до сжатия, байт
+    pub bytes: u64,
+}
+
+/// Распакованная копия: файлы уже на месте, разрешённые папки ждут подтверждения
+#[derive(Debug, Clone)]
+pub struct Restored {
+    pub files: usize,
+    pub allowed_dirs: Vec<PathBuf>,
+    pub network_dirs: Vec<PathBuf>,
+}
+
+impl Restored {
+    /// Папки из копии, которые есть на этом компьютере
+    pub fn present_dirs(&self) -> Vec<PathBuf> {
+        self.allowed_dirs
+            .iter()
+            .chain(&self.network_dirs)
+            .filter(|dir| paths::to_fs_path(dir).is_dir())
+            .cloned()
+            .collect()
+    }
+}
+
+/// Итог восстановления
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreReport {
+    pub files: usize,
+    pub granted_dirs: Vec<String>,
+    /// Папки, которых нет на этом компьютере или доступ к которым не подтверждён
+    pub skipped_dirs: Vec<String>,
+    /// Настройки читаются при запуске, поэтому программу нужно перезапустить
+    pub restart_required: bool,
+}
+
+// Корень копии, к которому относится запись
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Root {
+    Config,
+    Data,
+}
+
+impl Root {
+    fn dir(self) -> Result<PathBuf, String> {
+        match self {
+            Root::Config => paths::app_config_dir().ok_or_else(|| "Не удалось определить папку настроек".into()),
+            Root::Data => paths::app_data_dir().ok_or_else(|| "Не удалось определить папку данных приложения".into()),
+        }
+    }
+
+    fn prefix(self) -> &'static str {
+        match self {
+            Root::Config => CONFIG_PREFIX,
+            Root::Data => DATA_PREFIX,
+        }
+    }
+}
+
+// Файл не переносится: подписанные списки, лимиты вызовов этого компьютера,
+// служебные копии архива и незавершённые записи
+fn is_excluded(root: Root, rel: &str) -> bool {
+    let top_level = !rel.contains('/');
+    let name = rel.rsplit('/').next().unwrap_or(rel);
+    if name.ends_with(".tmp") || name.ends_with(".restore") {
+        return true;
+    }
+    match root {
+        Root::Config => top_level && (allowlist::SIGNED_FILES.contains(&rel) || rel == crate::RATE_LIMITS_FILE),
+        Root::Data => top_level && rel != archive::DB_FILE && rel.starts_with(archive::DB_FILE),
+    }
+}
+
+// Файлы папки dir (рекурсивно) как пары (путь в копии через /, путь на диске).
+// Символические ссылки пропускаются: копия не выходит за папки приложения
+fn collect(root: Root, base: &Path, dir: &Path, out: &mut Vec<(String, PathBuf)>) -> Result<(), String> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(paths::io_error_message("Ошибка чтения папки приложения", &e)),
+    };
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let path = entry.path();
+        if file_type.is_dir() {
+            collect(root, base, &path, out)?;
+            continue;
+        }
+        if !file_type.is_file() {
+            continue;
+        }
+        let Some(rel) = path
+            .strip_prefix(base)
+            .ok()
+            .and_then(|rel| rel.to_str())
+            .map(|rel| rel.replace('\\', "/"))
+        else {
+            continue;
+        };
+        // Архив открыт на запись, он попадает в копию согласованным снимком
+        let is_archive = root == Root::Data && rel == archive::DB_FILE;
+        if !is_excluded(root, &rel) && !is_archive {
+            out.push((format!("{}{}", root.prefix(), rel), path));
+        }
+    }
+    Ok(())
+}
+
+/// Собирает полную резервную копию в zip
+pub fn backup() -> Result<(Vec<u8>, BackupReport), String> {
+    let mut files = Vec::new();
+    for root in [Root::Config, Root::Data] {
+        let base = root.dir()?;
+        collect(root, &base, &base, &mut files)?;
+    }
+    if files.len() > MAX_FILES {
+        return Err(format!("В папках приложения больше {} файлов, копия не создана", MAX_FILES));
+    }
+
+    let manifest = Manifest {
+        format: FORMAT.into(),
+        version: FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").into(),
+        created_at: Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+        allowed_dirs: allowlist::dirs(),
+        network_dirs: allowlist::network_dirs(),
+    };
+    let manifest =
+        serde_json::to_vec_pretty(&manifest).map_err(|e| format!("Ошибка формирования манифеста копии: {}", e))?;
+
+    let zip_error = |e: zip::result::ZipError| format!("Ошибка формирования резервной копии: {}", e);
+    let io_error = |e: std::io::Error| format!("Ошибка формирования резервной копии: {}", e);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file(MANIFEST_FILE, options).map_err(zip_error)?;
+    zip.write_all(&manifest).map_err(io_error)?;
+
+    let mut report = BackupReport { files: 0, bytes: 0 };
+    let mut add = |zip: &mut ZipWriter<Cursor<Vec<u8>>>, name: &str, content: &[u8]| -> Result<(), String> {
+        report.bytes += content.len() as u64;
+        if report.bytes > MAX_BACKUP_SIZE {
+            return Err(format!(
+                "Данные приложения больше {} МБ, копия не создана",
+                MAX_BACKUP_SIZE / 1024 / 1024
+            ));
+        }
+        report.files += 1;
+        zip.start_file(name, options).map_err(zip_error)?;
+        zip.write_all(content).map_err(io_error)
+    };
+    for (name, path) in &files {
+        let content = match std::fs::read(path) {
+            Ok(content) => content,
+            // Файл удалили, пока собиралась копия (например, очистка автосохранений)
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(paths::io_error_message(&format!("Ошибка чтения {}", name), &e)),
+        };
+        add(&mut zip, name, &content)?;
+    }
+    if let Some(db) = archive::snapshot()? {
+        add(&mut zip, &format!("{}{}", DATA_PREFIX, archive::DB_FILE), &db)?;
+    }
+
+    let bytes = zip.finish().map_err(zip_error)?.into_inner();
+    Ok((bytes, report))
+}
+
+// Корень и путь внутри него для записи копии; None - запись не восстанавливается.
+// Допускаются только обычные компоненты пути: без «..», корня диска и префиксов Windows
+fn target_of(name: &str) -> Option<(Root, PathBuf)> {
+    let (root, rel) = if let Some(rel) = name.strip_prefix(CONFIG_PREFIX) {
+        (Root::Config, rel)
+    } else if let Some(rel) = name.strip_prefix(DATA_PREFIX) {
+        (Root::Data, rel)
+    } else {
+        return None;
+    };
+    if rel.is_empty() || rel.contains('\\') || is_excluded(root, rel) {
+        return None;
+    }
+    let path = Path::new(rel);
+    if !path.components().all(|c| matches!(c, Component::Normal(_))) {
+        return None;
+    }
+    if path.components().any(|c| paths::check_file_name(Path::new(c.as_os_str())).is_err()) {
+        return None;
+    }
+    Some((root, path.to_path_buf()))
+}
+
+fn write_replacing(path: &Path, content: &[u8]) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| paths::io_error_message("Ошибка создания папки приложения", &e))?;
+    }
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".restore");
+    let temp = path.with_file_name(name);
+    std::fs::write(&temp, content)
+        .and_then(|_| std::fs::rename(&temp, path))
+        .map_err(|e| {
+            let _ = std::fs::remove_file(&temp);
+            paths::io_error_message("Ошибка восстановления файла", &e)
+        })
+}
+
+/// Восстанавливает копию по пути path. Все записи проверяются до распаковки:
+/// копия с чужим форматом, лишними путями или сверх размера не меняет ни одного файла
+pub fn restore(path: &Path) -> Result<Restored, String> {
+    let file = std::fs::File::open(paths::to_fs_path(path))
+        .map_err(|e| paths::io_error_message("Ошибка открытия резервной копии", &e))?;
+    let mut zip = ZipArchive::new(file).map_err(|_| "Файл не является резервной копией программы".to_string())?;
+
+    let manifest: Manifest = {
+        let entry = zip
+            .by_name(MANIFEST_FILE)
+            .map_err(|_| "В резервной копии нет манифеста".to_string())?;
+        let mut raw = Vec::new();
+        entry
+            .take(MAX_MANIFEST_SIZE)
+            .read_to_end(&mut raw)
+            .map_err(|e| format!("Ошибка чтения манифеста копии: {}", e))?;
+        serde_json::from_slice(&raw).map_err(|e| format!("Повреждён манифест копии: {}", e))?
+    };
+    if manifest.format != FORMAT {
+        return Err("Файл не является резервной копией программы".into());
+    }
+    if manifest.version > FORMAT_VERSION {
+        return Err(format!(
+            "Копия создана более новой версией программы ({}). Обновите программу",
+            manifest.app_version
+        ));
+    }
+
+    let mut targets = Vec::new();
+    let mut total = 0u64;
+    for index in 0..zip.len() {
+        let entry = zip
+            .by_index(index)
+            .map_err(|e| format!("Ошибка чтения резервной копии: {}", e))?;
+        if entry.is_dir() || entry.name() == MANIFEST_FILE {
+            continue;
+        }
+        let Some(target) = target_of(entry.name()) else {
+            return Err(format!("Недопустимый путь в резервной копии: {}", entry.name()));
+        };
+        total += entry.size();
+        if total > MAX_BACKUP_SIZE {
+            return Err(format!("Копия больше {} МБ после распаковки", MAX_BACKUP_SIZE / 1024 / 1024));
+        }
+        targets.push((index, target));
+    }
+    if targets.len() > MAX_FILES {
+        return Err(format!("В копии больше {} файлов", MAX_FILES));
+    }
+
+    let config = Root::Config.dir()?;
+    let data = Root::Data.dir()?;
+    for (index, (root, rel)) in &targets {
+        let entry = zip
+            .by_index(*index)
+            .map_err(|e| format!("Ошибка чтения резервной копии: {}", e))?;
+        let declared = entry.size();
+        let mut content = Vec::new();
+        // Размер в заголовке записи проверен выше; читается не больше заявленного
+        entry
+            .take(declared + 1)
+            .read_to_end(&mut content)
+            .map_err(|e| format!("Ошибка чтения резервной копии: {}", e))?;
+        if content.len() as u64 > declared {
+            return Err("Повреждена резервная копия: размер файла не совпадает с заявленным".into());
+        }
+        let base = if *root == Root::Config { &config } else { &data };
+        write_replacing(&base.join(rel), &content)?;
+    }
+
+    Ok(Restored {
+        files: targets.len(),
+        allowed_dirs: manifest.allowed_dirs,
+        network_dirs: manifest.network_dirs,
+    })
+}
+
+/// Разрешает папки из копии, если пользователь подтвердил доступ к ним
+pub fn grant(restored: Restored, confirmed: bool) -> RestoreReport {
+    let mut report = RestoreReport {
+        files: restored.files,
+        granted_dirs: Vec::new(),
+        skipped_dirs: Vec::new(),
+        restart_required: restored.files > 0,
+    };
+    let dirs = restored.allowed_dirs.iter().map(|dir| (dir, false));
+    let network = restored.network_dirs.iter().map(|dir| (dir, true));
+    for (dir, is_network) in dirs.chain(network) {
+        let granted = confirmed
+            && if is_network {
+                paths::is_network_path(dir) && allowlist::add_network(dir).is_ok()
+            } else {
+                allowlist::add(dir).is_ok()
+            };
+        let shown = dir.to_string_lossy().to_string();
+        if granted {
+            report.granted_dirs.push(shown);
+        } else {
+            report.skipped_dirs.push(shown);
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_map_to_app_folders() {
+        assert_eq!(target_of("config/settings.json"), Some((Root::Config, PathBuf::from("settings.json"))));
+        assert_eq!(
+            target_of("data/snapshots/1.json"),
+            Some((Root::Data, PathBuf::from("snapshots").join("1.json")))
+        );
+        assert_eq!(target_of("data/archive.sqlite3"), Some((Root::Data, PathBuf::from("archive.sqlite3"))));
+    }
+
+    #[test]
+    fn entries_outside_app_folders_are_rejected() {
+        for name in [
+            "settings.json",
+            "config/",
+            "config/../settings.json",
+            "data/snapshots/../../x.json",
+            "config//etc/passwd",
+            "config/C:/x.json",
+            "config\\x.json",
+            "data/CON.json",
+        ] {
+            assert_eq!(target_of(name), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn signed_lists_are_not_restored() {
+        assert_eq!(target_of("config/allowed_dirs.json"), None);
+        assert_eq!(target_of("config/allowed_dirs.key"), None);
+        assert_eq!(target_of("config/rate_limits.json"), None);
+        // Одноимённый файл во вложенной папке - обычные данные
+        assert!(target_of("config/user/allowed_dirs.json").is_some());
+    }
+}
@@ -0,0 +1,165 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Смысловая проверка расписания. Схема (schema.rs) проверяет форму файла, а здесь
+// проверяется содержание: у операций есть исполнитель и корректное время, исполнитель
+// не занят двумя операциями сразу и не назначен на время внешнего события.
+//
+// Отдельного справочника исполнителей в расписании нет. Если фронтенд передаёт
+// список известных исполнителей, операции с другими исполнителями считаются ошибкой;
+// без списка проверяются только события, назначенные исполнителю без операций.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::model::Schedule;
+use crate::schedule::conflicts::{self, ConflictKind, ConflictSide};
+
+// После стольких замечаний проверка останавливается
+const MAX_ISSUES: usize = 200;
+
+/// Вид замечания
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// Документ не читается как расписание
+    Format,
+    /// Не заполнено обязательное поле
+    MissingField,
+    /// Дата или время не разбираются
+    InvalidTime,
+    /// Окончание раньше начала
+    EndBeforeStart,
+    /// Исполнителя нет в списке известных
+    UnknownWorker,
+    /// Исполнитель занят двумя операциями одновременно
+    Overlap,
+    /// Операция попадает на время внешнего события
+    Blocked,
+}
+
+/// Замечание к расписанию
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleIssue {
+    pub kind: IssueKind,
+    /// Место в документе (JSON Pointer)
+    pub pointer: String,
+    /// Второе место для пересечений: другая операция или событие
+    pub other: Option<String>,
+    pub worker: Option<String>,
+    pub message: String,
+}
+
+/// Результат проверки
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleReport {
+    pub valid: bool,
+    pub issues: Vec<ScheduleIssue>,
+    /// Проверка остановлена после MAX_ISSUES замечаний
+    pub truncated: bool,
+}
+
+#[derive(Default)]
+struct Collector {
+    issues: Vec<ScheduleIssue>,
+    truncated: bool,
+}
+
+impl Collector {
+    fn push(&mut self, kind: IssueKind, pointer: String, other: Option<String>, worker: Option<&str>, message: String) {
+        if self.issues.len() >= MAX_ISSUES {
+            self.truncated = true;
+            return;
+        }
+        self.issues.push(ScheduleIssue { kind, pointer, other, worker: worker.map(str::to_string), message });
+    }
+}
+
+/// Проверяет расписание в виде JSON; known_workers - список известных исполнителей
+pub fn validate(value: Value, known_workers: Option<&[String]>) -> ScheduleReport {
+    let mut out = Collector::default();
+    match serde_json::from_value::<Schedule>(value) {
+        Ok(schedule) => check(&schedule, known_workers, &mut out),
+        Err(e) => out.push(IssueKind::Format, String::new(), None, None, format!("Документ не является расписанием: {}", e)),
+    }
+    ScheduleReport { valid: out.issues.is_empty(), issues: out.issues, truncated: out.truncated }
+}
+
+fn check(schedule: &Schedule, known_workers: Option<&[String]>, out: &mut Collector) {
+    let is_known = |worker: &str| known_workers.is_none_or(|list| list.iter().any(|w| w.trim() == worker));
+
+    for (e, entry) in schedule.entries.iter().enumerate() {
+        if entry.title.trim().is_empty() {
+            out.push(IssueKind::MissingField, format!("/entries/{}/title", e), None, None, "Не указан заголовок записи".into());
+        }
+        for (r, row) in entry.rows.iter().enumerate() {
+            let pointer = format!("/entries/{}/rows/{}", e, r);
+            let worker = row.worker.trim();
+            if row.name.trim().is_empty() {
+                out.push(IssueKind::MissingField, format!("{}/name", pointer), None, None, "Не указано название операции".into());
+            }
+            if worker.is_empty() {
+                out.push(IssueKind::MissingField, format!("{}/worker", pointer), None, None, "Не указан исполнитель".into());
+            } else if !is_known(worker) {
+                out.push(
+                    IssueKind::UnknownWorker,
+                    format!("{}/worker", pointer),
+                    None,
+                    Some(worker),
+                    format!("Исполнитель «{}» не найден в списке исполнителей", worker),
+                );
+            }
+            match (row.start(), row.end()) {
+                (Some(start), Some(end)) if end < start => out.push(
+                    IssueKind::EndBeforeStart,
+                    pointer,
+                    None,
+                    Some(worker),
+                    "Окончание операции раньше начала".into(),
+                ),
+                (Some(_), Some(_)) => {}
+                _ => out.push(IssueKind::InvalidTime, pointer, None, Some(worker), "Не разобраны дата или время операции".into()),
+            }
+        }
+    }
+
+    let workers = schedule.workers();
+    for (b, slot) in schedule.blocked.iter().enumerate() {
+        let pointer = format!("/blocked/{}", b);
+        let worker = slot.worker.trim();
+        if !worker.is_empty() && !workers.iter().any(|w| w == worker) && !is_known(worker) {
+            out.push(
+                IssueKind::UnknownWorker,
+                format!("{}/worker", pointer),
+                None,
+                Some(worker),
+                format!("Событие назначено исполнителю «{}», которого нет в расписании", worker),
+            );
+        }
+        match (slot.start(), slot.end()) {
+            (Some(start), Some(end)) if end < start => {
+                out.push(IssueKind::EndBeforeStart, pointer, None, None, "Окончание события раньше начала".into())
+            }
+            (Some(_), Some(_)) => {}
+            _ => out.push(IssueKind::InvalidTime, pointer, None, None, "Не разобраны дата или время события".into()),
+        }
+    }
+
+    for conflict in conflicts::find(schedule) {
+        let pointer = |side: &ConflictSide| match (side.entry, side.row, side.blocked) {
+            (Some(e), Some(r), _) => format!("/entries/{}/rows/{}", e, r),
+            (_, _, Some(b)) => format!("/blocked/{}", b),
+            _ => String::new(),
+        };
+        // Для пересечения замечание относится к более поздней операции. Ограничения
+        // пользователя здесь не проверяются: find их не учитывает
+        let (kind, own, other) = match conflict.kind {
+            ConflictKind::DoubleBooked => (IssueKind::Overlap, &conflict.second, &conflict.first),
+            ConflictKind::Blocked => (IssueKind::Blocked, &conflict.first, &conflict.second),
+            ConflictKind::Constraint | ConflictKind::Preference => continue,
+        };
+        out.push(kind, pointer(own), Some(pointer(other)), Some(&conflict.worker), conflict.message.clone());
+    }
+}
@@ -0,0 +1,161 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Слежение за открытыми файлами: когда файл меняет другая программа (синхронизация
+// OneDrive, второй экземпляр приложения), фронтенд получает событие «file-changed»
+// и может предложить перечитать файл (данные события - в events.rs).
+//
+// Наблюдается папка файла, а не сам файл: многие программы сохраняют через
+// временный файл с переименованием, и наблюдение за старым файлом потерялось бы.
+// Событие отправляется, только если размер или время изменения файла отличаются
+// от последних известных. Это же отсекает собственные сохранения приложения
+// (после записи note_write запоминает новое состояние) и повторные события
+// одной синхронизации. В сетевых папках система может не сообщать об изменениях.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::SystemTime;
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+
+use crate::{events, paths};
+
+// Одновременно наблюдаемых файлов
+const MAX_WATCHED: usize = 32;
+
+/// Что произошло с файлом
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChangeKind {
+    Modified,
+    Removed,
+}
+
+/// Событие об изменении файла
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileChange {
+    pub path: String,
+    pub kind: ChangeKind,
+}
+
+// Размер и время изменения; None - файла нет
+type FileState = Option<(u64, SystemTime)>;
+
+struct Watched {
+    // Путь, переданный фронтендом (для события)
+    path: PathBuf,
+    // Канонический путь: так его сообщают некоторые системы (macOS)
+    canonical: Option<PathBuf>,
+    dir: PathBuf,
+    last: FileState,
+}
+
+static WATCHED: LazyLock<Mutex<HashMap<PathBuf, Watched>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+static WATCHER: LazyLock<Mutex<Option<RecommendedWatcher>>> = LazyLock::new(|| Mutex::new(None));
+
+fn file_state(path: &Path) -> FileState {
+    let metadata = std::fs::metadata(paths::to_fs_path(path)).ok()?;
+    Some((metadata.len(), metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH)))
+}
+
+fn lock_error() -> String {
+    "Ошибка доступа к наблюдению за файлами".into()
+}
+
+/// Обрабатывает событие системы: сверяет состояние затронутых наблюдаемых файлов
+fn handle(app: &tauri::AppHandle, event: notify::Event) {
+    if event.kind.is_access() {
+        return;
+    }
+    let Ok(mut watched) = WATCHED.lock() else { return };
+    for changed in &event.paths {
+        let changed = paths::nfc(changed);
+        for item in watched.values_mut() {
+            let hit = paths::same_path(&item.path, &changed)
+                || item.canonical.as_ref().is_some_and(|c| paths::same_path(c, &changed));
+            if !hit {
+                continue;
+            }
+            let state = file_state(&item.path);
+            if state == item.last {
+                continue;
+            }
+            let kind = if state.is_some() { ChangeKind::Modified } else { ChangeKind::Removed };
+            item.last = state;
+            events::emit(app, FileChange { path: item.path.to_string_lossy().to_string(), kind });
+        }
+    }
+}
+
+/// Начинает слежение за файлом (путь уже проверен)
+pub fn watch(app: &tauri::AppHandle, path: &Path) -> Result<(), String> {
+    let key = paths::nfc(path);
+    let dir = path.parent().ok_or("Не удалось определить папку файла")?.to_path_buf();
+
+    let mut watcher = WATCHER.lock().map_err(|_| lock_error())?;
+    let (already, dir_watched) = {
+        let watched = WATCHED.lock().map_err(|_| lock_error())?;
+        if !watched.contains_key(&key) && watched.len() >= MAX_WATCHED {
+            return Err("Слишком много наблюдаемых файлов".into());
+        }
+        (watched.contains_key(&key), watched.values().any(|w| paths::same_path(&w.dir, &dir)))
+    };
+    if already {
+        return Ok(());
+    }
+
+    if watcher.is_none() {
+        let app = app.clone();
+        let created = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            if let Ok(event) = event {
+                handle(&app, event);
+            }
+        })
+        .map_err(|e| format!("Не удалось включить наблюдение за файлами: {}", e))?;
+        *watcher = Some(created);
+    }
+    if !dir_watched {
+        if let Some(watcher) = watcher.as_mut() {
+            watcher
+                .watch(&paths::to_fs_path(&dir), RecursiveMode::NonRecursive)
+                .map_err(|e| format!("Не удалось наблюдать за папкой {}: {}", dir.display(), e))?;
+        }
+    }
+
+    let canonical = path.canonicalize().ok().map(|p| paths::nfc(&paths::strip_verbatim(&p)));
+    WATCHED.lock().map_err(|_| lock_error())?.insert(
+        key,
+        Watched { path: path.to_path_buf(), canonical, dir, last: file_state(path) },
+    );
+    Ok(())
+}
+
+/// Прекращает слежение за файлом
+pub fn unwatch(path: &Path) -> Result<(), String> {
+    let mut watcher = WATCHER.lock().map_err(|_| lock_error())?;
+    let (removed, dir_still_used) = {
+        let mut watched = WATCHED.lock().map_err(|_| lock_error())?;
+        let removed = watched.remove(&paths::nfc(path)).ok_or("Файл не наблюдается")?;
+        let used = watched.values().any(|w| paths::same_path(&w.dir, &removed.dir));
+        (removed, used)
+    };
+    if !dir_still_used {
+        if let Some(watcher) = watcher.as_mut() {
+            let _ = watcher.unwatch(&paths::to_fs_path(&removed.dir));
+        }
+    }
+    Ok(())
+}
+
+/// Запоминает состояние файла после записи самим приложением, чтобы
+/// собственное сохранение не выглядело внешним изменением
+pub fn note_write(path: &Path) {
+    if let Ok(mut watched) = WATCHED.lock() {
+        if let Some(item) = watched.get_mut(&paths::nfc(path)) {
+            item.last = file_state(path);
+        }
+    }
+}
@@ -0,0 +1,176 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Открытые документы: несколько расписаний одновременно (например, два периода
+// в соседних окнах или вкладках). Документ получает идентификатор от бэкенда, его
+// расписание и история правок хранятся в edits.rs, а здесь - путь к файлу,
+// название и окно, в котором документ открыт. Признак несохранённых изменений
+// берётся из истории правок.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+use crate::edits::{EditState, Edits};
+use crate::model::Schedule;
+use crate::paths;
+
+// Больше документов одновременно не открывается: каждый держит в памяти расписание и историю
+const MAX_DOCUMENTS: usize = 32;
+
+/// Открытый документ
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentInfo {
+    pub id: String,
+    /// Файл документа; None - новый, ещё не сохранённый
+    pub path: Option<PathBuf>,
+    pub title: String,
+    /// Метка окна, в котором документ открыт
+    pub window: Option<String>,
+    pub dirty: bool,
+    /// Время открытия, миллисекунды с 1970 года
+    pub opened_at: u64,
+}
+
+/// Открытый документ и его состояние правки
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenedDocument {
+    pub document: DocumentInfo,
+    pub state: EditState,
+    /// Файл уже был открыт: возвращён существующий документ
+    pub already_open: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    path: Option<PathBuf>,
+    title: String,
+    window: Option<String>,
+    opened_at: u64,
+}
+
+/// Открытые документы, хранятся в состоянии приложения
+#[derive(Default)]
+pub struct Workspace {
+    documents: Mutex<HashMap<String, Entry>>,
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+fn new_id() -> Result<String, String> {
+    let mut bytes = [0u8; 8];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("Не удалось создать идентификатор документа: {}", e))?;
+    Ok(format!("doc-{}", bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>()))
+}
+
+fn info(id: &str, entry: &Entry, edits: &Edits) -> DocumentInfo {
+    DocumentInfo {
+        id: id.to_string(),
+        path: entry.path.clone(),
+        title: entry.title.clone(),
+        window: entry.window.clone(),
+        dirty: edits.is_dirty(id),
+        opened_at: entry.opened_at,
+    }
+}
+
+// Документ, в котором уже открыт файл path. Ошибка - файл не открыт, а места для
+// нового документа нет. Общая проверка для open и adopt
+fn admit<'a>(documents: &'a HashMap<String, Entry>, path: Option<&Path>) -> Result<Option<&'a str>, String> {
+    let existing = documents
+        .iter()
+        .find(|(_, e)| matches!((&e.path, path), (Some(own), Some(p)) if paths::same_path(own, p)));
+    if let Some((id, _)) = existing {
+        return Ok(Some(id));
+    }
+    if documents.len() >= MAX_DOCUMENTS {
+        return Err(format!("Открыто слишком много документов (не больше {})", MAX_DOCUMENTS));
+    }
+    Ok(None)
+}
+
+impl Workspace {
+    /// Открывает документ. Файл, который уже открыт, второй раз не открывается:
+    /// возвращается существующий документ, чтобы две копии не перезаписывали друг друга
+    pub fn open(
+        &self,
+        edits: &Edits,
+        path: Option<&Path>,
+        schedule: Schedule,
+        window: Option<String>,
+    ) -> Result<OpenedDocument, String> {
+        if path.is_some_and(|p| !paths::is_path_allowed(p)) {
+            return Err("Доступ к файлу запрещён".into());
+        }
+        let mut documents = self.documents.lock().map_err(|_| "Ошибка доступа к списку документов".to_string())?;
+
+        if let Some(id) = admit(&documents, path)? {
+            let document = info(id, &documents[id], edits);
+            return Ok(OpenedDocument { document, state: edits.state(id)?, already_open: true });
+        }
+
+        let id = new_id()?;
+        let title = path
+            .and_then(|p| p.file_stem())
+            .map_or_else(|| "Новое расписание".to_string(), |n| n.to_string_lossy().to_string());
+        let entry = Entry { path: path.map(Path::to_path_buf), title, window, opened_at: now_ms() };
+        let state = edits.open(id.clone(), schedule)?;
+        let document = info(&id, &entry, edits);
+        documents.insert(id, entry);
+        Ok(OpenedDocument { document, state, already_open: false })
+    }
+
+    /// Закрывает документ. Документ с несохранёнными изменениями закрывается только с force
+    pub fn close(&self, edits: &Edits, id: &str, force: bool) -> Result<(), String> {
+        let mut documents = self.documents.lock().map_err(|_| "Ошибка доступа к списку документов".to_string())?;
+        if !documents.contains_key(id) {
+            return Err("Документ не открыт".into());
+        }
+        if !force && edits.is_dirty(id) {
+            return Err("В документе есть несохранённые изменения".into());
+        }
+        documents.remove(id);
+        edits.close(id)
+    }
+
+    /// Добавляет документ, уже открытый в истории правок (восстановленный после сбоя).
+    /// Ограничения те же, что у open, но открытый файл - ошибка: восстановленные
+    /// изменения нельзя подменить документом, который уже открыт
+    pub fn adopt(&self, edits: &Edits, id: &str, path: Option<PathBuf>) -> Result<DocumentInfo, String> {
+        let mut documents = self.documents.lock().map_err(|_| "Ошибка доступа к списку документов".to_string())?;
+        if admit(&documents, path.as_deref())?.is_some() {
+            return Err("Файл уже открыт: закройте его и восстановите изменения ещё раз".into());
+        }
+        let title = path
+            .as_deref()
+            .and_then(Path::file_stem)
+            .map_or_else(|| "Восстановленное расписание".to_string(), |n| n.to_string_lossy().to_string());
+        let entry = Entry { path, title, window: None, opened_at: now_ms() };
+        let document = info(id, &entry, edits);
+        documents.insert(id.to_string(), entry);
+        Ok(document)
+    }
+
+    /// Файл документа
+    pub fn path(&self, id: &str) -> Option<PathBuf> {
+        self.documents.lock().ok()?.get(id)?.path.clone()
+    }
+
+    /// Открытые документы в порядке открытия
+    pub fn list(&self, edits: &Edits) -> Vec<DocumentInfo> {
+        let Ok(documents) = self.documents.lock() else {
+            return Vec::new();
+        };
+        let mut list: Vec<DocumentInfo> = documents.iter().map(|(id, entry)| info(id, entry, edits)).collect();
+        list.sort_by_key(|d| d.opened_at);
+        list
+    }
+}
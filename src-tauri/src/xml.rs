@@ -0,0 +1,133 @@
+// Этот файл является частью time-to-table
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Проверка XML-файлов перед передачей во фронтенд. DOCTYPE запрещён целиком:
+// без DTD невозможны внешние сущности (XXE) и «бомбы» из вложенных сущностей,
+// а из ссылок допускаются только пять стандартных и числовые.
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::Serialize;
+
+// Наибольшая вложенность элементов
+const MAX_DEPTH: usize = 256;
+
+const PREDEFINED_ENTITIES: [&str; 5] = ["lt", "gt", "amp", "apos", "quot"];
+
+/// Первое нарушение в XML-файле
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct XmlError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+/// Результат проверки XML
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct XmlReport {
+    pub valid: bool,
+    /// Имя корневого элемента
+    pub root: Option<String>,
+    pub error: Option<XmlError>,
+}
+
+impl XmlReport {
+    /// Сообщение пользователю, если файл не прошёл проверку
+    pub fn summary(&self) -> Option<String> {
+        let error = self.error.as_ref()?;
+        Some(format!(
+            "Ошибка в XML: строка {}, колонка {}: {}",
+            error.line, error.column, error.message
+        ))
+    }
+}
+
+/// Проверяет корректность и безопасность XML-документа
+pub fn check(text: &str) -> XmlReport {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    match scan(text) {
+        Ok(root) => XmlReport { valid: true, root: Some(root), error: None },
+        Err((offset, message)) => {
+            let (line, column) = line_column(text, offset);
+            XmlReport { valid: false, root: None, error: Some(XmlError { line, column, message }) }
+        }
+    }
+}
+
+// Проходит документ; ошибка - смещение в байтах и описание
+fn scan(text: &str) -> Result<String, (usize, String)> {
+    let mut reader = Reader::from_str(text);
+    reader.config_mut().check_end_names = true;
+    let mut depth = 0;
+    let mut root: Option<String> = None;
+
+    loop {
+        let position = reader.buffer_position() as usize;
+        let event = reader
+            .read_event()
+            .map_err(|e| (reader.error_position() as usize, format!("документ повреждён: {}", e)))?;
+        match event {
+            Event::DocType(_) => {
+                return Err((position, "объявления DOCTYPE и DTD не допускаются".into()));
+            }
+            Event::Start(ref e) | Event::Empty(ref e) => {
+                if depth == 0 && root.is_some() {
+                    return Err((position, "в документе может быть только один корневой элемент".into()));
+                }
+                // Проверка атрибутов: повторы и ссылки на неизвестные сущности
+                for attr in e.attributes() {
+                    let attr = attr.map_err(|err| (position, format!("ошибка в атрибуте: {}", err)))?;
+                    attr.unescape_value()
+                        .map_err(|err| (position, format!("ошибка в значении атрибута: {}", err)))?;
+                }
+                if root.is_none() {
+                    root = Some(String::from_utf8_lossy(e.name().as_ref()).to_string());
+                }
+                if matches!(event, Event::Start(_)) {
+                    depth += 1;
+                    if depth > MAX_DEPTH {
+                        return Err((position, format!("вложенность элементов больше {}", MAX_DEPTH)));
+                    }
+                }
+            }
+            Event::End(_) => depth -= 1,
+            Event::GeneralRef(ref e) => {
+                if e.is_char_ref() {
+                    match e.resolve_char_ref() {
+                        Ok(Some(_)) => {}
+                        _ => return Err((position, "недопустимая числовая ссылка на символ".into())),
+                    }
+                } else {
+                    let name = String::from_utf8_lossy(e);
+                    if !PREDEFINED_ENTITIES.contains(&name.as_ref()) {
+                        return Err((position, format!("неизвестная сущность &{};", name)));
+                    }
+                }
+            }
+            Event::Text(ref e) if depth == 0 && !e.iter().all(u8::is_ascii_whitespace) => {
+                return Err((position, "текст вне корневого элемента".into()));
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if depth > 0 {
+        return Err((text.len(), "документ обрывается: не все элементы закрыты".into()));
+    }
+    root.ok_or((0, "в документе нет корневого элемента".into()))
+}
+
+/// Строка и колонка (с 1) для смещения в байтах
+fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+    (line, column)
+}
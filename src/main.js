@@ -43,6 +43,25 @@ async function initTauriApi() {
             // В Tauri v2 модули доступны через __TAURI__
             tauriDialog = globalThis.__TAURI__.dialog;
             tauriInvoke = globalThis.__TAURI__.core.invoke;
+            // Файлы, перетащенные в окно или открытые двойным щелчком, проверяет и читает бэкенд
+            const tauriEvent = globalThis.__TAURI__.event;
+            if (tauriEvent) {
+                await tauriEvent.listen('file-opened', ({ payload }) => {
+                    if (payload?.format === 'json' && typeof payload.text === 'string') {
+                        importBackupText(payload.text);
+                    } else if (payload?.format === 'encrypted' && typeof payload.path === 'string'
+                        && !payload.path.toLowerCase().endsWith('.xml.enc')) {
+                        openEncryptedFile(payload.path);
+                    } else {
+                        showMessage('Этот файл можно открыть только через меню импорта').catch(() => {});
+                    }
+                });
+                await tauriEvent.listen('file-open-failed', ({ payload }) => {
+                    showMessage('Ошибка: ' + (payload?.message || 'не удалось открыть файл')).catch(() => {});
+                });
+                // Подписка готова: бэкенд присылает файлы, с которыми запущено приложение
+                await tauriInvoke('open_pending_files');
+            }
             safeDebug('Tauri API доступен');
         } catch (e) {
             safeLogError('Tauri API init error:', e);
@@ -240,6 +259,69 @@ function showImportConflictDialog(conflicts) {
     });
 }
 
+// Диалог ввода пароля: пароль или null, если пользователь отказался
+function showPasswordDialog(message) {
+    return new Promise((resolve) => {
+        const modal = document.getElementById('passwordModal');
+        const textEl = document.getElementById('passwordText');
+        const input = document.getElementById('passwordInput');
+        const okBtn = document.getElementById('passwordOkBtn');
+        const cancelBtn = document.getElementById('passwordCancelBtn');
+        const closeBtn = document.getElementById('closePasswordModal');
+
+        textEl.textContent = message;
+        input.value = '';
+
+        function cleanup(result) {
+            modal.classList.remove('active');
+            input.value = '';
+            okBtn.removeEventListener('click', onOk);
+            cancelBtn.removeEventListener('click', onCancel);
+            closeBtn.removeEventListener('click', onCancel);
+            input.removeEventListener('keydown', onKey);
+            resolve(result);
+        }
+
+        function onOk()     { if (input.value) cleanup(input.value); }
+        function onCancel() { cleanup(null); }
+        function onKey(e) {
+            if (e.key === 'Enter') onOk();
+            else if (e.key === 'Escape') onCancel();
+        }
+
+        okBtn.addEventListener('click', onOk);
+        cancelBtn.addEventListener('click', onCancel);
+        closeBtn.addEventListener('click', onCancel);
+        input.addEventListener('keydown', onKey);
+
+        modal.classList.add('active');
+        input.focus();
+    });
+}
+
+// Открытие зашифрованного файла, перетащенного в окно или открытого системой:
+// пароль запрашивается, пока он не подойдёт или пользователь не откажется
+async function openEncryptedFile(path) {
+    const name = path.split(/[\\/]/).pop();
+    let message = `Файл «${name}» зашифрован. Введите пароль:`;
+    for (;;) {
+        const password = await showPasswordDialog(message);
+        if (password === null) return;
+        try {
+            const text = await tauriInvoke('read_file_encrypted', { path, password });
+            importBackupText(text);
+            return;
+        } catch (e) {
+            const error = String(e);
+            if (!error.startsWith('WRONG_PASSWORD')) {
+                showMessage('Ошибка: ' + error).catch(() => {});
+                return;
+            }
+            message = `Неверный пароль для файла «${name}». Попробуйте ещё раз:`;
+        }
+    }
+}
+
 // Определяет суффикс единицы измерения для заголовка таблицы
 function getHeaderUnitSuffix(rows) {
     const uniqueUnits = [...new Set(rows.map(r => r.unit || 'min'))];
@@ -513,6 +595,8 @@ function sanitizeObject(obj) {
 function validateImportData(obj) {
     if (typeof obj !== 'object' || obj === null) return false;
     return Object.entries(obj).every(([key, value]) => {
+        // Версия формата файла (проставляется при сохранении, см. migrate.rs)
+        if (key === 'schemaVersion') return Number.isInteger(value) && value >= 1;
         if (!key.startsWith('z7_card_')) return false;
         // Дополнительная проверка на опасные ключи
         const keyL = key.toLowerCase();
@@ -3604,6 +3688,10 @@ document.getElementById('deleteCardBtn').addEventListener('click', async () => {
         applyTheme(data.theme);
         try {
             await safeLocalStorageSet(DEFAULTS_KEY, JSON.stringify(data));
+            // Тема хранится и в настройках приложения, чтобы бэкенд знал её до загрузки страницы
+            if (tauriInvoke) {
+                await tauriInvoke('set_setting', { key: 'theme', value: data.theme }).catch(e => console.debug?.('set_setting error', e));
+            }
             closeSettingsModal();
             await showMessage('Настройки сохранены', 'Готово');
         } catch (e) {
@@ -3675,7 +3763,10 @@ document.getElementById('exportBtn').addEventListener('click', async () => {
         try {
             const filePath = await tauriDialog.save({
                 defaultPath: fileName,
-                filters: [{ name: 'JSON', extensions: ['json'] }]
+                filters: [
+                    { name: 'JSON', extensions: ['json'] },
+                    { name: 'Расписание Time-To-Table', extensions: ['ttable'] }
+                ]
             });
             
             if (filePath) {
@@ -3722,6 +3813,79 @@ document.getElementById('opsSortMode').addEventListener('change', (e) => {
     try { updateMainOperationLabels(); updateOperationInputPrefixes(); } catch (e) { /* ignore */ }
 });
 
+// Импорт резервной копии техкарт из текста JSON (выбор файла или перетаскивание в окно)
+async function importBackupText(text) {
+    try {
+        const d = safeJsonParse(text);
+        if (!d || !validateImportData(d)) {
+            showMessage('Ошибка: файл содержит некорректные данные').catch(() => {});
+            return;
+        }
+
+        const importKeys = Object.keys(d).filter(k => k.startsWith('z7_card_'));
+        if (importKeys.length === 0) {
+            showMessage('Ошибка: файл не содержит техкарт').catch(() => {});
+            return;
+        }
+
+        // Определяем конфликтующие техкарты (уже есть в localStorage)
+        const existingKeys = Object.keys(localStorage).filter(k => k.startsWith('z7_card_'));
+        const conflicts = importKeys.filter(k => existingKeys.includes(k));
+        const newKeys = importKeys.filter(k => !existingKeys.includes(k));
+
+        // mode: 'all' — перезаписать всё, 'new' — только новые, 'cancel' — отмена
+        let mode = 'all';
+
+        if (conflicts.length > 0) {
+            mode = await showImportConflictDialog(conflicts);
+            if (mode === 'cancel') return;
+        }
+
+        const keysToSave = mode === 'new' ? newKeys : importKeys;
+        for (const k of keysToSave) {
+            await safeLocalStorageSet(k, d[k]);
+        }
+
+        loadTechCards();
+        const added = newKeys.length;
+        const overwritten = mode === 'new' ? 0 : conflicts.length;
+        const skipped = mode === 'new' ? conflicts.length : 0;
+        const parts = [];
+        if (added > 0) parts.push(`добавлено: ${added}`);
+        if (overwritten > 0) parts.push(`перезаписано: ${overwritten}`);
+        if (skipped > 0) parts.push(`пропущено: ${skipped}`);
+        if (parts.length === 0) parts.push('без изменений');
+        await showMessage(`Импорт завершён (${parts.join(', ')}).`, 'Готово');
+    } catch (e) {
+        showMessage("Ошибка при импорте: " + e.message).catch(() => {});
+    }
+}
+
+// Распаковывает gzip по частям и прекращает чтение, как только распакованных байт
+// больше limit: маленький архив может развернуться в гигабайты. null - превышен limit
+async function readGzipText(file, limit) {
+    const reader = file.stream().pipeThrough(new DecompressionStream('gzip')).getReader();
+    const chunks = [];
+    let total = 0;
+    for (;;) {
+        const { done, value } = await reader.read();
+        if (done) break;
+        total += value.byteLength;
+        if (total > limit) {
+            await reader.cancel().catch(() => {});
+            return null;
+        }
+        chunks.push(value);
+    }
+    const bytes = new Uint8Array(total);
+    let offset = 0;
+    for (const chunk of chunks) {
+        bytes.set(chunk, offset);
+        offset += chunk.byteLength;
+    }
+    return new TextDecoder().decode(bytes);
+}
+
 document.getElementById('fileInput').addEventListener('change', (e) => {
     const file = e.target.files[0];
     if (!file) return;
@@ -3734,53 +3898,24 @@ document.getElementById('fileInput').addEventListener('change', (e) => {
         return;
     }
     
-    const reader = new FileReader();
-    reader.onload = async (ev) => {
-        try {
-            const d = safeJsonParse(ev.target.result);
-            if (!d || !validateImportData(d)) {
-                showMessage('Ошибка: файл содержит некорректные данные').catch(() => {});
-                return;
-            }
-
-            const importKeys = Object.keys(d).filter(k => k.startsWith('z7_card_'));
-            if (importKeys.length === 0) {
-                showMessage('Ошибка: файл не содержит техкарт').catch(() => {});
-                return;
-            }
-
-            // Определяем конфликтующие техкарты (уже есть в localStorage)
-            const existingKeys = Object.keys(localStorage).filter(k => k.startsWith('z7_card_'));
-            const conflicts = importKeys.filter(k => existingKeys.includes(k));
-            const newKeys = importKeys.filter(k => !existingKeys.includes(k));
-
-            // mode: 'all' — перезаписать всё, 'new' — только новые, 'cancel' — отмена
-            let mode = 'all';
-
-            if (conflicts.length > 0) {
-                mode = await showImportConflictDialog(conflicts);
-                if (mode === 'cancel') return;
-            }
-
-            const keysToSave = mode === 'new' ? newKeys : importKeys;
-            for (const k of keysToSave) {
-                await safeLocalStorageSet(k, d[k]);
-            }
+    // Сжатые .json.gz и .ttable.gz распаковываются здесь же; зашифрованные файлы
+    // открываются перетаскиванием в окно - пароль запрашивает openEncryptedFile
+    if (file.name.toLowerCase().endsWith('.gz')) {
+        readGzipText(file, MAX_FILE_SIZE)
+            .then((text) => {
+                if (text === null) {
+                    showMessage('Ошибка: распакованный файл слишком большой (макс. 1 МБ)').catch(() => {});
+                    return;
+                }
+                importBackupText(text);
+            })
+            .catch(() => showMessage('Ошибка: файл повреждён или не является архивом gzip').catch(() => {}));
+        e.target.value = '';
+        return;
+    }
 
-            loadTechCards();
-            const added = newKeys.length;
-            const overwritten = mode === 'new' ? 0 : conflicts.length;
-            const skipped = mode === 'new' ? conflicts.length : 0;
-            const parts = [];
-            if (added > 0) parts.push(`добавлено: ${added}`);
-            if (overwritten > 0) parts.push(`перезаписано: ${overwritten}`);
-            if (skipped > 0) parts.push(`пропущено: ${skipped}`);
-            if (parts.length === 0) parts.push('без изменений');
-            await showMessage(`Импорт завершён (${parts.join(', ')}).`, 'Готово');
-        } catch (e) {
-            showMessage("Ошибка при импорте: " + e.message).catch(() => {});
-        }
-    };
+    const reader = new FileReader();
+    reader.onload = (ev) => importBackupText(ev.target.result);
     reader.readAsText(file);
     e.target.value = ''; // Сброс input для повторного выбора того же файла
 });